@@ -0,0 +1,281 @@
+// Generated from the carf token tables by `cargo test`, do not edit
+
+module.exports = grammar({
+  name: 'carf',
+
+  extras: $ => [/\s/, $.comment],
+
+  word: $ => $.identifier,
+
+  rules: {
+    source_file: $ => repeat(choice(
+      $.fun,
+      $.stmt_let,
+      $.struct_def,
+      $.enum_def,
+      $.impl_def,
+      $.import_def,
+      $.pub_def,
+    )),
+
+    _statement: $ => choice(
+      $.stmt_break,
+      $.stmt_continue,
+      $.stmt_empty,
+      $.stmt_for,
+      $.stmt_if,
+      $.stmt_let,
+      $.stmt_multi,
+      $.stmt_return,
+      $.stmt_while,
+      seq($._expression, ';'),
+    ),
+
+    _expression: $ => choice(
+      $.expr_array,
+      $.expr_b_and,
+      $.expr_b_andeq,
+      $.expr_b_asterisk,
+      $.expr_b_asteriskeq,
+      $.expr_b_dand,
+      $.expr_b_ddot,
+      $.expr_b_dequal,
+      $.expr_b_dor,
+      $.expr_b_equal,
+      $.expr_b_greater,
+      $.expr_b_greatereq,
+      $.expr_b_less,
+      $.expr_b_lesseq,
+      $.expr_b_lshift,
+      $.expr_b_lshifteq,
+      $.expr_b_minus,
+      $.expr_b_minuseq,
+      $.expr_b_mod,
+      $.expr_b_modeq,
+      $.expr_b_nequal,
+      $.expr_b_or,
+      $.expr_b_oreq,
+      $.expr_b_plus,
+      $.expr_b_pluseq,
+      $.expr_b_rshift,
+      $.expr_b_rshifteq,
+      $.expr_b_slash,
+      $.expr_b_slasheq,
+      $.expr_b_xor,
+      $.expr_b_xoreq,
+      $.expr_call,
+      $.expr_char,
+      $.expr_false,
+      $.expr_field,
+      $.expr_float,
+      $.expr_identifer,
+      $.expr_indexing,
+      $.expr_integer,
+      $.expr_lambda,
+      $.expr_match,
+      $.expr_paren,
+      $.expr_path,
+      $.expr_string,
+      $.expr_struct,
+      $.expr_true,
+      $.expr_u_minus,
+      $.expr_u_not,
+      $.expr_u_post_dminus,
+      $.expr_u_post_dplus,
+      $.expr_u_pre_dminus,
+      $.expr_u_pre_dplus,
+    ),
+
+    enum_def: $ => seq('enum', $.identifier, '{', optional($.variants), '}'),
+
+    expr_args: $ => seq($._args, ',', $._expression),
+
+    expr_array: $ => seq('[', optional($.elements), ']'),
+
+    expr_b_and: $ => prec.left(8, seq($._expression, '&', $._expression)),
+
+    expr_b_andeq: $ => prec.right(1, seq($._expression, '&=', $._expression)),
+
+    expr_b_asterisk: $ => prec.left(11, seq($._expression, '*', $._expression)),
+
+    expr_b_asteriskeq: $ => prec.right(1, seq($._expression, '*=', $._expression)),
+
+    expr_b_dand: $ => prec.left(4, seq($._expression, '&&', $._expression)),
+
+    expr_b_ddot: $ => prec.left(2, seq($._expression, '..', $._expression)),
+
+    expr_b_dequal: $ => prec.left(5, seq($._expression, '==', $._expression)),
+
+    expr_b_dor: $ => prec.left(3, seq($._expression, '||', $._expression)),
+
+    expr_b_equal: $ => prec.right(1, seq($._expression, '=', $._expression)),
+
+    expr_b_greater: $ => prec.left(5, seq($._expression, '>', $._expression)),
+
+    expr_b_greatereq: $ => prec.left(5, seq($._expression, '>=', $._expression)),
+
+    expr_b_less: $ => prec.left(5, seq($._expression, '<', $._expression)),
+
+    expr_b_lesseq: $ => prec.left(5, seq($._expression, '<=', $._expression)),
+
+    expr_b_lshift: $ => prec.left(9, seq($._expression, '<<', $._expression)),
+
+    expr_b_lshifteq: $ => prec.right(1, seq($._expression, '<<=', $._expression)),
+
+    expr_b_minus: $ => prec.left(10, seq($._expression, '-', $._expression)),
+
+    expr_b_minuseq: $ => prec.right(1, seq($._expression, '-=', $._expression)),
+
+    expr_b_mod: $ => prec.left(11, seq($._expression, '%', $._expression)),
+
+    expr_b_modeq: $ => prec.right(1, seq($._expression, '%=', $._expression)),
+
+    expr_b_nequal: $ => prec.left(5, seq($._expression, '!=', $._expression)),
+
+    expr_b_or: $ => prec.left(6, seq($._expression, '|', $._expression)),
+
+    expr_b_oreq: $ => prec.right(1, seq($._expression, '|=', $._expression)),
+
+    expr_b_plus: $ => prec.left(10, seq($._expression, '+', $._expression)),
+
+    expr_b_pluseq: $ => prec.right(1, seq($._expression, '+=', $._expression)),
+
+    expr_b_rshift: $ => prec.left(9, seq($._expression, '>>', $._expression)),
+
+    expr_b_rshifteq: $ => prec.right(1, seq($._expression, '>>=', $._expression)),
+
+    expr_b_slash: $ => prec.left(11, seq($._expression, '/', $._expression)),
+
+    expr_b_slasheq: $ => prec.right(1, seq($._expression, '/=', $._expression)),
+
+    expr_b_xor: $ => prec.left(7, seq($._expression, '^', $._expression)),
+
+    expr_b_xoreq: $ => prec.right(1, seq($._expression, '^=', $._expression)),
+
+    expr_call: $ => prec.left(13, seq($._expression, '(', optional($._args), ')')),
+
+    expr_char: $ => $.char,
+
+    expr_false: $ => 'false',
+
+    expr_field: $ => prec.left(13, seq($._expression, '.', $.identifier)),
+
+    expr_float: $ => $.float,
+
+    expr_identifer: $ => $.identifier,
+
+    expr_indexing: $ => prec.left(13, seq($._expression, '[', $._expression, ']')),
+
+    expr_integer: $ => $.integer,
+
+    expr_lambda: $ => seq('fun', '(', optional($.params), ')', ':', $.type, $.stmt_multi),
+
+    expr_match: $ => seq('match', $._expression, '{', optional($.arms), '}'),
+
+    expr_paren: $ => seq('(', $._expression, ')'),
+
+    expr_path: $ => prec.left(13, seq($.identifier, '::', $.identifier)),
+
+    expr_string: $ => $.string,
+
+    expr_struct: $ => prec.left(13, seq($.identifier, '{', optional($.field_inits), '}')),
+
+    expr_true: $ => 'true',
+
+    expr_u_minus: $ => prec(12, seq('-', $._expression)),
+
+    expr_u_not: $ => prec(12, seq('!', $._expression)),
+
+    expr_u_post_dminus: $ => prec.left(13, seq($._expression, '--')),
+
+    expr_u_post_dplus: $ => prec.left(13, seq($._expression, '++')),
+
+    expr_u_pre_dminus: $ => prec(12, seq('--', $._expression)),
+
+    expr_u_pre_dplus: $ => prec(12, seq('++', $._expression)),
+
+    fun: $ => seq('fun', $.identifier, '(', optional($.params), ')', ':', $.type, $.stmt_multi),
+
+    impl_def: $ => seq('impl', $.identifier, '{', repeat($.fun), '}'),
+
+    import_def: $ => seq('import', $.identifier, repeat(seq('::', $.identifier)), ';'),
+
+    pub_def: $ => seq('pub', choice($.fun, $.stmt_let, $.struct_def, $.enum_def)),
+
+    stmt_break: $ => seq('break', ';'),
+
+    stmt_continue: $ => seq('continue', ';'),
+
+    stmt_else: $ => seq('else', $._statement),
+
+    stmt_empty: $ => ';',
+
+    stmt_for: $ => seq('for', $.identifier, 'in', $._expression, $._statement),
+
+    stmt_if: $ => prec.right(seq('if', '(', $._expression, ')', $._statement, optional($.stmt_else))),
+
+    stmt_let: $ => seq('let', $.identifier, optional(seq(':', $.type)), optional(seq('=', $._expression)), ';'),
+
+    stmt_multi: $ => seq('{', repeat($._statement), '}'),
+
+    stmt_return: $ => seq('return', optional($._expression), ';'),
+
+    stmt_while: $ => seq('while', '(', $._expression, ')', $._statement),
+
+    struct_def: $ => seq('struct', $.identifier, '{', optional($.fields), '}'),
+
+    params: $ => seq($.param, repeat(seq(',', $.param))),
+
+    param: $ => choice(
+      'self',
+      seq($.identifier, ':', $.type),
+    ),
+
+    fields: $ => prec.right(seq($.field, repeat(seq(',', $.field)), optional(','))),
+
+    field: $ => seq($.identifier, ':', $.type),
+
+    field_inits: $ => prec.right(seq($.field_init, repeat(seq(',', $.field_init)), optional(','))),
+
+    field_init: $ => seq($.identifier, ':', $._expression),
+
+    variants: $ => prec.right(seq($.variant, repeat(seq(',', $.variant)), optional(','))),
+
+    variant: $ => prec.right(seq($.identifier, optional(seq('(', optional(seq($.type, repeat(seq(',', $.type)), optional(','))), ')')))),
+
+    arms: $ => prec.right(seq($.arm, repeat(seq(',', $.arm)), optional(','))),
+
+    arm: $ => seq($.pattern, '=>', $._expression),
+
+    pattern: $ => choice(
+      seq($.identifier, '::', $.identifier, optional(seq('(', optional(seq($.pattern, repeat(seq(',', $.pattern)), optional(','))), ')'))),
+      $.identifier,
+      seq(choice(seq(optional('-'), choice($.integer, $.float)), $.char, $.string, 'true', 'false'), optional(seq('..', choice(seq(optional('-'), choice($.integer, $.float)), $.char, $.string, 'true', 'false')))),
+    ),
+
+    _args: $ => choice(
+      $.expr_args,
+      $._expression,
+    ),
+
+    elements: $ => seq($._expression, choice(seq(';', $._expression), seq(repeat(seq(',', $._expression)), optional(',')))),
+
+    type: $ => choice(
+      $.identifier,
+      seq('[', $.type, optional(seq(';', $.integer)), ']'),
+      seq('fun', '(', optional(seq($.type, repeat(seq(',', $.type)), optional(','))), ')', ':', $.type),
+    ),
+
+    identifier: $ => /[\p{L}_][\p{L}\p{N}_]*/,
+
+    string: $ => /"(?:[^"\\]|\\.)*"/,
+
+    char: $ => /'(?:[^'\\]|\\.)'/,
+
+    integer: $ => /[0-9]+/,
+
+    float: $ => /[0-9]+\.[0-9]+f?|[0-9]+f/,
+
+    comment: $ => token(seq('//', /.*/)),
+  },
+});
@@ -2,31 +2,92 @@ pub mod token;
 
 use token::Token;
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+// Children layout of each kind
+//   Fun: [Identifier, Params, Type, Block]     Param: [Type] (token is the name)
+//   Struct: [Identifier, Fields]               Field: [Type] (token is the name)
+//   Enum: [Identifier, Variants]               Variant: [Type...] (token is the name)
+//   Impl: [Type, Fun...]                      Import: [Identifier...] (`import a::b;`)
+//   Let: [Identifier, Type, Expr?]             Stmt: [Expr] or [] for `;`
+//   If: [Expr, Stmt, Else?]   Else: [Stmt]     While: [Expr, Stmt]
+//   For: [Identifier, Expr, Stmt]              Return: [Expr?]
+//   Call: [Expr, Args]        Index: [Expr, Expr]
+//   MethodCall: [Expr, Identifier, Args] (`a.m(...)`)
+//   BinOper: [Expr, Expr]     UnOper: [Expr]   Expr: [Expr] (parenthesized)
+//   PreStep: [Expr] (`++x`, `--x`)             PostStep: [Expr] (`x++`, `x--`)
+//   Array: [Expr...]          Repeat: [Expr, Expr] (element and count)
+//   StructLit: [Identifier, FieldInit...]      FieldInit: [Expr] (token is the name)
+//   Member: [Expr, Identifier] (`a.b`)         Path: [Identifier, Identifier] (`E::V`)
+//   Match: [Expr, Arm...]                      Arm: [Pattern, Expr] (token is `=>`)
+//   Lambda: [Params, Type, Block] (`fun(x: int): int { ... }`)
+// A Pattern is a Wildcard (`_`), a Binding (token is the name), a Literal or
+// UnOper `-` of one, a RangePat: [Literal, Literal] (token is `..`) or a
+// VariantPat: [Identifier, Identifier, Pattern...] (token is `::`)
+//   Type: [] for a name, [Type, Literal?] for `[Type; N]`, [Type..., Type]
+//   for `fun(A, B): R` with the return Type last
+// A Type node with an empty token stands for an omitted annotation, or the
+// implemented Type for the `self` parameter of a method
+// A `pub` item keeps its own kind and children, with `pub` as its token
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum AstKind {
   Root,
   Fun,
+  Params,
+  Param,
+  Struct,
+  Fields,
+  Field,
+  Enum,
+  Variants,
+  Variant,
+  Impl,
+  Import,
+  Type,
   Let,
   Stmt,
+  Block,
+  If,
+  Else,
+  While,
+  For,
+  Return,
+  Break,
+  Continue,
   Expr,
   Call,
+  MethodCall,
   Args,
+  Index,
+  Array,
+  Repeat,
+  StructLit,
+  FieldInit,
+  Member,
+  Path,
+  Match,
+  Arm,
+  Wildcard,
+  Binding,
+  RangePat,
+  VariantPat,
+  Lambda,
   Identifier,
   Literal,
   BinOper,
+  UnOper,
+  PreStep,
+  PostStep,
 
   // Temporary
   Chisato,
-  ExprOrCall,
 
   // Error
   Bad(&'static str),
 
   // Signal
-  GrapArgs,
   PushToStk,
 }
 
+#[derive(Clone)]
 pub struct AstNode<'a> {
   token: Token<'a>,
   kind: AstKind,
@@ -40,6 +101,11 @@ impl<'a> std::ops::Index<usize> for AstNode<'a> {
     &self.children[index]
   }
 }
+impl std::ops::IndexMut<usize> for AstNode<'_> {
+  fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+    &mut self.children[index]
+  }
+}
 impl<'a> AstNode<'a> {
   pub fn new(token: Token<'a>, kind: AstKind) -> AstNode<'a> {
     AstNode {
@@ -67,6 +133,63 @@ impl<'a> AstNode<'a> {
   pub fn child_count(&self) -> usize {
     self.children.len()
   }
+  pub fn get_children(&self) -> &Vec<AstNode<'a>> {
+    &self.children
+  }
+  pub fn get_mut_children(&mut self) -> &mut Vec<AstNode<'a>> {
+    &mut self.children
+  }
+  pub fn set_token(&mut self, token: Token<'a>) {
+    self.token = token;
+  }
+  // Root level item marked `pub`
+  pub fn is_pub(&self) -> bool {
+    self.token.get_kind() == token::TokenKind::Pub
+  }
+  pub fn is_bad(&self) -> bool {
+    matches!(self.kind, AstKind::Bad(_))
+  }
+  // S-expression form of the subtree, e.g. `(BinOper + (Identifier a) (Literal 1))`
+  pub fn dump(&self) -> String {
+    let mut out = String::new();
+    self.dump_to(&mut out);
+    out
+  }
+  // One node per line, children indented under their parent
+  pub fn dump_tree(&self) -> String {
+    let mut out = String::new();
+    let mut stk = vec![(self, 0_usize)];
+    while let Some((node, depth)) = stk.pop() {
+      out.push_str(&"  ".repeat(depth));
+      match node.kind {
+        AstKind::Bad(msg) => out.push_str(&format!("Bad \"{}\"", msg)),
+        kind => out.push_str(&format!("{:?}", kind)),
+      }
+      if !node.token.get_value().is_empty() {
+        out.push(' ');
+        out.push_str(node.token.get_value());
+      }
+      out.push('\n');
+      stk.extend(node.children.iter().rev().map(|child| (child, depth + 1)));
+    }
+    out
+  }
+  fn dump_to(&self, out: &mut String) {
+    out.push('(');
+    match self.kind {
+      AstKind::Bad(msg) => out.push_str(&format!("Bad \"{}\"", msg)),
+      kind => out.push_str(&format!("{:?}", kind)),
+    }
+    if !self.token.get_value().is_empty() {
+      out.push(' ');
+      out.push_str(self.token.get_value());
+    }
+    for child in self.children.iter() {
+      out.push(' ');
+      child.dump_to(out);
+    }
+    out.push(')');
+  }
 }
 
 impl AstNode<'_> {
@@ -97,6 +220,11 @@ impl<'a> std::ops::Index<usize> for Ast<'a> {
     &self.root[index]
   }
 }
+impl Default for Ast<'_> {
+  fn default() -> Self {
+    Ast::new()
+  }
+}
 impl<'a> Ast<'a> {
   pub fn new() -> Ast<'a> {
     Ast {
@@ -109,4 +237,7 @@ impl<'a> Ast<'a> {
   pub fn get_mut_root(&mut self) -> &mut AstNode<'a> {
     &mut self.root
   }
+  pub fn get_root(&self) -> &AstNode<'a> {
+    &self.root
+  }
 }
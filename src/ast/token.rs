@@ -1,3 +1,4 @@
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Clone, PartialEq, Eq, Debug, PartialOrd, Ord)]
 pub enum TokenKind {
   Identifier,
@@ -46,6 +47,8 @@ pub enum TokenKind {
   Comma,
   Dot,
   DDot,
+  DColon,
+  FatArrow,
 
   // Literal
   Int,
@@ -64,6 +67,14 @@ pub enum TokenKind {
   In,
   Fun,
   Let,
+  Struct,
+  Impl,
+  Enum,
+  Match,
+  Pub,
+  Import,
+  True,
+  False,
 
   // Singals
   EOF,
@@ -74,7 +85,7 @@ pub enum TokenKind {
   Empty,
 }
 
-pub fn sym_token_map<'a>(token: &'a str) -> TokenKind {
+pub fn sym_token_map(token: &str) -> TokenKind {
   match token {
     "=" => TokenKind::Equal,
     "+" => TokenKind::Plus,
@@ -119,11 +130,13 @@ pub fn sym_token_map<'a>(token: &'a str) -> TokenKind {
     "," => TokenKind::Comma,
     "." => TokenKind::Dot,
     ".." => TokenKind::DDot,
+    "::" => TokenKind::DColon,
+    "=>" => TokenKind::FatArrow,
     _ => TokenKind::Other,
   }
 }
 
-pub fn keyword_token_map<'a>(token: &'a str) -> TokenKind {
+pub fn keyword_token_map(token: &str) -> TokenKind {
   match token {
     "if" => TokenKind::If,
     "else" => TokenKind::Else,
@@ -135,16 +148,24 @@ pub fn keyword_token_map<'a>(token: &'a str) -> TokenKind {
     "in" => TokenKind::In,
     "fun" => TokenKind::Fun,
     "let" => TokenKind::Let,
+    "struct" => TokenKind::Struct,
+    "impl" => TokenKind::Impl,
+    "enum" => TokenKind::Enum,
+    "match" => TokenKind::Match,
+    "pub" => TokenKind::Pub,
+    "import" => TokenKind::Import,
+    "true" => TokenKind::True,
+    "false" => TokenKind::False,
     _ => TokenKind::Other,
   }
 }
 
-pub fn literal_token_map<'a>(token: &'a str) -> TokenKind {
+pub fn literal_token_map(token: &str) -> TokenKind {
   {
     // Check if token is an integer
-    let mut is_int = true;
+    let mut is_int = !token.is_empty();
     for c in token.chars() {
-      if !c.is_digit(10) {
+      if !c.is_ascii_digit() {
         is_int = false;
         break;
       }
@@ -155,7 +176,7 @@ pub fn literal_token_map<'a>(token: &'a str) -> TokenKind {
   }
   {
     // Check if token is a float
-    let mut is_float = true;
+    let mut is_float = !token.is_empty();
     let mut dot_count = 0_u32;
     for (i, c) in token.chars().enumerate() {
       if c == '.' && i == 0 {
@@ -167,7 +188,7 @@ pub fn literal_token_map<'a>(token: &'a str) -> TokenKind {
           is_float = false;
           break;
         }
-      } else if !(c.is_digit(10) || (c == 'f' && i == token.len() - 1)) {
+      } else if !(c.is_ascii_digit() || (c == 'f' && i == token.len() - 1)) {
         is_float = false;
         break;
       }
@@ -178,18 +199,13 @@ pub fn literal_token_map<'a>(token: &'a str) -> TokenKind {
   }
   {
     // Check if token is a char
-    let mut is_char = true;
-    if token.len() == 3 {
-      if token.chars().nth(0).unwrap() == '\'' && token.chars().nth(2).unwrap() == '\'' {
-        is_char = true;
-      }
+    let mut is_char = false;
+    let chars: Vec<char> = token.chars().collect();
+    if chars.len() == 3 && chars[0] == '\'' && chars[2] == '\'' && chars[1] != '\\' {
+      is_char = true;
     }
-    if token.len() == 4 {
-      if token.chars().nth(0).unwrap() == '\'' && token.chars().nth(3).unwrap() == '\'' {
-        if token.chars().nth(1).unwrap() == '\\' {
-          is_char = true;
-        }
-      }
+    if chars.len() == 4 && chars[0] == '\'' && chars[3] == '\'' && chars[1] == '\\' {
+      is_char = true;
     }
     if is_char {
       return TokenKind::Char;
@@ -197,20 +213,25 @@ pub fn literal_token_map<'a>(token: &'a str) -> TokenKind {
   }
   {
     // Check if token is a string
-    let mut is_string = true;
-    if token.len() >= 2 {
-      if token.chars().nth(0).unwrap() == '"' && token.chars().nth(token.len() - 1).unwrap() == '"' {
-        is_string = true;
-      }
-    }
+    let is_string = token.len() >= 2 && token.starts_with('"') && token.ends_with('"');
     if is_string {
       return TokenKind::String;
     }
   }
-  return TokenKind::Other;
+  TokenKind::Other
 }
 
-pub const SYMBOL_LIST: [&str; 43] = [
+// Regular expressions for the words `literal_token_map` accepts and the lexer
+// scans as identifiers, for editor grammars
+pub const LITERAL_PATTERNS: [(TokenKind, &str); 5] = [
+  (TokenKind::Float, r"[0-9]+\.[0-9]+f?|[0-9]+f"),
+  (TokenKind::Int, r"[0-9]+"),
+  (TokenKind::Char, r"'(?:[^'\\]|\\.)'"),
+  (TokenKind::String, r#""(?:[^"\\]|\\.)*""#),
+  (TokenKind::Identifier, r"[\p{L}_][\p{L}\p{N}_]*"),
+];
+
+pub const SYMBOL_LIST: [&str; 45] = [
   "=",
   "+",
   "++",
@@ -254,9 +275,18 @@ pub const SYMBOL_LIST: [&str; 43] = [
   ":",
   ".",
   "..",
+  "::",
+  "=>",
 ];
 
-pub const KEYWORD_LIST: [&str; 10] = [
+// Symbols other than brackets and separators
+pub fn is_operator(kind: TokenKind) -> bool {
+  use TokenKind::*;
+  !matches!(kind, LParen | RParen | LIndex | RIndex | LStmt | RStmt | SemiColon | Colon | Comma | FatArrow)
+    && SYMBOL_LIST.iter().any(|s| sym_token_map(s) == kind)
+}
+
+pub const KEYWORD_LIST: [&str; 18] = [
   "if",
   "else",
   "while",
@@ -267,26 +297,33 @@ pub const KEYWORD_LIST: [&str; 10] = [
   "in",
   "fun",
   "let",
+  "struct",
+  "impl",
+  "enum",
+  "match",
+  "pub",
+  "import",
+  "true",
+  "false",
 ];
 
+#[derive(Clone, Copy, Debug)]
 pub struct Token<'a> {
   kind: TokenKind,
   value: &'a str,
+  pos: usize,
 }
 
 impl<'a> Token<'a> {
-  pub fn new(kind: TokenKind, value: &'a str) -> Token<'a> {
+  pub fn new(kind: TokenKind, value: &'a str, pos: usize) -> Token<'a> {
     Token {
       kind,
       value,
+      pos,
     }
   }
-  pub fn from_token(token: &'a str) -> Token<'a> {
-    let mut kind = TokenKind::Other;
-    kind = match kind {
-      TokenKind::Other => sym_token_map(token),
-      _ => kind,
-    };
+  pub fn from_token(token: &'a str, pos: usize) -> Token<'a> {
+    let mut kind = sym_token_map(token);
     kind = match kind {
       TokenKind::Other => keyword_token_map(token),
       _ => kind,
@@ -298,6 +335,7 @@ impl<'a> Token<'a> {
     Token {
       kind,
       value: token,
+      pos,
     }
   }
   pub fn set_as_identifier(&mut self) {
@@ -309,6 +347,9 @@ impl<'a> Token<'a> {
   pub fn set_as_bad(&mut self, msg: &'static str) {
     self.kind = TokenKind::Bad(msg);
   }
+  pub fn get_value(&self) -> &'a str {
+    self.value
+  }
 }
 
 impl Token<'_> {
@@ -316,33 +357,44 @@ impl Token<'_> {
     Token {
       kind: TokenKind::Head,
       value: "",
+      pos: 0,
     }
   }
-  pub fn new_eof() -> Token<'static> {
+  pub fn new_eof(pos: usize) -> Token<'static> {
     Token {
       kind: TokenKind::EOF,
       value: "",
+      pos,
     }
   }
-  pub fn new_bad(msg: &'static str) -> Token<'static> {
+  pub fn new_bad(msg: &'static str, pos: usize) -> Token<'static> {
     Token {
       kind: TokenKind::Bad(msg),
       value: "",
+      pos,
     }
   }
   pub fn new_empty() -> Token<'static> {
     Token {
       kind: TokenKind::Empty,
       value: "",
+      pos: 0,
     }
   }
   pub fn get_kind(&self) -> TokenKind {
     self.kind
   }
+  pub fn get_pos(&self) -> usize {
+    self.pos
+  }
+  // Byte range of the token in its source, used by diagnostics
+  pub fn get_span(&self) -> (usize, usize) {
+    (self.pos, self.pos + self.value.len())
+  }
 }
 
 impl Default for Token<'_> {
   fn default() -> Self {
     Token::new_head()
   }
-}
\ No newline at end of file
+}
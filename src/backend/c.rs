@@ -0,0 +1,958 @@
+use std::collections::BTreeSet;
+
+use crate::ast::token::TokenKind;
+use crate::ast::{Ast, AstKind, AstNode};
+use crate::checker::types::{FunSig, Type};
+use crate::checker::{Checker, strip_assign};
+use crate::diagnostic::{Diagnostic, Span};
+use crate::source::SourceMap;
+use crate::utilities::literal;
+
+// Values, checked arithmetic and the prelude, copied to the top of every program
+const RUNTIME: &str = include_str!("runtime.h");
+
+// Lower a checked program to one C99 file. Enums, `match`, lambdas and
+// function values have no C lowering yet and are reported as errors.
+pub fn generate<'a>(ast: &Ast<'a>, checker: &Checker<'a>, files: &SourceMap) -> Result<String, Vec<Diagnostic>> {
+  let mut generator = Generator {
+    checker,
+    files,
+    methods: BTreeSet::new(),
+    helpers: BTreeSet::new(),
+    decls: Vec::new(),
+    defs: Vec::new(),
+    scopes: Vec::new(),
+    lines: Vec::new(),
+    indent: 0,
+    next: 0,
+    diagnostics: Vec::new(),
+  };
+  generator.program(ast.get_root());
+  match generator.diagnostics.is_empty() {
+    true => Ok(generator.finish()),
+    false => {
+      generator.diagnostics.sort_by_key(|d| d.span);
+      Err(generator.diagnostics)
+    },
+  }
+}
+
+// Assignable location, its parts already evaluated to atoms
+enum Place {
+  Var(String),
+  // Array, index and C type of the elements
+  Elem(String, String, String),
+  // Struct and C name of the field
+  Field(String, String),
+  // Char of the string held by the inner place, at an index checked by `at`
+  StrChar(Box<Place>, String, String),
+}
+
+struct Generator<'g, 'a> {
+  checker: &'g Checker<'a>,
+  files: &'g SourceMap,
+  // Methods declared by an `impl`, by method key and name
+  methods: BTreeSet<(String, &'a str)>,
+  // Composite types whose helpers were generated, by mangled name
+  helpers: BTreeSet<String>,
+  // Prototypes and struct declarations, then function definitions
+  decls: Vec<String>,
+  defs: Vec<String>,
+  // C name of each carf name in scope, innermost scope last. Globals are not in it.
+  scopes: Vec<Vec<(&'a str, String)>>,
+  // Body of the function being generated
+  lines: Vec<String>,
+  indent: usize,
+  // Numbers locals and temporaries apart
+  next: usize,
+  diagnostics: Vec<Diagnostic>,
+}
+
+impl<'g, 'a> Generator<'g, 'a> {
+  fn program(&mut self, root: &'g AstNode<'a>) {
+    let items = root.get_children();
+    for item in items {
+      match item.get_kind() {
+        AstKind::Struct => self.decls.push(format!("struct s_{};", c_name(item[0].get_token().get_value()))),
+        AstKind::Enum => self.diagnostics.push(unsupported(item, "enums")),
+        AstKind::Impl => {
+          let key = self.resolve(&item[0]).method_key().unwrap_or_default();
+          for method in item.get_children().iter().skip(1) {
+            self.methods.insert((key.clone(), method[0].get_token().get_value()));
+          }
+        },
+        _ => {},
+      }
+    }
+    for item in items.iter().filter(|item| item.get_kind() == AstKind::Struct) {
+      if let Err(d) = self.struct_def(item) {
+        self.diagnostics.push(d);
+      }
+    }
+    for item in items {
+      let result = match item.get_kind() {
+        AstKind::Let => self.global(item),
+        AstKind::Fun => self.fun_item(item),
+        AstKind::Impl => self.impl_item(item),
+        _ => Ok(()),
+      };
+      if let Err(d) = result {
+        self.diagnostics.push(d);
+      }
+    }
+    let globals: Vec<&'g AstNode<'a>> = items.iter().filter(|item| item.get_kind() == AstKind::Let).collect();
+    if let Err(d) = self.entry(&globals, items.iter().find(|item| item.get_kind() == AstKind::Fun && item[0].get_token().get_value() == "main")) {
+      self.diagnostics.push(d);
+    }
+  }
+  fn finish(self) -> String {
+    let mut out = String::from(RUNTIME);
+    out.push_str("\n/* Program */\n\n");
+    for decl in &self.decls {
+      out.push_str(decl);
+      out.push('\n');
+    }
+    for def in &self.defs {
+      out.push('\n');
+      out.push_str(def);
+    }
+    out
+  }
+}
+
+// Items
+impl<'g, 'a> Generator<'g, 'a> {
+  fn struct_def(&mut self, item: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    let name = item[0].get_token().get_value();
+    let def = match self.checker.get_struct(name) {
+      Some(def) => def.clone(),
+      None => return Ok(()),
+    };
+    let mut fields = Vec::new();
+    for (field, ty) in &def.fields {
+      fields.push(format!("  {} f_{};", self.c_type(ty, item)?, field));
+    }
+    self.defs.push(format!("struct s_{} {{\n{}\n}};\n", c_name(name), fields.join("\n")));
+    Ok(())
+  }
+  fn global(&mut self, item: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    let ty = self.binding_type(&item[0]);
+    let c_type = self.c_type(&ty, &item[0])?;
+    self.decls.push(format!("static {} g_{};", c_type, c_name(item[0].get_token().get_value())));
+    Ok(())
+  }
+  fn fun_item(&mut self, item: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    let name = item[0].get_token().get_value();
+    match self.checker.get_fun(name) {
+      Some(sig) => self.function(format!("f_{}", c_name(name)), item, &sig.clone()),
+      None => Ok(()),
+    }
+  }
+  fn impl_item(&mut self, item: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    let receiver = self.resolve(&item[0]);
+    let key = match receiver.method_key() {
+      Some(key) if !matches!(receiver, Type::Enum(_)) => key,
+      _ => return Err(unsupported(item, "methods of enums")),
+    };
+    for method in item.get_children().iter().skip(1) {
+      let name = method[0].get_token().get_value();
+      if let Some(sig) = self.checker.get_method(&receiver, name) {
+        self.function(format!("m_{}_{}", c_name(&key), name), method, &sig)?;
+      }
+    }
+    Ok(())
+  }
+  fn function(&mut self, c_name: String, fun: &'g AstNode<'a>, sig: &FunSig) -> Result<(), Diagnostic> {
+    let ret = match sig.ret {
+      Type::Unit => "void".to_string(),
+      _ => self.c_type(&sig.ret, &fun[2])?,
+    };
+    self.scopes = vec![Vec::new()];
+    let mut params = Vec::new();
+    for (param, ty) in fun[1].get_children().iter().zip(&sig.params) {
+      let c_type = self.c_type(ty, param)?;
+      let local = self.declare(param.get_token().get_value());
+      params.push(format!("{} {}", c_type, local));
+    }
+    let head = format!("static {} {}({})", ret, c_name, if params.is_empty() { "void".to_string() } else { params.join(", ") });
+    self.lines.clear();
+    self.indent = 1;
+    self.body(&fun[3])?;
+    self.decls.push(format!("{};", head));
+    let body = self.take_lines();
+    self.defs.push(format!("{} {{\n{}}}\n", head, body));
+    Ok(())
+  }
+  // Globals are set in source order before `main` runs, as the evaluator does
+  fn entry(&mut self, globals: &[&'g AstNode<'a>], main: Option<&'g AstNode<'a>>) -> Result<(), Diagnostic> {
+    self.scopes = vec![Vec::new()];
+    self.lines.clear();
+    self.indent = 1;
+    for item in globals {
+      if item.child_count() > 2 {
+        let value = self.expr(&item[2])?;
+        self.line(format!("g_{} = {};", c_name(item[0].get_token().get_value()), value));
+      }
+    }
+    let init = self.take_lines();
+    self.defs.push(format!("static void carf_init(void) {{\n{}}}\n", init));
+    let main = match main {
+      Some(main) if main[1].child_count() > 0 => {
+        return Err(Diagnostic::error(Span::of_token(&main[0]), "`main` must not take parameters".to_string()));
+      },
+      Some(main) => main,
+      None => return Err(Diagnostic::error(Span::new(0, 0), "no `main` function to run".to_string())),
+    };
+    // What `main` returns is printed, like `carf run` does
+    let ret = self.checker.get_fun("main").map(|sig| sig.ret.clone()).unwrap_or(Type::Unit);
+    self.line("carf_init();".to_string());
+    match ret {
+      Type::Unit => self.line("f_main();".to_string()),
+      _ => {
+        let c_type = self.c_type(&ret, &main[2])?;
+        let put = self.put_fn(&ret, &main[2])?;
+        self.line(format!("{} result = f_main();", c_type));
+        self.line("carf_buffer out = {0};".to_string());
+        self.line(format!("{}(&out, result, false);", put));
+        self.line("carf_put_cstr(&out, \"\\n\");".to_string());
+        self.line("carf_print(carf_done(&out));".to_string());
+      },
+    }
+    self.line("return 0;".to_string());
+    let body = self.take_lines();
+    self.defs.push(format!("int main(void) {{\n{}}}\n", body));
+    Ok(())
+  }
+}
+
+// Statements
+impl<'g, 'a> Generator<'g, 'a> {
+  // A statement in a C block of its own, so its locals end with it
+  fn body(&mut self, node: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    match node.get_kind() {
+      AstKind::Block => {
+        self.scopes.push(Vec::new());
+        let result = node.get_children().iter().try_for_each(|stmt| self.stmt(stmt));
+        self.scopes.pop();
+        result
+      },
+      _ => {
+        self.scopes.push(Vec::new());
+        let result = self.stmt(node);
+        self.scopes.pop();
+        result
+      },
+    }
+  }
+  fn nested(&mut self, node: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    self.indent += 1;
+    let result = self.body(node);
+    self.indent -= 1;
+    result
+  }
+  fn stmt(&mut self, node: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    match node.get_kind() {
+      AstKind::Let => {
+        let ty = self.binding_type(&node[0]);
+        let c_type = self.c_type(&ty, &node[0])?;
+        let value = match node.child_count() > 2 {
+          true => self.expr(&node[2])?,
+          false => "{0}".to_string(),
+        };
+        let local = self.declare(node[0].get_token().get_value());
+        self.line(format!("{} {} = {};", c_type, local, value));
+      },
+      AstKind::Stmt if node.child_count() > 0 => {
+        self.expr(&node[0])?;
+      },
+      AstKind::Stmt => {},
+      AstKind::Block => {
+        self.line("{".to_string());
+        self.nested(node)?;
+        self.line("}".to_string());
+      },
+      AstKind::If => {
+        let cond = self.expr(&node[0])?;
+        self.line(format!("if ({}) {{", cond));
+        self.nested(&node[1])?;
+        if node.child_count() > 2 && node[2].child_count() > 0 {
+          self.line("} else {".to_string());
+          self.nested(&node[2][0])?;
+        }
+        self.line("}".to_string());
+      },
+      // The condition is evaluated at the top of each turn, where `continue` goes
+      AstKind::While => {
+        self.line("for (;;) {".to_string());
+        self.indent += 1;
+        let cond = self.expr(&node[0])?;
+        self.line(format!("if (!{}) break;", cond));
+        self.body(&node[1])?;
+        self.indent -= 1;
+        self.line("}".to_string());
+      },
+      AstKind::For => self.for_stmt(node)?,
+      AstKind::Return if node.child_count() > 0 => {
+        let value = self.expr(&node[0])?;
+        match value.is_empty() {
+          true => self.line("return;".to_string()),
+          false => self.line(format!("return {};", value)),
+        }
+      },
+      AstKind::Return => self.line("return;".to_string()),
+      AstKind::Break => self.line("break;".to_string()),
+      AstKind::Continue => self.line("continue;".to_string()),
+      _ => return Err(unsupported(node, "this statement")),
+    }
+    Ok(())
+  }
+  // Arrays are read as the loop goes, so the body sees its own writes
+  fn for_stmt(&mut self, node: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    let name = node[0].get_token().get_value();
+    let mut iter = &node[1];
+    while iter.get_kind() == AstKind::Expr {
+      iter = &iter[0];
+    }
+    let i = self.fresh("i");
+    let (head, item) = match self.type_of(iter) {
+      Type::Array(elem, _) => {
+        let items = self.expr(iter)?;
+        let c_type = self.c_type(&elem, &node[0])?;
+        let item = format!("CARF_AT({}, {}, {})", c_type, items, i);
+        (format!("for (int64_t {i} = 0; {i} < {}->len; {i}++) {{", items), (c_type, item))
+      },
+      Type::String => {
+        let text = self.expr(iter)?;
+        (format!("for (int64_t {i} = 0; {i} < {}.len;) {{", text), ("uint32_t".to_string(), format!("carf_decode({}, &{})", text, i)))
+      },
+      _ => return Err(unsupported(iter, "this iterator")),
+    };
+    self.line(head);
+    self.indent += 1;
+    self.scopes.push(Vec::new());
+    let local = self.declare(name);
+    self.line(format!("{} {} = {};", item.0, local, item.1));
+    let result = self.body(&node[2]);
+    self.scopes.pop();
+    self.indent -= 1;
+    self.line("}".to_string());
+    result
+  }
+}
+
+// Expressions. Each one is evaluated into a temporary in the order the
+// evaluator uses, the returned atom being the temporary, a literal, or empty
+// for a `unit` value.
+impl<'g, 'a> Generator<'g, 'a> {
+  fn expr(&mut self, node: &'g AstNode<'a>) -> Result<String, Diagnostic> {
+    let token = node.get_token();
+    match node.get_kind() {
+      AstKind::Literal => literal_atom(node),
+      AstKind::Identifier => {
+        let ty = self.type_of(node);
+        if let Type::Fun(..) = ty {
+          return Err(unsupported(node, "function values"));
+        }
+        let c_type = self.c_type(&ty, node)?;
+        let var = self.var(token.get_value());
+        Ok(self.temp(&c_type, var))
+      },
+      AstKind::Expr => self.expr(&node[0]),
+      AstKind::UnOper => {
+        let operand = self.expr(&node[0])?;
+        let value = match (token.get_kind(), self.type_of(&node[0])) {
+          (TokenKind::Minus, Type::Int) => format!("carf_neg({}, {})", operand, self.at(node)),
+          (TokenKind::Minus, _) => format!("-{}", operand),
+          _ => format!("!{}", operand),
+        };
+        let c_type = self.c_type(&self.type_of(node), node)?;
+        Ok(self.temp(&c_type, value))
+      },
+      // `x++` and `x--` give the value from before the step, `++x` and `--x`
+      // the one after it
+      AstKind::PreStep | AstKind::PostStep => {
+        let place = self.place(&node[0])?;
+        let old = self.read(&place);
+        let old = self.temp("int64_t", old);
+        let step = match token.get_kind() {
+          TokenKind::DPlus => "carf_add",
+          _ => "carf_sub",
+        };
+        let at = self.at(node);
+        let new = format!("{}({}, 1, {})", step, old, at);
+        if node.get_kind() == AstKind::PostStep {
+          self.write(&place, new);
+          return Ok(old);
+        }
+        let new = self.temp("int64_t", new);
+        self.write(&place, new.clone());
+        Ok(new)
+      },
+      AstKind::BinOper => self.binary(node),
+      AstKind::Call => self.call(node),
+      AstKind::MethodCall => self.method_call(node),
+      AstKind::Array => {
+        let elem = self.elem_type(node)?;
+        let mut items = Vec::new();
+        for item in node.get_children() {
+          items.push(self.expr(item)?);
+        }
+        let array = self.temp("carf_array *", format!("carf_array_new({}, sizeof({}))", items.len(), elem));
+        for (i, item) in items.iter().enumerate() {
+          self.line(format!("CARF_AT({}, {}, {}) = {};", elem, array, i, item));
+        }
+        Ok(array)
+      },
+      // Every element gets its own copy, so `[[0; 2]; 2]` has two distinct rows
+      AstKind::Repeat => {
+        let c_type = self.elem_type(node)?;
+        let elem_type = self.type_of(&node[0]);
+        let elem = self.expr(&node[0])?;
+        let count = self.expr(&node[1])?;
+        let count = self.temp("int64_t", format!("carf_repeat_count({}, {})", count, self.at(&node[1])));
+        let array = self.temp("carf_array *", format!("carf_array_new({}, sizeof({}))", count, c_type));
+        let copy = self.copy_fn(&elem_type, node)?;
+        let i = self.fresh("i");
+        self.line(format!("for (int64_t {i} = 0; {i} < {}; {i}++) CARF_AT({}, {}, {i}) = {}({});", count, c_type, array, copy, elem));
+        Ok(array)
+      },
+      AstKind::StructLit => {
+        let name = node[0].get_token().get_value();
+        let mut given = Vec::new();
+        for init in node.get_children().iter().skip(1) {
+          given.push((init.get_token().get_value(), self.expr(&init[0])?));
+        }
+        let record = self.temp(&format!("struct s_{} *", c_name(name)), format!("carf_alloc(sizeof(struct s_{}))", c_name(name)));
+        for (field, value) in given {
+          self.line(format!("{}->f_{} = {};", record, field, value));
+        }
+        Ok(record)
+      },
+      AstKind::Member => {
+        let record = self.expr(&node[0])?;
+        let c_type = self.c_type(&self.type_of(node), node)?;
+        Ok(self.temp(&c_type, format!("{}->f_{}", record, node[1].get_token().get_value())))
+      },
+      AstKind::Index => {
+        let base = self.expr(&node[0])?;
+        let index = self.expr(&node[1])?;
+        let at = self.at(node);
+        match self.type_of(&node[0]) {
+          Type::String => Ok(self.temp("uint32_t", format!("carf_str_at({}, {}, {})", base, index, at))),
+          _ => {
+            let elem = self.elem_type(&node[0])?;
+            Ok(self.temp(&elem, format!("CARF_AT({}, {}, carf_index({}, {}, {}))", elem, base, base, index, at)))
+          },
+        }
+      },
+      AstKind::Path => Err(unsupported(node, "enums")),
+      AstKind::Match => Err(unsupported(node, "`match`")),
+      AstKind::Lambda => Err(unsupported(node, "lambdas")),
+      _ => Err(unsupported(node, "this expression")),
+    }
+  }
+  fn binary(&mut self, node: &'g AstNode<'a>) -> Result<String, Diagnostic> {
+    let kind = node.get_token().get_kind();
+    match kind {
+      TokenKind::Equal => {
+        let place = self.place(&node[0])?;
+        let value = self.expr(&node[1])?;
+        self.write(&place, value);
+        Ok(String::new())
+      },
+      // Left by the desugaring pass when the target is not pure, which is evaluated once
+      TokenKind::PlusEq | TokenKind::MinusEq | TokenKind::AsteriskEq | TokenKind::SlashEq | TokenKind::ModEq
+      | TokenKind::AndEq | TokenKind::OrEq | TokenKind::XorEq | TokenKind::LShiftEq | TokenKind::RShiftEq => {
+        let place = self.place(&node[0])?;
+        let old = self.read(&place);
+        let c_type = self.c_type(&self.type_of(&node[0]), node)?;
+        let old = self.temp(&c_type, old);
+        let rhs = self.expr(&node[1])?;
+        let value = self.operator(strip_assign(kind), &self.type_of(&node[0]), &old, &rhs, node)?;
+        let value = self.temp(&c_type, value);
+        self.write(&place, value);
+        Ok(String::new())
+      },
+      // The right operand only runs when the left one does not decide
+      TokenKind::DAnd | TokenKind::DOr => {
+        let lhs = self.expr(&node[0])?;
+        let result = self.temp("bool", lhs);
+        self.line(format!("if ({}{}) {{", if kind == TokenKind::DAnd { "" } else { "!" }, result));
+        self.indent += 1;
+        let rhs = self.expr(&node[1]);
+        if let Ok(rhs) = &rhs {
+          self.line(format!("{} = {};", result, rhs));
+        }
+        self.indent -= 1;
+        self.line("}".to_string());
+        rhs.map(|_| result)
+      },
+      TokenKind::DDot => Err(unsupported(node, "ranges outside of `for`")),
+      _ => {
+        let lhs = self.expr(&node[0])?;
+        let rhs = self.expr(&node[1])?;
+        let value = self.operator(kind, &self.type_of(&node[0]), &lhs, &rhs, node)?;
+        let c_type = self.c_type(&self.type_of(node), node)?;
+        Ok(self.temp(&c_type, value))
+      },
+    }
+  }
+  // C expression applying a binary operator to two atoms of type `ty`
+  fn operator(&mut self, kind: TokenKind, ty: &Type, lhs: &str, rhs: &str, node: &AstNode<'a>) -> Result<String, Diagnostic> {
+    use TokenKind::*;
+    let symbol = node.get_token().get_value().trim_end_matches('=');
+    let symbol = match kind {
+      DEqual => "==",
+      NEqual => "!=",
+      LessEq => "<=",
+      GreaterEq => ">=",
+      _ => symbol,
+    };
+    let at = self.at(node);
+    let value = match (ty, kind) {
+      (Type::Int, Plus | Minus | Asterisk | Slash | Mod | LShift | RShift) => {
+        let fun = match kind {
+          Plus => "carf_add",
+          Minus => "carf_sub",
+          Asterisk => "carf_mul",
+          Slash => "carf_div",
+          Mod => "carf_rem",
+          LShift => "carf_shl",
+          _ => "carf_shr",
+        };
+        format!("{}({}, {}, {})", fun, lhs, rhs, at)
+      },
+      (Type::String, Plus) => format!("carf_concat({}, {})", lhs, rhs),
+      (Type::String, Less | Greater | LessEq | GreaterEq) => format!("carf_cmp_string({}, {}) {} 0", lhs, rhs, symbol),
+      (Type::Int | Type::Float | Type::Char | Type::Bool, _) => format!("{} {} {}", lhs, symbol, rhs),
+      (_, DEqual | NEqual) => {
+        let eq = self.eq_fn(ty, node)?;
+        format!("{}{}({}, {})", if kind == NEqual { "!" } else { "" }, eq, lhs, rhs)
+      },
+      _ => return Err(unsupported(node, &format!("`{}` on `{}`", symbol, ty))),
+    };
+    Ok(value)
+  }
+  fn call(&mut self, node: &'g AstNode<'a>) -> Result<String, Diagnostic> {
+    let callee = &node[0];
+    let name = callee.get_token().get_value();
+    if callee.get_kind() == AstKind::Path {
+      return Err(unsupported(node, "enums"));
+    }
+    if callee.get_kind() != AstKind::Identifier || self.is_local(name) {
+      return Err(unsupported(node, "calls of function values"));
+    }
+    let mut args = Vec::new();
+    for arg in node[1].get_children() {
+      args.push(self.expr(arg)?);
+    }
+    match self.checker.get_fun(name) {
+      Some(sig) => {
+        let value = format!("f_{}({})", c_name(name), args.join(", "));
+        match sig.ret {
+          Type::Unit => {
+            self.line(format!("{};", value));
+            Ok(String::new())
+          },
+          _ => {
+            let c_type = self.c_type(&self.type_of(node), node)?;
+            Ok(self.temp(&c_type, value))
+          },
+        }
+      },
+      None => self.prelude(name, &args, node),
+    }
+  }
+  fn method_call(&mut self, node: &'g AstNode<'a>) -> Result<String, Diagnostic> {
+    let receiver = self.type_of(&node[0]);
+    let name = node[1].get_token().get_value();
+    let mut args = vec![self.expr(&node[0])?];
+    for arg in node[2].get_children() {
+      args.push(self.expr(arg)?);
+    }
+    let key = receiver.method_key().unwrap_or_default();
+    if !self.methods.contains(&(key.clone(), name)) {
+      return self.prelude(name, &args, node);
+    }
+    let value = format!("m_{}_{}({})", c_name(&key), name, args.join(", "));
+    match self.type_of(node) {
+      Type::Unit => {
+        self.line(format!("{};", value));
+        Ok(String::new())
+      },
+      ty => {
+        let c_type = self.c_type(&ty, node)?;
+        Ok(self.temp(&c_type, value))
+      },
+    }
+  }
+  // Prelude functions and the built-in methods, on arguments already evaluated
+  fn prelude(&mut self, name: &str, args: &[String], node: &'g AstNode<'a>) -> Result<String, Diagnostic> {
+    let first = match node.get_kind() {
+      AstKind::MethodCall => self.type_of(&node[0]),
+      _ => node[1].get_children().first().map(|arg| self.type_of(arg)).unwrap_or(Type::Unit),
+    };
+    let at = self.at(node);
+    let arg = |i: usize| args[i].as_str();
+    let (c_type, value) = match (name, &first) {
+      ("print" | "println" | "to_string", _) => {
+        let put = self.put_fn(&first, node)?;
+        let out = self.fresh("out");
+        self.line(format!("carf_buffer {} = {{0}};", out));
+        self.line(format!("{}(&{}, {}, false);", put, out, arg(0)));
+        if name == "to_string" {
+          return Ok(self.temp("carf_string", format!("carf_done(&{})", out)));
+        }
+        if name == "println" {
+          self.line(format!("carf_put_cstr(&{}, \"\\n\");", out));
+        }
+        self.line(format!("carf_print(carf_done(&{}));", out));
+        return Ok(String::new());
+      },
+      ("read_line", _) => ("carf_string", "carf_read_line()".to_string()),
+      ("abs", Type::Int) => ("int64_t", format!("carf_abs({}, {})", arg(0), at)),
+      ("abs", _) => ("double", format!("fabs({})", arg(0))),
+      ("min", Type::Int) => ("int64_t", format!("{1} < {0} ? {1} : {0}", arg(0), arg(1))),
+      ("max", Type::Int) => ("int64_t", format!("{1} > {0} ? {1} : {0}", arg(0), arg(1))),
+      ("min" | "max", _) => ("double", format!("carf_{}_float({}, {})", name, arg(0), arg(1))),
+      ("pow", Type::Int) => ("int64_t", format!("carf_pow({}, {}, {})", arg(0), arg(1), at)),
+      ("pow", _) => ("double", format!("pow({}, {})", arg(0), arg(1))),
+      ("sqrt", _) => ("double", format!("sqrt({})", arg(0))),
+      ("floor", _) => ("int64_t", format!("carf_floor({}, {})", arg(0), at)),
+      ("len", Type::String) => ("int64_t", format!("carf_str_len({})", arg(0))),
+      ("len", _) => ("int64_t", format!("{}->len", arg(0))),
+      ("substr", _) => ("carf_string", format!("carf_substr({}, {}, {}, {})", arg(0), arg(1), arg(2), at)),
+      ("split", _) => ("carf_array *", format!("carf_split({}, {}, {})", arg(0), arg(1), at)),
+      ("to_int", Type::Float) => ("int64_t", format!("carf_to_int_float({}, {})", arg(0), at)),
+      ("to_int", Type::Char) => ("int64_t", format!("(int64_t){}", arg(0))),
+      ("to_int", _) => ("int64_t", format!("carf_to_int_string({}, {})", arg(0), at)),
+      ("push", Type::Array(elem, _)) => {
+        let elem = self.c_type(elem, node)?;
+        // The slot is made first, growing the array may move its elements
+        let slot = self.temp("int64_t", format!("carf_push_slot({}, sizeof({}))", arg(0), elem));
+        self.line(format!("CARF_AT({}, {}, {}) = {};", elem, arg(0), slot, arg(1)));
+        return Ok(String::new());
+      },
+      ("pop", Type::Array(elem, _)) => {
+        let elem = self.c_type(elem, node)?;
+        return Ok(self.temp(&elem, format!("CARF_AT({}, {}, carf_pop_slot({}, {}))", elem, arg(0), arg(0), at)));
+      },
+      ("sort", Type::Array(elem, _)) => {
+        let c_type = self.c_type(elem, node)?;
+        let compare = match **elem {
+          Type::Int => "carf_sort_int",
+          Type::Float => "carf_sort_float",
+          Type::Char => "carf_sort_char",
+          Type::String => "carf_sort_string",
+          _ => return Err(unsupported(node, &format!("sorting `{}`", first))),
+        };
+        self.line(format!("carf_sort({}, sizeof({}), {});", arg(0), c_type, compare));
+        return Ok(String::new());
+      },
+      _ => return Err(unsupported(node, &format!("`{}`", name))),
+    };
+    Ok(self.temp(c_type, value))
+  }
+}
+
+// Places
+impl<'g, 'a> Generator<'g, 'a> {
+  fn place(&mut self, node: &'g AstNode<'a>) -> Result<Place, Diagnostic> {
+    match node.get_kind() {
+      AstKind::Identifier => Ok(Place::Var(self.var(node.get_token().get_value()))),
+      AstKind::Expr => self.place(&node[0]),
+      AstKind::Member => {
+        let record = self.expr(&node[0])?;
+        Ok(Place::Field(record, format!("f_{}", node[1].get_token().get_value())))
+      },
+      AstKind::Index => {
+        let base = &node[0];
+        let (place, value) = match base.get_kind() {
+          AstKind::Identifier | AstKind::Index | AstKind::Expr | AstKind::Member => {
+            let place = self.place(base)?;
+            let value = self.read(&place);
+            (Some(place), value)
+          },
+          _ => (None, self.expr(base)?),
+        };
+        let ty = self.type_of(base);
+        let c_type = self.c_type(&ty, base)?;
+        let value = self.temp(&c_type, value);
+        let index = self.expr(&node[1])?;
+        let at = self.at(node);
+        match (ty, place) {
+          (Type::String, Some(place)) => {
+            self.line(format!("carf_str_at({}, {}, {});", value, index, at));
+            Ok(Place::StrChar(Box::new(place), index, at))
+          },
+          (Type::Array(elem, _), _) => {
+            let elem = self.c_type(&elem, node)?;
+            let index = self.temp("int64_t", format!("carf_index({}, {}, {})", value, index, at));
+            Ok(Place::Elem(value, index, elem))
+          },
+          _ => Err(unsupported(node, "this assignment")),
+        }
+      },
+      _ => Err(unsupported(node, "this assignment")),
+    }
+  }
+  fn read(&self, place: &Place) -> String {
+    match place {
+      Place::Var(name) => name.clone(),
+      Place::Elem(array, index, elem) => format!("CARF_AT({}, {}, {})", elem, array, index),
+      Place::Field(record, field) => format!("{}->{}", record, field),
+      Place::StrChar(inner, index, at) => format!("carf_str_at({}, {}, {})", self.read(inner), index, at),
+    }
+  }
+  // Strings are values, so writing a char rebuilds the string held by the inner place
+  fn write(&mut self, place: &Place, value: String) {
+    match place {
+      Place::StrChar(inner, index, at) => {
+        let text = format!("carf_str_set({}, {}, {}, {})", self.read(inner), index, value, at);
+        self.write(inner, text);
+      },
+      _ => {
+        let target = self.read(place);
+        self.line(format!("{} = {};", target, value));
+      },
+    }
+  }
+}
+
+// Helpers for composite types: `put_` writes a value as `print` does, `eq_`
+// compares two like `==` and `copy_` copies one deeply like `[x; n]`
+impl<'g, 'a> Generator<'g, 'a> {
+  fn put_fn(&mut self, ty: &Type, node: &AstNode<'a>) -> Result<String, Diagnostic> {
+    match ty {
+      Type::Int | Type::Float | Type::Char | Type::String | Type::Bool => Ok(format!("carf_put_{}", ty)),
+      _ => Ok(format!("put_{}", self.helpers(ty, node)?)),
+    }
+  }
+  fn eq_fn(&mut self, ty: &Type, node: &AstNode<'a>) -> Result<String, Diagnostic> {
+    match ty {
+      Type::String => Ok("carf_eq_string".to_string()),
+      _ => Ok(format!("eq_{}", self.helpers(ty, node)?)),
+    }
+  }
+  fn copy_fn(&mut self, ty: &Type, node: &AstNode<'a>) -> Result<String, Diagnostic> {
+    match ty {
+      Type::Array(..) | Type::Struct(_) => Ok(format!("copy_{}", self.helpers(ty, node)?)),
+      _ => Ok(String::new()),
+    }
+  }
+  // Element-wise C of `==`, `print` and copying for one type, by its C name
+  fn elem_ops(&mut self, ty: &Type, node: &AstNode<'a>) -> Result<(String, String, String), Diagnostic> {
+    let put = self.put_fn(ty, node)?;
+    let copy = self.copy_fn(ty, node)?;
+    let eq = match ty {
+      Type::Int | Type::Float | Type::Char | Type::Bool => String::new(),
+      _ => self.eq_fn(ty, node)?,
+    };
+    Ok((put, eq, copy))
+  }
+  // Generate the helpers of an array or struct type once, giving its mangled name
+  fn helpers(&mut self, ty: &Type, node: &AstNode<'a>) -> Result<String, Diagnostic> {
+    let name = self.mangle(ty, node)?;
+    if !self.helpers.insert(name.clone()) {
+      return Ok(name);
+    }
+    let c_type = self.c_type(ty, node)?;
+    let eq_of = |eq: &str, a: String, b: String| match eq.is_empty() {
+      true => format!("{} == {}", a, b),
+      false => format!("{}({}, {})", eq, a, b),
+    };
+    let (put, eq, copy) = match ty {
+      Type::Array(elem, _) => {
+        let elem_type = self.c_type(elem, node)?;
+        let (put, eq, copy) = self.elem_ops(elem, node)?;
+        let at = |v: &str| format!("CARF_AT({}, {}, i)", elem_type, v);
+        (
+          format!(
+            "  int64_t i;\n  (void)repr;\n  carf_put_cstr(b, \"[\");\n  for (i = 0; i < v->len; i++) {{\n    if (i) carf_put_cstr(b, \", \");\n    {}(b, {}, true);\n  }}\n  carf_put_cstr(b, \"]\");\n",
+            put, at("v")
+          ),
+          format!(
+            "  int64_t i;\n  if (a == b) return true;\n  if (a->len != b->len) return false;\n  for (i = 0; i < a->len; i++) {{\n    if (!({})) return false;\n  }}\n  return true;\n",
+            eq_of(&eq, at("a"), at("b"))
+          ),
+          format!(
+            "  carf_array *c = carf_array_new(v->len, sizeof({}));\n  int64_t i;\n  for (i = 0; i < v->len; i++) {} = {}({});\n  return c;\n",
+            elem_type, at("c"), copy, at("v")
+          ),
+        )
+      },
+      Type::Struct(name) => {
+        let fields = self.checker.get_struct(name).map(|def| def.fields.clone()).unwrap_or_default();
+        let mut put_fields = String::new();
+        let mut eq_fields = Vec::new();
+        let mut copy_fields = String::new();
+        for (i, (field, field_ty)) in fields.iter().enumerate() {
+          let (put, eq, copy) = self.elem_ops(field_ty, node)?;
+          let sep = if i == 0 { " " } else { ", " };
+          put_fields.push_str(&format!("  carf_put_cstr(b, \"{}{}: \");\n  {}(b, v->f_{}, true);\n", sep, field, put, field));
+          eq_fields.push(eq_of(&eq, format!("a->f_{}", field), format!("b->f_{}", field)));
+          copy_fields.push_str(&format!("  c->f_{} = {}(v->f_{});\n", field, copy, field));
+        }
+        let close = if fields.is_empty() { "}" } else { " }" };
+        eq_fields.insert(0, "true".to_string());
+        (
+          format!("  (void)repr;\n  carf_put_cstr(b, \"{} {{\");\n{}  carf_put_cstr(b, \"{}\");\n", name, put_fields, close),
+          format!("  return a == b || ({});\n", eq_fields.join(" && ")),
+          format!("  {} c = carf_alloc(sizeof(struct s_{}));\n{}  return c;\n", c_type, c_name(name), copy_fields),
+        )
+      },
+      _ => return Err(unsupported(node, &format!("values of type `{}`", ty))),
+    };
+    let heads = [
+      format!("static inline void put_{}(carf_buffer *b, {} v, bool repr)", name, c_type),
+      format!("static inline bool eq_{}({} a, {} b)", name, c_type, c_type),
+      format!("static inline {} copy_{}({} v)", c_type, name, c_type),
+    ];
+    for (head, body) in heads.iter().zip([put, eq, copy]) {
+      self.decls.push(format!("{};", head));
+      self.defs.push(format!("{} {{\n{}}}\n", head, body));
+    }
+    Ok(name)
+  }
+}
+
+// Types, names and emitted lines
+impl<'g, 'a> Generator<'g, 'a> {
+  fn c_type(&self, ty: &Type, node: &AstNode<'a>) -> Result<String, Diagnostic> {
+    match ty {
+      Type::Int => Ok("int64_t".to_string()),
+      Type::Float => Ok("double".to_string()),
+      Type::Char => Ok("uint32_t".to_string()),
+      Type::Bool => Ok("bool".to_string()),
+      Type::String => Ok("carf_string".to_string()),
+      Type::Array(..) => Ok("carf_array *".to_string()),
+      Type::Struct(name) => Ok(format!("struct s_{} *", c_name(name))),
+      Type::Enum(_) => Err(unsupported(node, "enums")),
+      Type::Fun(..) => Err(unsupported(node, "function values")),
+      Type::Var(_) | Type::Error => Err(Diagnostic::error(Span::of(node), "type annotations needed to compile this to C".to_string())),
+      _ => Err(unsupported(node, &format!("values of type `{}`", ty))),
+    }
+  }
+  // Identifier naming the type in helper names, e.g. `arr_arr_int` for `[[int]]`
+  fn mangle(&self, ty: &Type, node: &AstNode<'a>) -> Result<String, Diagnostic> {
+    match ty {
+      Type::Array(elem, _) => Ok(format!("arr_{}", self.mangle(elem, node)?)),
+      Type::Struct(name) => Ok(format!("s_{}", c_name(name))),
+      Type::Int | Type::Float | Type::Char | Type::String | Type::Bool => Ok(ty.to_string()),
+      _ => self.c_type(ty, node),
+    }
+  }
+  fn elem_type(&self, node: &AstNode<'a>) -> Result<String, Diagnostic> {
+    match self.type_of(node) {
+      Type::Array(elem, _) => self.c_type(&elem, node),
+      ty => self.c_type(&ty, node),
+    }
+  }
+  fn type_of(&self, node: &AstNode<'a>) -> Type {
+    match node.get_kind() {
+      AstKind::Literal => match node.get_token().get_kind() {
+        TokenKind::Int => Type::Int,
+        TokenKind::Float => Type::Float,
+        TokenKind::Char => Type::Char,
+        TokenKind::String => Type::String,
+        _ => Type::Bool,
+      },
+      _ => self.checker.get_type_at(node.get_token().get_pos()).cloned().unwrap_or(Type::Error),
+    }
+  }
+  fn binding_type(&self, name: &AstNode<'a>) -> Type {
+    self.checker.get_binding_type(name.get_token().get_pos()).cloned().unwrap_or(Type::Error)
+  }
+  fn resolve(&self, ty: &AstNode<'a>) -> Type {
+    Type::from_name(ty.get_token().get_value()).unwrap_or_else(|| Type::Struct(ty.get_token().get_value().to_string()))
+  }
+  // `"file:line:col"`, where a runtime error at the node is reported
+  fn at(&self, node: &AstNode) -> String {
+    let pos = Span::of(node).start;
+    let file = self.files.lookup(pos);
+    let (line, col) = file.line_col(pos);
+    c_string(&format!("{}:{}:{}", file.get_path().display(), line, col))
+  }
+  fn declare(&mut self, name: &'a str) -> String {
+    let local = self.fresh(name);
+    self.scopes.last_mut().unwrap().push((name, local.clone()));
+    local
+  }
+  fn is_local(&self, name: &str) -> bool {
+    self.scopes.iter().flatten().any(|(local, _)| *local == name)
+  }
+  // C name of a variable, the innermost local of that name or the global
+  fn var(&self, name: &str) -> String {
+    match self.scopes.iter().flatten().rev().find(|(local, _)| *local == name) {
+      Some((_, local)) => local.clone(),
+      None => format!("g_{}", c_name(name)),
+    }
+  }
+  fn fresh(&mut self, name: &str) -> String {
+    self.next += 1;
+    format!("{}_{}", name, self.next)
+  }
+  fn temp(&mut self, c_type: &str, value: String) -> String {
+    let temp = self.fresh("t");
+    let space = if c_type.ends_with('*') { "" } else { " " };
+    self.line(format!("{}{}{} = {};", c_type, space, temp, value));
+    temp
+  }
+  fn line(&mut self, line: String) {
+    self.lines.push(format!("{}{}", "  ".repeat(self.indent), line));
+  }
+  fn take_lines(&mut self) -> String {
+    std::mem::take(&mut self.lines).into_iter().map(|line| line + "\n").collect()
+  }
+}
+
+fn unsupported(node: &AstNode, what: &str) -> Diagnostic {
+  super::unsupported(node, what, "C")
+}
+
+fn literal_atom(node: &AstNode) -> Result<String, Diagnostic> {
+  let text = node.get_token().get_value();
+  let atom = match node.get_token().get_kind() {
+    TokenKind::Int => literal::parse_int(text).map(|n| match n {
+      i64::MIN => "INT64_MIN".to_string(),
+      n => format!("INT64_C({})", n),
+    }),
+    TokenKind::Float => literal::parse_float(text).map(|x| match x {
+      x if x.is_nan() => "NAN".to_string(),
+      x if x.is_infinite() => format!("{}INFINITY", if x < 0.0 { "-" } else { "" }),
+      x => format!("{:?}", x),
+    }),
+    TokenKind::Char => literal::parse_char(text).map(|c| format!("UINT32_C({})", c as u32)),
+    TokenKind::String => literal::parse_string(text).map(|s| format!("carf_str({}, {})", c_string(&s), s.len())),
+    TokenKind::True => Some("true".to_string()),
+    TokenKind::False => Some("false".to_string()),
+    _ => None,
+  };
+  atom.ok_or_else(|| Diagnostic::error(Span::of(node), format!("invalid literal `{}`", text)))
+}
+
+// Item name as a C identifier. A private item renamed `a::f` becomes `1a1f`,
+// each segment after its length, which no name starting with a letter or `_` can be.
+fn c_name(name: &str) -> String {
+  match name.contains("::") {
+    true => name.split("::").map(|segment| format!("{}{}", segment.len(), segment)).collect(),
+    false => name.to_string(),
+  }
+}
+
+// C string literal of the bytes, with octal escapes for anything but printable ASCII
+fn c_string(text: &str) -> String {
+  let mut out = String::from("\"");
+  for byte in text.bytes() {
+    match byte {
+      b'"' | b'\\' | b'?' => {
+        out.push('\\');
+        out.push(byte as char);
+      },
+      b' '..=b'~' => out.push(byte as char),
+      _ => out.push_str(&format!("\\{:03o}", byte)),
+    }
+  }
+  out.push('"');
+  out
+}
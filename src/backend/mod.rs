@@ -0,0 +1,10 @@
+pub mod c;
+pub mod wat;
+
+use crate::ast::AstNode;
+use crate::diagnostic::{Diagnostic, Span};
+
+// Error for a construct a backend has no lowering for, e.g. "lambdas cannot be compiled to C"
+fn unsupported(node: &AstNode, what: &str, target: &str) -> Diagnostic {
+  Diagnostic::error(Span::of(node), format!("{} cannot be compiled to {}", what, target))
+}
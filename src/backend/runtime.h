@@ -0,0 +1,511 @@
+/* Runtime of carf programs compiled to C: values, checked arithmetic and the
+   prelude. Memory is never freed, a program keeps what it allocates until it
+   exits. Functions are `static inline` so the ones a program does not use
+   raise no warning. */
+#include <math.h>
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+/* Immutable UTF-8 text, `len` in bytes */
+typedef struct {
+  const char *ptr;
+  int64_t len;
+} carf_string;
+
+/* Shared by reference like carf arrays, elements of one C type in `data` */
+typedef struct {
+  int64_t len, cap;
+  char *data;
+} carf_array;
+
+/* Text being built */
+typedef struct {
+  char *ptr;
+  int64_t len, cap;
+} carf_buffer;
+
+#define CARF_AT(T, a, i) (((T *)(a)->data)[i])
+
+/* Print a runtime error at `at` (`file:line:col`) and exit with status 1 */
+static inline void carf_fail(const char *at, const char *format, ...) {
+  va_list args;
+  fflush(stdout);
+  fprintf(stderr, "%s: error: ", at);
+  va_start(args, format);
+  vfprintf(stderr, format, args);
+  va_end(args);
+  fputc('\n', stderr);
+  exit(1);
+}
+
+static inline void *carf_alloc(size_t size) {
+  void *p = calloc(1, size ? size : 1);
+  if (!p) {
+    fputs("error: out of memory\n", stderr);
+    exit(1);
+  }
+  return p;
+}
+
+/* Integers, checked like the evaluator does */
+
+static inline void carf_overflow(const char *at) {
+  carf_fail(at, "arithmetic overflow");
+}
+
+static inline int64_t carf_add(int64_t a, int64_t b, const char *at) {
+  if ((b > 0 && a > INT64_MAX - b) || (b < 0 && a < INT64_MIN - b)) carf_overflow(at);
+  return a + b;
+}
+
+static inline int64_t carf_sub(int64_t a, int64_t b, const char *at) {
+  if ((b < 0 && a > INT64_MAX + b) || (b > 0 && a < INT64_MIN + b)) carf_overflow(at);
+  return a - b;
+}
+
+static inline int64_t carf_mul(int64_t a, int64_t b, const char *at) {
+  if (a > 0 ? (b > 0 ? a > INT64_MAX / b : b < INT64_MIN / a)
+            : (b > 0 ? a < INT64_MIN / b : a != 0 && b < INT64_MAX / a)) carf_overflow(at);
+  return a * b;
+}
+
+static inline int64_t carf_div(int64_t a, int64_t b, const char *at) {
+  if (b == 0) carf_fail(at, "division by zero");
+  if (a == INT64_MIN && b == -1) carf_overflow(at);
+  return a / b;
+}
+
+static inline int64_t carf_rem(int64_t a, int64_t b, const char *at) {
+  if (b == 0) carf_fail(at, "remainder by zero");
+  if (a == INT64_MIN && b == -1) carf_overflow(at);
+  return a % b;
+}
+
+static inline int64_t carf_neg(int64_t a, const char *at) {
+  if (a == INT64_MIN) carf_overflow(at);
+  return -a;
+}
+
+static inline int64_t carf_shl(int64_t a, int64_t b, const char *at) {
+  if (b < 0 || b >= 64) carf_fail(at, "shift by %lld bits overflows a 64-bit `int`", (long long)b);
+  return (int64_t)((uint64_t)a << b);
+}
+
+/* Arithmetic shift, whatever the compiler does with negative numbers */
+static inline int64_t carf_shr(int64_t a, int64_t b, const char *at) {
+  if (b < 0 || b >= 64) carf_fail(at, "shift by %lld bits overflows a 64-bit `int`", (long long)b);
+  return a < 0 ? ~(~a >> b) : a >> b;
+}
+
+static inline int64_t carf_abs(int64_t a, const char *at) {
+  return a < 0 ? carf_neg(a, at) : a;
+}
+
+static inline int64_t carf_pow(int64_t base, int64_t exp, const char *at) {
+  int64_t result = 1;
+  if (exp < 0) carf_fail(at, "negative exponent %lld in `pow`", (long long)exp);
+  while (exp > 0) {
+    if (exp & 1) result = carf_mul(result, base, at);
+    exp >>= 1;
+    if (exp > 0) base = carf_mul(base, base, at);
+  }
+  return result;
+}
+
+/* Floats in the order `sort`, `min` and `max` use: NaN after everything */
+static inline int carf_cmp_float(double a, double b) {
+  int64_t x, y;
+  memcpy(&x, &a, sizeof x);
+  memcpy(&y, &b, sizeof y);
+  x ^= (int64_t)((uint64_t)(x >> 63) >> 1);
+  y ^= (int64_t)((uint64_t)(y >> 63) >> 1);
+  return (x > y) - (x < y);
+}
+
+static inline int64_t carf_float_to_int(double x, const char *at);
+
+/* Text */
+
+static inline void carf_put(carf_buffer *b, const char *p, int64_t len) {
+  if (b->len + len > b->cap) {
+    int64_t cap = b->cap ? b->cap : 16;
+    char *grown;
+    while (cap < b->len + len) cap *= 2;
+    grown = carf_alloc((size_t)cap);
+    if (b->len) memcpy(grown, b->ptr, (size_t)b->len);
+    b->ptr = grown;
+    b->cap = cap;
+  }
+  if (len) memcpy(b->ptr + b->len, p, (size_t)len);
+  b->len += len;
+}
+
+static inline void carf_put_cstr(carf_buffer *b, const char *s) {
+  carf_put(b, s, (int64_t)strlen(s));
+}
+
+static inline carf_string carf_done(carf_buffer *b) {
+  carf_string s;
+  s.ptr = b->ptr;
+  s.len = b->len;
+  return s;
+}
+
+static inline carf_string carf_str(const char *p, int64_t len) {
+  carf_string s;
+  s.ptr = p;
+  s.len = len;
+  return s;
+}
+
+static inline void carf_put_utf8(carf_buffer *b, uint32_t c) {
+  char out[4];
+  int n;
+  if (c < 0x80) {
+    out[0] = (char)c;
+    n = 1;
+  } else if (c < 0x800) {
+    out[0] = (char)(0xC0 | (c >> 6));
+    out[1] = (char)(0x80 | (c & 0x3F));
+    n = 2;
+  } else if (c < 0x10000) {
+    out[0] = (char)(0xE0 | (c >> 12));
+    out[1] = (char)(0x80 | ((c >> 6) & 0x3F));
+    out[2] = (char)(0x80 | (c & 0x3F));
+    n = 3;
+  } else {
+    out[0] = (char)(0xF0 | (c >> 18));
+    out[1] = (char)(0x80 | ((c >> 12) & 0x3F));
+    out[2] = (char)(0x80 | ((c >> 6) & 0x3F));
+    out[3] = (char)(0x80 | (c & 0x3F));
+    n = 4;
+  }
+  carf_put(b, out, n);
+}
+
+/* Code point at byte `*at` of valid UTF-8, moving `*at` past it */
+static inline uint32_t carf_decode(carf_string s, int64_t *at) {
+  const unsigned char *p = (const unsigned char *)s.ptr + *at;
+  int n = p[0] < 0x80 ? 1 : p[0] < 0xE0 ? 2 : p[0] < 0xF0 ? 3 : 4;
+  uint32_t c = n == 1 ? p[0] : n == 2 ? p[0] & 0x1F : n == 3 ? p[0] & 0x0F : p[0] & 0x07;
+  int i;
+  for (i = 1; i < n; i++) c = (c << 6) | (p[i] & 0x3F);
+  *at += n;
+  return c;
+}
+
+static inline int64_t carf_str_len(carf_string s) {
+  int64_t n = 0, i;
+  for (i = 0; i < s.len; i++) n += ((unsigned char)s.ptr[i] & 0xC0) != 0x80;
+  return n;
+}
+
+/* Byte offset of char `index`, which must be in bounds */
+static inline int64_t carf_offset(carf_string s, int64_t index, const char *at) {
+  int64_t len = carf_str_len(s), offset = 0;
+  if (index < 0 || index >= len) carf_fail(at, "index %lld out of bounds for length %lld", (long long)index, (long long)len);
+  while (index-- > 0) carf_decode(s, &offset);
+  return offset;
+}
+
+static inline uint32_t carf_str_at(carf_string s, int64_t index, const char *at) {
+  int64_t offset = carf_offset(s, index, at);
+  return carf_decode(s, &offset);
+}
+
+/* The string with char `index` replaced */
+static inline carf_string carf_str_set(carf_string s, int64_t index, uint32_t c, const char *at) {
+  carf_buffer b = {0};
+  int64_t offset = carf_offset(s, index, at), next = offset;
+  carf_decode(s, &next);
+  carf_put(&b, s.ptr, offset);
+  carf_put_utf8(&b, c);
+  carf_put(&b, s.ptr + next, s.len - next);
+  return carf_done(&b);
+}
+
+static inline carf_string carf_concat(carf_string a, carf_string b) {
+  carf_buffer out = {0};
+  carf_put(&out, a.ptr, a.len);
+  carf_put(&out, b.ptr, b.len);
+  return carf_done(&out);
+}
+
+/* Byte order, the one of Rust strings */
+static inline int carf_cmp_string(carf_string a, carf_string b) {
+  int64_t n = a.len < b.len ? a.len : b.len;
+  int c = n ? memcmp(a.ptr, b.ptr, (size_t)n) : 0;
+  if (c) return c < 0 ? -1 : 1;
+  return (a.len > b.len) - (a.len < b.len);
+}
+
+static inline bool carf_eq_string(carf_string a, carf_string b) {
+  return a.len == b.len && (a.len == 0 || memcmp(a.ptr, b.ptr, (size_t)a.len) == 0);
+}
+
+static inline void carf_put_int(carf_buffer *b, int64_t n, bool repr) {
+  char text[24];
+  (void)repr;
+  snprintf(text, sizeof text, "%lld", (long long)n);
+  carf_put_cstr(b, text);
+}
+
+/* Shortest digits that read back as the float, written the way Rust's `{:?}`
+   writes them: `1.0`, `0.25`, `1e16`, `1.5e-7` */
+static inline void carf_put_float(carf_buffer *b, double x, bool repr) {
+  char text[40], digits[20];
+  int precision, n = 0, exp, i;
+  const char *c;
+  (void)repr;
+  if (x != x) {
+    carf_put_cstr(b, "NaN");
+    return;
+  }
+  if (signbit(x)) {
+    carf_put_cstr(b, "-");
+    x = -x;
+  }
+  if (isinf(x)) {
+    carf_put_cstr(b, "inf");
+    return;
+  }
+  if (x == 0) {
+    carf_put_cstr(b, "0.0");
+    return;
+  }
+  for (precision = 0; precision < 17; precision++) {
+    snprintf(text, sizeof text, "%.*e", precision, x);
+    if (strtod(text, NULL) == x) break;
+  }
+  for (c = text; *c != 'e'; c++) {
+    if (*c != '.') digits[n++] = *c;
+  }
+  exp = atoi(c + 1);
+  while (n > 1 && digits[n - 1] == '0') n--;
+  if (x < 1e-4 || x >= 1e16) {
+    carf_put(b, digits, 1);
+    if (n > 1) {
+      carf_put_cstr(b, ".");
+      carf_put(b, digits + 1, n - 1);
+    }
+    snprintf(text, sizeof text, "e%d", exp);
+    carf_put_cstr(b, text);
+  } else if (exp < 0) {
+    carf_put_cstr(b, "0.");
+    for (i = -1; i > exp; i--) carf_put_cstr(b, "0");
+    carf_put(b, digits, n);
+  } else {
+    for (i = 0; i <= exp; i++) carf_put(b, i < n ? digits + i : "0", 1);
+    carf_put_cstr(b, ".");
+    if (n > exp + 1) carf_put(b, digits + exp + 1, n - exp - 1);
+    else carf_put_cstr(b, "0");
+  }
+}
+
+static inline void carf_put_bool(carf_buffer *b, bool v, bool repr) {
+  (void)repr;
+  carf_put_cstr(b, v ? "true" : "false");
+}
+
+/* The escapes a literal would use */
+static inline void carf_put_escaped(carf_buffer *b, uint32_t c, uint32_t quote) {
+  switch (c) {
+    case '\n': carf_put_cstr(b, "\\n"); break;
+    case '\t': carf_put_cstr(b, "\\t"); break;
+    case '\r': carf_put_cstr(b, "\\r"); break;
+    case '\0': carf_put_cstr(b, "\\0"); break;
+    case '\\': carf_put_cstr(b, "\\\\"); break;
+    default:
+      if (c == quote) carf_put_cstr(b, "\\");
+      carf_put_utf8(b, c);
+  }
+}
+
+/* In an array or struct, chars and strings are quoted */
+static inline void carf_put_char(carf_buffer *b, uint32_t c, bool repr) {
+  if (!repr) {
+    carf_put_utf8(b, c);
+    return;
+  }
+  carf_put_cstr(b, "'");
+  carf_put_escaped(b, c, '\'');
+  carf_put_cstr(b, "'");
+}
+
+static inline void carf_put_string(carf_buffer *b, carf_string s, bool repr) {
+  int64_t at = 0;
+  if (!repr) {
+    carf_put(b, s.ptr, s.len);
+    return;
+  }
+  carf_put_cstr(b, "\"");
+  while (at < s.len) carf_put_escaped(b, carf_decode(s, &at), '"');
+  carf_put_cstr(b, "\"");
+}
+
+/* Arrays */
+
+static inline carf_array *carf_array_new(int64_t len, size_t size) {
+  carf_array *a = carf_alloc(sizeof(carf_array));
+  a->len = a->cap = len;
+  a->data = carf_alloc((size_t)len * size);
+  return a;
+}
+
+static inline int64_t carf_repeat_count(int64_t n, const char *at) {
+  if (n < 0) carf_fail(at, "negative array repeat count %lld", (long long)n);
+  return n;
+}
+
+static inline int64_t carf_index(carf_array *a, int64_t index, const char *at) {
+  if (index < 0 || index >= a->len) carf_fail(at, "index %lld out of bounds for length %lld", (long long)index, (long long)a->len);
+  return index;
+}
+
+/* Index of a new last element, growing the array */
+static inline int64_t carf_push_slot(carf_array *a, size_t size) {
+  if (a->len == a->cap) {
+    int64_t cap = a->cap ? a->cap * 2 : 4;
+    char *grown = carf_alloc((size_t)cap * size);
+    if (a->len) memcpy(grown, a->data, (size_t)a->len * size);
+    a->data = grown;
+    a->cap = cap;
+  }
+  return a->len++;
+}
+
+/* Index of the last element, dropped from the array */
+static inline int64_t carf_pop_slot(carf_array *a, const char *at) {
+  if (a->len == 0) carf_fail(at, "cannot pop from an empty array");
+  return --a->len;
+}
+
+static inline int carf_sort_int(const void *a, const void *b) {
+  int64_t x = *(const int64_t *)a, y = *(const int64_t *)b;
+  return (x > y) - (x < y);
+}
+
+static inline int carf_sort_float(const void *a, const void *b) {
+  return carf_cmp_float(*(const double *)a, *(const double *)b);
+}
+
+static inline int carf_sort_char(const void *a, const void *b) {
+  uint32_t x = *(const uint32_t *)a, y = *(const uint32_t *)b;
+  return (x > y) - (x < y);
+}
+
+static inline int carf_sort_string(const void *a, const void *b) {
+  return carf_cmp_string(*(const carf_string *)a, *(const carf_string *)b);
+}
+
+static inline void carf_sort(carf_array *a, size_t size, int (*compare)(const void *, const void *)) {
+  if (a->len > 1) qsort(a->data, (size_t)a->len, size, compare);
+}
+
+/* Prelude */
+
+static inline void carf_print(carf_string s) {
+  fwrite(s.ptr, 1, (size_t)s.len, stdout);
+}
+
+/* The next line without its line break, empty once the input has ended */
+static inline carf_string carf_read_line(void) {
+  carf_buffer b = {0};
+  int c;
+  fflush(stdout);
+  while ((c = getchar()) != EOF) {
+    char byte = (char)c;
+    carf_put(&b, &byte, 1);
+    if (c == '\n') break;
+  }
+  if (b.len && b.ptr[b.len - 1] == '\n') b.len--;
+  if (b.len && b.ptr[b.len - 1] == '\r') b.len--;
+  return carf_done(&b);
+}
+
+static inline double carf_min_float(double a, double b) {
+  return carf_cmp_float(b, a) < 0 ? b : a;
+}
+
+static inline double carf_max_float(double a, double b) {
+  return carf_cmp_float(b, a) > 0 ? b : a;
+}
+
+static inline int64_t carf_float_to_int(double x, const char *at) {
+  if (!(x >= -9223372036854775808.0 && x < 9223372036854775808.0)) {
+    carf_buffer b = {0};
+    carf_put_float(&b, x, false);
+    carf_put(&b, "", 1);
+    carf_fail(at, "%s does not fit in an int", b.ptr);
+  }
+  return (int64_t)x;
+}
+
+static inline int64_t carf_floor(double x, const char *at) {
+  return carf_float_to_int(floor(x), at);
+}
+
+static inline int64_t carf_to_int_float(double x, const char *at) {
+  return carf_float_to_int(x < 0 ? ceil(x) : floor(x), at);
+}
+
+/* Decimal digits with an optional sign, around which whitespace is ignored */
+static inline int64_t carf_to_int_string(carf_string s, const char *at) {
+  int64_t start = 0, end = s.len, i, n = 0;
+  bool negative = false, ok;
+  while (start < end && strchr(" \t\n\r\f\v", s.ptr[start]) && s.ptr[start]) start++;
+  while (end > start && strchr(" \t\n\r\f\v", s.ptr[end - 1]) && s.ptr[end - 1]) end--;
+  i = start;
+  if (i < end && (s.ptr[i] == '+' || s.ptr[i] == '-')) negative = s.ptr[i++] == '-';
+  ok = i < end;
+  for (; ok && i < end; i++) {
+    int digit = s.ptr[i] - '0';
+    if (digit < 0 || digit > 9) ok = false;
+    else if (negative ? n < (INT64_MIN + digit) / 10 : n > (INT64_MAX - digit) / 10) ok = false;
+    else n = negative ? n * 10 - digit : n * 10 + digit;
+  }
+  if (!ok) {
+    carf_buffer b = {0};
+    carf_put_string(&b, s, true);
+    carf_put(&b, "", 1);
+    carf_fail(at, "cannot parse %s as an int", b.ptr);
+  }
+  return n;
+}
+
+static inline carf_string carf_substr(carf_string s, int64_t start, int64_t count, const char *at) {
+  int64_t len = carf_str_len(s), from = 0, to, i;
+  if (start < 0 || count < 0 || start > len - count) {
+    carf_fail(at, "substring of %lld chars at %lld out of bounds for length %lld", (long long)count, (long long)start, (long long)len);
+  }
+  for (i = 0; i < start; i++) carf_decode(s, &from);
+  to = from;
+  for (i = 0; i < count; i++) carf_decode(s, &to);
+  return carf_str(s.ptr + from, to - from);
+}
+
+static inline void carf_push_string(carf_array *a, carf_string s) {
+  int64_t slot = carf_push_slot(a, sizeof(carf_string));
+  CARF_AT(carf_string, a, slot) = s;
+}
+
+static inline carf_array *carf_split(carf_string s, carf_string sep, const char *at) {
+  carf_array *parts = carf_array_new(0, sizeof(carf_string));
+  int64_t start = 0, i = 0;
+  if (sep.len == 0) carf_fail(at, "cannot split on an empty separator");
+  while (i + sep.len <= s.len) {
+    if (memcmp(s.ptr + i, sep.ptr, (size_t)sep.len) == 0) {
+      carf_push_string(parts, carf_str(s.ptr + start, i - start));
+      i += sep.len;
+      start = i;
+    } else {
+      i++;
+    }
+  }
+  carf_push_string(parts, carf_str(s.ptr + start, s.len - start));
+  return parts;
+}
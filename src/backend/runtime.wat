@@ -0,0 +1,642 @@
+  ;; Runtime of carf programs compiled to WebAssembly, the start of the module
+  ;; the program is added to. The host provides output, runtime errors and
+  ;; float formatting:
+  ;;   write(ptr, len)              print `len` bytes of UTF-8 at `ptr`
+  ;;   fail(ptr, len)               report the runtime error at `ptr`, then trap
+  ;;   format_float(x, ptr) -> len  write the shortest text that reads back as
+  ;;                                `x`, like Rust's `{:?}`, at most 32 bytes
+  ;;
+  ;; Values: `int` is i64, `float` f64, `char` and `bool` i32. Strings, arrays
+  ;; and structs are i32 addresses in linear memory:
+  ;;   string  [len: i32][UTF-8 bytes]
+  ;;   array   [len: i32][cap: i32][data: i32], 8-byte slots at `data`
+  ;;   struct  one 8-byte slot per field, in declaration order
+  ;; Text being built is a buffer laid out like an array of bytes. Memory is
+  ;; taken from `$carf_heap` and never freed. Address 0 holds an empty string.
+  ;; `(carf.str "...")` is replaced by the address of that string.
+  (import "carf" "write" (func $carf_write (param i32 i32)))
+  (import "carf" "fail" (func $carf_host_fail (param i32 i32)))
+  (import "carf" "format_float" (func $carf_format_float (param f64 i32) (result i32)))
+
+  (func $carf_out_of_memory
+    (local $s i32)
+    (local.set $s (carf.str "error: out of memory"))
+    (call $carf_host_fail (i32.add (local.get $s) (i32.const 4)) (i32.load (local.get $s)))
+    (unreachable))
+
+  ;; Zeroed memory, 8-byte aligned
+  (func $carf_alloc (param $size i32) (result i32)
+    (local $ptr i32)
+    (local $end i64)
+    (local $grow i32)
+    (local.set $ptr (global.get $carf_heap))
+    (local.set $end (i64.and (i64.add (i64.add (i64.extend_i32_u (local.get $ptr)) (i64.extend_i32_u (local.get $size))) (i64.const 7)) (i64.const -8)))
+    (if (i64.gt_u (local.get $end) (i64.const 0xFFFF0000)) (then (call $carf_out_of_memory)))
+    (local.set $grow (i32.sub (i32.wrap_i64 (i64.shr_u (i64.add (local.get $end) (i64.const 0xFFFF)) (i64.const 16))) (memory.size)))
+    (if (i32.gt_s (local.get $grow) (i32.const 0))
+      (then
+        (if (i32.eq (memory.grow (local.get $grow)) (i32.const -1)) (then (call $carf_out_of_memory)))))
+    (global.set $carf_heap (i32.wrap_i64 (local.get $end)))
+    (local.get $ptr))
+
+  ;; Text
+
+  (func $carf_buffer (result i32)
+    (call $carf_alloc (i32.const 12)))
+
+  ;; Room for `extra` more elements of `size` bytes in a buffer or array
+  (func $carf_reserve (param $a i32) (param $extra i32) (param $size i32)
+    (local $cap i32)
+    (local $data i32)
+    (if (i32.le_u (i32.add (i32.load (local.get $a)) (local.get $extra)) (i32.load offset=4 (local.get $a))) (then (return)))
+    (local.set $cap (select (i32.shl (i32.load offset=4 (local.get $a)) (i32.const 1)) (i32.const 8) (i32.load offset=4 (local.get $a))))
+    (block $done
+      (loop $grow
+        (br_if $done (i32.ge_u (local.get $cap) (i32.add (i32.load (local.get $a)) (local.get $extra))))
+        (local.set $cap (i32.shl (local.get $cap) (i32.const 1)))
+        (br $grow)))
+    (if (i32.gt_u (local.get $cap) (i32.div_u (i32.const 0x7FFFFFFF) (local.get $size))) (then (call $carf_out_of_memory)))
+    (local.set $data (call $carf_alloc (i32.mul (local.get $cap) (local.get $size))))
+    (memory.copy (local.get $data) (i32.load offset=8 (local.get $a)) (i32.mul (i32.load (local.get $a)) (local.get $size)))
+    (i32.store offset=4 (local.get $a) (local.get $cap))
+    (i32.store offset=8 (local.get $a) (local.get $data)))
+
+  (func $carf_put_bytes (param $b i32) (param $ptr i32) (param $len i32)
+    (call $carf_reserve (local.get $b) (local.get $len) (i32.const 1))
+    (memory.copy (i32.add (i32.load offset=8 (local.get $b)) (i32.load (local.get $b))) (local.get $ptr) (local.get $len))
+    (i32.store (local.get $b) (i32.add (i32.load (local.get $b)) (local.get $len))))
+
+  (func $carf_put_str (param $b i32) (param $s i32)
+    (call $carf_put_bytes (local.get $b) (i32.add (local.get $s) (i32.const 4)) (i32.load (local.get $s))))
+
+  ;; String of `len` bytes copied from `ptr`
+  (func $carf_str (param $ptr i32) (param $len i32) (result i32)
+    (local $s i32)
+    (local.set $s (call $carf_alloc (i32.add (local.get $len) (i32.const 4))))
+    (i32.store (local.get $s) (local.get $len))
+    (memory.copy (i32.add (local.get $s) (i32.const 4)) (local.get $ptr) (local.get $len))
+    (local.get $s))
+
+  (func $carf_done (param $b i32) (result i32)
+    (call $carf_str (i32.load offset=8 (local.get $b)) (i32.load (local.get $b))))
+
+  (func $carf_print (param $b i32)
+    (call $carf_write (i32.load offset=8 (local.get $b)) (i32.load (local.get $b))))
+
+  (func $carf_put_utf8 (param $b i32) (param $c i32)
+    (local $n i32)
+    (local $at i32)
+    (local $i i32)
+    (local.set $n
+      (select (i32.const 1)
+        (select (i32.const 2) (select (i32.const 3) (i32.const 4) (i32.lt_u (local.get $c) (i32.const 0x10000))) (i32.lt_u (local.get $c) (i32.const 0x800)))
+        (i32.lt_u (local.get $c) (i32.const 0x80))))
+    (call $carf_reserve (local.get $b) (local.get $n) (i32.const 1))
+    (local.set $at (i32.add (i32.load offset=8 (local.get $b)) (i32.load (local.get $b))))
+    (i32.store (local.get $b) (i32.add (i32.load (local.get $b)) (local.get $n)))
+    ;; Continuation bytes, last first, then the lead byte
+    (local.set $i (i32.sub (local.get $n) (i32.const 1)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $i)))
+        (i32.store8 (i32.add (local.get $at) (local.get $i)) (i32.or (i32.const 0x80) (i32.and (local.get $c) (i32.const 0x3F))))
+        (local.set $c (i32.shr_u (local.get $c) (i32.const 6)))
+        (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.store8 (local.get $at)
+      (i32.or (local.get $c)
+        (select (i32.const 0) (i32.and (i32.shl (i32.const 0xF0) (i32.sub (i32.const 4) (local.get $n))) (i32.const 0xF0)) (i32.eq (local.get $n) (i32.const 1))))))
+
+  ;; Bytes taken by the UTF-8 char starting at `p`
+  (func $carf_width (param $p i32) (result i32)
+    (local $c i32)
+    (local.set $c (i32.load8_u (local.get $p)))
+    (select (i32.const 1)
+      (select (i32.const 2) (select (i32.const 3) (i32.const 4) (i32.lt_u (local.get $c) (i32.const 0xF0))) (i32.lt_u (local.get $c) (i32.const 0xE0)))
+      (i32.lt_u (local.get $c) (i32.const 0x80))))
+
+  ;; Code point of the valid UTF-8 char starting at `p`
+  (func $carf_decode (param $p i32) (result i32)
+    (local $n i32)
+    (local $c i32)
+    (local $i i32)
+    (local.set $n (call $carf_width (local.get $p)))
+    (local.set $c (i32.load8_u (local.get $p)))
+    (if (i32.eq (local.get $n) (i32.const 1)) (then (return (local.get $c))))
+    (local.set $c (i32.and (local.get $c) (i32.shr_u (i32.const 0xFF) (i32.add (local.get $n) (i32.const 1)))))
+    (local.set $i (i32.const 1))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+        (local.set $c (i32.or (i32.shl (local.get $c) (i32.const 6)) (i32.and (i32.load8_u (i32.add (local.get $p) (local.get $i))) (i32.const 0x3F))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (local.get $c))
+
+  (func $carf_str_len (param $s i32) (result i64)
+    (local $p i32)
+    (local $end i32)
+    (local $n i64)
+    (local.set $p (i32.add (local.get $s) (i32.const 4)))
+    (local.set $end (i32.add (local.get $p) (i32.load (local.get $s))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $p) (local.get $end)))
+        (if (i32.ne (i32.and (i32.load8_u (local.get $p)) (i32.const 0xC0)) (i32.const 0x80))
+          (then (local.set $n (i64.add (local.get $n) (i64.const 1)))))
+        (local.set $p (i32.add (local.get $p) (i32.const 1)))
+        (br $next)))
+    (local.get $n))
+
+  ;; Address of char `index`, which must be in bounds
+  (func $carf_offset (param $s i32) (param $index i64) (param $at i32) (result i32)
+    (local $p i32)
+    (local.set $index (call $carf_index_in (local.get $index) (call $carf_str_len (local.get $s)) (local.get $at)))
+    (local.set $p (i32.add (local.get $s) (i32.const 4)))
+    (block $done
+      (loop $next
+        (br_if $done (i64.eqz (local.get $index)))
+        (local.set $p (i32.add (local.get $p) (call $carf_width (local.get $p))))
+        (local.set $index (i64.sub (local.get $index) (i64.const 1)))
+        (br $next)))
+    (local.get $p))
+
+  (func $carf_str_at (param $s i32) (param $index i64) (param $at i32) (result i32)
+    (call $carf_decode (call $carf_offset (local.get $s) (local.get $index) (local.get $at))))
+
+  ;; The string with char `index` replaced
+  (func $carf_str_set (param $s i32) (param $index i64) (param $c i32) (param $at i32) (result i32)
+    (local $b i32)
+    (local $p i32)
+    (local $next i32)
+    (local.set $p (call $carf_offset (local.get $s) (local.get $index) (local.get $at)))
+    (local.set $next (i32.add (local.get $p) (call $carf_width (local.get $p))))
+    (local.set $b (call $carf_buffer))
+    (call $carf_put_bytes (local.get $b) (i32.add (local.get $s) (i32.const 4)) (i32.sub (local.get $p) (i32.add (local.get $s) (i32.const 4))))
+    (call $carf_put_utf8 (local.get $b) (local.get $c))
+    (call $carf_put_bytes (local.get $b) (local.get $next) (i32.sub (i32.add (i32.add (local.get $s) (i32.const 4)) (i32.load (local.get $s))) (local.get $next)))
+    (call $carf_done (local.get $b)))
+
+  (func $carf_concat (param $a i32) (param $b i32) (result i32)
+    (local $out i32)
+    (local.set $out (call $carf_buffer))
+    (call $carf_put_str (local.get $out) (local.get $a))
+    (call $carf_put_str (local.get $out) (local.get $b))
+    (call $carf_done (local.get $out)))
+
+  ;; Byte order, the one of Rust strings
+  (func $carf_cmp_string (param $a i32) (param $b i32) (result i32)
+    (local $i i32)
+    (local $n i32)
+    (local $x i32)
+    (local $y i32)
+    (local.set $n (select (i32.load (local.get $a)) (i32.load (local.get $b)) (i32.lt_u (i32.load (local.get $a)) (i32.load (local.get $b)))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+        (local.set $x (i32.load8_u offset=4 (i32.add (local.get $a) (local.get $i))))
+        (local.set $y (i32.load8_u offset=4 (i32.add (local.get $b) (local.get $i))))
+        (if (i32.ne (local.get $x) (local.get $y)) (then (return (select (i32.const -1) (i32.const 1) (i32.lt_u (local.get $x) (local.get $y))))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.sub (i32.gt_u (i32.load (local.get $a)) (i32.load (local.get $b))) (i32.lt_u (i32.load (local.get $a)) (i32.load (local.get $b)))))
+
+  (func $carf_eq_string (param $a i32) (param $b i32) (result i32)
+    (i32.eqz (call $carf_cmp_string (local.get $a) (local.get $b))))
+
+  (func $carf_put_int (param $b i32) (param $n i64) (param $repr i32)
+    (local $count i32)
+    (local $rest i64)
+    (local $at i32)
+    ;; Digits are taken from the negative value, which `int` always has room for
+    (if (i64.lt_s (local.get $n) (i64.const 0))
+      (then (call $carf_put_utf8 (local.get $b) (i32.const 45)))
+      (else (local.set $n (i64.sub (i64.const 0) (local.get $n)))))
+    (local.set $rest (local.get $n))
+    (loop $count_digits
+      (local.set $count (i32.add (local.get $count) (i32.const 1)))
+      (local.set $rest (i64.div_s (local.get $rest) (i64.const 10)))
+      (br_if $count_digits (i64.ne (local.get $rest) (i64.const 0))))
+    (call $carf_reserve (local.get $b) (local.get $count) (i32.const 1))
+    (local.set $at (i32.add (i32.add (i32.load offset=8 (local.get $b)) (i32.load (local.get $b))) (local.get $count)))
+    (i32.store (local.get $b) (i32.add (i32.load (local.get $b)) (local.get $count)))
+    (loop $digit
+      (local.set $at (i32.sub (local.get $at) (i32.const 1)))
+      (i32.store8 (local.get $at) (i32.sub (i32.const 48) (i32.wrap_i64 (i64.rem_s (local.get $n) (i64.const 10)))))
+      (local.set $n (i64.div_s (local.get $n) (i64.const 10)))
+      (br_if $digit (i64.ne (local.get $n) (i64.const 0)))))
+
+  (func $carf_put_float (param $b i32) (param $x f64) (param $repr i32)
+    (call $carf_reserve (local.get $b) (i32.const 32) (i32.const 1))
+    (i32.store (local.get $b)
+      (i32.add (i32.load (local.get $b))
+        (call $carf_format_float (local.get $x) (i32.add (i32.load offset=8 (local.get $b)) (i32.load (local.get $b)))))))
+
+  (func $carf_put_bool (param $b i32) (param $v i32) (param $repr i32)
+    (call $carf_put_str (local.get $b) (select (carf.str "true") (carf.str "false") (local.get $v))))
+
+  ;; The escapes a literal would use
+  (func $carf_put_escaped (param $b i32) (param $c i32) (param $quote i32)
+    (local $escape i32)
+    (local.set $escape
+      (if (result i32) (i32.eq (local.get $c) (i32.const 10)) (then (i32.const 110))
+        (else (if (result i32) (i32.eq (local.get $c) (i32.const 9)) (then (i32.const 116))
+          (else (if (result i32) (i32.eq (local.get $c) (i32.const 13)) (then (i32.const 114))
+            (else (if (result i32) (i32.eqz (local.get $c)) (then (i32.const 48))
+              (else (select (local.get $c) (i32.const 0)
+                (i32.or (i32.eq (local.get $c) (i32.const 92)) (i32.eq (local.get $c) (local.get $quote)))))))))))))
+    (if (local.get $escape)
+      (then
+        (call $carf_put_utf8 (local.get $b) (i32.const 92))
+        (call $carf_put_utf8 (local.get $b) (local.get $escape)))
+      (else (call $carf_put_utf8 (local.get $b) (local.get $c)))))
+
+  ;; In an array or struct, chars and strings are quoted
+  (func $carf_put_char (param $b i32) (param $c i32) (param $repr i32)
+    (if (i32.eqz (local.get $repr)) (then (call $carf_put_utf8 (local.get $b) (local.get $c)) (return)))
+    (call $carf_put_utf8 (local.get $b) (i32.const 39))
+    (call $carf_put_escaped (local.get $b) (local.get $c) (i32.const 39))
+    (call $carf_put_utf8 (local.get $b) (i32.const 39)))
+
+  (func $carf_put_string (param $b i32) (param $s i32) (param $repr i32)
+    (local $p i32)
+    (local $end i32)
+    (if (i32.eqz (local.get $repr)) (then (call $carf_put_str (local.get $b) (local.get $s)) (return)))
+    (local.set $p (i32.add (local.get $s) (i32.const 4)))
+    (local.set $end (i32.add (local.get $p) (i32.load (local.get $s))))
+    (call $carf_put_utf8 (local.get $b) (i32.const 34))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $p) (local.get $end)))
+        (call $carf_put_escaped (local.get $b) (call $carf_decode (local.get $p)) (i32.const 34))
+        (local.set $p (i32.add (local.get $p) (call $carf_width (local.get $p))))
+        (br $next)))
+    (call $carf_put_utf8 (local.get $b) (i32.const 34)))
+
+  ;; Runtime errors, reported as `at: error: message`
+
+  (func $carf_fail (param $at i32) (param $message i32)
+    (local $b i32)
+    (local.set $b (call $carf_buffer))
+    (call $carf_put_str (local.get $b) (local.get $at))
+    (call $carf_put_str (local.get $b) (carf.str ": error: "))
+    (call $carf_put_bytes (local.get $b) (i32.load offset=8 (local.get $message)) (i32.load (local.get $message)))
+    (call $carf_host_fail (i32.load offset=8 (local.get $b)) (i32.load (local.get $b)))
+    (unreachable))
+
+  (func $carf_fail_str (param $at i32) (param $message i32)
+    (local $b i32)
+    (local.set $b (call $carf_buffer))
+    (call $carf_put_str (local.get $b) (local.get $message))
+    (call $carf_fail (local.get $at) (local.get $b)))
+
+  ;; `index`, failing unless it is in `0..len`
+  (func $carf_index_in (param $index i64) (param $len i64) (param $at i32) (result i64)
+    (local $b i32)
+    (if (i64.lt_u (local.get $index) (local.get $len)) (then (return (local.get $index))))
+    (local.set $b (call $carf_buffer))
+    (call $carf_put_str (local.get $b) (carf.str "index "))
+    (call $carf_put_int (local.get $b) (local.get $index) (i32.const 0))
+    (call $carf_put_str (local.get $b) (carf.str " out of bounds for length "))
+    (call $carf_put_int (local.get $b) (local.get $len) (i32.const 0))
+    (call $carf_fail (local.get $at) (local.get $b))
+    (unreachable))
+
+  ;; Integers, checked like the evaluator does
+
+  (func $carf_overflow (param $at i32)
+    (call $carf_fail_str (local.get $at) (carf.str "arithmetic overflow")))
+
+  (func $carf_add (param $a i64) (param $b i64) (param $at i32) (result i64)
+    (local $r i64)
+    (local.set $r (i64.add (local.get $a) (local.get $b)))
+    (if (i64.lt_s (i64.and (i64.xor (local.get $a) (local.get $r)) (i64.xor (local.get $b) (local.get $r))) (i64.const 0))
+      (then (call $carf_overflow (local.get $at))))
+    (local.get $r))
+
+  (func $carf_sub (param $a i64) (param $b i64) (param $at i32) (result i64)
+    (local $r i64)
+    (local.set $r (i64.sub (local.get $a) (local.get $b)))
+    (if (i64.lt_s (i64.and (i64.xor (local.get $a) (local.get $b)) (i64.xor (local.get $a) (local.get $r))) (i64.const 0))
+      (then (call $carf_overflow (local.get $at))))
+    (local.get $r))
+
+  (func $carf_mul (param $a i64) (param $b i64) (param $at i32) (result i64)
+    (local $r i64)
+    (local.set $r (i64.mul (local.get $a) (local.get $b)))
+    (if (i64.eq (local.get $a) (i64.const -1))
+      (then
+        (if (i64.eq (local.get $b) (i64.const -9223372036854775808)) (then (call $carf_overflow (local.get $at)))))
+      (else
+        (if (i64.ne (local.get $a) (i64.const 0))
+          (then
+            (if (i64.ne (i64.div_s (local.get $r) (local.get $a)) (local.get $b)) (then (call $carf_overflow (local.get $at))))))))
+    (local.get $r))
+
+  (func $carf_div (param $a i64) (param $b i64) (param $at i32) (result i64)
+    (if (i64.eqz (local.get $b)) (then (call $carf_fail_str (local.get $at) (carf.str "division by zero"))))
+    (if (i32.and (i64.eq (local.get $a) (i64.const -9223372036854775808)) (i64.eq (local.get $b) (i64.const -1)))
+      (then (call $carf_overflow (local.get $at))))
+    (i64.div_s (local.get $a) (local.get $b)))
+
+  (func $carf_rem (param $a i64) (param $b i64) (param $at i32) (result i64)
+    (if (i64.eqz (local.get $b)) (then (call $carf_fail_str (local.get $at) (carf.str "remainder by zero"))))
+    (if (i32.and (i64.eq (local.get $a) (i64.const -9223372036854775808)) (i64.eq (local.get $b) (i64.const -1)))
+      (then (call $carf_overflow (local.get $at))))
+    (i64.rem_s (local.get $a) (local.get $b)))
+
+  (func $carf_neg (param $a i64) (param $at i32) (result i64)
+    (if (i64.eq (local.get $a) (i64.const -9223372036854775808)) (then (call $carf_overflow (local.get $at))))
+    (i64.sub (i64.const 0) (local.get $a)))
+
+  (func $carf_check_shift (param $n i64) (param $at i32)
+    (local $b i32)
+    (if (i64.lt_u (local.get $n) (i64.const 64)) (then (return)))
+    (local.set $b (call $carf_buffer))
+    (call $carf_put_str (local.get $b) (carf.str "shift by "))
+    (call $carf_put_int (local.get $b) (local.get $n) (i32.const 0))
+    (call $carf_put_str (local.get $b) (carf.str " bits overflows a 64-bit `int`"))
+    (call $carf_fail (local.get $at) (local.get $b)))
+
+  (func $carf_shl (param $a i64) (param $b i64) (param $at i32) (result i64)
+    (call $carf_check_shift (local.get $b) (local.get $at))
+    (i64.shl (local.get $a) (local.get $b)))
+
+  (func $carf_shr (param $a i64) (param $b i64) (param $at i32) (result i64)
+    (call $carf_check_shift (local.get $b) (local.get $at))
+    (i64.shr_s (local.get $a) (local.get $b)))
+
+  (func $carf_abs (param $a i64) (param $at i32) (result i64)
+    (if (result i64) (i64.lt_s (local.get $a) (i64.const 0))
+      (then (call $carf_neg (local.get $a) (local.get $at)))
+      (else (local.get $a))))
+
+  (func $carf_pow (param $base i64) (param $exp i64) (param $at i32) (result i64)
+    (local $result i64)
+    (local $b i32)
+    (if (i64.lt_s (local.get $exp) (i64.const 0))
+      (then
+        (local.set $b (call $carf_buffer))
+        (call $carf_put_str (local.get $b) (carf.str "negative exponent "))
+        (call $carf_put_int (local.get $b) (local.get $exp) (i32.const 0))
+        (call $carf_put_str (local.get $b) (carf.str " in `pow`"))
+        (call $carf_fail (local.get $at) (local.get $b))))
+    (local.set $result (i64.const 1))
+    (block $done
+      (loop $next
+        (br_if $done (i64.eqz (local.get $exp)))
+        (if (i32.wrap_i64 (i64.and (local.get $exp) (i64.const 1)))
+          (then (local.set $result (call $carf_mul (local.get $result) (local.get $base) (local.get $at)))))
+        (local.set $exp (i64.shr_u (local.get $exp) (i64.const 1)))
+        (if (i64.ne (local.get $exp) (i64.const 0))
+          (then (local.set $base (call $carf_mul (local.get $base) (local.get $base) (local.get $at)))))
+        (br $next)))
+    (local.get $result))
+
+  ;; Floats in the order `sort`, `min` and `max` use: NaN after everything
+  (func $carf_cmp_float (param $a f64) (param $b f64) (result i32)
+    (local $x i64)
+    (local $y i64)
+    (local.set $x (i64.reinterpret_f64 (local.get $a)))
+    (local.set $y (i64.reinterpret_f64 (local.get $b)))
+    (local.set $x (i64.xor (local.get $x) (i64.shr_u (i64.shr_s (local.get $x) (i64.const 63)) (i64.const 1))))
+    (local.set $y (i64.xor (local.get $y) (i64.shr_u (i64.shr_s (local.get $y) (i64.const 63)) (i64.const 1))))
+    (i32.sub (i64.gt_s (local.get $x) (local.get $y)) (i64.lt_s (local.get $x) (local.get $y))))
+
+  ;; Arrays
+
+  (func $carf_array_new (param $len i64) (result i32)
+    (local $a i32)
+    (if (i64.gt_u (local.get $len) (i64.const 0x0FFFFFFF)) (then (call $carf_out_of_memory)))
+    (local.set $a (call $carf_alloc (i32.const 12)))
+    (i32.store (local.get $a) (i32.wrap_i64 (local.get $len)))
+    (i32.store offset=4 (local.get $a) (i32.wrap_i64 (local.get $len)))
+    (i32.store offset=8 (local.get $a) (call $carf_alloc (i32.shl (i32.wrap_i64 (local.get $len)) (i32.const 3))))
+    (local.get $a))
+
+  (func $carf_repeat_count (param $n i64) (param $at i32) (result i64)
+    (local $b i32)
+    (if (i64.ge_s (local.get $n) (i64.const 0)) (then (return (local.get $n))))
+    (local.set $b (call $carf_buffer))
+    (call $carf_put_str (local.get $b) (carf.str "negative array repeat count "))
+    (call $carf_put_int (local.get $b) (local.get $n) (i32.const 0))
+    (call $carf_fail (local.get $at) (local.get $b))
+    (unreachable))
+
+  (func $carf_index (param $a i32) (param $index i64) (param $at i32) (result i64)
+    (call $carf_index_in (local.get $index) (i64.extend_i32_u (i32.load (local.get $a))) (local.get $at)))
+
+  ;; Address of element `index`, which must be in bounds
+  (func $carf_slot (param $a i32) (param $index i64) (result i32)
+    (i32.add (i32.load offset=8 (local.get $a)) (i32.shl (i32.wrap_i64 (local.get $index)) (i32.const 3))))
+
+  ;; Address of a new last element, growing the array
+  (func $carf_push_slot (param $a i32) (result i32)
+    (local $len i32)
+    (call $carf_reserve (local.get $a) (i32.const 1) (i32.const 8))
+    (local.set $len (i32.load (local.get $a)))
+    (i32.store (local.get $a) (i32.add (local.get $len) (i32.const 1)))
+    (i32.add (i32.load offset=8 (local.get $a)) (i32.shl (local.get $len) (i32.const 3))))
+
+  ;; Address of the last element, dropped from the array
+  (func $carf_pop_slot (param $a i32) (param $at i32) (result i32)
+    (local $len i32)
+    (local.set $len (i32.load (local.get $a)))
+    (if (i32.eqz (local.get $len)) (then (call $carf_fail_str (local.get $at) (carf.str "cannot pop from an empty array"))))
+    (local.set $len (i32.sub (local.get $len) (i32.const 1)))
+    (i32.store (local.get $a) (local.get $len))
+    (i32.add (i32.load offset=8 (local.get $a)) (i32.shl (local.get $len) (i32.const 3))))
+
+  ;; Order of the elements at `p` and `q`. `kind` is 0 for `int`, 1 for
+  ;; `float`, 2 for `char` and 3 for `string`.
+  (func $carf_cmp_slot (param $kind i32) (param $p i32) (param $q i32) (result i32)
+    (block $string
+      (block $char
+        (block $float
+          (block $int
+            (br_table $int $float $char $string (local.get $kind)))
+          (return (i32.sub (i64.gt_s (i64.load (local.get $p)) (i64.load (local.get $q))) (i64.lt_s (i64.load (local.get $p)) (i64.load (local.get $q))))))
+        (return (call $carf_cmp_float (f64.load (local.get $p)) (f64.load (local.get $q)))))
+      (return (i32.sub (i32.gt_u (i32.load (local.get $p)) (i32.load (local.get $q))) (i32.lt_u (i32.load (local.get $p)) (i32.load (local.get $q))))))
+    (call $carf_cmp_string (i32.load (local.get $p)) (i32.load (local.get $q))))
+
+  ;; Stable insertion sort, the element being placed kept in `item`
+  (func $carf_sort (param $a i32) (param $kind i32)
+    (local $data i32)
+    (local $len i32)
+    (local $i i32)
+    (local $j i32)
+    (local $item i32)
+    (local.set $data (i32.load offset=8 (local.get $a)))
+    (local.set $len (i32.load (local.get $a)))
+    (local.set $item (call $carf_alloc (i32.const 8)))
+    (local.set $i (i32.const 1))
+    (block $sorted
+      (loop $next
+        (br_if $sorted (i32.ge_u (local.get $i) (local.get $len)))
+        (i64.store (local.get $item) (i64.load (i32.add (local.get $data) (i32.shl (local.get $i) (i32.const 3)))))
+        (local.set $j (local.get $i))
+        (block $placed
+          (loop $shift
+            (br_if $placed (i32.eqz (local.get $j)))
+            (br_if $placed
+              (i32.le_s (call $carf_cmp_slot (local.get $kind) (i32.add (local.get $data) (i32.shl (i32.sub (local.get $j) (i32.const 1)) (i32.const 3))) (local.get $item)) (i32.const 0)))
+            (i64.store (i32.add (local.get $data) (i32.shl (local.get $j) (i32.const 3)))
+              (i64.load (i32.add (local.get $data) (i32.shl (i32.sub (local.get $j) (i32.const 1)) (i32.const 3)))))
+            (local.set $j (i32.sub (local.get $j) (i32.const 1)))
+            (br $shift)))
+        (i64.store (i32.add (local.get $data) (i32.shl (local.get $j) (i32.const 3))) (i64.load (local.get $item)))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next))))
+
+  ;; Prelude
+
+  (func $carf_min_float (param $a f64) (param $b f64) (result f64)
+    (select (local.get $b) (local.get $a) (i32.lt_s (call $carf_cmp_float (local.get $b) (local.get $a)) (i32.const 0))))
+
+  (func $carf_max_float (param $a f64) (param $b f64) (result f64)
+    (select (local.get $b) (local.get $a) (i32.gt_s (call $carf_cmp_float (local.get $b) (local.get $a)) (i32.const 0))))
+
+  (func $carf_float_to_int (param $x f64) (param $at i32) (result i64)
+    (local $b i32)
+    (if (i32.and (f64.ge (local.get $x) (f64.const -0x1p63)) (f64.lt (local.get $x) (f64.const 0x1p63)))
+      (then (return (i64.trunc_f64_s (local.get $x)))))
+    (local.set $b (call $carf_buffer))
+    (call $carf_put_float (local.get $b) (local.get $x) (i32.const 0))
+    (call $carf_put_str (local.get $b) (carf.str " does not fit in an int"))
+    (call $carf_fail (local.get $at) (local.get $b))
+    (unreachable))
+
+  (func $carf_floor (param $x f64) (param $at i32) (result i64)
+    (call $carf_float_to_int (f64.floor (local.get $x)) (local.get $at)))
+
+  (func $carf_to_int_float (param $x f64) (param $at i32) (result i64)
+    (call $carf_float_to_int (f64.trunc (local.get $x)) (local.get $at)))
+
+  (func $carf_is_space (param $c i32) (result i32)
+    (i32.or (i32.eq (local.get $c) (i32.const 32)) (i32.and (i32.ge_u (local.get $c) (i32.const 9)) (i32.le_u (local.get $c) (i32.const 13)))))
+
+  ;; Decimal digits with an optional sign, around which whitespace is ignored
+  (func $carf_to_int_string (param $s i32) (param $at i32) (result i64)
+    (local $p i32)
+    (local $end i32)
+    (local $negative i32)
+    (local $ok i32)
+    (local $n i64)
+    (local $digit i64)
+    (local $b i32)
+    (local.set $p (i32.add (local.get $s) (i32.const 4)))
+    (local.set $end (i32.add (local.get $p) (i32.load (local.get $s))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $p) (local.get $end)))
+        (br_if $done (i32.eqz (call $carf_is_space (i32.load8_u (local.get $p)))))
+        (local.set $p (i32.add (local.get $p) (i32.const 1)))
+        (br $next)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.le_u (local.get $end) (local.get $p)))
+        (br_if $done (i32.eqz (call $carf_is_space (i32.load8_u (i32.sub (local.get $end) (i32.const 1))))))
+        (local.set $end (i32.sub (local.get $end) (i32.const 1)))
+        (br $next)))
+    (if (i32.lt_u (local.get $p) (local.get $end))
+      (then
+        (if (i32.or (i32.eq (i32.load8_u (local.get $p)) (i32.const 43)) (i32.eq (i32.load8_u (local.get $p)) (i32.const 45)))
+          (then
+            (local.set $negative (i32.eq (i32.load8_u (local.get $p)) (i32.const 45)))
+            (local.set $p (i32.add (local.get $p) (i32.const 1)))))))
+    (local.set $ok (i32.lt_u (local.get $p) (local.get $end)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.or (i32.eqz (local.get $ok)) (i32.ge_u (local.get $p) (local.get $end))))
+        (local.set $digit (i64.sub (i64.extend_i32_u (i32.load8_u (local.get $p))) (i64.const 48)))
+        (if (i64.gt_u (local.get $digit) (i64.const 9)) (then (local.set $ok (i32.const 0)) (br $done)))
+        (if (local.get $negative)
+          (then
+            (if (i64.lt_s (local.get $n) (i64.div_s (i64.add (i64.const -9223372036854775808) (local.get $digit)) (i64.const 10)))
+              (then (local.set $ok (i32.const 0)) (br $done)))
+            (local.set $n (i64.sub (i64.mul (local.get $n) (i64.const 10)) (local.get $digit))))
+          (else
+            (if (i64.gt_s (local.get $n) (i64.div_s (i64.sub (i64.const 9223372036854775807) (local.get $digit)) (i64.const 10)))
+              (then (local.set $ok (i32.const 0)) (br $done)))
+            (local.set $n (i64.add (i64.mul (local.get $n) (i64.const 10)) (local.get $digit)))))
+        (local.set $p (i32.add (local.get $p) (i32.const 1)))
+        (br $next)))
+    (if (i32.eqz (local.get $ok))
+      (then
+        (local.set $b (call $carf_buffer))
+        (call $carf_put_str (local.get $b) (carf.str "cannot parse "))
+        (call $carf_put_string (local.get $b) (local.get $s) (i32.const 1))
+        (call $carf_put_str (local.get $b) (carf.str " as an int"))
+        (call $carf_fail (local.get $at) (local.get $b))))
+    (local.get $n))
+
+  (func $carf_substr (param $s i32) (param $start i64) (param $count i64) (param $at i32) (result i32)
+    (local $len i64)
+    (local $from i32)
+    (local $to i32)
+    (local $b i32)
+    (local.set $len (call $carf_str_len (local.get $s)))
+    (if (i32.or (i32.or (i64.lt_s (local.get $start) (i64.const 0)) (i64.lt_s (local.get $count) (i64.const 0)))
+          (i64.gt_s (local.get $start) (i64.sub (local.get $len) (local.get $count))))
+      (then
+        (local.set $b (call $carf_buffer))
+        (call $carf_put_str (local.get $b) (carf.str "substring of "))
+        (call $carf_put_int (local.get $b) (local.get $count) (i32.const 0))
+        (call $carf_put_str (local.get $b) (carf.str " chars at "))
+        (call $carf_put_int (local.get $b) (local.get $start) (i32.const 0))
+        (call $carf_put_str (local.get $b) (carf.str " out of bounds for length "))
+        (call $carf_put_int (local.get $b) (local.get $len) (i32.const 0))
+        (call $carf_fail (local.get $at) (local.get $b))))
+    (local.set $from (i32.add (local.get $s) (i32.const 4)))
+    (block $done
+      (loop $next
+        (br_if $done (i64.eqz (local.get $start)))
+        (local.set $from (i32.add (local.get $from) (call $carf_width (local.get $from))))
+        (local.set $start (i64.sub (local.get $start) (i64.const 1)))
+        (br $next)))
+    (local.set $to (local.get $from))
+    (block $done
+      (loop $next
+        (br_if $done (i64.eqz (local.get $count)))
+        (local.set $to (i32.add (local.get $to) (call $carf_width (local.get $to))))
+        (local.set $count (i64.sub (local.get $count) (i64.const 1)))
+        (br $next)))
+    (call $carf_str (local.get $from) (i32.sub (local.get $to) (local.get $from))))
+
+  ;; Whether the `n` bytes at `p` and `q` are the same
+  (func $carf_same_bytes (param $p i32) (param $q i32) (param $n i32) (result i32)
+    (local $i i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+        (if (i32.ne (i32.load8_u (i32.add (local.get $p) (local.get $i))) (i32.load8_u (i32.add (local.get $q) (local.get $i))))
+          (then (return (i32.const 0))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.const 1))
+
+  (func $carf_split (param $s i32) (param $sep i32) (param $at i32) (result i32)
+    (local $parts i32)
+    (local $p i32)
+    (local $start i32)
+    (local $end i32)
+    (local $n i32)
+    (local.set $parts (call $carf_array_new (i64.const 0)))
+    (local.set $n (i32.load (local.get $sep)))
+    (if (i32.eqz (local.get $n)) (then (call $carf_fail_str (local.get $at) (carf.str "cannot split on an empty separator"))))
+    (local.set $p (i32.add (local.get $s) (i32.const 4)))
+    (local.set $start (local.get $p))
+    (local.set $end (i32.add (local.get $p) (i32.load (local.get $s))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.gt_u (i32.add (local.get $p) (local.get $n)) (local.get $end)))
+        (if (call $carf_same_bytes (local.get $p) (i32.add (local.get $sep) (i32.const 4)) (local.get $n))
+          (then
+            (i32.store (call $carf_push_slot (local.get $parts)) (call $carf_str (local.get $start) (i32.sub (local.get $p) (local.get $start))))
+            (local.set $p (i32.add (local.get $p) (local.get $n)))
+            (local.set $start (local.get $p)))
+          (else (local.set $p (i32.add (local.get $p) (i32.const 1)))))
+        (br $next)))
+    (i32.store (call $carf_push_slot (local.get $parts)) (call $carf_str (local.get $start) (i32.sub (local.get $end) (local.get $start))))
+    (local.get $parts))
@@ -0,0 +1,1082 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::ast::token::TokenKind;
+use crate::ast::{Ast, AstKind, AstNode};
+use crate::checker::types::{FunSig, Type};
+use crate::checker::{Checker, strip_assign};
+use crate::diagnostic::{Diagnostic, Span};
+use crate::source::SourceMap;
+use crate::utilities::literal;
+
+// Allocation, checked arithmetic, text and the prelude, the start of every module
+const RUNTIME: &str = include_str!("runtime.wat");
+// Where the strings of the module start, below it is the empty string at 0
+const DATA_START: usize = 8;
+const PAGE_SIZE: usize = 65536;
+
+// Lower a checked program to one WebAssembly text module. It imports its
+// output and errors from the host and exports `main` and its memory. Enums,
+// `match`, lambdas, function values and `read_line` have no lowering yet and
+// are reported as errors.
+pub fn generate<'a>(ast: &Ast<'a>, checker: &Checker<'a>, files: &SourceMap) -> Result<String, Vec<Diagnostic>> {
+  let mut generator = Generator {
+    checker,
+    files,
+    methods: BTreeSet::new(),
+    helpers: BTreeSet::new(),
+    strings: BTreeMap::new(),
+    data: Vec::new(),
+    globals: Vec::new(),
+    funcs: Vec::new(),
+    scopes: Vec::new(),
+    locals: Vec::new(),
+    loops: Vec::new(),
+    lines: Vec::new(),
+    indent: 0,
+    next: 0,
+    diagnostics: Vec::new(),
+  };
+  generator.program(ast.get_root());
+  match generator.diagnostics.is_empty() {
+    true => Ok(generator.finish()),
+    false => {
+      generator.diagnostics.sort_by_key(|d| d.span);
+      Err(generator.diagnostics)
+    },
+  }
+}
+
+// Assignable location, its parts already evaluated to atoms
+enum Place {
+  Local(String),
+  Global(String),
+  // Array, index checked against its length, and wasm type of the elements
+  Elem(String, String, &'static str),
+  // Struct, byte offset and wasm type of the field
+  Field(String, usize, &'static str),
+  // Char of the string held by the inner place, at an index checked by `at`
+  StrChar(Box<Place>, String, String),
+}
+
+struct Generator<'g, 'a> {
+  checker: &'g Checker<'a>,
+  files: &'g SourceMap,
+  // Methods declared by an `impl`, by method key and name
+  methods: BTreeSet<(String, &'a str)>,
+  // Composite types whose helpers were generated, by mangled name
+  helpers: BTreeSet<String>,
+  // Address of each string in the data segment, which `data` holds
+  strings: BTreeMap<String, usize>,
+  data: Vec<u8>,
+  globals: Vec<String>,
+  funcs: Vec<String>,
+  // Wasm name of each carf name in scope, innermost scope last. Globals are not in it.
+  scopes: Vec<Vec<(&'a str, String)>>,
+  // Locals of the function being generated, parameters excluded
+  locals: Vec<(String, &'static str)>,
+  // Label number of each enclosing loop, innermost last
+  loops: Vec<usize>,
+  // Body of the function being generated
+  lines: Vec<String>,
+  indent: usize,
+  // Numbers locals, temporaries and labels apart
+  next: usize,
+  diagnostics: Vec<Diagnostic>,
+}
+
+impl<'g, 'a> Generator<'g, 'a> {
+  fn program(&mut self, root: &'g AstNode<'a>) {
+    let items = root.get_children();
+    for item in items {
+      match item.get_kind() {
+        AstKind::Enum => self.diagnostics.push(unsupported(item, "enums")),
+        AstKind::Impl => {
+          let key = self.resolve(&item[0]).method_key().unwrap_or_default();
+          for method in item.get_children().iter().skip(1) {
+            self.methods.insert((key.clone(), method[0].get_token().get_value()));
+          }
+        },
+        _ => {},
+      }
+    }
+    for item in items {
+      let result = match item.get_kind() {
+        AstKind::Let => self.global(item),
+        AstKind::Fun => self.fun_item(item),
+        AstKind::Impl => self.impl_item(item),
+        _ => Ok(()),
+      };
+      if let Err(d) = result {
+        self.diagnostics.push(d);
+      }
+    }
+    let globals: Vec<&'g AstNode<'a>> = items.iter().filter(|item| item.get_kind() == AstKind::Let).collect();
+    if let Err(d) = self.entry(&globals, items.iter().find(|item| item.get_kind() == AstKind::Fun && item[0].get_token().get_value() == "main")) {
+      self.diagnostics.push(d);
+    }
+  }
+  fn finish(mut self) -> String {
+    let runtime = self.runtime_strings();
+    let heap = (DATA_START + self.data.len()).next_multiple_of(8);
+    let mut out = String::from("(module\n");
+    out.push_str(&runtime);
+    out.push_str("\n  ;; Program\n\n");
+    out.push_str(&format!("  (memory (export \"memory\") {})\n", heap.div_ceil(PAGE_SIZE).max(1)));
+    out.push_str(&format!("  (global $carf_heap (mut i32) (i32.const {}))\n", heap));
+    for global in &self.globals {
+      out.push_str(global);
+      out.push('\n');
+    }
+    for func in &self.funcs {
+      out.push('\n');
+      out.push_str(func);
+    }
+    out.push_str(&format!("\n  (data (i32.const {}) {})\n)\n", DATA_START, wat_string(&self.data)));
+    out
+  }
+  // The runtime with each `(carf.str "...")` replaced by the string's address
+  fn runtime_strings(&mut self) -> String {
+    let mut out = String::new();
+    let mut rest = RUNTIME;
+    while let Some(start) = rest.find("(carf.str \"") {
+      let text_start = start + "(carf.str \"".len();
+      let len = rest[text_start..].find("\")").unwrap_or(0);
+      let address = self.string(&rest[text_start..text_start + len]);
+      out.push_str(&rest[..start]);
+      out.push_str(&address);
+      rest = &rest[text_start + len + 2..];
+    }
+    out.push_str(rest);
+    out
+  }
+}
+
+// Items
+impl<'g, 'a> Generator<'g, 'a> {
+  fn global(&mut self, item: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    let ty = self.binding_type(&item[0]);
+    let wasm_type = self.wasm_type(&ty, &item[0])?;
+    let name = item[0].get_token().get_value();
+    self.globals.push(format!("  (global $g_{} (mut {}) ({}.const 0))", name, wasm_type, wasm_type));
+    Ok(())
+  }
+  fn fun_item(&mut self, item: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    let name = item[0].get_token().get_value();
+    match self.checker.get_fun(name) {
+      Some(sig) => self.function(format!("$f_{}", name), item, &sig.clone()),
+      None => Ok(()),
+    }
+  }
+  fn impl_item(&mut self, item: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    let receiver = self.resolve(&item[0]);
+    let key = match receiver.method_key() {
+      Some(key) if !matches!(receiver, Type::Enum(_)) => key,
+      _ => return Err(unsupported(item, "methods of enums")),
+    };
+    for method in item.get_children().iter().skip(1) {
+      let name = method[0].get_token().get_value();
+      if let Some(sig) = self.checker.get_method(&receiver, name) {
+        self.function(format!("$m_{}_{}", key, name), method, &sig)?;
+      }
+    }
+    Ok(())
+  }
+  fn function(&mut self, wasm_name: String, fun: &'g AstNode<'a>, sig: &FunSig) -> Result<(), Diagnostic> {
+    self.scopes = vec![Vec::new()];
+    let mut head = format!("(func {}", wasm_name);
+    for (param, ty) in fun[1].get_children().iter().zip(&sig.params) {
+      let wasm_type = self.wasm_type(ty, param)?;
+      let local = self.declare(param.get_token().get_value());
+      head.push_str(&format!(" (param {} {})", local, wasm_type));
+    }
+    if sig.ret != Type::Unit {
+      head.push_str(&format!(" (result {})", self.wasm_type(&sig.ret, &fun[2])?));
+    }
+    self.locals.clear();
+    self.lines.clear();
+    self.indent = 2;
+    self.body(&fun[3])?;
+    // Every path returned already, the checker made sure of it
+    if sig.ret != Type::Unit {
+      self.line("(unreachable)".to_string());
+    }
+    self.emit_function(head);
+    Ok(())
+  }
+  // Globals are set in source order before `main` runs, as the evaluator does
+  fn entry(&mut self, globals: &[&'g AstNode<'a>], main: Option<&'g AstNode<'a>>) -> Result<(), Diagnostic> {
+    self.scopes = vec![Vec::new()];
+    self.locals.clear();
+    self.lines.clear();
+    self.indent = 2;
+    for item in globals {
+      if item.child_count() > 2 {
+        let value = self.expr(&item[2])?;
+        self.line(format!("(global.set $g_{} {})", item[0].get_token().get_value(), value));
+      }
+    }
+    self.emit_function("(func $carf_init".to_string());
+    let main = match main {
+      Some(main) if main[1].child_count() > 0 => {
+        return Err(Diagnostic::error(Span::of_token(&main[0]), "`main` must not take parameters".to_string()));
+      },
+      Some(main) => main,
+      None => return Err(Diagnostic::error(Span::new(0, 0), "no `main` function to run".to_string())),
+    };
+    // What `main` returns is printed, like `carf run` does
+    let ret = self.checker.get_fun("main").map(|sig| sig.ret.clone()).unwrap_or(Type::Unit);
+    self.line("(call $carf_init)".to_string());
+    match ret {
+      Type::Unit => self.line("(call $f_main)".to_string()),
+      _ => {
+        let wasm_type = self.wasm_type(&ret, &main[2])?;
+        let put = self.put_fn(&ret, &main[2])?;
+        let result = self.temp(wasm_type, "(call $f_main)".to_string());
+        let out = self.temp("i32", "(call $carf_buffer)".to_string());
+        self.line(format!("(call {} {} {} (i32.const 0))", put, out, result));
+        self.line(format!("(call $carf_put_utf8 {} (i32.const 10))", out));
+        self.line(format!("(call $carf_print {})", out));
+      },
+    }
+    self.emit_function("(func $carf_main (export \"main\")".to_string());
+    Ok(())
+  }
+  fn emit_function(&mut self, head: String) {
+    let mut func = format!("  {}\n", head);
+    for (local, wasm_type) in std::mem::take(&mut self.locals) {
+      func.push_str(&format!("    (local {} {})\n", local, wasm_type));
+    }
+    func.push_str(&self.take_lines());
+    func.push_str("  )\n");
+    self.funcs.push(func);
+  }
+}
+
+// Statements
+impl<'g, 'a> Generator<'g, 'a> {
+  // A statement with a scope of its own, so its names end with it
+  fn body(&mut self, node: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    self.scopes.push(Vec::new());
+    let result = match node.get_kind() {
+      AstKind::Block => node.get_children().iter().try_for_each(|stmt| self.stmt(stmt)),
+      _ => self.stmt(node),
+    };
+    self.scopes.pop();
+    result
+  }
+  fn nested(&mut self, node: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    self.indent += 1;
+    let result = self.body(node);
+    self.indent -= 1;
+    result
+  }
+  fn stmt(&mut self, node: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    match node.get_kind() {
+      AstKind::Let => {
+        let ty = self.binding_type(&node[0]);
+        let wasm_type = self.wasm_type(&ty, &node[0])?;
+        let value = match node.child_count() > 2 {
+          true => self.expr(&node[2])?,
+          false => format!("({}.const 0)", wasm_type),
+        };
+        let local = self.declare(node[0].get_token().get_value());
+        self.locals.push((local.clone(), wasm_type));
+        self.line(format!("(local.set {} {})", local, value));
+      },
+      AstKind::Stmt if node.child_count() > 0 => {
+        self.expr(&node[0])?;
+      },
+      AstKind::Stmt => {},
+      AstKind::Block => self.body(node)?,
+      AstKind::If => {
+        let cond = self.expr(&node[0])?;
+        self.line(format!("(if {}", cond));
+        self.line("  (then".to_string());
+        self.indent += 1;
+        self.nested(&node[1])?;
+        self.line(")".to_string());
+        if node.child_count() > 2 && node[2].child_count() > 0 {
+          self.line("(else".to_string());
+          self.nested(&node[2][0])?;
+          self.line(")".to_string());
+        }
+        self.indent -= 1;
+        self.line(")".to_string());
+      },
+      // The condition is evaluated at the top of each turn
+      AstKind::While => {
+        let label = self.open_loop();
+        let cond = self.expr(&node[0])?;
+        self.line(format!("(br_if $break_{} (i32.eqz {}))", label, cond));
+        self.loop_body(label, &node[1])?;
+        self.close_loop(label);
+      },
+      AstKind::For => self.for_stmt(node)?,
+      AstKind::Return if node.child_count() > 0 => {
+        let value = self.expr(&node[0])?;
+        match value.is_empty() {
+          true => self.line("(return)".to_string()),
+          false => self.line(format!("(return {})", value)),
+        }
+      },
+      AstKind::Return => self.line("(return)".to_string()),
+      AstKind::Break => {
+        let label = self.loops.last().copied().unwrap_or_default();
+        self.line(format!("(br $break_{})", label));
+      },
+      AstKind::Continue => {
+        let label = self.loops.last().copied().unwrap_or_default();
+        self.line(format!("(br $continue_{})", label));
+      },
+      _ => return Err(unsupported(node, "this statement")),
+    }
+    Ok(())
+  }
+  // `break` leaves the outer block, `continue` the block around the body,
+  // after which the loop steps and goes back to the top
+  fn open_loop(&mut self) -> usize {
+    self.next += 1;
+    let label = self.next;
+    self.line(format!("(block $break_{}", label));
+    self.line(format!("  (loop $top_{}", label));
+    self.indent += 2;
+    label
+  }
+  fn loop_body(&mut self, label: usize, body: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    self.line(format!("(block $continue_{}", label));
+    self.loops.push(label);
+    let result = self.nested(body);
+    self.loops.pop();
+    self.line(")".to_string());
+    result
+  }
+  fn close_loop(&mut self, label: usize) {
+    self.line(format!("(br $top_{})", label));
+    self.indent -= 2;
+    self.line("  )".to_string());
+    self.line(")".to_string());
+  }
+  // Arrays are read as the loop goes, so the body sees its own writes
+  fn for_stmt(&mut self, node: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    let name = node[0].get_token().get_value();
+    let mut iter = &node[1];
+    while iter.get_kind() == AstKind::Expr {
+      iter = &iter[0];
+    }
+    self.scopes.push(Vec::new());
+    let result = self.for_loop(name, iter, &node[2]);
+    self.scopes.pop();
+    result
+  }
+  fn for_loop(&mut self, name: &'a str, iter: &'g AstNode<'a>, body: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    match self.type_of(iter) {
+      Type::Array(elem, _) => {
+        let items = self.expr(iter)?;
+        let wasm_type = self.wasm_type(&elem, iter)?;
+        let i = self.local("i64", "(i64.const 0)".to_string());
+        let label = self.open_loop();
+        self.line(format!("(br_if $break_{} (i64.ge_s (local.get {}) (i64.extend_i32_u (i32.load {}))))", label, i, items));
+        self.bind(name, wasm_type, format!("({}.load (call $carf_slot {} (local.get {})))", wasm_type, items, i));
+        self.loop_body(label, body)?;
+        self.line(format!("(local.set {i} (i64.add (local.get {i}) (i64.const 1)))"));
+        self.close_loop(label);
+      },
+      // The char is decoded and passed before the body runs
+      Type::String => {
+        let text = self.expr(iter)?;
+        let p = self.local("i32", format!("(i32.add {} (i32.const 4))", text));
+        let end = self.temp("i32", format!("(i32.add (local.get {}) (i32.load {}))", p, text));
+        let label = self.open_loop();
+        self.line(format!("(br_if $break_{} (i32.ge_u (local.get {}) {}))", label, p, end));
+        self.bind(name, "i32", format!("(call $carf_decode (local.get {}))", p));
+        self.line(format!("(local.set {p} (i32.add (local.get {p}) (call $carf_width (local.get {p}))))"));
+        self.loop_body(label, body)?;
+        self.close_loop(label);
+      },
+      _ => return Err(unsupported(iter, "this iterator")),
+    }
+    Ok(())
+  }
+  // Declare the loop variable and set it for this turn
+  fn bind(&mut self, name: &'a str, wasm_type: &'static str, value: String) {
+    let local = self.declare(name);
+    self.locals.push((local.clone(), wasm_type));
+    self.line(format!("(local.set {} {})", local, value));
+  }
+}
+
+// Expressions. Each one is evaluated into a local in the order the evaluator
+// uses, the returned atom reading that local, being a constant, or empty for
+// a `unit` value.
+impl<'g, 'a> Generator<'g, 'a> {
+  fn expr(&mut self, node: &'g AstNode<'a>) -> Result<String, Diagnostic> {
+    let token = node.get_token();
+    match node.get_kind() {
+      AstKind::Literal => self.literal_atom(node),
+      AstKind::Identifier => {
+        let ty = self.type_of(node);
+        if let Type::Fun(..) = ty {
+          return Err(unsupported(node, "function values"));
+        }
+        let wasm_type = self.wasm_type(&ty, node)?;
+        let var = self.read(&self.var(token.get_value()));
+        Ok(self.temp(wasm_type, var))
+      },
+      AstKind::Expr => self.expr(&node[0]),
+      AstKind::UnOper => {
+        let operand = self.expr(&node[0])?;
+        let value = match (token.get_kind(), self.type_of(&node[0])) {
+          (TokenKind::Minus, Type::Int) => format!("(call $carf_neg {} {})", operand, self.at(node)),
+          (TokenKind::Minus, _) => format!("(f64.neg {})", operand),
+          _ => format!("(i32.eqz {})", operand),
+        };
+        let wasm_type = self.wasm_type(&self.type_of(node), node)?;
+        Ok(self.temp(wasm_type, value))
+      },
+      // `x++` and `x--` give the value from before the step, `++x` and `--x`
+      // the one after it
+      AstKind::PreStep | AstKind::PostStep => {
+        let place = self.place(&node[0])?;
+        let old = self.read(&place);
+        let old = self.temp("i64", old);
+        let step = match token.get_kind() {
+          TokenKind::DPlus => "$carf_add",
+          _ => "$carf_sub",
+        };
+        let at = self.at(node);
+        let new = format!("(call {} {} (i64.const 1) {})", step, old, at);
+        if node.get_kind() == AstKind::PostStep {
+          self.write(&place, new);
+          return Ok(old);
+        }
+        let new = self.temp("i64", new);
+        self.write(&place, new.clone());
+        Ok(new)
+      },
+      AstKind::BinOper => self.binary(node),
+      AstKind::Call => self.call(node),
+      AstKind::MethodCall => self.method_call(node),
+      AstKind::Array => {
+        let elem = self.elem_type(node)?;
+        let mut items = Vec::new();
+        for item in node.get_children() {
+          items.push(self.expr(item)?);
+        }
+        let array = self.temp("i32", format!("(call $carf_array_new (i64.const {}))", items.len()));
+        for (i, item) in items.iter().enumerate() {
+          self.line(format!("({}.store offset={} (i32.load offset=8 {}) {})", elem, i * 8, array, item));
+        }
+        Ok(array)
+      },
+      // Every element gets its own copy, so `[[0; 2]; 2]` has two distinct rows
+      AstKind::Repeat => {
+        let wasm_type = self.elem_type(node)?;
+        let elem_type = self.type_of(&node[0]);
+        let elem = self.expr(&node[0])?;
+        let count = self.expr(&node[1])?;
+        let at = self.at(&node[1]);
+        let count = self.temp("i64", format!("(call $carf_repeat_count {} {})", count, at));
+        let array = self.temp("i32", format!("(call $carf_array_new {})", count));
+        let copy = self.copy_of(&elem_type, elem, node)?;
+        let i = self.local("i64", "(i64.const 0)".to_string());
+        let label = self.open_loop();
+        self.line(format!("(br_if $break_{} (i64.ge_s (local.get {}) {}))", label, i, count));
+        self.line(format!("({}.store (call $carf_slot {} (local.get {})) {})", wasm_type, array, i, copy));
+        self.line(format!("(local.set {i} (i64.add (local.get {i}) (i64.const 1)))"));
+        self.close_loop(label);
+        Ok(array)
+      },
+      AstKind::StructLit => {
+        let name = node[0].get_token().get_value();
+        let fields = self.checker.get_struct(name).map(|def| def.fields.clone()).unwrap_or_default();
+        let mut given = Vec::new();
+        for init in node.get_children().iter().skip(1) {
+          given.push((init.get_token().get_value(), self.expr(&init[0])?));
+        }
+        let record = self.temp("i32", format!("(call $carf_alloc (i32.const {}))", fields.len() * 8));
+        for (field, value) in given {
+          let (offset, wasm_type) = self.field(name, field, node)?;
+          self.line(format!("({}.store offset={} {} {})", wasm_type, offset, record, value));
+        }
+        Ok(record)
+      },
+      AstKind::Member => {
+        let record = self.expr(&node[0])?;
+        let (offset, wasm_type) = self.member(node)?;
+        Ok(self.temp(wasm_type, format!("({}.load offset={} {})", wasm_type, offset, record)))
+      },
+      AstKind::Index => {
+        let base = self.expr(&node[0])?;
+        let index = self.expr(&node[1])?;
+        let at = self.at(node);
+        match self.type_of(&node[0]) {
+          Type::String => Ok(self.temp("i32", format!("(call $carf_str_at {} {} {})", base, index, at))),
+          _ => {
+            let elem = self.elem_type(&node[0])?;
+            let value = format!("({}.load (call $carf_slot {} (call $carf_index {} {} {})))", elem, base, base, index, at);
+            Ok(self.temp(elem, value))
+          },
+        }
+      },
+      AstKind::Path => Err(unsupported(node, "enums")),
+      AstKind::Match => Err(unsupported(node, "`match`")),
+      AstKind::Lambda => Err(unsupported(node, "lambdas")),
+      _ => Err(unsupported(node, "this expression")),
+    }
+  }
+  fn binary(&mut self, node: &'g AstNode<'a>) -> Result<String, Diagnostic> {
+    let kind = node.get_token().get_kind();
+    match kind {
+      TokenKind::Equal => {
+        let place = self.place(&node[0])?;
+        let value = self.expr(&node[1])?;
+        self.write(&place, value);
+        Ok(String::new())
+      },
+      // Left by the desugaring pass when the target is not pure, which is evaluated once
+      TokenKind::PlusEq | TokenKind::MinusEq | TokenKind::AsteriskEq | TokenKind::SlashEq | TokenKind::ModEq
+      | TokenKind::AndEq | TokenKind::OrEq | TokenKind::XorEq | TokenKind::LShiftEq | TokenKind::RShiftEq => {
+        let place = self.place(&node[0])?;
+        let old = self.read(&place);
+        let wasm_type = self.wasm_type(&self.type_of(&node[0]), node)?;
+        let old = self.temp(wasm_type, old);
+        let rhs = self.expr(&node[1])?;
+        let value = self.operator(strip_assign(kind), &self.type_of(&node[0]), &old, &rhs, node)?;
+        let value = self.temp(wasm_type, value);
+        self.write(&place, value);
+        Ok(String::new())
+      },
+      // The right operand only runs when the left one does not decide
+      TokenKind::DAnd | TokenKind::DOr => {
+        let lhs = self.expr(&node[0])?;
+        let result = self.local("i32", lhs);
+        match kind {
+          TokenKind::DAnd => self.line(format!("(if (local.get {})", result)),
+          _ => self.line(format!("(if (i32.eqz (local.get {}))", result)),
+        }
+        self.line("  (then".to_string());
+        self.indent += 2;
+        let rhs = self.expr(&node[1]);
+        if let Ok(rhs) = &rhs {
+          self.line(format!("(local.set {} {})", result, rhs));
+        }
+        self.indent -= 2;
+        self.line("  )".to_string());
+        self.line(")".to_string());
+        rhs.map(|_| format!("(local.get {})", result))
+      },
+      TokenKind::DDot => Err(unsupported(node, "ranges outside of `for`")),
+      _ => {
+        let lhs = self.expr(&node[0])?;
+        let rhs = self.expr(&node[1])?;
+        let value = self.operator(kind, &self.type_of(&node[0]), &lhs, &rhs, node)?;
+        let wasm_type = self.wasm_type(&self.type_of(node), node)?;
+        Ok(self.temp(wasm_type, value))
+      },
+    }
+  }
+  // Wasm expression applying a binary operator to two atoms of type `ty`
+  fn operator(&mut self, kind: TokenKind, ty: &Type, lhs: &str, rhs: &str, node: &AstNode<'a>) -> Result<String, Diagnostic> {
+    use TokenKind::*;
+    let at = self.at(node);
+    let instr = match (ty, kind) {
+      (Type::Int, Plus | Minus | Asterisk | Slash | Mod | LShift | RShift) => {
+        let fun = match kind {
+          Plus => "$carf_add",
+          Minus => "$carf_sub",
+          Asterisk => "$carf_mul",
+          Slash => "$carf_div",
+          Mod => "$carf_rem",
+          LShift => "$carf_shl",
+          _ => "$carf_shr",
+        };
+        return Ok(format!("(call {} {} {} {})", fun, lhs, rhs, at));
+      },
+      (Type::Int, And) => "i64.and",
+      (Type::Int, Or) => "i64.or",
+      (Type::Int, Xor) => "i64.xor",
+      (Type::Int, _) => match compare(kind, "i64", "_s") {
+        Some(instr) => return Ok(format!("({} {} {})", instr, lhs, rhs)),
+        None => "",
+      },
+      (Type::Float, Plus) => "f64.add",
+      (Type::Float, Minus) => "f64.sub",
+      (Type::Float, Asterisk) => "f64.mul",
+      (Type::Float, Slash) => "f64.div",
+      (Type::Float, _) => match compare(kind, "f64", "") {
+        Some(instr) => return Ok(format!("({} {} {})", instr, lhs, rhs)),
+        None => "",
+      },
+      (Type::Char, _) => match compare(kind, "i32", "_u") {
+        Some(instr) => return Ok(format!("({} {} {})", instr, lhs, rhs)),
+        None => "",
+      },
+      (Type::Bool, DEqual) => "i32.eq",
+      (Type::Bool, NEqual) => "i32.ne",
+      (Type::String, Plus) => "call $carf_concat",
+      (Type::String, Less | Greater | LessEq | GreaterEq) => {
+        let instr = compare(kind, "i32", "_s").unwrap_or_default();
+        return Ok(format!("({} (call $carf_cmp_string {} {}) (i32.const 0))", instr, lhs, rhs));
+      },
+      (_, DEqual | NEqual) => {
+        let eq = self.eq_of(ty, lhs.to_string(), rhs.to_string(), node)?;
+        return Ok(if kind == NEqual { format!("(i32.eqz {})", eq) } else { eq });
+      },
+      _ => "",
+    };
+    match instr.is_empty() {
+      true => Err(unsupported(node, &format!("`{}` on `{}`", node.get_token().get_value().trim_end_matches('='), ty))),
+      false => Ok(format!("({} {} {})", instr, lhs, rhs)),
+    }
+  }
+  fn call(&mut self, node: &'g AstNode<'a>) -> Result<String, Diagnostic> {
+    let callee = &node[0];
+    let name = callee.get_token().get_value();
+    if callee.get_kind() == AstKind::Path {
+      return Err(unsupported(node, "enums"));
+    }
+    if callee.get_kind() != AstKind::Identifier || self.is_local(name) {
+      return Err(unsupported(node, "calls of function values"));
+    }
+    let mut args = Vec::new();
+    for arg in node[1].get_children() {
+      args.push(self.expr(arg)?);
+    }
+    match self.checker.get_fun(name) {
+      Some(_) => self.call_value(format!("$f_{}", name), &args, node),
+      None => self.prelude(name, &args, node),
+    }
+  }
+  fn method_call(&mut self, node: &'g AstNode<'a>) -> Result<String, Diagnostic> {
+    let receiver = self.type_of(&node[0]);
+    let name = node[1].get_token().get_value();
+    let mut args = vec![self.expr(&node[0])?];
+    for arg in node[2].get_children() {
+      args.push(self.expr(arg)?);
+    }
+    let key = receiver.method_key().unwrap_or_default();
+    match self.methods.contains(&(key.clone(), name)) {
+      true => self.call_value(format!("$m_{}_{}", key, name), &args, node),
+      false => self.prelude(name, &args, node),
+    }
+  }
+  fn call_value(&mut self, fun: String, args: &[String], node: &'g AstNode<'a>) -> Result<String, Diagnostic> {
+    let value = format!("(call {}{})", fun, args.iter().map(|arg| format!(" {}", arg)).collect::<String>());
+    match self.type_of(node) {
+      Type::Unit => {
+        self.line(value);
+        Ok(String::new())
+      },
+      ty => {
+        let wasm_type = self.wasm_type(&ty, node)?;
+        Ok(self.temp(wasm_type, value))
+      },
+    }
+  }
+  // Prelude functions and the built-in methods, on arguments already evaluated
+  fn prelude(&mut self, name: &str, args: &[String], node: &'g AstNode<'a>) -> Result<String, Diagnostic> {
+    let first = match node.get_kind() {
+      AstKind::MethodCall => self.type_of(&node[0]),
+      _ => node[1].get_children().first().map(|arg| self.type_of(arg)).unwrap_or(Type::Unit),
+    };
+    let at = self.at(node);
+    let arg = |i: usize| args[i].as_str();
+    let (wasm_type, value) = match (name, &first) {
+      ("print" | "println" | "to_string", _) => {
+        let put = self.put_fn(&first, node)?;
+        let out = self.temp("i32", "(call $carf_buffer)".to_string());
+        self.line(format!("(call {} {} {} (i32.const 0))", put, out, arg(0)));
+        if name == "to_string" {
+          return Ok(self.temp("i32", format!("(call $carf_done {})", out)));
+        }
+        if name == "println" {
+          self.line(format!("(call $carf_put_utf8 {} (i32.const 10))", out));
+        }
+        self.line(format!("(call $carf_print {})", out));
+        return Ok(String::new());
+      },
+      ("abs", Type::Int) => ("i64", format!("(call $carf_abs {} {})", arg(0), at)),
+      ("abs", _) => ("f64", format!("(f64.abs {})", arg(0))),
+      ("min", Type::Int) => ("i64", format!("(select {1} {0} (i64.lt_s {1} {0}))", arg(0), arg(1))),
+      ("max", Type::Int) => ("i64", format!("(select {1} {0} (i64.gt_s {1} {0}))", arg(0), arg(1))),
+      ("min" | "max", _) => ("f64", format!("(call $carf_{}_float {} {})", name, arg(0), arg(1))),
+      ("pow", Type::Int) => ("i64", format!("(call $carf_pow {} {} {})", arg(0), arg(1), at)),
+      ("sqrt", _) => ("f64", format!("(f64.sqrt {})", arg(0))),
+      ("floor", _) => ("i64", format!("(call $carf_floor {} {})", arg(0), at)),
+      ("len", Type::String) => ("i64", format!("(call $carf_str_len {})", arg(0))),
+      ("len", _) => ("i64", format!("(i64.extend_i32_u (i32.load {}))", arg(0))),
+      ("substr", _) => ("i32", format!("(call $carf_substr {} {} {} {})", arg(0), arg(1), arg(2), at)),
+      ("split", _) => ("i32", format!("(call $carf_split {} {} {})", arg(0), arg(1), at)),
+      ("to_int", Type::Float) => ("i64", format!("(call $carf_to_int_float {} {})", arg(0), at)),
+      ("to_int", Type::Char) => ("i64", format!("(i64.extend_i32_u {})", arg(0))),
+      ("to_int", _) => ("i64", format!("(call $carf_to_int_string {} {})", arg(0), at)),
+      ("push", Type::Array(elem, _)) => {
+        let elem = self.wasm_type(elem, node)?;
+        self.line(format!("({}.store (call $carf_push_slot {}) {})", elem, arg(0), arg(1)));
+        return Ok(String::new());
+      },
+      ("pop", Type::Array(elem, _)) => {
+        let elem = self.wasm_type(elem, node)?;
+        return Ok(self.temp(elem, format!("({}.load (call $carf_pop_slot {} {}))", elem, arg(0), at)));
+      },
+      ("sort", Type::Array(elem, _)) => {
+        let kind = match **elem {
+          Type::Int => 0,
+          Type::Float => 1,
+          Type::Char => 2,
+          Type::String => 3,
+          _ => return Err(unsupported(node, &format!("sorting `{}`", first))),
+        };
+        self.line(format!("(call $carf_sort {} (i32.const {}))", arg(0), kind));
+        return Ok(String::new());
+      },
+      ("pow", _) => return Err(unsupported(node, &format!("`pow` on `{}`", first))),
+      _ => return Err(unsupported(node, &format!("`{}`", name))),
+    };
+    Ok(self.temp(wasm_type, value))
+  }
+  fn literal_atom(&mut self, node: &AstNode) -> Result<String, Diagnostic> {
+    let text = node.get_token().get_value();
+    let atom = match node.get_token().get_kind() {
+      TokenKind::Int => literal::parse_int(text).map(|n| format!("(i64.const {})", n)),
+      TokenKind::Float => literal::parse_float(text).map(|x| match x {
+        x if x.is_nan() => "(f64.const nan)".to_string(),
+        x => format!("(f64.const {:?})", x),
+      }),
+      TokenKind::Char => literal::parse_char(text).map(|c| format!("(i32.const {})", c as u32)),
+      TokenKind::String => literal::parse_string(text).map(|s| self.string(&s)),
+      TokenKind::True => Some("(i32.const 1)".to_string()),
+      TokenKind::False => Some("(i32.const 0)".to_string()),
+      _ => None,
+    };
+    atom.ok_or_else(|| Diagnostic::error(Span::of(node), format!("invalid literal `{}`", text)))
+  }
+}
+
+// Places
+impl<'g, 'a> Generator<'g, 'a> {
+  fn place(&mut self, node: &'g AstNode<'a>) -> Result<Place, Diagnostic> {
+    match node.get_kind() {
+      AstKind::Identifier => Ok(self.var(node.get_token().get_value())),
+      AstKind::Expr => self.place(&node[0]),
+      AstKind::Member => {
+        let record = self.expr(&node[0])?;
+        let (offset, wasm_type) = self.member(node)?;
+        Ok(Place::Field(record, offset, wasm_type))
+      },
+      AstKind::Index => {
+        let base = &node[0];
+        let (place, value) = match base.get_kind() {
+          AstKind::Identifier | AstKind::Index | AstKind::Expr | AstKind::Member => {
+            let place = self.place(base)?;
+            let value = self.read(&place);
+            (Some(place), value)
+          },
+          _ => (None, self.expr(base)?),
+        };
+        let ty = self.type_of(base);
+        let wasm_type = self.wasm_type(&ty, base)?;
+        let value = self.temp(wasm_type, value);
+        let index = self.expr(&node[1])?;
+        let at = self.at(node);
+        match (ty, place) {
+          (Type::String, Some(place)) => {
+            self.line(format!("(drop (call $carf_str_at {} {} {}))", value, index, at));
+            Ok(Place::StrChar(Box::new(place), index, at))
+          },
+          (Type::Array(elem, _), _) => {
+            let elem = self.wasm_type(&elem, node)?;
+            let index = self.temp("i64", format!("(call $carf_index {} {} {})", value, index, at));
+            Ok(Place::Elem(value, index, elem))
+          },
+          _ => Err(unsupported(node, "this assignment")),
+        }
+      },
+      _ => Err(unsupported(node, "this assignment")),
+    }
+  }
+  fn read(&self, place: &Place) -> String {
+    match place {
+      Place::Local(name) => format!("(local.get {})", name),
+      Place::Global(name) => format!("(global.get {})", name),
+      Place::Elem(array, index, elem) => format!("({}.load (call $carf_slot {} {}))", elem, array, index),
+      Place::Field(record, offset, wasm_type) => format!("({}.load offset={} {})", wasm_type, offset, record),
+      Place::StrChar(inner, index, at) => format!("(call $carf_str_at {} {} {})", self.read(inner), index, at),
+    }
+  }
+  // Strings are values, so writing a char rebuilds the string held by the inner place
+  fn write(&mut self, place: &Place, value: String) {
+    let line = match place {
+      Place::StrChar(inner, index, at) => {
+        let text = format!("(call $carf_str_set {} {} {} {})", self.read(inner), index, value, at);
+        return self.write(inner, text);
+      },
+      Place::Local(name) => format!("(local.set {} {})", name, value),
+      Place::Global(name) => format!("(global.set {} {})", name, value),
+      // The address is taken here, after the value, which may have grown the array
+      Place::Elem(array, index, elem) => format!("({}.store (call $carf_slot {} {}) {})", elem, array, index, value),
+      Place::Field(record, offset, wasm_type) => format!("({}.store offset={} {} {})", wasm_type, offset, record, value),
+    };
+    self.line(line);
+  }
+}
+
+// Helpers for composite types: `put_` writes a value as `print` does, `eq_`
+// compares two like `==` and `copy_` copies one deeply like `[x; n]`
+impl<'g, 'a> Generator<'g, 'a> {
+  fn put_fn(&mut self, ty: &Type, node: &AstNode<'a>) -> Result<String, Diagnostic> {
+    match ty {
+      Type::Int | Type::Float | Type::Char | Type::String | Type::Bool => Ok(format!("$carf_put_{}", ty)),
+      _ => Ok(format!("$put_{}", self.helpers(ty, node)?)),
+    }
+  }
+  fn eq_of(&mut self, ty: &Type, a: String, b: String, node: &AstNode<'a>) -> Result<String, Diagnostic> {
+    let eq = match ty {
+      Type::Int => "i64.eq".to_string(),
+      Type::Float => "f64.eq".to_string(),
+      Type::Char | Type::Bool => "i32.eq".to_string(),
+      Type::String => "call $carf_eq_string".to_string(),
+      _ => format!("call $eq_{}", self.helpers(ty, node)?),
+    };
+    Ok(format!("({} {} {})", eq, a, b))
+  }
+  fn copy_of(&mut self, ty: &Type, v: String, node: &AstNode<'a>) -> Result<String, Diagnostic> {
+    match ty {
+      Type::Array(..) | Type::Struct(_) => Ok(format!("(call $copy_{} {})", self.helpers(ty, node)?, v)),
+      _ => Ok(v),
+    }
+  }
+  // Generate the helpers of an array or struct type once, giving its mangled name
+  fn helpers(&mut self, ty: &Type, node: &AstNode<'a>) -> Result<String, Diagnostic> {
+    let name = self.mangle(ty, node)?;
+    if !self.helpers.insert(name.clone()) {
+      return Ok(name);
+    }
+    let (put, eq, copy) = match ty {
+      Type::Array(elem, _) => {
+        let wasm_type = self.wasm_type(elem, node)?;
+        let at = |v: &str| format!("({}.load (call $carf_slot (local.get ${}) (local.get $i)))", wasm_type, v);
+        let put = self.put_fn(elem, node)?;
+        let eq = self.eq_of(elem, at("a"), at("b"), node)?;
+        let copy = self.copy_of(elem, at("v"), node)?;
+        // The body run for each index `$i` of the array `$v`
+        let each = |v: &str, body: String| {
+          format!(
+            "    (block $done\n      (loop $next\n        (br_if $done (i64.ge_u (local.get $i) (i64.extend_i32_u (i32.load (local.get ${})))))\n{}        (local.set $i (i64.add (local.get $i) (i64.const 1)))\n        (br $next)))\n",
+            v, body
+          )
+        };
+        let separator = self.string(", ");
+        (
+          format!(
+            "    (local $i i64)\n    (call $carf_put_utf8 (local.get $b) (i32.const 91))\n{}    (call $carf_put_utf8 (local.get $b) (i32.const 93))\n",
+            each("v", format!(
+              "        (if (i32.wrap_i64 (local.get $i)) (then (call $carf_put_str (local.get $b) {})))\n        (call {} (local.get $b) {} (i32.const 1))\n",
+              separator, put, at("v")
+            ))
+          ),
+          format!(
+            "    (local $i i64)\n    (if (i32.eq (local.get $a) (local.get $b)) (then (return (i32.const 1))))\n    (if (i32.ne (i32.load (local.get $a)) (i32.load (local.get $b))) (then (return (i32.const 0))))\n{}    (i32.const 1)\n",
+            each("a", format!("        (if (i32.eqz {}) (then (return (i32.const 0))))\n", eq))
+          ),
+          format!(
+            "    (local $c i32)\n    (local $i i64)\n    (local.set $c (call $carf_array_new (i64.extend_i32_u (i32.load (local.get $v)))))\n{}    (local.get $c)\n",
+            each("v", format!("        ({}.store (call $carf_slot (local.get $c) (local.get $i)) {})\n", wasm_type, copy))
+          ),
+        )
+      },
+      Type::Struct(struct_name) => {
+        let fields = self.checker.get_struct(struct_name).map(|def| def.fields.clone()).unwrap_or_default();
+        let mut put = String::new();
+        let mut eq = String::new();
+        let mut copy = String::new();
+        for (i, (field, field_ty)) in fields.iter().enumerate() {
+          let wasm_type = self.wasm_type(field_ty, node)?;
+          let at = |v: &str| format!("({}.load offset={} (local.get ${}))", wasm_type, i * 8, v);
+          let label = self.string(&format!("{}{}: ", if i == 0 { " " } else { ", " }, field));
+          let put_field = self.put_fn(field_ty, node)?;
+          put.push_str(&format!("    (call $carf_put_str (local.get $b) {})\n    (call {} (local.get $b) {} (i32.const 1))\n", label, put_field, at("v")));
+          let eq_field = self.eq_of(field_ty, at("a"), at("b"), node)?;
+          eq.push_str(&format!("    (if (i32.eqz {}) (then (return (i32.const 0))))\n", eq_field));
+          let copy_field = self.copy_of(field_ty, at("v"), node)?;
+          copy.push_str(&format!("    ({}.store offset={} (local.get $c) {})\n", wasm_type, i * 8, copy_field));
+        }
+        let open = self.string(&format!("{} {{", struct_name));
+        let close = self.string(if fields.is_empty() { "}" } else { " }" });
+        (
+          format!("    (call $carf_put_str (local.get $b) {})\n{}    (call $carf_put_str (local.get $b) {})\n", open, put, close),
+          format!("    (if (i32.eq (local.get $a) (local.get $b)) (then (return (i32.const 1))))\n{}    (i32.const 1)\n", eq),
+          format!("    (local $c i32)\n    (local.set $c (call $carf_alloc (i32.const {})))\n{}    (local.get $c)\n", fields.len() * 8, copy),
+        )
+      },
+      _ => return Err(unsupported(node, &format!("values of type `{}`", ty))),
+    };
+    let heads = [
+      format!("(func $put_{} (param $b i32) (param $v i32) (param $repr i32)", name),
+      format!("(func $eq_{} (param $a i32) (param $b i32) (result i32)", name),
+      format!("(func $copy_{} (param $v i32) (result i32)", name),
+    ];
+    for (head, body) in heads.iter().zip([put, eq, copy]) {
+      self.funcs.push(format!("  {}\n{}  )\n", head, body));
+    }
+    Ok(name)
+  }
+}
+
+// Types, names, strings and emitted lines
+impl<'g, 'a> Generator<'g, 'a> {
+  fn wasm_type(&self, ty: &Type, node: &AstNode<'a>) -> Result<&'static str, Diagnostic> {
+    match ty {
+      Type::Int => Ok("i64"),
+      Type::Float => Ok("f64"),
+      Type::Char | Type::Bool | Type::String | Type::Array(..) | Type::Struct(_) => Ok("i32"),
+      Type::Enum(_) => Err(unsupported(node, "enums")),
+      Type::Fun(..) => Err(unsupported(node, "function values")),
+      Type::Var(_) | Type::Error => {
+        Err(Diagnostic::error(Span::of(node), "type annotations needed to compile this to WebAssembly".to_string()))
+      },
+      _ => Err(unsupported(node, &format!("values of type `{}`", ty))),
+    }
+  }
+  // Identifier naming the type in helper names, e.g. `arr_arr_int` for `[[int]]`
+  fn mangle(&self, ty: &Type, node: &AstNode<'a>) -> Result<String, Diagnostic> {
+    match ty {
+      Type::Array(elem, _) => Ok(format!("arr_{}", self.mangle(elem, node)?)),
+      Type::Struct(name) => Ok(format!("s_{}", name)),
+      Type::Int | Type::Float | Type::Char | Type::String | Type::Bool => Ok(ty.to_string()),
+      _ => self.wasm_type(ty, node).map(str::to_string),
+    }
+  }
+  fn elem_type(&self, node: &AstNode<'a>) -> Result<&'static str, Diagnostic> {
+    match self.type_of(node) {
+      Type::Array(elem, _) => self.wasm_type(&elem, node),
+      ty => self.wasm_type(&ty, node),
+    }
+  }
+  // Byte offset and wasm type of a field, one 8-byte slot each
+  fn field(&self, name: &str, field: &str, node: &AstNode<'a>) -> Result<(usize, &'static str), Diagnostic> {
+    let fields = self.checker.get_struct(name).map(|def| def.fields.clone()).unwrap_or_default();
+    match fields.iter().position(|(f, _)| f == field) {
+      Some(i) => Ok((i * 8, self.wasm_type(&fields[i].1, node)?)),
+      None => Err(unsupported(node, &format!("field `{}`", field))),
+    }
+  }
+  fn member(&self, node: &AstNode<'a>) -> Result<(usize, &'static str), Diagnostic> {
+    match self.type_of(&node[0]) {
+      Type::Struct(name) => self.field(&name, node[1].get_token().get_value(), node),
+      ty => Err(unsupported(node, &format!("fields of `{}`", ty))),
+    }
+  }
+  fn type_of(&self, node: &AstNode<'a>) -> Type {
+    match node.get_kind() {
+      AstKind::Literal => match node.get_token().get_kind() {
+        TokenKind::Int => Type::Int,
+        TokenKind::Float => Type::Float,
+        TokenKind::Char => Type::Char,
+        TokenKind::String => Type::String,
+        _ => Type::Bool,
+      },
+      _ => self.checker.get_type_at(node.get_token().get_pos()).cloned().unwrap_or(Type::Error),
+    }
+  }
+  fn binding_type(&self, name: &AstNode<'a>) -> Type {
+    self.checker.get_binding_type(name.get_token().get_pos()).cloned().unwrap_or(Type::Error)
+  }
+  fn resolve(&self, ty: &AstNode<'a>) -> Type {
+    Type::from_name(ty.get_token().get_value()).unwrap_or_else(|| Type::Struct(ty.get_token().get_value().to_string()))
+  }
+  // Address of `"file:line:col"`, where a runtime error at the node is reported
+  fn at(&mut self, node: &AstNode) -> String {
+    let pos = Span::of(node).start;
+    let file = self.files.lookup(pos);
+    let (line, col) = file.line_col(pos);
+    let at = format!("{}:{}:{}", file.get_path().display(), line, col);
+    self.string(&at)
+  }
+  // Address of the string in the data segment, added once
+  fn string(&mut self, text: &str) -> String {
+    let address = match self.strings.get(text) {
+      Some(address) => *address,
+      None => {
+        while !self.data.len().is_multiple_of(4) {
+          self.data.push(0);
+        }
+        let address = DATA_START + self.data.len();
+        self.data.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        self.data.extend_from_slice(text.as_bytes());
+        self.strings.insert(text.to_string(), address);
+        address
+      },
+    };
+    format!("(i32.const {})", address)
+  }
+  fn declare(&mut self, name: &'a str) -> String {
+    let local = self.fresh(name);
+    self.scopes.last_mut().unwrap().push((name, local.clone()));
+    local
+  }
+  fn is_local(&self, name: &str) -> bool {
+    self.scopes.iter().flatten().any(|(local, _)| *local == name)
+  }
+  // The innermost local of that name, or the global
+  fn var(&self, name: &str) -> Place {
+    match self.scopes.iter().flatten().rev().find(|(local, _)| *local == name) {
+      Some((_, local)) => Place::Local(local.clone()),
+      None => Place::Global(format!("$g_{}", name)),
+    }
+  }
+  fn fresh(&mut self, name: &str) -> String {
+    self.next += 1;
+    format!("${}_{}", name, self.next)
+  }
+  // A new local set to the value, returning the atom that reads it
+  fn temp(&mut self, wasm_type: &'static str, value: String) -> String {
+    let temp = self.local(wasm_type, value);
+    format!("(local.get {})", temp)
+  }
+  // A new local set to the value, returning its name
+  fn local(&mut self, wasm_type: &'static str, value: String) -> String {
+    let local = self.fresh("t");
+    self.locals.push((local.clone(), wasm_type));
+    self.line(format!("(local.set {} {})", local, value));
+    local
+  }
+  fn line(&mut self, line: String) {
+    self.lines.push(format!("{}{}", "  ".repeat(self.indent), line));
+  }
+  fn take_lines(&mut self) -> String {
+    std::mem::take(&mut self.lines).into_iter().map(|line| line + "\n").collect()
+  }
+}
+
+// Comparison instruction of a type, `suffix` telling signed from unsigned
+fn compare(kind: TokenKind, prefix: &str, suffix: &str) -> Option<String> {
+  let name = match kind {
+    TokenKind::DEqual => return Some(format!("{}.eq", prefix)),
+    TokenKind::NEqual => return Some(format!("{}.ne", prefix)),
+    TokenKind::Less => "lt",
+    TokenKind::Greater => "gt",
+    TokenKind::LessEq => "le",
+    TokenKind::GreaterEq => "ge",
+    _ => return None,
+  };
+  Some(format!("{}.{}{}", prefix, name, suffix))
+}
+
+fn unsupported(node: &AstNode, what: &str) -> Diagnostic {
+  super::unsupported(node, what, "WebAssembly")
+}
+
+// WAT string of the bytes, with hex escapes for anything but printable ASCII
+fn wat_string(bytes: &[u8]) -> String {
+  let mut out = String::from("\"");
+  for byte in bytes {
+    match byte {
+      b'"' | b'\\' => out.push_str(&format!("\\{}", *byte as char)),
+      b' '..=b'~' => out.push(*byte as char),
+      _ => out.push_str(&format!("\\{:02x}", byte)),
+    }
+  }
+  out.push('"');
+  out
+}
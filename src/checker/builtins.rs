@@ -0,0 +1,82 @@
+use super::types::{FunSig, Type};
+
+// Signature of a built-in method, the receiver being the first parameter
+pub fn builtin_method(receiver: &Type, name: &str) -> Option<FunSig> {
+  let sig = |params: Vec<Type>, ret: Type| Some(FunSig { params, ret });
+  match (receiver, name) {
+    (Type::String, "len") | (Type::Array(..), "len") => sig(vec![receiver.clone()], Type::Int),
+    (Type::Array(elem, _), "push") => sig(vec![receiver.clone(), (**elem).clone()], Type::Unit),
+    (Type::Array(elem, _), "pop") => sig(vec![receiver.clone()], (**elem).clone()),
+    _ => None,
+  }
+}
+
+// Whether a built-in method changes the length of its receiver
+pub fn resizes(name: &str) -> bool {
+  matches!(name, "push" | "pop")
+}
+
+// Functions of the prelude with the signature shown to the user. `T` is any
+// type and `num` is `int` or `float`, the same one throughout a call.
+pub const BUILTIN_FUNS: [(&str, &str); 17] = [
+  ("print", "fun print(value: T): unit"),
+  ("println", "fun println(value: T): unit"),
+  ("read_line", "fun read_line(): string"),
+  ("abs", "fun abs(x: num): num"),
+  ("min", "fun min(a: num, b: num): num"),
+  ("max", "fun max(a: num, b: num): num"),
+  ("pow", "fun pow(base: num, exp: num): num"),
+  ("sqrt", "fun sqrt(x: float): float"),
+  ("floor", "fun floor(x: float): int"),
+  ("len", "fun len(value: string | [T]): int"),
+  ("substr", "fun substr(s: string, start: int, count: int): string"),
+  ("split", "fun split(s: string, sep: string): [string]"),
+  ("to_int", "fun to_int(value: string | float | char): int"),
+  ("to_string", "fun to_string(value: T): string"),
+  ("push", "fun push(array: [T], value: T): unit"),
+  ("pop", "fun pop(array: [T]): T"),
+  ("sort", "fun sort(array: [T]): unit"),
+];
+
+pub fn is_builtin_fun(name: &str) -> bool {
+  BUILTIN_FUNS.iter().any(|(builtin, _)| *builtin == name)
+}
+
+// Signature of a prelude function for arguments of these types, the first one
+// choosing the overload. `elem` stands for the element type of an array that
+// is not one yet.
+pub fn builtin_fun(name: &str, args: &[Type], elem: Type) -> Option<FunSig> {
+  let sig = |params: Vec<Type>, ret: Type| Some(FunSig { params, ret });
+  let first = args.first().cloned().unwrap_or(Type::Error);
+  let num = match first {
+    Type::Float => Type::Float,
+    _ => Type::Int,
+  };
+  let array = match first {
+    Type::Array(..) => first.clone(),
+    _ => Type::Array(Box::new(elem), None),
+  };
+  let array_elem = match &array {
+    Type::Array(elem, _) => (**elem).clone(),
+    _ => unreachable!(),
+  };
+  match name {
+    "print" | "println" => sig(vec![first], Type::Unit),
+    "read_line" => sig(vec![], Type::String),
+    "abs" => sig(vec![num.clone()], num),
+    "min" | "max" | "pow" => sig(vec![num.clone(), num.clone()], num),
+    "sqrt" => sig(vec![Type::Float], Type::Float),
+    "floor" => sig(vec![Type::Float], Type::Int),
+    "len" if matches!(first, Type::Array(..)) => sig(vec![first], Type::Int),
+    "len" => sig(vec![Type::String], Type::Int),
+    "substr" => sig(vec![Type::String, Type::Int, Type::Int], Type::String),
+    "split" => sig(vec![Type::String, Type::String], Type::Array(Box::new(Type::String), None)),
+    "to_int" if matches!(first, Type::Float | Type::Char) => sig(vec![first], Type::Int),
+    "to_int" => sig(vec![Type::String], Type::Int),
+    "to_string" => sig(vec![first], Type::String),
+    "push" => sig(vec![array, array_elem], Type::Unit),
+    "pop" => sig(vec![array], array_elem),
+    "sort" => sig(vec![array], Type::Unit),
+    _ => None,
+  }
+}
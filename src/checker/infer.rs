@@ -0,0 +1,58 @@
+use super::types::Type;
+
+// Bindings of the type variables handed out for unannotated `let`s
+#[derive(Clone)]
+pub struct Substitution {
+  vars: Vec<Option<Type>>,
+}
+
+impl Default for Substitution {
+  fn default() -> Self {
+    Substitution::new()
+  }
+}
+
+impl Substitution {
+  pub fn new() -> Substitution {
+    Substitution {
+      vars: Vec::new(),
+    }
+  }
+  pub fn fresh(&mut self) -> Type {
+    self.vars.push(None);
+    Type::Var(self.vars.len() as u32 - 1)
+  }
+  // Follow bindings all the way down, leaving only unbound variables
+  pub fn resolve(&self, ty: &Type) -> Type {
+    match ty {
+      Type::Var(v) => match &self.vars[*v as usize] {
+        Some(bound) => self.resolve(bound),
+        None => ty.clone(),
+      },
+      Type::Array(elem, len) => Type::Array(Box::new(self.resolve(elem)), *len),
+      Type::Fun(params, ret) => Type::Fun(params.iter().map(|p| self.resolve(p)).collect(), Box::new(self.resolve(ret))),
+      _ => ty.clone(),
+    }
+  }
+  pub fn unify(&mut self, a: &Type, b: &Type) -> bool {
+    let (a, b) = (self.resolve(a), self.resolve(b));
+    match (&a, &b) {
+      (Type::Error, _) | (_, Type::Error) => true,
+      (Type::Var(x), Type::Var(y)) if x == y => true,
+      (Type::Var(v), other) | (other, Type::Var(v)) => {
+        if other.contains_var(*v) {
+          return false;
+        }
+        self.vars[*v as usize] = Some(other.clone());
+        true
+      },
+      // Lengths must agree: a fixed array seen as a resizable one could be
+      // pushed to, and a resizable one could have any length
+      (Type::Array(x, n), Type::Array(y, m)) => n == m && self.unify(x, y),
+      (Type::Fun(xs, x), Type::Fun(ys, y)) => {
+        xs.len() == ys.len() && xs.iter().zip(ys.iter()).all(|(x, y)| self.unify(x, y)) && self.unify(x, y)
+      },
+      _ => a == b,
+    }
+  }
+}
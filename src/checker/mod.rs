@@ -0,0 +1,1139 @@
+pub mod types;
+pub mod infer;
+pub mod builtins;
+pub mod patterns;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::ast::token::TokenKind;
+use crate::ast::{Ast, AstKind, AstNode};
+use crate::diagnostic::{Diagnostic, Span};
+use crate::fold;
+use crate::printer;
+use crate::utilities::literal;
+use infer::Substitution;
+use patterns::{Ctor, Pat, Space};
+use types::{EnumDef, FunSig, StructDef, Type};
+
+// Operator use whose operand type was still unknown when it was checked
+#[derive(Clone)]
+struct Deferred<'a> {
+  span: Span,
+  op: &'a str,
+  kind: TokenKind,
+  unary: bool,
+  ty: Type,
+}
+
+#[derive(Clone)]
+pub struct Checker<'a> {
+  scopes: Vec<BTreeMap<&'a str, Type>>,
+  funs: BTreeMap<&'a str, FunSig>,
+  structs: BTreeMap<&'a str, StructDef>,
+  enums: BTreeMap<&'a str, EnumDef>,
+  // Keyed by the method key of the implemented type and the method name,
+  // `self` is the first parameter
+  methods: BTreeMap<(String, &'a str), FunSig>,
+  ret_type: Option<Type>,
+  // Type of every checked expression, keyed by the position of its token
+  types: BTreeMap<usize, Type>,
+  // Type of every `let`, parameter and `for` variable, keyed by the position of its name
+  bindings: BTreeMap<usize, Type>,
+  subst: Substitution,
+  deferred: Vec<Deferred<'a>>,
+  item_bindings: Vec<(Span, &'a str, Type)>,
+  // Globals of the host, which a script reads but cannot assign or define again
+  constants: BTreeSet<&'a str>,
+  diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for Checker<'_> {
+  fn default() -> Self {
+    Checker::new()
+  }
+}
+
+impl<'a> Checker<'a> {
+  pub fn new() -> Checker<'a> {
+    Checker {
+      scopes: vec![BTreeMap::new()],
+      funs: BTreeMap::new(),
+      structs: BTreeMap::new(),
+      enums: BTreeMap::new(),
+      methods: BTreeMap::new(),
+      ret_type: None,
+      types: BTreeMap::new(),
+      bindings: BTreeMap::new(),
+      subst: Substitution::new(),
+      deferred: Vec::new(),
+      item_bindings: Vec::new(),
+      constants: BTreeSet::new(),
+      diagnostics: Vec::new(),
+    }
+  }
+  // Declarations made by one call stay visible to the trees checked after it,
+  // the diagnostics returned are those of this call only
+  pub fn check(&mut self, ast: &Ast<'a>) -> &Vec<Diagnostic> {
+    self.diagnostics.clear();
+    let root = ast.get_root();
+    // Type names before any type is resolved, so fields can name any struct or enum
+    let types: Vec<&AstNode<'a>> = root.get_children().iter().filter(|item| matches!(item.get_kind(), AstKind::Struct | AstKind::Enum)).collect();
+    let declared: Vec<bool> = types.iter().map(|item| self.declare_type_name(item)).collect();
+    for (item, _) in types.iter().zip(declared).filter(|(_, declared)| *declared) {
+      match item.get_kind() {
+        AstKind::Struct => self.declare_struct(item),
+        _ => self.declare_enum(item),
+      }
+    }
+    // Signatures first so functions can call each other in any order
+    for item in root.get_children() {
+      match item.get_kind() {
+        AstKind::Fun => self.declare_fun(item),
+        AstKind::Impl => self.declare_impl(item),
+        _ => {},
+      }
+    }
+    for item in root.get_children() {
+      if item.get_kind() == AstKind::Let {
+        self.check_stmt(item);
+        self.finish_item();
+      }
+    }
+    for item in root.get_children() {
+      match item.get_kind() {
+        AstKind::Fun => {
+          self.check_fun(item);
+          self.finish_item();
+        },
+        AstKind::Impl => self.check_impl(item),
+        _ => {},
+      }
+    }
+    for ty in self.types.values_mut().chain(self.bindings.values_mut()) {
+      *ty = self.subst.resolve(ty);
+    }
+    self.diagnostics.sort_by_key(|d| d.span);
+
+    &self.diagnostics
+  }
+  // Check a statement written outside of any function, as the REPL does, and
+  // give the type of its expression
+  pub fn check_root_stmt(&mut self, node: &AstNode<'a>) -> (Type, &Vec<Diagnostic>) {
+    self.diagnostics.clear();
+    self.check_stmt(node);
+    self.finish_item();
+    let ty = match node.get_kind() {
+      AstKind::Stmt if node.child_count() > 0 => {
+        let ty = self.types.get(&node[0].get_token().get_pos()).cloned().unwrap_or(Type::Unit);
+        self.subst.resolve(&ty)
+      },
+      _ => Type::Unit,
+    };
+    self.diagnostics.sort_by_key(|d| d.span);
+    (ty, &self.diagnostics)
+  }
+  // A function the host provides, declared before any tree is checked
+  pub fn declare_native(&mut self, name: &'a str, sig: FunSig) {
+    self.funs.insert(name, sig);
+  }
+  // A global the host provides, which scripts only read
+  pub fn declare_const(&mut self, name: &'a str, ty: Type) {
+    self.scopes[0].insert(name, ty);
+    self.constants.insert(name);
+  }
+  // Drop a function so a later tree can define it again
+  pub fn forget_fun(&mut self, name: &str) {
+    self.funs.remove(name);
+  }
+  // Drop a method of the type named `ty` so a later `impl` can define it again
+  pub fn forget_method(&mut self, ty: &str, name: &str) {
+    self.methods.retain(|(key, method), _| !(key == ty && *method == name));
+  }
+  pub fn get_diagnostics(&self) -> &Vec<Diagnostic> {
+    &self.diagnostics
+  }
+  pub fn get_type_at(&self, pos: usize) -> Option<&Type> {
+    self.types.get(&pos)
+  }
+  // Type of the binding whose name starts at `pos`
+  pub fn get_binding_type(&self, pos: usize) -> Option<&Type> {
+    self.bindings.get(&pos)
+  }
+  pub fn get_fun(&self, name: &str) -> Option<&FunSig> {
+    self.funs.get(name)
+  }
+  pub fn get_struct(&self, name: &str) -> Option<&StructDef> {
+    self.structs.get(name)
+  }
+  pub fn get_enum(&self, name: &str) -> Option<&EnumDef> {
+    self.enums.get(name)
+  }
+  // Method of `ty` named `name`, from an `impl` or built in
+  pub fn get_method(&self, ty: &Type, name: &str) -> Option<FunSig> {
+    let key = ty.method_key();
+    let declared = key.and_then(|key| self.methods.get(&(key, name)).cloned());
+    declared.or_else(|| builtins::builtin_method(ty, name))
+  }
+}
+
+impl<'a> Checker<'a> {
+  fn error(&mut self, span: Span, message: String) {
+    self.diagnostics.push(Diagnostic::error(span, message));
+  }
+  fn warning(&mut self, span: Span, message: String) {
+    self.diagnostics.push(Diagnostic::warning(span, message));
+  }
+  fn lookup(&self, name: &str) -> Option<&Type> {
+    self.scopes.iter().rev().find_map(|scope| scope.get(name))
+  }
+  // Whether the name reads a constant of the host rather than a variable of the script
+  fn is_const(&self, name: &str) -> bool {
+    self.constants.contains(name) && self.scopes.iter().rposition(|scope| scope.contains_key(name)) == Some(0)
+  }
+  fn declare(&mut self, name: &'a str, ty: Type) {
+    self.scopes.last_mut().unwrap().insert(name, ty);
+  }
+  fn resolve_type(&mut self, node: &AstNode<'a>) -> Type {
+    if node.get_token().get_kind() == TokenKind::Empty {
+      return self.subst.fresh();
+    }
+    if node.get_token().get_kind() == TokenKind::Fun {
+      let Some((ret, params)) = node.get_children().split_last() else {
+        return Type::Error;
+      };
+      let params = params.iter().map(|param| self.resolve_type(param)).collect();
+      return Type::Fun(params, Box::new(self.resolve_type(ret)));
+    }
+    if node.get_token().get_kind() == TokenKind::LIndex {
+      if node.child_count() == 0 {
+        return Type::Error;
+      }
+      let elem = self.resolve_type(&node[0]);
+      let len = match node.child_count() {
+        1 => None,
+        _ => match literal::parse_int(node[1].get_token().get_value()).and_then(|n| usize::try_from(n).ok()) {
+          Some(len) => Some(len),
+          None => {
+            self.error(Span::of(&node[1]), format!("invalid array length `{}`", node[1].get_token().get_value()));
+            return Type::Error;
+          },
+        },
+      };
+      return Type::Array(Box::new(elem), len);
+    }
+    let name = node.get_token().get_value();
+    match Type::from_name(name) {
+      Some(ty) => ty,
+      None if self.structs.contains_key(name) => Type::Struct(name.to_string()),
+      None if self.enums.contains_key(name) => Type::Enum(name.to_string()),
+      None => {
+        self.error(Span::of(node), format!("cannot find type `{}`", name));
+        Type::Error
+      },
+    }
+  }
+  fn expect_type(&mut self, expected: &Type, found: &Type, node: &AstNode<'a>, what: &str) {
+    let found = self.fit_array_literal(expected, found, node);
+    if !self.subst.unify(expected, &found) {
+      let (expected, found) = (self.subst.resolve(expected), self.subst.resolve(&found));
+      self.error(Span::of(node), format!("mismatched types in {}: expected `{}`, found `{}`", what, expected, found));
+    }
+  }
+  // An array literal is resizable, unless it is checked against a fixed
+  // length that it has. Nested literals fit the element type the same way.
+  fn fit_array_literal(&mut self, expected: &Type, found: &Type, node: &AstNode<'a>) -> Type {
+    let mut node = node;
+    while node.get_kind() == AstKind::Expr && node.child_count() == 1 {
+      node = &node[0];
+    }
+    let (Type::Array(want_elem, Some(want)), Type::Array(elem, None)) = (self.subst.resolve(expected), self.subst.resolve(found)) else {
+      return found.clone();
+    };
+    let (len, items) = match node.get_kind() {
+      AstKind::Array => (Some(node.child_count()), node.get_children().iter().collect::<Vec<_>>()),
+      AstKind::Repeat if node.child_count() == 2 => (repeat_len(&node[1]), vec![&node[0]]),
+      _ => return found.clone(),
+    };
+    match len {
+      Some(len) if len == want => {},
+      // Its own length, to show in the mismatch
+      Some(len) => return Type::Array(elem, Some(len)),
+      None => return found.clone(),
+    }
+    let fitted: Vec<Type> = items.iter().map(|item| self.fit_array_literal(&want_elem, &elem, item)).collect();
+    let elem = match fitted.first() {
+      Some(first) if fitted.iter().all(|ty| ty == first) => first.clone(),
+      _ => *elem,
+    };
+    let ty = Type::Array(Box::new(elem), Some(want));
+    self.types.insert(node.get_token().get_pos(), ty.clone());
+    ty
+  }
+  // Give an unknown type the only type an operator accepts
+  fn pin(&mut self, ty: &Type, target: Type) {
+    if self.subst.resolve(ty).is_var() {
+      self.subst.unify(ty, &target);
+    }
+  }
+  // Report what inference could not solve once a root level item is done
+  fn finish_item(&mut self) {
+    for (span, name, ty) in std::mem::take(&mut self.item_bindings) {
+      let ty = self.subst.resolve(&ty);
+      if ty.has_vars() {
+        self.error(span, format!("type annotations needed: cannot infer the type of `{}`", name));
+      }
+    }
+    for deferred in std::mem::take(&mut self.deferred) {
+      let ty = self.subst.resolve(&deferred.ty);
+      if ty.has_vars() || ty.is_error() || accepts_operands(deferred.kind, &ty) {
+        continue;
+      }
+      let message = match deferred.unary {
+        true => format!("cannot apply unary operator `{}` to type `{}`", deferred.op, ty),
+        false => format!("cannot apply binary operator `{}` to types `{}` and `{}`", deferred.op, ty, ty),
+      };
+      self.error(deferred.span, message);
+    }
+  }
+  // Claim the name of a struct or enum, false when it cannot be declared
+  fn declare_type_name(&mut self, node: &AstNode<'a>) -> bool {
+    if node.child_count() < 2 || node.is_bad() {
+      return false;
+    }
+    let name = node[0].get_token().get_value();
+    if Type::from_name(name).is_some() {
+      self.error(Span::of_token(&node[0]), format!("`{}` is a builtin type and cannot be redefined", name));
+      return false;
+    }
+    if self.structs.contains_key(name) || self.enums.contains_key(name) {
+      let keyword = node.get_token().get_value();
+      self.error(Span::of_token(&node[0]), format!("{} `{}` is defined more than once", keyword, name));
+      return false;
+    }
+    match node.get_kind() {
+      AstKind::Struct => self.structs.insert(name, StructDef { fields: Vec::new() }).is_none(),
+      _ => self.enums.insert(name, EnumDef { variants: Vec::new() }).is_none(),
+    }
+  }
+  fn declare_struct(&mut self, node: &AstNode<'a>) {
+    let mut fields: Vec<(String, Type)> = Vec::new();
+    for field in node[1].get_children().iter().filter(|f| f.child_count() > 0) {
+      let name = field.get_token().get_value();
+      let ty = self.resolve_type(&field[0]);
+      if fields.iter().any(|(seen, _)| seen == name) {
+        self.error(Span::of_token(field), format!("field `{}` is declared more than once", name));
+        continue;
+      }
+      self.bindings.insert(field.get_token().get_pos(), ty.clone());
+      fields.push((name.to_string(), ty));
+    }
+    self.structs.insert(node[0].get_token().get_value(), StructDef { fields });
+  }
+  fn declare_enum(&mut self, node: &AstNode<'a>) {
+    let mut variants: Vec<(String, Vec<Type>)> = Vec::new();
+    for variant in node[1].get_children() {
+      let name = variant.get_token().get_value();
+      let fields = variant.get_children().iter().map(|ty| self.resolve_type(ty)).collect();
+      if variants.iter().any(|(seen, _)| seen == name) {
+        self.error(Span::of_token(variant), format!("variant `{}` is declared more than once", name));
+        continue;
+      }
+      variants.push((name.to_string(), fields));
+    }
+    self.enums.insert(node[0].get_token().get_value(), EnumDef { variants });
+  }
+  fn declare_fun(&mut self, node: &AstNode<'a>) {
+    if node.child_count() < 4 || node.is_bad() {
+      return;
+    }
+    let name = node[0].get_token().get_value();
+    let params = self.resolve_params(&node[1], None);
+    let ret = self.resolve_type(&node[2]);
+    if self.funs.contains_key(name) {
+      self.error(Span::of_token(&node[0]), format!("function `{}` is defined more than once", name));
+      return;
+    }
+    self.funs.insert(name, FunSig { params, ret });
+  }
+  // Parameter types of a function or lambda, or of a method of `receiver`
+  fn resolve_params(&mut self, node: &AstNode<'a>, receiver: Option<&Type>) -> Vec<Type> {
+    let mut params = Vec::new();
+    for (i, param) in node.get_children().iter().filter(|p| p.child_count() > 0).enumerate() {
+      if param[0].get_token().get_kind() != TokenKind::Empty {
+        params.push(self.resolve_type(&param[0]));
+        continue;
+      }
+      match receiver {
+        Some(receiver) if i == 0 => params.push(receiver.clone()),
+        _ => {
+          self.error(Span::of_token(param), "`self` is only allowed as the first parameter of a method".to_string());
+          params.push(Type::Error);
+        },
+      }
+    }
+    params
+  }
+  fn declare_impl(&mut self, node: &AstNode<'a>) {
+    if node.child_count() < 1 || node.is_bad() {
+      return;
+    }
+    let receiver = self.resolve_type(&node[0]);
+    let key = match receiver.method_key() {
+      Some(key) => key,
+      None => return,
+    };
+    for method in node.get_children().iter().skip(1).filter(|m| m.child_count() == 4 && !m.is_bad()) {
+      let name = method[0].get_token().get_value();
+      let takes_self = method[1].get_children().first().is_some_and(|p| p.child_count() > 0 && p[0].get_token().get_kind() == TokenKind::Empty);
+      let params = self.resolve_params(&method[1], Some(&receiver));
+      let ret = self.resolve_type(&method[2]);
+      if !takes_self {
+        self.error(Span::of_token(&method[0]), format!("method `{}` must take `self` as its first parameter", name));
+        continue;
+      }
+      if builtins::builtin_method(&receiver, name).is_some() {
+        self.error(Span::of_token(&method[0]), format!("method `{}` of `{}` is built in and cannot be redefined", name, receiver));
+        continue;
+      }
+      if self.methods.contains_key(&(key.clone(), name)) {
+        self.error(Span::of_token(&method[0]), format!("method `{}` of `{}` is defined more than once", name, receiver));
+        continue;
+      }
+      self.methods.insert((key.clone(), name), FunSig { params, ret });
+    }
+  }
+  fn check_impl(&mut self, node: &AstNode<'a>) {
+    let key = match self.resolve_type(&node[0]).method_key() {
+      Some(key) => key,
+      None => return,
+    };
+    for method in node.get_children().iter().skip(1).filter(|m| m.child_count() == 4 && !m.is_bad()) {
+      if let Some(sig) = self.methods.get(&(key.clone(), method[0].get_token().get_value())).cloned() {
+        self.check_body(&method[1], &method[3], sig);
+        self.finish_item();
+      }
+    }
+  }
+  fn check_fun(&mut self, node: &AstNode<'a>) {
+    if node.child_count() < 4 || node.is_bad() {
+      return;
+    }
+    let name = node[0].get_token().get_value();
+    let sig = match self.funs.get(name) {
+      Some(sig) => sig.clone(),
+      None => return,
+    };
+    self.check_body(&node[1], &node[3], sig);
+  }
+  // Lambdas nest, so the return type of the enclosing body comes back afterwards
+  fn check_body(&mut self, params: &AstNode<'a>, body: &AstNode<'a>, sig: FunSig) {
+    self.scopes.push(BTreeMap::new());
+    let mut seen = Vec::new();
+    for (param, ty) in params.get_children().iter().zip(sig.params.iter()) {
+      let param_name = param.get_token().get_value();
+      if seen.contains(&param_name) {
+        self.error(Span::of_token(param), format!("parameter `{}` is bound more than once", param_name));
+      }
+      seen.push(param_name);
+      self.bindings.insert(param.get_token().get_pos(), ty.clone());
+      self.declare(param_name, ty.clone());
+    }
+    let outer = self.ret_type.replace(sig.ret);
+    self.check_stmt(body);
+    self.ret_type = outer;
+    self.scopes.pop();
+  }
+}
+
+impl<'a> Checker<'a> {
+  fn check_stmt(&mut self, node: &AstNode<'a>) {
+    match node.get_kind() {
+      AstKind::Let => {
+        if node.child_count() < 2 {
+          return;
+        }
+        let name = node[0].get_token().get_value();
+        let expected = self.resolve_type(&node[1]);
+        if node.child_count() > 2 {
+          let found = self.check_expr(&node[2]);
+          self.expect_type(&expected, &found, &node[2], "`let` initializer");
+        } else if self.ret_type.is_none() {
+          self.error(Span::of_token(&node[0]), format!("global `{}` must be initialized", name));
+        }
+        if self.ret_type.is_none() && self.constants.contains(name) {
+          self.error(Span::of_token(&node[0]), format!("global `{}` is already defined by the host", name));
+        }
+        if expected.is_var() {
+          self.item_bindings.push((Span::of_token(&node[0]), name, expected.clone()));
+        }
+        self.bindings.insert(node[0].get_token().get_pos(), expected.clone());
+        self.declare(name, expected);
+      },
+      AstKind::Stmt if node.child_count() > 0 => {
+        self.check_expr(&node[0]);
+      },
+      AstKind::Block => {
+        self.scopes.push(BTreeMap::new());
+        for stmt in node.get_children() {
+          self.check_stmt(stmt);
+        }
+        self.scopes.pop();
+      },
+      AstKind::If | AstKind::While => {
+        if node.child_count() < 2 {
+          return;
+        }
+        let keyword = node.get_token().get_value();
+        let cond = self.check_expr(&node[0]);
+        self.expect_type(&Type::Bool, &cond, &node[0], &format!("`{}` condition", keyword));
+        self.check_stmt(&node[1]);
+        if node.child_count() > 2 && node[2].child_count() > 0 {
+          self.check_stmt(&node[2][0]);
+        }
+      },
+      AstKind::For => {
+        if node.child_count() < 3 {
+          return;
+        }
+        let iter = self.check_expr(&node[1]);
+        let elem = match self.subst.resolve(&iter) {
+          Type::Range => Type::Int,
+          Type::Array(elem, _) => *elem,
+          Type::String => Type::Char,
+          Type::Error => Type::Error,
+          Type::Var(_) => {
+            self.error(Span::of(&node[1]), "type annotations needed: the type iterated by `for` must be known here".to_string());
+            Type::Error
+          },
+          other => {
+            self.error(Span::of(&node[1]), format!("`for` cannot iterate over `{}`, expected a range, array or string", other));
+            Type::Error
+          },
+        };
+        self.scopes.push(BTreeMap::new());
+        self.bindings.insert(node[0].get_token().get_pos(), elem.clone());
+        self.declare(node[0].get_token().get_value(), elem);
+        self.check_stmt(&node[2]);
+        self.scopes.pop();
+      },
+      AstKind::Return => {
+        let expected = self.ret_type.clone().unwrap_or(Type::Unit);
+        if node.child_count() > 0 {
+          let found = self.check_expr(&node[0]);
+          self.expect_type(&expected, &found, &node[0], "`return` value");
+        } else if !self.subst.unify(&expected, &Type::Unit) {
+          self.error(Span::of_token(node), format!("`return;` in a function returning `{}`", expected));
+        }
+      },
+      _ => {},
+    }
+  }
+}
+
+impl<'a> Checker<'a> {
+  fn check_expr(&mut self, node: &AstNode<'a>) -> Type {
+    let ty = self.infer_expr(node);
+    self.types.insert(node.get_token().get_pos(), ty.clone());
+    ty
+  }
+  fn infer_expr(&mut self, node: &AstNode<'a>) -> Type {
+    let token = node.get_token();
+    match node.get_kind() {
+      AstKind::Literal => match token.get_kind() {
+        TokenKind::Int => Type::Int,
+        TokenKind::Float => Type::Float,
+        TokenKind::Char => Type::Char,
+        TokenKind::String => Type::String,
+        TokenKind::True | TokenKind::False => Type::Bool,
+        _ => Type::Error,
+      },
+      AstKind::Identifier => {
+        let name = token.get_value();
+        match self.lookup(name) {
+          Some(ty) => ty.clone(),
+          None if self.funs.contains_key(name) => self.funs[name].as_type(),
+          None if builtins::is_builtin_fun(name) => {
+            self.error(Span::of_token(node), format!("built-in function `{}` can only be called", name));
+            Type::Error
+          },
+          None => {
+            self.error(Span::of_token(node), format!("cannot find value `{}` in this scope", name));
+            Type::Error
+          },
+        }
+      },
+      AstKind::Expr => match node.child_count() {
+        0 => Type::Error,
+        _ => self.check_expr(&node[0]),
+      },
+      AstKind::UnOper => self.check_unary(node),
+      AstKind::PreStep | AstKind::PostStep => self.check_step(node),
+      AstKind::BinOper => self.check_binary(node),
+      AstKind::Call => self.check_call(node),
+      AstKind::MethodCall => self.check_method_call(node),
+      AstKind::Array => {
+        let elem = self.subst.fresh();
+        for item in node.get_children() {
+          let found = self.check_expr(item);
+          self.expect_type(&elem, &found, item, "array element");
+        }
+        Type::Array(Box::new(elem), None)
+      },
+      AstKind::Repeat => {
+        if node.child_count() < 2 {
+          return Type::Error;
+        }
+        let elem = self.check_expr(&node[0]);
+        let count = self.check_expr(&node[1]);
+        self.expect_type(&Type::Int, &count, &node[1], "array repeat count");
+        Type::Array(Box::new(elem), None)
+      },
+      AstKind::StructLit => self.check_struct_lit(node),
+      AstKind::Path => self.check_variant(node, None),
+      AstKind::Match => self.check_match(node),
+      AstKind::Lambda if node.child_count() == 3 => {
+        let sig = FunSig { params: self.resolve_params(&node[0], None), ret: self.resolve_type(&node[1]) };
+        let ty = sig.as_type();
+        self.check_body(&node[0], &node[2], sig);
+        ty
+      },
+      AstKind::Member => {
+        if node.child_count() < 2 {
+          return Type::Error;
+        }
+        let base = self.check_expr(&node[0]);
+        let field = node[1].get_token().get_value();
+        match self.subst.resolve(&base) {
+          Type::Struct(name) => match self.structs.get(name.as_str()).and_then(|def| def.get_field(field)) {
+            Some(ty) => ty.clone(),
+            None => {
+              self.error(Span::of_token(&node[1]), format!("no field `{}` on type `{}`", field, name));
+              Type::Error
+            },
+          },
+          Type::Error => Type::Error,
+          Type::Var(_) => {
+            self.error(Span::of(&node[0]), "type annotations needed: the type before `.` must be known here".to_string());
+            Type::Error
+          },
+          other => {
+            self.error(Span::of_token(&node[1]), format!("no field `{}` on type `{}`", field, other));
+            Type::Error
+          },
+        }
+      },
+      AstKind::Index => {
+        if node.child_count() < 2 {
+          return Type::Error;
+        }
+        let base = self.check_expr(&node[0]);
+        let index = self.check_expr(&node[1]);
+        self.expect_type(&Type::Int, &index, &node[1], "index");
+        match self.subst.resolve(&base) {
+          Type::Array(elem, _) => *elem,
+          Type::String => Type::Char,
+          Type::Error => Type::Error,
+          Type::Var(_) => {
+            self.error(Span::of(&node[0]), "type annotations needed: the indexed value's type must be known here".to_string());
+            Type::Error
+          },
+          other => {
+            self.error(Span::of(&node[0]), format!("cannot index into a value of type `{}`", other));
+            Type::Error
+          },
+        }
+      },
+      _ => Type::Error,
+    }
+  }
+  fn check_unary(&mut self, node: &AstNode<'a>) -> Type {
+    if node.child_count() < 1 {
+      return Type::Error;
+    }
+    let operand = self.check_expr(&node[0]);
+    let kind = node.get_token().get_kind();
+    if kind == TokenKind::Not {
+      self.pin(&operand, Type::Bool);
+    }
+    let operand = self.subst.resolve(&operand);
+    if operand.is_error() {
+      return Type::Error;
+    }
+    if operand.is_var() {
+      self.deferred.push(Deferred {
+        span: Span::of(node), op: node.get_token().get_value(), kind, unary: true, ty: operand.clone(),
+      });
+      return operand;
+    }
+    let ok = match kind {
+      TokenKind::Minus => operand.is_numeric(),
+      TokenKind::Not => operand == Type::Bool,
+      _ => false,
+    };
+    if !ok {
+      self.error(Span::of(node), format!(
+        "cannot apply unary operator `{}` to type `{}`", node.get_token().get_value(), operand
+      ));
+      return Type::Error;
+    }
+    operand
+  }
+  // Prefix and postfix `++` and `--`
+  fn check_step(&mut self, node: &AstNode<'a>) -> Type {
+    let operand = self.check_expr(&node[0]);
+    self.check_assignable(&node[0]);
+    self.pin(&operand, Type::Int);
+    let operand = self.subst.resolve(&operand);
+    if !operand.is_error() && operand != Type::Int {
+      self.error(Span::of(node), format!(
+        "cannot apply `{}` to type `{}`, expected `int`", node.get_token().get_value(), operand
+      ));
+      return Type::Error;
+    }
+    Type::Int
+  }
+  fn check_binary(&mut self, node: &AstNode<'a>) -> Type {
+    let lhs = self.check_expr(&node[0]);
+    let rhs = self.check_expr(&node[1]);
+    let kind = node.get_token().get_kind();
+    use TokenKind::*;
+    match kind {
+      Equal => {
+        self.check_assignable(&node[0]);
+        self.expect_type(&lhs, &rhs, &node[1], "assignment");
+        return Type::Unit;
+      },
+      PlusEq | MinusEq | AsteriskEq | SlashEq | ModEq | AndEq | OrEq | XorEq | LShiftEq | RShiftEq => {
+        self.check_assignable(&node[0]);
+        let result = self.binary_result(node, strip_assign(kind), &lhs, &rhs);
+        if !result.is_error() && !result.is_var() {
+          self.expect_type(&lhs, &result, node, "compound assignment");
+        }
+        return Type::Unit;
+      },
+      _ => {},
+    }
+    self.binary_result(node, kind, &lhs, &rhs)
+  }
+  // Result type of a binary operator, reporting operand types it does not accept
+  fn binary_result(&mut self, node: &AstNode<'a>, kind: TokenKind, lhs: &Type, rhs: &Type) -> Type {
+    use TokenKind::*;
+    match kind {
+      Mod | And | Or | Xor | LShift | RShift | DDot => {
+        self.pin(lhs, Type::Int);
+        self.pin(rhs, Type::Int);
+      },
+      DAnd | DOr => {
+        self.pin(lhs, Type::Bool);
+        self.pin(rhs, Type::Bool);
+      },
+      _ => {
+        // Both operands share one type, so an unknown side takes the other's
+        if self.subst.resolve(lhs).is_var() || self.subst.resolve(rhs).is_var() {
+          self.subst.unify(lhs, rhs);
+        }
+      },
+    }
+    let (lhs, rhs) = (self.subst.resolve(lhs), self.subst.resolve(rhs));
+    if lhs.is_error() || rhs.is_error() {
+      return match kind {
+        DEqual | NEqual | Less | Greater | LessEq | GreaterEq | DAnd | DOr => Type::Bool,
+        DDot => Type::Range,
+        _ => Type::Error,
+      };
+    }
+    let op = node.get_token().get_value();
+    if lhs.is_var() {
+      self.deferred.push(Deferred { span: Span::of(node), op, kind, unary: false, ty: lhs.clone() });
+      return result_of(kind, &lhs);
+    }
+    if lhs != rhs || !accepts_operands(kind, &lhs) {
+      self.error(Span::of(node), format!(
+        "cannot apply binary operator `{}` to types `{}` and `{}`", op, lhs, rhs
+      ));
+      return match result_of(kind, &lhs) {
+        result @ (Type::Bool | Type::Range) => result,
+        _ => Type::Error,
+      };
+    }
+    result_of(kind, &lhs)
+  }
+  fn check_assignable(&mut self, node: &AstNode<'a>) {
+    // An element or field of a constant is shared with the host, so it is
+    // read only as well
+    let mut root = node;
+    while matches!(root.get_kind(), AstKind::Index | AstKind::Member | AstKind::Expr) && root.child_count() > 0 {
+      root = &root[0];
+    }
+    if root.get_kind() == AstKind::Identifier && self.is_const(root.get_token().get_value()) {
+      self.error(Span::of(node), format!("cannot assign to `{}`, a constant of the host", root.get_token().get_value()));
+      return;
+    }
+    let ok = match node.get_kind() {
+      AstKind::Identifier | AstKind::Member => true,
+      AstKind::Index => node.child_count() > 0 && node[0].get_kind() != AstKind::Literal,
+      AstKind::Expr => {
+        if node.child_count() > 0 {
+          self.check_assignable(&node[0]);
+        }
+        return;
+      },
+      _ => false,
+    };
+    if !ok {
+      self.error(Span::of(node), "cannot assign to this expression".to_string());
+    }
+  }
+  fn check_struct_lit(&mut self, node: &AstNode<'a>) -> Type {
+    let name = node[0].get_token().get_value();
+    let def = match self.structs.get(name) {
+      Some(def) => def.clone(),
+      None => {
+        for init in node.get_children().iter().skip(1).filter(|init| init.child_count() > 0) {
+          self.check_expr(&init[0]);
+        }
+        self.error(Span::of_token(&node[0]), format!("cannot find struct `{}`", name));
+        return Type::Error;
+      },
+    };
+    let mut given = Vec::new();
+    for init in node.get_children().iter().skip(1).filter(|init| init.child_count() > 0) {
+      let field = init.get_token().get_value();
+      let found = self.check_expr(&init[0]);
+      if given.contains(&field) {
+        self.error(Span::of_token(init), format!("field `{}` is given more than once", field));
+        continue;
+      }
+      given.push(field);
+      match def.get_field(field) {
+        Some(expected) => self.expect_type(expected, &found, &init[0], &format!("field `{}`", field)),
+        None => self.error(Span::of_token(init), format!("struct `{}` has no field `{}`", name, field)),
+      }
+    }
+    let missing: Vec<String> = def.fields.iter()
+      .filter(|(field, _)| !given.contains(&field.as_str()))
+      .map(|(field, _)| format!("`{}`", field))
+      .collect();
+    if !missing.is_empty() {
+      let noun = if missing.len() == 1 { "field" } else { "fields" };
+      self.error(Span::of(node), format!("missing {} {} in `{}` literal", noun, missing.join(", "), name));
+    }
+    Type::Struct(name.to_string())
+  }
+  fn check_call(&mut self, node: &AstNode<'a>) -> Type {
+    if node.child_count() < 2 {
+      return Type::Error;
+    }
+    if node[0].get_kind() == AstKind::Path {
+      return self.check_variant(&node[0], Some(node));
+    }
+    let args: Vec<Type> = node[1].get_children().iter().map(|arg| self.check_expr(arg)).collect();
+    let callee = &node[0];
+    // A named function, unless a variable of the same name shadows it
+    if callee.get_kind() == AstKind::Identifier {
+      let name = callee.get_token().get_value();
+      match (self.lookup(name), self.funs.get(name)) {
+        (None, Some(sig)) => {
+          let sig = sig.clone();
+          self.check_args(node, &node[1], &args, &sig.params, "function", name);
+          return sig.ret;
+        },
+        (None, None) if builtins::is_builtin_fun(name) => return self.check_builtin_call(node, name, &args),
+        (None, None) => {
+          self.error(Span::of_token(callee), format!("cannot find function `{}`", name));
+          return Type::Error;
+        },
+        _ => {},
+      }
+    }
+    let ty = self.check_expr(callee);
+    let name = match callee.get_kind() {
+      AstKind::Lambda => "fun".to_string(),
+      _ => printer::print_expr(callee),
+    };
+    match self.subst.resolve(&ty) {
+      Type::Fun(params, ret) => {
+        self.check_args(node, &node[1], &args, &params, "function", &name);
+        *ret
+      },
+      Type::Error => Type::Error,
+      // Calling it is what tells its type
+      Type::Var(v) => {
+        let ret = self.subst.fresh();
+        self.subst.unify(&Type::Var(v), &Type::Fun(args, Box::new(ret.clone())));
+        ret
+      },
+      other => {
+        self.error(Span::of(callee), format!("`{}` is a `{}`, not a function", name, other));
+        Type::Error
+      },
+    }
+  }
+  // `E::V` alone, or called with the fields of the variant
+  fn check_variant(&mut self, path: &AstNode<'a>, call: Option<&AstNode<'a>>) -> Type {
+    let args: Vec<Type> = match call {
+      Some(call) => call[1].get_children().iter().map(|arg| self.check_expr(arg)).collect(),
+      None => Vec::new(),
+    };
+    if path.child_count() < 2 {
+      return Type::Error;
+    }
+    let name = path[0].get_token().get_value();
+    let variant = path[1].get_token().get_value();
+    let fields = match self.enums.get(name).map(|def| def.get_variant(variant).map(|(_, fields)| fields.clone())) {
+      Some(Some(fields)) => fields,
+      Some(None) => {
+        self.error(Span::of_token(&path[1]), format!("no variant `{}` in enum `{}`", variant, name));
+        return Type::Error;
+      },
+      None => {
+        self.error(Span::of_token(&path[0]), format!("cannot find enum `{}`", name));
+        return Type::Error;
+      },
+    };
+    let qualified = format!("{}::{}", name, variant);
+    match call {
+      Some(call) => self.check_args(call, &call[1], &args, &fields, "variant", &qualified),
+      None if !fields.is_empty() => {
+        self.error(Span::of(path), format!("variant `{}` has fields and must be called like `{}(...)`", qualified, qualified));
+      },
+      None => {},
+    }
+    Type::Enum(name.to_string())
+  }
+  fn check_match(&mut self, node: &AstNode<'a>) -> Type {
+    if node.child_count() < 1 {
+      return Type::Error;
+    }
+    let scrutinee = self.check_expr(&node[0]);
+    let result = match node.child_count() {
+      1 => Type::Unit,
+      _ => self.subst.fresh(),
+    };
+    let mut rows = Vec::new();
+    let mut clean = true;
+    for arm in node.get_children().iter().skip(1).filter(|arm| arm.child_count() == 2) {
+      let errors = self.diagnostics.len();
+      self.scopes.push(BTreeMap::new());
+      let pat = self.check_pattern(&arm[0], &scrutinee, &mut Vec::new());
+      clean &= self.diagnostics.len() == errors;
+      let found = self.check_expr(&arm[1]);
+      self.scopes.pop();
+      self.expect_type(&result, &found, &arm[1], "`match` arm");
+      rows.push((pat, &arm[0]));
+    }
+    let ty = self.subst.resolve(&scrutinee);
+    if !clean || ty.has_vars() || ty.is_error() {
+      return result;
+    }
+    let space = Space::new(&self.enums);
+    let mut unreachable = Vec::new();
+    for (i, (pat, _)) in rows.iter().enumerate() {
+      let before: Vec<Vec<Pat>> = rows[..i].iter().map(|(pat, _)| vec![pat.clone()]).collect();
+      if space.useful(&before, std::slice::from_ref(pat), std::slice::from_ref(&ty)).is_none() {
+        unreachable.push(i);
+      }
+    }
+    let all: Vec<Vec<Pat>> = rows.iter().map(|(pat, _)| vec![pat.clone()]).collect();
+    let missing: Vec<String> = space.missing(&all, &ty).iter().map(|pat| format!("`{}`", space.show(pat, &ty))).collect();
+    for i in unreachable {
+      self.warning(Span::of(rows[i].1), "unreachable pattern".to_string());
+    }
+    if !missing.is_empty() {
+      let noun = if missing.len() == 1 { "pattern" } else { "patterns" };
+      self.error(Span::of_token(node), format!("non-exhaustive `match`: {} {} not covered", noun, missing.join(", ")));
+    }
+    result
+  }
+  // Check a pattern against the type it matches, declaring its bindings in
+  // the current scope. `bound` holds the names bound so far in the arm.
+  fn check_pattern(&mut self, node: &AstNode<'a>, expected: &Type, bound: &mut Vec<&'a str>) -> Pat {
+    match node.get_kind() {
+      AstKind::Binding => {
+        let name = node.get_token().get_value();
+        if bound.contains(&name) {
+          self.error(Span::of_token(node), format!("`{}` is bound more than once in the same pattern", name));
+        }
+        bound.push(name);
+        self.bindings.insert(node.get_token().get_pos(), expected.clone());
+        self.declare(name, expected.clone());
+        Pat::Wild
+      },
+      AstKind::Literal | AstKind::UnOper => {
+        let found = self.check_expr(node);
+        self.expect_type(expected, &found, node, "pattern");
+        match patterns::pattern_const(node) {
+          Some(fold::Const::Bool(b)) => Pat::Ctor(Ctor::Bool(b), Vec::new()),
+          Some(c) => Pat::Ctor(Ctor::Lit(c), Vec::new()),
+          None => {
+            self.error(Span::of(node), format!("invalid literal pattern `{}`", printer::print_expr(node)));
+            Pat::Wild
+          },
+        }
+      },
+      AstKind::RangePat if node.child_count() == 2 => {
+        for bound in node.get_children() {
+          let found = self.check_expr(bound);
+          self.expect_type(expected, &found, bound, "range pattern");
+        }
+        let ty = self.subst.resolve(expected);
+        let (lo, hi) = (patterns::pattern_const(&node[0]), patterns::pattern_const(&node[1]));
+        match (lo, hi) {
+          _ if !matches!(ty, Type::Int | Type::Char | Type::Error) => {
+            self.error(Span::of(node), format!("range patterns only match `int` or `char`, not `{}`", ty));
+            Pat::Wild
+          },
+          (Some(lo), Some(hi)) if patterns::in_range(&lo, &hi, &lo) => Pat::Ctor(Ctor::Range(lo, hi), Vec::new()),
+          _ => {
+            self.error(Span::of(node), format!("range pattern `{}` matches nothing", printer::print_pattern(node)));
+            Pat::Wild
+          },
+        }
+      },
+      AstKind::VariantPat if node.child_count() >= 2 => {
+        let name = node[0].get_token().get_value();
+        let variant = node[1].get_token().get_value();
+        let subpatterns = &node.get_children()[2..];
+        let found = match self.enums.get(name).map(|def| def.get_variant(variant).map(|(i, fields)| (i, fields.clone()))) {
+          Some(Some(found)) => Some(found),
+          Some(None) => {
+            self.error(Span::of_token(&node[1]), format!("no variant `{}` in enum `{}`", variant, name));
+            None
+          },
+          None => {
+            self.error(Span::of_token(&node[0]), format!("cannot find enum `{}`", name));
+            None
+          },
+        };
+        let Some((index, fields)) = found else {
+          for sub in subpatterns {
+            self.check_pattern(sub, &Type::Error, bound);
+          }
+          return Pat::Wild;
+        };
+        self.expect_type(expected, &Type::Enum(name.to_string()), node, "pattern");
+        if fields.len() != subpatterns.len() {
+          self.error(Span::of(node), format!(
+            "variant `{}::{}` has {} field{}, but the pattern has {}",
+            name, variant, fields.len(), if fields.len() == 1 { "" } else { "s" }, subpatterns.len()
+          ));
+        }
+        let args = subpatterns.iter().enumerate()
+          .map(|(i, sub)| self.check_pattern(sub, fields.get(i).unwrap_or(&Type::Error), bound))
+          .collect();
+        Pat::Ctor(Ctor::Variant(index), args)
+      },
+      _ => Pat::Wild,
+    }
+  }
+  fn check_method_call(&mut self, node: &AstNode<'a>) -> Type {
+    if node.child_count() < 3 {
+      return Type::Error;
+    }
+    let receiver = self.check_expr(&node[0]);
+    let args: Vec<Type> = node[2].get_children().iter().map(|arg| self.check_expr(arg)).collect();
+    let name = node[1].get_token().get_value();
+    let receiver = match self.subst.resolve(&receiver) {
+      Type::Error => return Type::Error,
+      Type::Var(_) => {
+        self.error(Span::of(&node[0]), "type annotations needed: the type before `.` must be known here".to_string());
+        return Type::Error;
+      },
+      receiver => receiver,
+    };
+    let sig = match self.get_method(&receiver, name) {
+      Some(sig) => sig,
+      None => {
+        self.error(Span::of_token(&node[1]), format!("no method `{}` on type `{}`", name, receiver));
+        return Type::Error;
+      },
+    };
+    if let Type::Array(_, Some(_)) = receiver && builtins::builtin_method(&receiver, name).is_some() && builtins::resizes(name) {
+      self.error(Span::of_token(&node[1]), format!("cannot `{}` on `{}`, its length is fixed", name, receiver));
+    }
+    self.check_args(node, &node[2], &args, &sig.params[1..], "method", name);
+    sig.ret
+  }
+  // A prelude function, unless the program declares one of the same name
+  fn check_builtin_call(&mut self, node: &AstNode<'a>, name: &str, args: &[Type]) -> Type {
+    let resolved: Vec<Type> = args.iter().map(|arg| self.subst.resolve(arg)).collect();
+    let elem = self.subst.fresh();
+    let sig = match builtins::builtin_fun(name, &resolved, elem) {
+      Some(sig) => sig,
+      None => return Type::Error,
+    };
+    self.check_args(node, &node[1], args, &sig.params, "function", name);
+    match (name, resolved.first()) {
+      ("push" | "pop", Some(array @ Type::Array(_, Some(_)))) => {
+        self.error(Span::of(&node[1][0]), format!("cannot `{}` on `{}`, its length is fixed", name, array));
+      },
+      ("sort", Some(array @ Type::Array(elem, _))) if !elem.is_var() && !accepts_operands(TokenKind::Less, elem) => {
+        self.error(Span::of(&node[1][0]), format!("cannot sort `{}`, its elements cannot be compared with `<`", array));
+      },
+      _ => {},
+    }
+    sig.ret
+  }
+  fn check_args(&mut self, node: &AstNode<'a>, arg_nodes: &AstNode<'a>, args: &[Type], params: &[Type], what: &str, name: &str) {
+    if params.len() != args.len() {
+      self.error(Span::of(node), format!(
+        "{} `{}` takes {} argument{} but {} {} supplied",
+        what, name, params.len(), if params.len() == 1 { "" } else { "s" },
+        args.len(), if args.len() == 1 { "was" } else { "were" }
+      ));
+      return;
+    }
+    for (i, (param, arg)) in params.iter().zip(args.iter()).enumerate() {
+      self.expect_type(param, arg, &arg_nodes[i], &format!("argument {} of `{}`", i + 1, name));
+    }
+  }
+}
+
+// Length of `[x; n]` for a literal count `n`, the only one known while checking
+fn repeat_len(count: &AstNode) -> Option<usize> {
+  match count.get_kind() {
+    AstKind::Literal => literal::parse_int(count.get_token().get_value()).and_then(|n| usize::try_from(n).ok()),
+    _ => None,
+  }
+}
+
+// Whether a binary operator (or unary `-`) takes operands of type `ty`
+fn accepts_operands(kind: TokenKind, ty: &Type) -> bool {
+  use TokenKind::*;
+  match kind {
+    Plus => matches!(ty, Type::Int | Type::Float | Type::String),
+    Minus | Asterisk | Slash => ty.is_numeric(),
+    Mod | And | Or | Xor | LShift | RShift | DDot => *ty == Type::Int,
+    DAnd | DOr | Not => *ty == Type::Bool,
+    DEqual | NEqual => !matches!(ty, Type::Unit | Type::Range | Type::Fun(..)),
+    Less | Greater | LessEq | GreaterEq => matches!(ty, Type::Int | Type::Float | Type::Char | Type::String),
+    _ => false,
+  }
+}
+
+fn result_of(kind: TokenKind, operand: &Type) -> Type {
+  use TokenKind::*;
+  match kind {
+    DDot => Type::Range,
+    DAnd | DOr | DEqual | NEqual | Less | Greater | LessEq | GreaterEq => Type::Bool,
+    _ => operand.clone(),
+  }
+}
+
+// `+=` to `+` and so on
+pub fn strip_assign(kind: TokenKind) -> TokenKind {
+  use TokenKind::*;
+  match kind {
+    PlusEq => Plus,
+    MinusEq => Minus,
+    AsteriskEq => Asterisk,
+    SlashEq => Slash,
+    ModEq => Mod,
+    AndEq => And,
+    OrEq => Or,
+    XorEq => Xor,
+    LShiftEq => LShift,
+    RShiftEq => RShift,
+    _ => kind,
+  }
+}
@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+
+use crate::ast::token::TokenKind;
+use crate::ast::{AstKind, AstNode};
+use crate::fold::Const;
+use crate::utilities::literal;
+use super::types::{EnumDef, Type};
+
+// A `match` pattern reduced to the values it covers, bindings become wildcards
+#[derive(Clone, PartialEq, Debug)]
+pub enum Pat {
+  Wild,
+  Ctor(Ctor, Vec<Pat>),
+}
+
+// What a pattern requires of a value before looking at its fields
+#[derive(Clone, PartialEq, Debug)]
+pub enum Ctor {
+  // Index of the variant in its enum
+  Variant(usize),
+  Bool(bool),
+  Lit(Const),
+  // `lo..hi`, `hi` excluded
+  Range(Const, Const),
+}
+
+// Value of a literal pattern, which may be a negated number
+pub fn pattern_const(node: &AstNode) -> Option<Const> {
+  match node.get_kind() {
+    AstKind::UnOper if node.get_token().get_kind() == TokenKind::Minus && node.child_count() == 1 => {
+      match Const::from_literal(&node[0])? {
+        Const::Int(n) => n.checked_neg().map(Const::Int),
+        Const::Float(x) => Some(Const::Float(-x)),
+        _ => None,
+      }
+    },
+    _ => Const::from_literal(node),
+  }
+}
+
+// Whether `lo..hi` holds `c`, for ints and chars
+pub fn in_range(lo: &Const, hi: &Const, c: &Const) -> bool {
+  match (lo, hi, c) {
+    (Const::Int(lo), Const::Int(hi), Const::Int(n)) => lo <= n && n < hi,
+    (Const::Char(lo), Const::Char(hi), Const::Char(c)) => lo <= c && c < hi,
+    _ => false,
+  }
+}
+
+// Whether every value `c` stands for is also one `d` stands for. Ranges are
+// only compared as a whole, so this may answer false for a range that a few
+// literals happen to cover, which only makes a pattern look useful.
+fn covers(d: &Ctor, c: &Ctor) -> bool {
+  match (d, c) {
+    (Ctor::Range(lo, hi), Ctor::Lit(n)) => in_range(lo, hi, n),
+    (Ctor::Range(lo, hi), Ctor::Range(a, b)) => match (lo, hi, a, b) {
+      (Const::Int(lo), Const::Int(hi), Const::Int(a), Const::Int(b)) => lo <= a && b <= hi,
+      (Const::Char(lo), Const::Char(hi), Const::Char(a), Const::Char(b)) => lo <= a && b <= hi,
+      _ => false,
+    },
+    _ => d == c,
+  }
+}
+
+fn wilds(n: usize) -> Vec<Pat> {
+  vec![Pat::Wild; n]
+}
+
+// Exhaustiveness and reachability of patterns, by the usefulness algorithm:
+// a pattern is useful after some rows when it matches a value none of them does
+pub struct Space<'m, 'a> {
+  enums: &'m BTreeMap<&'a str, EnumDef>,
+}
+
+impl<'m, 'a> Space<'m, 'a> {
+  pub fn new(enums: &'m BTreeMap<&'a str, EnumDef>) -> Space<'m, 'a> {
+    Space { enums }
+  }
+  // Types of the fields of a value of `ty` built by `ctor`
+  fn fields(&self, ty: &Type, ctor: &Ctor) -> Vec<Type> {
+    match (ty, ctor) {
+      (Type::Enum(name), Ctor::Variant(i)) => self.enums.get(name.as_str()).map(|def| def.variants[*i].1.clone()).unwrap_or_default(),
+      _ => Vec::new(),
+    }
+  }
+  // Every constructor of `ty`, None when they are too many to list
+  fn all_ctors(&self, ty: &Type) -> Option<Vec<Ctor>> {
+    match ty {
+      Type::Bool => Some(vec![Ctor::Bool(true), Ctor::Bool(false)]),
+      Type::Enum(name) => self.enums.get(name.as_str()).map(|def| (0..def.variants.len()).map(Ctor::Variant).collect()),
+      _ => None,
+    }
+  }
+  // Rows that match values built by `ctor`, with the fields in place of the first column
+  fn specialize(&self, rows: &[Vec<Pat>], ctor: &Ctor, arity: usize) -> Vec<Vec<Pat>> {
+    rows.iter().filter_map(|row| {
+      let mut head = match &row[0] {
+        Pat::Wild => wilds(arity),
+        Pat::Ctor(d, args) if covers(d, ctor) => match args.len() {
+          0 => wilds(arity),
+          _ => args.clone(),
+        },
+        Pat::Ctor(..) => return None,
+      };
+      head.extend(row[1..].iter().cloned());
+      Some(head)
+    }).collect()
+  }
+  // A value `v` matches and no row does, one pattern per column of `tys`,
+  // or None when `v` is not useful after `rows`
+  pub fn useful(&self, rows: &[Vec<Pat>], v: &[Pat], tys: &[Type]) -> Option<Vec<Pat>> {
+    if v.is_empty() {
+      return rows.is_empty().then(Vec::new);
+    }
+    let rebuild = |ctor: &Ctor, arity: usize, mut witness: Vec<Pat>| {
+      let rest = witness.split_off(arity);
+      let mut out = vec![Pat::Ctor(ctor.clone(), witness)];
+      out.extend(rest);
+      out
+    };
+    match &v[0] {
+      Pat::Ctor(ctor, args) => {
+        let fields = self.fields(&tys[0], ctor);
+        let arity = fields.len();
+        let mut next = match args.len() {
+          0 => wilds(arity),
+          _ => args.clone(),
+        };
+        next.extend(v[1..].iter().cloned());
+        let mut next_tys = fields;
+        next_tys.extend(tys[1..].iter().cloned());
+        let witness = self.useful(&self.specialize(rows, ctor, arity), &next, &next_tys)?;
+        Some(rebuild(ctor, arity, witness))
+      },
+      Pat::Wild => {
+        let heads: Vec<&Ctor> = rows.iter().filter_map(|row| match &row[0] {
+          Pat::Ctor(ctor, _) => Some(ctor),
+          Pat::Wild => None,
+        }).collect();
+        let all = self.all_ctors(&tys[0]);
+        let missing: Vec<Ctor> = all.iter().flatten().filter(|c| !heads.iter().any(|d| covers(d, c))).cloned().collect();
+        // Every constructor appears, so one of them has to be useful
+        if let Some(all) = all.filter(|_| missing.is_empty()) {
+          return all.into_iter().find_map(|ctor| {
+            let mut next = vec![Pat::Ctor(ctor, Vec::new())];
+            next.extend(v[1..].iter().cloned());
+            self.useful(rows, &next, tys)
+          });
+        }
+        let default: Vec<Vec<Pat>> = rows.iter().filter(|row| row[0] == Pat::Wild).map(|row| row[1..].to_vec()).collect();
+        let mut witness = self.useful(&default, &v[1..], &tys[1..])?;
+        let head = match missing.into_iter().next() {
+          Some(ctor) => {
+            let arity = self.fields(&tys[0], &ctor).len();
+            Pat::Ctor(ctor, wilds(arity))
+          },
+          None => Pat::Wild,
+        };
+        witness.insert(0, head);
+        Some(witness)
+      },
+    }
+  }
+  // Values of `ty` no row matches, one witness for each constructor left out
+  pub fn missing(&self, rows: &[Vec<Pat>], ty: &Type) -> Vec<Pat> {
+    let tys = [ty.clone()];
+    match self.all_ctors(ty) {
+      Some(all) => all.into_iter()
+        .filter_map(|ctor| self.useful(rows, &[Pat::Ctor(ctor, Vec::new())], &tys))
+        .map(|mut witness| witness.remove(0))
+        .collect(),
+      None => self.useful(rows, &[Pat::Wild], &tys).map(|mut witness| witness.remove(0)).into_iter().collect(),
+    }
+  }
+  // Source text of a witness
+  pub fn show(&self, pat: &Pat, ty: &Type) -> String {
+    match pat {
+      Pat::Wild => "_".to_string(),
+      Pat::Ctor(Ctor::Bool(b), _) => b.to_string(),
+      Pat::Ctor(Ctor::Lit(c), _) => show_const(c),
+      Pat::Ctor(Ctor::Range(lo, hi), _) => format!("{}..{}", show_const(lo), show_const(hi)),
+      Pat::Ctor(ctor @ Ctor::Variant(i), args) => {
+        let Type::Enum(name) = ty else {
+          return "_".to_string();
+        };
+        let variant = self.enums.get(name.as_str()).map(|def| def.variants[*i].0.as_str()).unwrap_or("_");
+        let fields = self.fields(ty, ctor);
+        match args.is_empty() {
+          true => format!("{}::{}", name, variant),
+          false => {
+            let args: Vec<String> = args.iter().zip(fields.iter()).map(|(arg, ty)| self.show(arg, ty)).collect();
+            format!("{}::{}({})", name, variant, args.join(", "))
+          },
+        }
+      },
+    }
+  }
+}
+
+fn show_const(c: &Const) -> String {
+  match c {
+    Const::Int(n) => n.to_string(),
+    Const::Float(x) => literal::format_float(*x),
+    Const::Char(c) => literal::quote_char(*c),
+    Const::Str(s) => literal::quote_string(s),
+    Const::Bool(b) => b.to_string(),
+  }
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Type {
+  Int,
+  Float,
+  Char,
+  String,
+  Bool,
+  Unit,
+  // Element type and, when it is known, the length
+  Array(Box<Type>, Option<usize>),
+  // Declared with `struct`, named
+  Struct(String),
+  // Declared with `enum`, named
+  Enum(String),
+  // Function value: parameter types and return type
+  Fun(Vec<Type>, Box<Type>),
+  // Produced by `a..b`, only usable as the iterator of `for`
+  Range,
+  // Unknown type of an unannotated `let`, solved by unification
+  Var(u32),
+  // Stands in for an expression that already failed to check, so errors do not cascade
+  Error,
+}
+
+// Names accepted by `Type::from_name`
+pub const TYPE_NAMES: [&str; 6] = ["int", "float", "char", "string", "bool", "unit"];
+
+impl Type {
+  pub fn from_name(name: &str) -> Option<Type> {
+    match name {
+      "int" => Some(Type::Int),
+      "float" => Some(Type::Float),
+      "char" => Some(Type::Char),
+      "string" => Some(Type::String),
+      "bool" => Some(Type::Bool),
+      "unit" => Some(Type::Unit),
+      _ => None,
+    }
+  }
+  pub fn is_error(&self) -> bool {
+    *self == Type::Error
+  }
+  pub fn is_var(&self) -> bool {
+    matches!(self, Type::Var(_))
+  }
+  pub fn contains_var(&self, v: u32) -> bool {
+    match self {
+      Type::Var(x) => *x == v,
+      Type::Array(elem, _) => elem.contains_var(v),
+      Type::Fun(params, ret) => params.iter().any(|p| p.contains_var(v)) || ret.contains_var(v),
+      _ => false,
+    }
+  }
+  pub fn has_vars(&self) -> bool {
+    match self {
+      Type::Var(_) => true,
+      Type::Array(elem, _) => elem.has_vars(),
+      Type::Fun(params, ret) => params.iter().any(|p| p.has_vars()) || ret.has_vars(),
+      _ => false,
+    }
+  }
+  // Name the methods of an `impl` are found under, None for types that cannot have one
+  pub fn method_key(&self) -> Option<String> {
+    match self {
+      Type::Struct(name) | Type::Enum(name) => Some(name.clone()),
+      Type::Int | Type::Float | Type::Char | Type::String | Type::Bool | Type::Unit => Some(self.to_string()),
+      _ => None,
+    }
+  }
+  pub fn is_numeric(&self) -> bool {
+    matches!(self, Type::Int | Type::Float)
+  }
+}
+
+impl fmt::Display for Type {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Type::Int => write!(f, "int"),
+      Type::Float => write!(f, "float"),
+      Type::Char => write!(f, "char"),
+      Type::String => write!(f, "string"),
+      Type::Bool => write!(f, "bool"),
+      Type::Unit => write!(f, "unit"),
+      Type::Array(elem, Some(len)) => write!(f, "[{}; {}]", elem, len),
+      Type::Array(elem, None) => write!(f, "[{}]", elem),
+      Type::Struct(name) | Type::Enum(name) => write!(f, "{}", name),
+      Type::Fun(params, ret) => {
+        let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+        write!(f, "fun({}): {}", params.join(", "), ret)
+      },
+      Type::Range => write!(f, "range"),
+      Type::Var(_) => write!(f, "_"),
+      Type::Error => write!(f, "{{error}}"),
+    }
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FunSig {
+  pub params: Vec<Type>,
+  pub ret: Type,
+}
+
+impl FunSig {
+  // Type of the function used as a value
+  pub fn as_type(&self) -> Type {
+    Type::Fun(self.params.clone(), Box::new(self.ret.clone()))
+  }
+}
+
+impl fmt::Display for FunSig {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.as_type())
+  }
+}
+
+// Fields of a `struct` in declaration order
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct StructDef {
+  pub fields: Vec<(String, Type)>,
+}
+
+impl StructDef {
+  pub fn get_field(&self, name: &str) -> Option<&Type> {
+    self.fields.iter().find(|(field, _)| field == name).map(|(_, ty)| ty)
+  }
+}
+
+// Variants of an `enum` in declaration order, each with the types of its fields
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EnumDef {
+  pub variants: Vec<(String, Vec<Type>)>,
+}
+
+impl EnumDef {
+  // Index and field types of a variant
+  pub fn get_variant(&self, name: &str) -> Option<(usize, &Vec<Type>)> {
+    self.variants.iter().position(|(variant, _)| variant == name).map(|i| (i, &self.variants[i].1))
+  }
+}
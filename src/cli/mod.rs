@@ -0,0 +1,417 @@
+use std::io::{BufReader, IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::ast::Ast;
+use crate::backend;
+use crate::checker::Checker;
+use crate::diagnostic::{self, Diagnostic};
+use crate::driver::{self, Analysis};
+use crate::ir::{self, passes::PassManager};
+use crate::interpreter::{Interpreter, limits::Limits, prelude::Console, value::Value};
+use crate::lexer;
+use crate::module::Program;
+use crate::parser::Parser;
+use crate::printer;
+use crate::repl;
+use crate::source::SourceMap;
+use crate::lsp;
+
+const USAGE: &str = "\
+usage: carf <command> [options] [file]
+
+Reads the file, or standard input when it is omitted or `-`. `run`, `check`,
+`c`, `wat`, `ir` and `desugar` also read the modules it imports, found under the file's
+directory.
+
+commands:
+  run       check the program, then run its `main`
+  check     report parse and semantic diagnostics
+  c         check the program, then print it as one C99 file
+  wat       check the program, then print it as a WebAssembly text module
+  ir        check the program, then print its SSA intermediate representation
+  desugar   check the program, then print it without compound assignments,
+            statement `++` and `--`, or `for` over a range
+  fmt       print the formatted source, or rewrite the file in place
+  ast       dump the syntax tree
+  tokens    dump the tokens
+  repl      start an interactive session
+  lsp       serve the Language Server Protocol over stdio
+
+options:
+  --check                        with fmt, only report whether the source is formatted
+  --max-steps=N                  with run, stop after N statements and expressions
+  --max-depth=N                  with run, stop when calls nest deeper than N (default 256)
+                                 or than the stack holds
+  --max-heap=BYTES               with run, stop once the program allocated BYTES
+  --timeout=MS                   with run, stop after MS milliseconds
+  --passes=LIST                  with ir, run these passes in order, each of dce,
+                                 copy-prop and cse, e.g. `--passes=cse,copy-prop,dce`
+  --message-format=human|json    how diagnostics are printed
+  --color=auto|always|never      colored diagnostics
+  -h, --help                     print this message";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Command {
+  Run,
+  Check,
+  C,
+  Wat,
+  Ir,
+  Desugar,
+  Fmt,
+  Ast,
+  Tokens,
+  Repl,
+  Lsp,
+}
+
+struct Options {
+  command: Command,
+  file: Option<String>,
+  json: bool,
+  color: bool,
+  check: bool,
+  limits: Limits,
+  passes: PassManager,
+}
+
+// Stack of the thread the commands run on, room for far deeper calls than the
+// default depth limit
+pub const STACK_SIZE: usize = 256 << 20;
+
+// Entry point of the `carf` binary, returns the exit code
+pub fn main() -> i32 {
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  let thread = std::thread::Builder::new()
+    .stack_size(STACK_SIZE)
+    .spawn(move || run(&args, &mut std::io::stdin(), &mut std::io::stdout(), &mut std::io::stderr()));
+  match thread.map(|thread| thread.join()) {
+    Ok(Ok(code)) => code,
+    _ => 2,
+  }
+}
+
+// Exit code 0 on success, 1 when the source has errors, 2 on bad usage or I/O failure
+pub fn run(args: &[String], stdin: &mut dyn Read, out: &mut dyn Write, err: &mut dyn Write) -> i32 {
+  if args.iter().any(|a| a == "-h" || a == "--help") {
+    let _ = writeln!(out, "{}", USAGE);
+    return 0;
+  }
+  let options = match parse_args(args) {
+    Ok(options) => options,
+    Err(message) => {
+      let _ = writeln!(err, "error: {}\n\n{}", message, USAGE);
+      return 2;
+    },
+  };
+  if options.command == Command::Repl {
+    return match repl::run(&mut BufReader::new(stdin), out) {
+      Ok(()) => 0,
+      Err(e) => {
+        let _ = writeln!(err, "error: {}", e);
+        2
+      },
+    };
+  }
+  if options.command == Command::Lsp {
+    return match lsp::run(&mut BufReader::new(stdin), out) {
+      Ok(code) => code,
+      Err(e) => {
+        let _ = writeln!(err, "error: {}", e);
+        2
+      },
+    };
+  }
+  let source = match read_source(&options, stdin) {
+    Ok(source) => source,
+    Err(message) => {
+      let _ = writeln!(err, "error: {}", message);
+      return 2;
+    },
+  };
+  let program = || Program::new(PathBuf::from(file_name(&options)), source.clone());
+  let result = match options.command {
+    Command::Run => run_program(&options, &program(), stdin, out, err),
+    Command::Check => check_program(&options, &program(), out, err),
+    Command::C => compile(&options, &program(), backend::c::generate, out, err),
+    Command::Wat => compile(&options, &program(), backend::wat::generate, out, err),
+    Command::Ir => print_ir(&options, &program(), out, err),
+    Command::Desugar => print_core(&options, &program(), out, err),
+    Command::Fmt => fmt_file(&options, &source, out, err),
+    Command::Ast => dump_ast(&options, &source, out, err),
+    Command::Tokens => dump_tokens(&source, out),
+    Command::Repl | Command::Lsp => unreachable!(),
+  };
+  match result {
+    Ok(code) => code,
+    Err(e) => {
+      let _ = writeln!(err, "error: {}", e);
+      2
+    },
+  }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+  let mut args = args.iter();
+  let command = match args.next().map(|s| s.as_str()) {
+    Some("run") => Command::Run,
+    Some("check") => Command::Check,
+    Some("c") => Command::C,
+    Some("wat") => Command::Wat,
+    Some("ir") => Command::Ir,
+    Some("desugar") => Command::Desugar,
+    Some("fmt") => Command::Fmt,
+    Some("ast") => Command::Ast,
+    Some("tokens") => Command::Tokens,
+    Some("repl") => Command::Repl,
+    Some("lsp") => Command::Lsp,
+    Some(other) => return Err(format!("unknown command `{}`", other)),
+    None => return Err("no command given".to_string()),
+  };
+  let mut options = Options {
+    command,
+    file: None,
+    json: false,
+    color: std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+    check: false,
+    // Calls may take half the stack of the thread the commands run on
+    limits: Limits { max_stack: STACK_SIZE / 2, ..Limits::default() },
+    passes: PassManager::new(),
+  };
+  for arg in args {
+    match arg.as_str() {
+      "--check" if command == Command::Fmt => options.check = true,
+      flag if command == Command::Run && flag.starts_with("--max-steps=") => {
+        options.limits.max_steps = Some(number(flag)?);
+      },
+      flag if command == Command::Run && flag.starts_with("--max-depth=") => {
+        options.limits.max_depth = number(flag)?;
+      },
+      flag if command == Command::Run && flag.starts_with("--max-heap=") => {
+        options.limits.max_heap = Some(number(flag)?);
+      },
+      flag if command == Command::Run && flag.starts_with("--timeout=") => {
+        options.limits.timeout = Some(Duration::from_millis(number(flag)?));
+      },
+      flag if command == Command::Ir && flag.starts_with("--passes=") => {
+        options.passes = PassManager::parse(&flag["--passes=".len()..])?;
+      },
+      "--message-format=human" => options.json = false,
+      "--message-format=json" => options.json = true,
+      "--color=always" => options.color = true,
+      "--color=never" => options.color = false,
+      "--color=auto" => {},
+      "-" => options.file = None,
+      flag if flag.starts_with('-') => return Err(format!("unknown option `{}`", flag)),
+      file => {
+        if options.file.is_some() {
+          return Err("only one file can be given".to_string());
+        }
+        options.file = Some(file.to_string());
+      },
+    }
+  }
+  Ok(options)
+}
+
+// Value of a `--name=N` option
+fn number<T: std::str::FromStr>(flag: &str) -> Result<T, String> {
+  let (name, value) = flag.split_once('=').unwrap();
+  value.parse().map_err(|_| format!("`{}` takes a number, not `{}`", name, value))
+}
+
+fn read_source(options: &Options, stdin: &mut dyn Read) -> Result<String, String> {
+  let mut source = String::new();
+  match &options.file {
+    Some(path) => {
+      source = std::fs::read_to_string(path).map_err(|e| format!("cannot read `{}`: {}", path, e))?;
+    },
+    None => {
+      stdin.read_to_string(&mut source).map_err(|e| format!("cannot read standard input: {}", e))?;
+    },
+  }
+  Ok(source)
+}
+
+fn file_name(options: &Options) -> &str {
+  options.file.as_deref().unwrap_or("<stdin>")
+}
+
+// JSON lines go to stdout for tools, human readable text goes to stderr. Each
+// diagnostic is shown in the file it points into.
+fn report(options: &Options, files: &SourceMap, diagnostics: &[Diagnostic], out: &mut dyn Write, err: &mut dyn Write) -> std::io::Result<()> {
+  for d in diagnostics {
+    if options.json {
+      writeln!(out, "{}", files.to_json(d))?;
+    } else {
+      writeln!(err, "{}", files.render(d, options.color))?;
+    }
+  }
+  Ok(())
+}
+
+// The one file read, for the commands that do not follow imports
+fn source_map(options: &Options, source: &str) -> SourceMap {
+  let mut files = SourceMap::new();
+  files.add(PathBuf::from(file_name(options)), source.to_string());
+  files
+}
+
+// 1 when any diagnostic is an error
+fn exit_code(diagnostics: &[Diagnostic]) -> i32 {
+  match diagnostics.iter().any(|d| d.is_error()) {
+    true => 1,
+    false => 0,
+  }
+}
+
+// The checked program, folded when it has no errors
+fn analyze(program: &Program) -> Analysis<'_> {
+  let mut analysis = driver::analyze_program(program);
+  analysis.fold();
+  analysis
+}
+
+fn check_program(options: &Options, program: &Program, out: &mut dyn Write, err: &mut dyn Write) -> std::io::Result<i32> {
+  let analysis = analyze(program);
+  report(options, &program.files, &analysis.diagnostics, out, err)?;
+  Ok(exit_code(&analysis.diagnostics))
+}
+
+fn run_program(options: &Options, program: &Program, stdin: &mut dyn Read, out: &mut dyn Write, err: &mut dyn Write) -> std::io::Result<i32> {
+  let mut analysis = analyze(program);
+  report(options, &program.files, &analysis.diagnostics, out, err)?;
+  if analysis.has_errors() {
+    return Ok(1);
+  }
+  let diagnostics = analysis.desugar();
+  if !diagnostics.is_empty() {
+    report(options, &program.files, &diagnostics, out, err)?;
+    return Ok(1);
+  }
+  // `read_line` gets what is left of standard input, nothing when the program came from it
+  let mut interpreter = Interpreter::empty();
+  interpreter.set_console(Console::new(Box::new(BufReader::new(stdin)), Box::new(&mut *out)));
+  interpreter.set_limits(options.limits.clone());
+  let result = interpreter.load(&analysis.ast).and_then(|_| interpreter.run_main());
+  drop(interpreter);
+  match result {
+    Ok(Value::Unit) => Ok(0),
+    Ok(value) => {
+      writeln!(out, "{}", value)?;
+      Ok(0)
+    },
+    Err(error) => {
+      report(options, &program.files, &[error], out, err)?;
+      Ok(1)
+    },
+  }
+}
+
+// Code a backend generates from the folded and desugared program, or why it could not
+type Generate = for<'a> fn(&Ast<'a>, &Checker<'a>, &SourceMap) -> Result<String, Vec<Diagnostic>>;
+
+fn compile(options: &Options, program: &Program, generate: Generate, out: &mut dyn Write, err: &mut dyn Write) -> std::io::Result<i32> {
+  let mut analysis = analyze(program);
+  report(options, &program.files, &analysis.diagnostics, out, err)?;
+  if analysis.has_errors() {
+    return Ok(1);
+  }
+  let diagnostics = analysis.desugar();
+  if !diagnostics.is_empty() {
+    report(options, &program.files, &diagnostics, out, err)?;
+    return Ok(1);
+  }
+  match generate(&analysis.ast, &analysis.checker, &program.files) {
+    Ok(code) => {
+      write!(out, "{}", code)?;
+      Ok(0)
+    },
+    Err(diagnostics) => {
+      report(options, &program.files, &diagnostics, out, err)?;
+      Ok(1)
+    },
+  }
+}
+
+// The program lowered to IR, with the `--passes` pipeline run over it
+fn print_ir(options: &Options, program: &Program, out: &mut dyn Write, err: &mut dyn Write) -> std::io::Result<i32> {
+  let mut analysis = analyze(program);
+  report(options, &program.files, &analysis.diagnostics, out, err)?;
+  if analysis.has_errors() {
+    return Ok(1);
+  }
+  let diagnostics = analysis.desugar();
+  if !diagnostics.is_empty() {
+    report(options, &program.files, &diagnostics, out, err)?;
+    return Ok(1);
+  }
+  let mut module = match ir::lower::lower(&analysis.ast, &analysis.checker) {
+    Ok(module) => module,
+    Err(diagnostics) => {
+      report(options, &program.files, &diagnostics, out, err)?;
+      return Ok(1);
+    },
+  };
+  if let Err(e) = options.passes.run(&mut module) {
+    writeln!(err, "error: {}", e)?;
+    return Ok(2);
+  }
+  write!(out, "{}", module)?;
+  Ok(0)
+}
+
+// The program in the core language the desugaring pass leaves
+fn print_core(options: &Options, program: &Program, out: &mut dyn Write, err: &mut dyn Write) -> std::io::Result<i32> {
+  let mut analysis = driver::analyze_program(program);
+  report(options, &program.files, &analysis.diagnostics, out, err)?;
+  if analysis.has_errors() {
+    return Ok(1);
+  }
+  let diagnostics = analysis.desugar();
+  if !diagnostics.is_empty() {
+    report(options, &program.files, &diagnostics, out, err)?;
+    return Ok(1);
+  }
+  write!(out, "{}", printer::print_ast(&analysis.ast))?;
+  Ok(0)
+}
+
+fn fmt_file(options: &Options, source: &str, out: &mut dyn Write, err: &mut dyn Write) -> std::io::Result<i32> {
+  let formatted = match printer::format_source(source) {
+    Ok(formatted) => formatted,
+    Err(errors) => {
+      report(options, &source_map(options, source), &errors, out, err)?;
+      return Ok(1);
+    },
+  };
+  if options.check {
+    if formatted == source {
+      return Ok(0);
+    }
+    writeln!(err, "{} is not formatted", file_name(options))?;
+    return Ok(1);
+  }
+  match &options.file {
+    Some(path) if formatted != source => std::fs::write(path, formatted)?,
+    Some(_) => {},
+    None => write!(out, "{}", formatted)?,
+  }
+  Ok(0)
+}
+
+fn dump_ast(options: &Options, source: &str, out: &mut dyn Write, err: &mut dyn Write) -> std::io::Result<i32> {
+  let files = source_map(options, source);
+  let mut parser = Parser::for_file(files.lookup(0));
+  let ast = parser.parse();
+  write!(out, "{}", ast.get_root().dump_tree())?;
+  let errors = diagnostic::parse_errors(ast);
+  report(options, &files, &errors, out, err)?;
+  Ok(exit_code(&errors))
+}
+
+fn dump_tokens(source: &str, out: &mut dyn Write) -> std::io::Result<i32> {
+  write!(out, "{}", lexer::dump_tokens(source))?;
+  Ok(0)
+}
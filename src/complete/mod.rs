@@ -0,0 +1,431 @@
+use std::collections::BTreeMap;
+
+use crate::ast::token::{self, Token, TokenKind};
+use crate::checker::builtins::BUILTIN_FUNS;
+use crate::checker::types::TYPE_NAMES;
+use crate::lexer::Lexer;
+use crate::parser::{Parser, ITEM_KINDS, PUB_KINDS};
+use crate::utilities::trie::Trie;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CompletionKind {
+  Keyword,
+  Type,
+  Function,
+  Variable,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Completion {
+  pub label: String,
+  pub kind: CompletionKind,
+  // Signature of a function or declaration of a variable
+  pub detail: Option<String>,
+}
+
+impl Completion {
+  fn new(label: &str, kind: CompletionKind, detail: Option<String>) -> Completion {
+    Completion { label: label.to_string(), kind, detail }
+  }
+}
+
+// What the grammar accepts at the cursor
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Position {
+  // Start of a root level item
+  Item,
+  // Start of a method inside an `impl`
+  Method,
+  // After `pub`, an item it can mark
+  Pub,
+  // Start of a statement, which can also be an expression
+  Stmt,
+  Expr,
+  // After the `:` of an annotation
+  Type,
+  // After `for i`
+  ForIn,
+  // A name being declared, or right after an operand
+  Nothing,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum BlockKind {
+  Fun,
+  Loop,
+  If,
+  // Fields of a `struct` declaration
+  Struct,
+  // Fields of a struct literal
+  Literal,
+  // Variants of an `enum` declaration
+  Enum,
+  // Arms of a `match`, each starting with a pattern
+  Match,
+  Impl,
+  Other,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ParenKind {
+  // Condition of `if` or `while`, a statement follows it
+  Cond,
+  Params,
+  // Parameter types of a `fun(...)` type
+  Type,
+  Other,
+}
+
+struct Var<'a> {
+  name: &'a str,
+  detail: String,
+}
+
+struct Block<'a> {
+  kind: BlockKind,
+  vars: Vec<Var<'a>>,
+  // Brackets open at its `{`
+  depth: usize,
+}
+
+// Scopes and brackets open at the cursor. It only looks at tokens, so it keeps
+// going where the parser would give up on broken or unfinished source.
+struct Scan<'a> {
+  blocks: Vec<Block<'a>>,
+  parens: Vec<ParenKind>,
+  // Block kind and names of the next body, like the parameters of a `fun` header
+  pending: Option<Block<'a>>,
+  // `let` whose name becomes visible at its `;`
+  pending_let: Option<Var<'a>>,
+  globals: Vec<Var<'a>>,
+  // Kind of the block the last `}` closed
+  closed: Option<BlockKind>,
+  // Kind of the last `)`
+  closed_paren: Option<ParenKind>,
+}
+
+impl<'a> Scan<'a> {
+  fn run(tokens: &[Token<'a>]) -> Scan<'a> {
+    let mut scan = Scan {
+      blocks: Vec::new(),
+      parens: Vec::new(),
+      pending: None,
+      pending_let: None,
+      globals: Vec::new(),
+      closed: None,
+      closed_paren: None,
+    };
+    for i in 0..tokens.len() {
+      scan.step(tokens, i);
+    }
+    scan
+  }
+  fn step(&mut self, tokens: &[Token<'a>], i: usize) {
+    let kind_at = |j: usize| tokens.get(j).map(|t| t.get_kind());
+    let prev = i.checked_sub(1).and_then(kind_at);
+    match tokens[i].get_kind() {
+      TokenKind::LParen => {
+        let kind = match (i.checked_sub(2).and_then(kind_at), prev) {
+          (_, Some(TokenKind::If | TokenKind::While)) => ParenKind::Cond,
+          (Some(TokenKind::Fun), Some(TokenKind::Identifier)) => ParenKind::Params,
+          (Some(TokenKind::Colon), Some(TokenKind::Fun)) => ParenKind::Type,
+          (_, Some(TokenKind::Fun)) if self.parens.last() == Some(&ParenKind::Type) => ParenKind::Type,
+          // A lambda
+          (_, Some(TokenKind::Fun)) => ParenKind::Params,
+          _ => ParenKind::Other,
+        };
+        self.parens.push(kind);
+      },
+      TokenKind::RParen => {
+        self.closed_paren = self.parens.pop();
+        if self.closed_paren == Some(ParenKind::Params) {
+          let open = (0..i).rev().find(|j| tokens[*j].get_kind() == TokenKind::LParen).unwrap_or(i);
+          self.pending = Some(Block { kind: BlockKind::Fun, vars: params(&tokens[open + 1..i]), depth: 0 });
+        }
+      },
+      // The `;` of `[int; 4]` and `[0; n]` ends no statement
+      TokenKind::LIndex => self.parens.push(ParenKind::Other),
+      TokenKind::RIndex => { self.parens.pop(); },
+      TokenKind::While | TokenKind::For => self.pending = Some(Block { kind: BlockKind::Loop, vars: Vec::new(), depth: 0 }),
+      TokenKind::If => self.pending = Some(Block { kind: BlockKind::If, vars: Vec::new(), depth: 0 }),
+      TokenKind::Else => self.pending = Some(Block { kind: BlockKind::Other, vars: Vec::new(), depth: 0 }),
+      TokenKind::Identifier if prev == Some(TokenKind::For) => {
+        let name = tokens[i].get_value();
+        if let Some(block) = &mut self.pending {
+          block.vars.push(Var { name, detail: format!("(loop variable) {}", name) });
+        }
+      },
+      TokenKind::Identifier if prev == Some(TokenKind::Let) => {
+        let name = tokens[i].get_value();
+        let detail = match (kind_at(i + 1), type_at(tokens, i + 2)) {
+          (Some(TokenKind::Colon), Some(ty)) => format!("let {}: {}", name, ty),
+          _ => format!("let {}", name),
+        };
+        self.pending_let = Some(Var { name, detail });
+      },
+      TokenKind::Struct => self.pending = Some(Block { kind: BlockKind::Struct, vars: Vec::new(), depth: 0 }),
+      TokenKind::Impl => self.pending = Some(Block { kind: BlockKind::Impl, vars: Vec::new(), depth: 0 }),
+      TokenKind::Enum => self.pending = Some(Block { kind: BlockKind::Enum, vars: Vec::new(), depth: 0 }),
+      TokenKind::Match => self.pending = Some(Block { kind: BlockKind::Match, vars: Vec::new(), depth: 0 }),
+      TokenKind::LStmt => {
+        let kind = match prev {
+          Some(TokenKind::Identifier) if self.pending.is_none() => BlockKind::Literal,
+          _ => BlockKind::Other,
+        };
+        let mut block = self.pending.take().unwrap_or(Block { kind, vars: Vec::new(), depth: 0 });
+        block.depth = self.parens.len();
+        self.blocks.push(block);
+      },
+      TokenKind::RStmt => {
+        self.closed = self.blocks.pop().map(|block| block.kind);
+        // A literal or `match` can sit inside brackets, a body ends the ones left open in it
+        if !matches!(self.closed, Some(BlockKind::Literal | BlockKind::Match)) {
+          self.parens.clear();
+        }
+      },
+      TokenKind::SemiColon if self.parens.is_empty() => {
+        if let Some(var) = self.pending_let.take() {
+          match self.blocks.last_mut() {
+            Some(block) => block.vars.push(var),
+            None => self.globals.push(var),
+          }
+        }
+        // A body without braces ends here
+        if self.pending.as_ref().is_some_and(|block| block.kind != BlockKind::Fun) {
+          self.pending = None;
+        }
+      },
+      _ => {},
+    }
+  }
+  fn in_loop(&self) -> bool {
+    let pending = self.pending.as_ref().is_some_and(|block| block.kind == BlockKind::Loop);
+    pending || self.blocks.iter().any(|block| block.kind == BlockKind::Loop)
+  }
+  // Kind of the field, variant or arm list the cursor is directly in
+  fn fields(&self) -> Option<BlockKind> {
+    let block = self.blocks.last()?;
+    let fields = matches!(block.kind, BlockKind::Struct | BlockKind::Literal | BlockKind::Enum | BlockKind::Match);
+    (fields && block.depth == self.parens.len()).then_some(block.kind)
+  }
+  // Inside the parentheses of a variant declaration
+  fn in_variant(&self) -> bool {
+    self.blocks.last().is_some_and(|block| block.kind == BlockKind::Enum && block.depth + 1 == self.parens.len())
+  }
+  fn position(&self, tokens: &[Token<'a>]) -> Position {
+    let prev = tokens.last().map(|t| t.get_kind());
+    let before = tokens.len().checked_sub(2).map(|i| tokens[i].get_kind());
+    let statement = match self.blocks.last().map(|block| block.kind) {
+      None | Some(BlockKind::Struct | BlockKind::Enum) => Position::Item,
+      Some(BlockKind::Impl) => Position::Method,
+      Some(_) => Position::Stmt,
+    };
+    use TokenKind::*;
+    match (prev, self.fields()) {
+      (Some(LStmt | Comma), Some(_)) => return Position::Nothing,
+      (Some(Colon), Some(BlockKind::Literal)) => return Position::Expr,
+      (Some(LParen | Comma), None) if self.in_variant() => return Position::Type,
+      _ => {},
+    }
+    match prev {
+      None => Position::Item,
+      Some(Fun | Let | For | Struct | Enum | Import | Dot | DColon) => Position::Nothing,
+      Some(Pub) => Position::Pub,
+      Some(RStmt) if matches!(self.closed, Some(BlockKind::Literal | BlockKind::Match)) => Position::Nothing,
+      Some(RStmt) if matches!(self.closed, Some(BlockKind::Struct | BlockKind::Enum)) => Position::Item,
+      Some(LParen | Comma) if self.parens.last() == Some(&ParenKind::Params) => Position::Nothing,
+      Some(LParen | Comma) if self.parens.last() == Some(&ParenKind::Type) => Position::Type,
+      Some(Colon | Impl) => Position::Type,
+      Some(LIndex) if tokens.iter().rev().find(|t| t.get_kind() != LIndex).is_some_and(|t| t.get_kind() == Colon) => Position::Type,
+      Some(Identifier) if before == Some(For) => Position::ForIn,
+      Some(RParen) if self.closed_paren == Some(ParenKind::Cond) => Position::Stmt,
+      Some(RParen) if self.closed_paren == Some(ParenKind::Params) => Position::Nothing,
+      Some(Else) => Position::Stmt,
+      Some(LStmt | RStmt) => statement,
+      Some(SemiColon) if self.parens.is_empty() => statement,
+      // After an operand `++` and `--` are postfix, otherwise their operand follows
+      Some(DPlus | DMinus) if matches!(before, Some(Identifier | Int | Float | Char | String | True | False | RParen | RIndex)) => Position::Nothing,
+      Some(Identifier | Int | Float | Char | String | True | False | RParen | RIndex) => Position::Nothing,
+      Some(_) => Position::Expr,
+    }
+  }
+}
+
+// Text of the type annotation starting at `start`, None when it is incomplete
+fn type_at(tokens: &[Token], start: usize) -> Option<String> {
+  let mut text = String::new();
+  let mut depth = 0_usize;
+  for token in tokens.get(start..)? {
+    match token.get_kind() {
+      TokenKind::LIndex => depth += 1,
+      TokenKind::RIndex => depth = depth.checked_sub(1)?,
+      TokenKind::SemiColon => {
+        text.push_str("; ");
+        continue;
+      },
+      TokenKind::Identifier | TokenKind::Int => {},
+      _ => return None,
+    }
+    text.push_str(token.get_value());
+    if depth == 0 {
+      return Some(text);
+    }
+  }
+  None
+}
+
+// Parameters of a `fun` header, from the tokens between its parentheses
+fn params<'a>(tokens: &[Token<'a>]) -> Vec<Var<'a>> {
+  tokens.split(|t| t.get_kind() == TokenKind::Comma)
+    .filter(|param| param.first().is_some_and(|t| t.get_kind() == TokenKind::Identifier))
+    .map(|param| {
+      let name = param[0].get_value();
+      // `self` of a method has no annotation
+      if param.len() == 1 && name == "self" {
+        return Var { name, detail: format!("(parameter) {}", name) };
+      }
+      let ty = type_at(param, 2).unwrap_or_default();
+      Var { name, detail: format!("(parameter) {}: {}", name, ty) }
+    })
+    .collect()
+}
+
+// Name and signature of every root level `fun` whose header is complete,
+// methods inside an `impl` are only called through a value
+fn funs<'a>(tokens: &[Token<'a>]) -> Vec<(&'a str, String)> {
+  let mut funs = Vec::new();
+  let mut depth = 0_usize;
+  for (i, window) in tokens.windows(3).enumerate() {
+    match window[0].get_kind() {
+      TokenKind::LStmt => depth += 1,
+      TokenKind::RStmt => depth = depth.saturating_sub(1),
+      _ => {},
+    }
+    let kinds: Vec<TokenKind> = window.iter().map(|t| t.get_kind()).collect();
+    if depth > 0 || kinds != [TokenKind::Fun, TokenKind::Identifier, TokenKind::LParen] {
+      continue;
+    }
+    let start = i + 3;
+    let Some(close) = tokens[start..].iter().position(|t| matches!(t.get_kind(), TokenKind::RParen | TokenKind::LStmt)) else {
+      continue;
+    };
+    let close = start + close;
+    let ret = &tokens[close..];
+    if ret.len() < 3 || ret[0].get_kind() != TokenKind::RParen || ret[1].get_kind() != TokenKind::Colon {
+      continue;
+    }
+    let Some(ret) = type_at(ret, 2) else {
+      continue;
+    };
+    let params: Vec<String> = params(&tokens[start..close]).iter()
+      .map(|param| param.detail.trim_start_matches("(parameter) ").to_string())
+      .collect();
+    let name = window[1].get_value();
+    funs.push((name, format!("fun {}({}): {}", name, params.join(", "), ret)));
+  }
+  funs
+}
+
+// Candidates for the word being typed at `offset`, in order
+pub fn complete(source: &str, offset: usize) -> Vec<Completion> {
+  let offset = offset.min(source.len());
+  if !source.is_char_boundary(offset) {
+    return Vec::new();
+  }
+  let mut lexer = Lexer::new(source);
+  let mut all = Vec::new();
+  while let Some(token) = lexer.next() {
+    if token.get_kind() == TokenKind::EOF {
+      break;
+    }
+    all.push(token);
+  }
+  let in_comment = lexer.get_comments().iter().any(|(pos, text)| *pos < offset && offset <= pos + text.len());
+  if in_comment {
+    return Vec::new();
+  }
+  let mut end = all.iter().take_while(|t| t.get_pos() < offset).count();
+  let mut prefix = "";
+  if let Some(last) = end.checked_sub(1).map(|i| all[i]) {
+    let (start, stop) = last.get_span();
+    // Inside a literal, or after one that is still open
+    let literal = last.get_value().starts_with(['"', '\'']);
+    let closed = matches!(last.get_kind(), TokenKind::String | TokenKind::Char);
+    if literal && (offset < stop || (offset == stop && !closed)) {
+      return Vec::new();
+    }
+    // The word under the cursor is what gets completed
+    if last.get_value().starts_with(|c: char| c.is_alphabetic() || c == '_') && offset <= stop {
+      prefix = &source[start..offset];
+      end -= 1;
+    }
+  }
+  let tokens = &all[..end];
+  let scan = Scan::run(tokens);
+  let position = scan.position(tokens);
+
+  let mut candidates: BTreeMap<String, Completion> = BTreeMap::new();
+  let mut offer = |completion: Completion| {
+    candidates.insert(completion.label.clone(), completion);
+  };
+  let parser = Parser::new(Lexer::new(""));
+  for keyword in token::KEYWORD_LIST {
+    let kind = token::keyword_token_map(keyword);
+    let valid = match position {
+      Position::Item => ITEM_KINDS.contains(&kind),
+      Position::Method => kind == TokenKind::Fun,
+      Position::Pub => PUB_KINDS.contains(&kind),
+      Position::Stmt => match kind {
+        TokenKind::Else => tokens.last().map(|t| t.get_kind()) == Some(TokenKind::RStmt) && scan.closed == Some(BlockKind::If),
+        TokenKind::Break | TokenKind::Continue => scan.in_loop(),
+        // A statement starting with `fun` is a nested item, not a lambda
+        _ => parser.starts_stmt(&kind) || (parser.starts_expr(&kind) && !ITEM_KINDS.contains(&kind)),
+      },
+      Position::Expr => parser.starts_expr(&kind),
+      Position::ForIn => kind == TokenKind::In,
+      Position::Type => kind == TokenKind::Fun,
+      Position::Nothing => false,
+    };
+    if valid {
+      offer(Completion::new(keyword, CompletionKind::Keyword, None));
+    }
+  }
+  if position == Position::Type {
+    for name in TYPE_NAMES {
+      offer(Completion::new(name, CompletionKind::Type, None));
+    }
+    for window in all.windows(2).filter(|w| matches!(w[0].get_kind(), TokenKind::Struct | TokenKind::Enum) && w[1].get_kind() == TokenKind::Identifier) {
+      let name = window[1].get_value();
+      offer(Completion::new(name, CompletionKind::Type, Some(format!("{} {}", window[0].get_value(), name))));
+    }
+  }
+  if matches!(position, Position::Stmt | Position::Expr) {
+    // Inner names shadow outer ones, and variables shadow functions
+    for (name, signature) in BUILTIN_FUNS {
+      offer(Completion::new(name, CompletionKind::Function, Some(signature.to_string())));
+    }
+    for (name, signature) in funs(&all) {
+      offer(Completion::new(name, CompletionKind::Function, Some(signature)));
+    }
+    // Every global is visible inside a function, only earlier ones at the root
+    let all_globals;
+    let globals = match scan.blocks.is_empty() {
+      true => &scan.globals,
+      false => {
+        all_globals = Scan::run(&all).globals;
+        &all_globals
+      },
+    };
+    let locals = scan.blocks.iter().flat_map(|block| block.vars.iter());
+    let pending = scan.pending.iter().flat_map(|block| block.vars.iter());
+    for var in globals.iter().chain(locals).chain(pending) {
+      offer(Completion::new(var.name, CompletionKind::Variable, Some(var.detail.clone())));
+    }
+  }
+
+  let mut trie = Trie::new();
+  for label in candidates.keys() {
+    trie.insert(label);
+  }
+  trie.words_with_prefix(prefix).iter().filter_map(|label| candidates.remove(label)).collect()
+}
@@ -0,0 +1,226 @@
+use std::collections::BTreeSet;
+
+use crate::ast::token::{Token, TokenKind};
+use crate::ast::{Ast, AstKind, AstNode};
+use crate::checker::strip_assign;
+use crate::diagnostic::Span;
+use crate::utilities::intern::intern;
+
+// Rewrites a checked tree into the core language in place:
+//   `t op= e`          `t = t op (e)`
+//   `t++;`, `--t;`     `t = t + 1;`, `t = t - 1;`, prefix or postfix alike
+//   `for i in a..b S`  `{ let __i_next = a; let __i_end = b;
+//                        while (__i_next < __i_end) { let i = __i_next; __i_next = __i_next + 1; S } }`
+// New nodes take the positions of the tokens they stand for, so every span
+// stays within the source of the construct it replaces. Two nodes only share a
+// position when they have the same type, so checking the result again gives
+// every node the type the backends look up at its position. A statement whose
+// target indexes with a call or the like first saves the index in a `let`,
+// to evaluate it once. A `++` or `--` whose value is used, and a compound
+// assignment to such a target outside a statement, have no core form and stay.
+// The names the pass adds are numbered past any the program uses.
+pub fn desugar(ast: &mut Ast) {
+  let mut taken = BTreeSet::new();
+  identifiers(ast.get_root(), &mut taken);
+  desugar_node(ast.get_mut_root(), &taken);
+}
+
+fn identifiers<'a>(node: &AstNode<'a>, taken: &mut BTreeSet<&'a str>) {
+  if node.get_token().get_kind() == TokenKind::Identifier {
+    taken.insert(node.get_token().get_value());
+  }
+  for child in node.get_children() {
+    identifiers(child, taken);
+  }
+}
+
+// `base`, or `base` with the first number that makes it a name the program does not use
+fn hidden(base: &str, taken: &BTreeSet<&str>) -> &'static str {
+  let mut name = base.to_string();
+  let mut n = 1;
+  while taken.contains(name.as_str()) {
+    name = format!("{}{}", base, n);
+    n += 1;
+  }
+  intern(&name)
+}
+
+fn desugar_node(node: &mut AstNode, taken: &BTreeSet<&str>) {
+  for child in node.get_mut_children().iter_mut() {
+    desugar_node(child, taken);
+  }
+  match node.get_kind() {
+    AstKind::Stmt if node.child_count() == 1 => desugar_stmt(node, taken),
+    AstKind::BinOper if node.child_count() == 2 && is_compound(node.get_token().get_kind()) && is_pure(&node[0]) => {
+      assign(node);
+    },
+    AstKind::For if node.child_count() == 3 => desugar_for(node, taken),
+    _ => {},
+  }
+}
+
+fn desugar_stmt(stmt: &mut AstNode, taken: &BTreeSet<&str>) {
+  let expr = &stmt[0];
+  let step = matches!(expr.get_kind(), AstKind::PreStep | AstKind::PostStep) && expr.child_count() == 1;
+  let compound = expr.get_kind() == AstKind::BinOper && is_compound(expr.get_token().get_kind()) && expr.child_count() == 2;
+  if !(step || compound) || !is_place(&expr[0]) {
+    return;
+  }
+  let mut lets = Vec::new();
+  hoist(&mut stmt[0][0], &mut lets, taken);
+  if step {
+    into_compound(&mut stmt[0]);
+  }
+  assign(&mut stmt[0]);
+  if lets.is_empty() {
+    return;
+  }
+  let pos = Span::of(stmt).start;
+  let inner = std::mem::replace(stmt, AstNode::new(Token::new(TokenKind::LStmt, "{", pos), AstKind::Block));
+  stmt.get_mut_children().extend(lets);
+  stmt.add_node(inner);
+}
+
+// `t++` or `++t` as `t += 1`, `t--` or `--t` as `t -= 1`
+fn into_compound(node: &mut AstNode) {
+  let token = *node.get_token();
+  let pos = token.get_pos();
+  let (kind, text) = match token.get_kind() {
+    TokenKind::DPlus => (TokenKind::PlusEq, "+="),
+    _ => (TokenKind::MinusEq, "-="),
+  };
+  node.set_token(Token::new(kind, text, pos));
+  node.set_kind(AstKind::BinOper);
+  node.add_node(AstNode::new(Token::new(TokenKind::Int, "1", pos), AstKind::Literal));
+}
+
+// `t op= e` as `t = t op (e)`, the operator at the position of its own
+// characters and `=` at the last one. The parentheses take the position of the
+// operator they hold.
+fn assign(node: &mut AstNode) {
+  let token = *node.get_token();
+  let text = token.get_value();
+  let op_len = text.len() - 1;
+  let pos = token.get_pos();
+  let mut rhs = node.get_mut_children().pop().unwrap();
+  if rhs.get_kind() == AstKind::BinOper && rhs.child_count() == 2 {
+    let mut paren = AstNode::new(Token::new(TokenKind::LParen, "(", rhs.get_token().get_pos()), AstKind::Expr);
+    paren.add_node(rhs);
+    rhs = paren;
+  }
+  let mut op = AstNode::new(Token::new(strip_assign(token.get_kind()), intern(&text[..op_len]), pos), AstKind::BinOper);
+  op.add_node(node[0].clone());
+  op.add_node(rhs);
+  node.add_node(op);
+  node.set_token(Token::new(TokenKind::Equal, "=", pos + op_len));
+}
+
+// `{ let __i_next = a; let __i_end = b; while (__i_next < __i_end) { ... } }`,
+// for a range written in the loop. The counter and its step sit at the loop
+// variable, and the test at `..`.
+fn desugar_for(node: &mut AstNode, taken: &BTreeSet<&str>) {
+  let mut range = &node[1];
+  while range.get_kind() == AstKind::Expr && range.child_count() == 1 {
+    range = &range[0];
+  }
+  if range.get_kind() != AstKind::BinOper || range.child_count() != 2 || range.get_token().get_kind() != TokenKind::DDot {
+    return;
+  }
+  let (lo, hi) = (range[0].clone(), range[1].clone());
+  let dots = range.get_token().get_pos();
+  let pos = node.get_token().get_pos();
+  let var = *node[0].get_token();
+  let at = var.get_pos();
+  let name = var.get_value();
+  let next = hidden(&format!("__{}_next", name), taken);
+  let end = hidden(&format!("__{}_end", name), taken);
+  let ident = |name: &'static str, pos: usize| AstNode::new(Token::new(TokenKind::Identifier, name, pos), AstKind::Identifier);
+
+  let mut cond = AstNode::new(Token::new(TokenKind::Less, "<", dots), AstKind::BinOper);
+  cond.add_node(ident(next, lo.get_token().get_pos()));
+  cond.add_node(ident(end, hi.get_token().get_pos()));
+  let mut step = AstNode::new(Token::new(TokenKind::PlusEq, "+=", at), AstKind::BinOper);
+  step.add_node(ident(next, at));
+  step.add_node(AstNode::new(Token::new(TokenKind::Int, "1", at), AstKind::Literal));
+  assign(&mut step);
+  let mut step_stmt = AstNode::new(Token::new(TokenKind::SemiColon, ";", at), AstKind::Stmt);
+  step_stmt.add_node(step);
+
+  let body = node.get_mut_children().pop().unwrap();
+  let mut inner = AstNode::new(Token::new(TokenKind::LStmt, "{", Span::of(&body).start), AstKind::Block);
+  inner.add_node(new_let(AstNode::new(var, AstKind::Identifier), ident(next, at)));
+  inner.add_node(step_stmt);
+  // The statements of a block body share the scope of the loop variable,
+  // which they may shadow like any other
+  match body.get_kind() {
+    AstKind::Block => inner.get_mut_children().extend(body.get_children().iter().cloned()),
+    _ => {
+      inner.add_node(body);
+    },
+  }
+  let mut repeat = AstNode::new(Token::new(TokenKind::While, "while", pos), AstKind::While);
+  repeat.add_node(cond);
+  repeat.add_node(inner);
+
+  let mut block = AstNode::new(Token::new(TokenKind::LStmt, "{", pos), AstKind::Block);
+  block.add_node(new_let(ident(next, at), lo));
+  block.add_node(new_let(ident(end, at), hi));
+  block.add_node(repeat);
+  *node = block;
+}
+
+// Place the `let` at its initializer, which is where its value comes from
+fn new_let<'a>(name: AstNode<'a>, init: AstNode<'a>) -> AstNode<'a> {
+  let pos = Span::of(&init).start;
+  let mut node = AstNode::new(Token::new(TokenKind::Let, "let", pos), AstKind::Let);
+  node.add_node(name);
+  node.add_node(AstNode::new(Token::new(TokenKind::Empty, "", pos), AstKind::Type));
+  node.add_node(init);
+  node
+}
+
+// Save each index of the place that is not pure in a `let`, outermost first,
+// reading the saved value in its place. The name takes the position of the
+// index's own token, whose type is the index's.
+fn hoist<'a>(place: &mut AstNode<'a>, lets: &mut Vec<AstNode<'a>>, taken: &BTreeSet<&str>) {
+  match place.get_kind() {
+    AstKind::Expr | AstKind::Member => hoist(&mut place[0], lets, taken),
+    AstKind::Index => {
+      hoist(&mut place[0], lets, taken);
+      if is_pure(&place[1]) {
+        return;
+      }
+      let pos = place[1].get_token().get_pos();
+      let name = hidden(&format!("__index{}", lets.len()), taken);
+      let ident = AstNode::new(Token::new(TokenKind::Identifier, name, pos), AstKind::Identifier);
+      let index = std::mem::replace(&mut place[1], ident.clone());
+      lets.push(new_let(ident, index));
+    },
+    _ => {},
+  }
+}
+
+fn is_compound(kind: TokenKind) -> bool {
+  strip_assign(kind) != kind
+}
+
+// A variable, or a field or element of one, which the statement can hoist the
+// indexes of
+fn is_place(node: &AstNode) -> bool {
+  match node.get_kind() {
+    AstKind::Identifier => true,
+    AstKind::Expr | AstKind::Member => node.child_count() > 0 && is_place(&node[0]),
+    AstKind::Index => node.child_count() == 2 && is_place(&node[0]),
+    _ => false,
+  }
+}
+
+// Evaluating it twice gives the same value and does nothing else
+fn is_pure(node: &AstNode) -> bool {
+  match node.get_kind() {
+    AstKind::Identifier | AstKind::Literal => true,
+    AstKind::Expr | AstKind::Member => node.child_count() > 0 && is_pure(&node[0]),
+    AstKind::Index => node.child_count() == 2 && is_pure(&node[0]) && is_pure(&node[1]),
+    _ => false,
+  }
+}
@@ -0,0 +1,97 @@
+use crate::ast::token::TokenKind;
+use crate::ast::{Ast, AstKind, AstNode};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+  Error,
+  Warning,
+}
+
+// Byte range in the source text
+#[derive(Clone, Copy, PartialEq, Eq, Debug, PartialOrd, Ord)]
+pub struct Span {
+  pub start: usize,
+  pub end: usize,
+}
+
+impl Span {
+  pub fn new(start: usize, end: usize) -> Span {
+    Span {
+      start,
+      end,
+    }
+  }
+  // Span of the node's own token
+  pub fn of_token(node: &AstNode) -> Span {
+    let (start, end) = node.get_token().get_span();
+    Span::new(start, end)
+  }
+  // Span covering every token in the subtree
+  pub fn of(node: &AstNode) -> Span {
+    let mut span: Option<Span> = None;
+    let mut stk = vec![node];
+    while let Some(node) = stk.pop() {
+      let token = node.get_token();
+      match token.get_kind() {
+        TokenKind::Head | TokenKind::Empty => {},
+        _ => {
+          let (start, end) = token.get_span();
+          span = Some(match span {
+            Some(span) => Span::new(span.start.min(start), span.end.max(end)),
+            None => Span::new(start, end),
+          });
+        },
+      }
+      stk.extend(node.get_children().iter());
+    }
+    span.unwrap_or(Span::new(0, 0))
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Diagnostic {
+  pub severity: Severity,
+  pub span: Span,
+  pub message: String,
+}
+
+impl Diagnostic {
+  pub fn error(span: Span, message: String) -> Diagnostic {
+    Diagnostic {
+      severity: Severity::Error,
+      span,
+      message,
+    }
+  }
+  pub fn warning(span: Span, message: String) -> Diagnostic {
+    Diagnostic {
+      severity: Severity::Warning,
+      span,
+      message,
+    }
+  }
+  pub fn is_error(&self) -> bool {
+    self.severity == Severity::Error
+  }
+}
+
+// Every Bad node the parser left in the tree
+pub fn parse_errors(ast: &Ast) -> Vec<Diagnostic> {
+  let mut diagnostics = Vec::new();
+  let mut stk = vec![ast.get_root()];
+  while let Some(node) = stk.pop() {
+    if let AstKind::Bad(msg) = node.get_kind() {
+      let token = node.get_token();
+      let message = match token.get_kind() {
+        TokenKind::EOF => format!("{}, found end of file", msg),
+        TokenKind::Bad(lex_msg) => format!("{} `{}`", lex_msg, token.get_value()),
+        _ if token.get_value().is_empty() => msg.to_string(),
+        _ => format!("{}, found `{}`", msg, token.get_value()),
+      };
+      diagnostics.push(Diagnostic::error(Span::of_token(node), message));
+    }
+    stk.extend(node.get_children().iter().rev());
+  }
+  diagnostics.sort_by_key(|d| d.span);
+  diagnostics
+}
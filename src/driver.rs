@@ -0,0 +1,89 @@
+use crate::ast::Ast;
+use crate::checker::Checker;
+use crate::desugar;
+use crate::diagnostic::{self, Diagnostic};
+use crate::flow;
+use crate::fold;
+use crate::lexer::Lexer;
+use crate::module::{self, Program};
+use crate::parser::Parser;
+
+// Everything the front end knows about one source text
+pub struct Analysis<'a> {
+  pub ast: Ast<'a>,
+  pub checker: Checker<'a>,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Analysis<'_> {
+  pub fn has_errors(&self) -> bool {
+    self.diagnostics.iter().any(|d| d.is_error())
+  }
+  // Fold the constants of a program without errors, adding what folding
+  // finds to the diagnostics in source order
+  pub fn fold(&mut self) {
+    if self.has_errors() {
+      return;
+    }
+    self.diagnostics.extend(fold::fold(&mut self.ast));
+    self.diagnostics.sort_by_key(|d| d.span);
+  }
+  // Rewrite a program without errors into the core language, then check it
+  // again for the types of the nodes the rewrite added. What that check finds
+  // is returned, nothing unless the rewrite changed what the program means.
+  pub fn desugar(&mut self) -> Vec<Diagnostic> {
+    if self.has_errors() {
+      return Vec::new();
+    }
+    desugar::desugar(&mut self.ast);
+    let mut checker = Checker::new();
+    let diagnostics = checker.check(&self.ast).to_vec();
+    self.checker = checker;
+    diagnostics
+  }
+}
+
+// Parse, then type check and flow check a tree that parsed cleanly
+pub fn analyze(source: &str) -> Analysis<'_> {
+  analyze_with(source, Checker::new())
+}
+
+// Like `analyze`, with a checker that already knows some names
+pub fn analyze_with<'a>(source: &'a str, mut checker: Checker<'a>) -> Analysis<'a> {
+  let mut parser = Parser::new(Lexer::new(source));
+  parser.parse();
+  let ast = parser.into_ast();
+  let mut diagnostics = diagnostic::parse_errors(&ast);
+  if diagnostics.is_empty() {
+    diagnostics.extend(checker.check(&ast).iter().cloned());
+    diagnostics.extend(flow::check_flow(&ast));
+  }
+  diagnostics.sort_by_key(|d| d.span);
+
+  Analysis {
+    ast,
+    checker,
+    diagnostics,
+  }
+}
+
+// Like `analyze`, for every module of a program checked as one tree
+pub fn analyze_program(program: &Program) -> Analysis<'_> {
+  let ast = program.parse();
+  let mut diagnostics = program.diagnostics.clone();
+  diagnostics.extend(diagnostic::parse_errors(&ast));
+  diagnostics.extend(module::check_paths(program, &ast));
+  let mut checker = Checker::new();
+  if diagnostics.is_empty() {
+    diagnostics.extend(checker.check(&ast).iter().cloned());
+    diagnostics.extend(flow::check_flow(&ast));
+    diagnostics.extend(module::check_visibility(program, &ast));
+  }
+  diagnostics.sort_by_key(|d| d.span);
+
+  Analysis {
+    ast,
+    checker,
+    diagnostics,
+  }
+}
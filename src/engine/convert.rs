@@ -0,0 +1,197 @@
+use crate::checker::types::{FunSig, Type};
+use crate::interpreter::value::Value;
+
+// A Rust type a carf value converts into, for arguments of native functions
+// and results of calls into a script
+pub trait FromValue: Sized {
+  fn ty() -> Type;
+  fn from_value(value: &Value) -> Option<Self>;
+}
+
+// A Rust type that converts into a carf value, for results of native
+// functions, constants and arguments of calls into a script
+pub trait IntoValue {
+  fn ty() -> Type;
+  fn into_value(self) -> Value;
+}
+
+macro_rules! convert_scalar {
+  ($rust:ty, $ty:ident, $variant:ident) => {
+    impl FromValue for $rust {
+      fn ty() -> Type {
+        Type::$ty
+      }
+      fn from_value(value: &Value) -> Option<Self> {
+        match value {
+          Value::$variant(x) => Some(*x),
+          _ => None,
+        }
+      }
+    }
+
+    impl IntoValue for $rust {
+      fn ty() -> Type {
+        Type::$ty
+      }
+      fn into_value(self) -> Value {
+        Value::$variant(self)
+      }
+    }
+  };
+}
+
+convert_scalar!(i64, Int, Int);
+convert_scalar!(f64, Float, Float);
+convert_scalar!(bool, Bool, Bool);
+convert_scalar!(char, Char, Char);
+
+impl FromValue for String {
+  fn ty() -> Type {
+    Type::String
+  }
+  fn from_value(value: &Value) -> Option<Self> {
+    match value {
+      Value::Str(s) => Some(s.to_string()),
+      _ => None,
+    }
+  }
+}
+
+impl IntoValue for String {
+  fn ty() -> Type {
+    Type::String
+  }
+  fn into_value(self) -> Value {
+    Value::Str(self.into())
+  }
+}
+
+impl IntoValue for &str {
+  fn ty() -> Type {
+    Type::String
+  }
+  fn into_value(self) -> Value {
+    Value::Str(self.into())
+  }
+}
+
+impl FromValue for () {
+  fn ty() -> Type {
+    Type::Unit
+  }
+  fn from_value(value: &Value) -> Option<Self> {
+    match value {
+      Value::Unit => Some(()),
+      _ => None,
+    }
+  }
+}
+
+impl IntoValue for () {
+  fn ty() -> Type {
+    Type::Unit
+  }
+  fn into_value(self) -> Value {
+    Value::Unit
+  }
+}
+
+// Arrays are copied in both directions
+impl<T: FromValue> FromValue for Vec<T> {
+  fn ty() -> Type {
+    Type::Array(Box::new(T::ty()), None)
+  }
+  fn from_value(value: &Value) -> Option<Self> {
+    match value {
+      Value::Array(items) => items.borrow().iter().map(T::from_value).collect(),
+      _ => None,
+    }
+  }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+  fn ty() -> Type {
+    Type::Array(Box::new(T::ty()), None)
+  }
+  fn into_value(self) -> Value {
+    Value::array(self.into_iter().map(T::into_value).collect())
+  }
+}
+
+// What a native function gives back: a value, or an error message that stops
+// the script like any runtime error
+pub trait NativeResult {
+  fn ty() -> Type;
+  fn into_result(self) -> Result<Value, String>;
+}
+
+impl<T: IntoValue> NativeResult for T {
+  fn ty() -> Type {
+    T::ty()
+  }
+  fn into_result(self) -> Result<Value, String> {
+    Ok(self.into_value())
+  }
+}
+
+impl<T: IntoValue> NativeResult for Result<T, String> {
+  fn ty() -> Type {
+    T::ty()
+  }
+  fn into_result(self) -> Result<Value, String> {
+    self.map(T::into_value)
+  }
+}
+
+// A Rust closure callable from carf, `Args` being the tuple of its parameter types
+pub trait NativeFn<Args> {
+  fn sig() -> FunSig;
+  fn call(&self, args: Vec<Value>) -> Result<Value, String>;
+}
+
+// Arguments of a call into a script
+pub trait IntoArgs {
+  fn types(&self) -> Vec<Type>;
+  fn into_args(self) -> Vec<Value>;
+}
+
+macro_rules! arity {
+  ($($arg:ident),*) => {
+    impl<F, R, $($arg),*> NativeFn<($($arg,)*)> for F
+    where
+      F: Fn($($arg),*) -> R,
+      R: NativeResult,
+      $($arg: FromValue),*
+    {
+      fn sig() -> FunSig {
+        FunSig { params: vec![$($arg::ty()),*], ret: R::ty() }
+      }
+      #[allow(non_snake_case, unused_mut, unused_variables)]
+      fn call(&self, args: Vec<Value>) -> Result<Value, String> {
+        let mut args = args.iter();
+        $(
+          let $arg = args.next().and_then($arg::from_value).ok_or_else(|| format!("expected an argument of type `{}`", $arg::ty()))?;
+        )*
+        self($($arg),*).into_result()
+      }
+    }
+
+    impl<$($arg: IntoValue),*> IntoArgs for ($($arg,)*) {
+      fn types(&self) -> Vec<Type> {
+        vec![$($arg::ty()),*]
+      }
+      #[allow(non_snake_case)]
+      fn into_args(self) -> Vec<Value> {
+        let ($($arg,)*) = self;
+        vec![$($arg.into_value()),*]
+      }
+    }
+  };
+}
+
+arity!();
+arity!(A);
+arity!(A, B);
+arity!(A, B, C);
+arity!(A, B, C, D);
+arity!(A, B, C, D, E);
@@ -0,0 +1,197 @@
+pub mod convert;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::checker::Checker;
+use crate::checker::types::{FunSig, Type};
+use crate::diagnostic::Diagnostic;
+use crate::driver::{self, Analysis};
+use crate::interpreter::{Interpreter, Native, limits::Limits, prelude::Console, value::Value};
+use crate::source::SourceFile;
+use convert::{FromValue, IntoArgs, IntoValue, NativeFn};
+
+// Why the host's request failed. Nothing a script does makes the engine panic.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Error {
+  // The script did not parse or check, every error and warning is listed
+  Compile(Vec<Diagnostic>),
+  // Raised while running, by carf code or by a native function
+  Runtime(Diagnostic),
+  // Stopped by one of the engine's limits, or cancelled
+  Limit(Diagnostic),
+  // A call from the host that does not fit the script: no such function,
+  // other arguments, or a result of another type
+  Call(String),
+}
+
+impl Error {
+  // Diagnostics with the source lines they point at
+  pub fn render(&self, source: &str) -> String {
+    let file = SourceFile::anonymous(source);
+    match self {
+      Error::Compile(diagnostics) => diagnostics.iter().map(|d| file.render(d, false)).collect::<Vec<String>>().join("\n"),
+      Error::Runtime(diagnostic) | Error::Limit(diagnostic) => file.render(diagnostic, false),
+      Error::Call(message) => message.clone(),
+    }
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Error::Compile(diagnostics) => {
+        let messages: Vec<&str> = diagnostics.iter().filter(|d| d.is_error()).map(|d| d.message.as_str()).collect();
+        write!(f, "{}", messages.join("; "))
+      },
+      Error::Runtime(diagnostic) | Error::Limit(diagnostic) => write!(f, "{}", diagnostic.message),
+      Error::Call(message) => write!(f, "{}", message),
+    }
+  }
+}
+
+impl std::error::Error for Error {}
+
+// Compiles and runs carf source for a host program, which adds its own
+// functions and constants to what every script can use
+#[derive(Default)]
+pub struct Engine {
+  natives: BTreeMap<String, (FunSig, Native)>,
+  constants: BTreeMap<String, Value>,
+  // Types of the constants, by name
+  constant_types: BTreeMap<String, Type>,
+  limits: Limits,
+}
+
+impl Engine {
+  pub fn new() -> Engine {
+    Engine::default()
+  }
+  // A Rust closure scripts call by name. Its parameter and result types become
+  // the carf signature, e.g. `|a: i64, b: i64| a + b` is `fun(int, int): int`.
+  // Returning `Err(message)` stops the script with a runtime error.
+  pub fn register_fn<Args, F>(&mut self, name: &str, fun: F) -> &mut Engine
+  where
+    F: NativeFn<Args> + 'static,
+  {
+    let native: Native = Rc::new(move |args| fun.call(args));
+    self.natives.insert(name.to_string(), (F::sig(), native));
+    self
+  }
+  // A global every script starts with and can only read
+  pub fn register_const<T: IntoValue>(&mut self, name: &str, value: T) -> &mut Engine {
+    self.constant_types.insert(name.to_string(), T::ty());
+    self.constants.insert(name.to_string(), value.into_value());
+    self
+  }
+  // Bounds on each run of a script: loading its globals, then every call
+  pub fn set_limits(&mut self, limits: Limits) -> &mut Engine {
+    self.limits = limits;
+    self
+  }
+  // Parse and check the source, reporting every error at once
+  pub fn compile<'s>(&'s self, source: &'s str) -> Result<Script<'s>, Error> {
+    let mut checker = Checker::new();
+    for (name, (sig, _)) in &self.natives {
+      checker.declare_native(name, sig.clone());
+    }
+    for (name, ty) in &self.constant_types {
+      checker.declare_const(name, ty.clone());
+    }
+    let mut analysis = driver::analyze_with(source, checker);
+    analysis.fold();
+    match analysis.has_errors() {
+      true => Err(Error::Compile(analysis.diagnostics)),
+      false => Ok(Script { engine: self, analysis }),
+    }
+  }
+  // Compile the source and run its `main`
+  pub fn run<R: FromValue>(&self, source: &str) -> Result<R, Error> {
+    self.compile(source)?.instantiate()?.call("main", ())
+  }
+}
+
+// A checked script, ready to be instantiated any number of times
+pub struct Script<'s> {
+  engine: &'s Engine,
+  analysis: Analysis<'s>,
+}
+
+impl<'s> Script<'s> {
+  // Warnings the script compiled with
+  pub fn get_warnings(&self) -> &Vec<Diagnostic> {
+    &self.analysis.diagnostics
+  }
+  // A fresh set of globals, printing to and reading from the process
+  pub fn instantiate(&self) -> Result<Instance<'_, 's>, Error> {
+    self.instantiate_with(Console::std())
+  }
+  // Same as `instantiate`, with `print` and `read_line` going through `console`
+  pub fn instantiate_with<'i>(&'i self, console: Console<'i>) -> Result<Instance<'i, 's>, Error> {
+    let engine: &'s Engine = self.engine;
+    let mut interpreter = Interpreter::empty();
+    interpreter.set_console(console);
+    interpreter.set_limits(engine.limits.clone());
+    for (name, (_, native)) in &engine.natives {
+      interpreter.define_native(name, native.clone());
+    }
+    for (name, value) in &engine.constants {
+      interpreter.define_global(name, value.deep_copy());
+    }
+    let loaded = interpreter.load(&self.analysis.ast);
+    loaded.map_err(|diagnostic| runtime_error(&interpreter, diagnostic))?;
+    Ok(Instance { script: self, interpreter })
+  }
+}
+
+// Globals of one run of a script, kept from one call to the next
+pub struct Instance<'i, 's> {
+  script: &'i Script<'s>,
+  interpreter: Interpreter<'i, 's>,
+}
+
+impl Instance<'_, '_> {
+  // Call a function of the script, e.g. `instance.call::<i64>("add", (1, 2))`,
+  // with the limits of the engine in full
+  pub fn call<R: FromValue>(&mut self, name: &str, args: impl IntoArgs) -> Result<R, Error> {
+    let sig = match self.script.analysis.checker.get_fun(name) {
+      Some(sig) if !self.script.engine.natives.contains_key(name) => sig,
+      _ => return Err(Error::Call(format!("the script has no function `{}`", name))),
+    };
+    let types = args.types();
+    if types.len() != sig.params.len() || !sig.params.iter().zip(&types).all(|(param, ty)| fits(param, ty)) {
+      let types: Vec<String> = types.iter().map(|ty| ty.to_string()).collect();
+      return Err(Error::Call(format!("`{}` is `{}`, it cannot take ({})", name, sig, types.join(", "))));
+    }
+    // Checked before the call, which could change the globals
+    if !fits(&sig.ret, &R::ty()) {
+      return Err(Error::Call(format!("`{}` returns `{}`, not `{}`", name, sig.ret, R::ty())));
+    }
+    self.interpreter.reset_budget();
+    let value = self.interpreter.call(name, args.into_args());
+    let value = value.map_err(|diagnostic| runtime_error(&self.interpreter, diagnostic))?;
+    R::from_value(&value).ok_or_else(|| Error::Call(format!("`{}` returns `{}`, not `{}`", name, sig.ret, R::ty())))
+  }
+  // Value of a global, once its initializer has run
+  pub fn get_global<R: FromValue>(&self, name: &str) -> Option<R> {
+    self.interpreter.get_global(name).and_then(R::from_value)
+  }
+}
+
+fn runtime_error(interpreter: &Interpreter, diagnostic: Diagnostic) -> Error {
+  match interpreter.limit_exceeded() {
+    true => Error::Limit(diagnostic),
+    false => Error::Runtime(diagnostic),
+  }
+}
+
+// Whether a value of type `found` passes for one of type `expected`, in
+// either direction between host and script. An array of the host has no
+// fixed length, so it matches a script array of any length.
+fn fits(expected: &Type, found: &Type) -> bool {
+  match (expected, found) {
+    (Type::Array(expected, _), Type::Array(found, None)) | (Type::Array(expected, None), Type::Array(found, _)) => fits(expected, found),
+    _ => expected == found,
+  }
+}
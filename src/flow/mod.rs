@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+
+use crate::ast::token::TokenKind;
+use crate::ast::{Ast, AstKind, AstNode};
+use crate::diagnostic::{Diagnostic, Span};
+use crate::printer;
+
+pub struct BasicBlock {
+  // Positions of the statements that start in this block
+  pub stmts: Vec<usize>,
+  pub succs: Vec<usize>,
+}
+
+// Control-flow graph of one function body. Falling off the end of the body
+// leads to `exit`, every `return` leads to `ret`.
+pub struct Cfg {
+  pub blocks: Vec<BasicBlock>,
+  pub entry: usize,
+  pub exit: usize,
+  pub ret: usize,
+  stmt_block: BTreeMap<usize, usize>,
+}
+
+impl Cfg {
+  pub fn build(body: &AstNode, diagnostics: &mut Vec<Diagnostic>) -> Cfg {
+    let mut builder = CfgBuilder {
+      blocks: Vec::new(),
+      current: 0,
+      ret: 0,
+      loop_stk: Vec::new(),
+      stmt_block: BTreeMap::new(),
+      diagnostics,
+    };
+    let entry = builder.new_block();
+    let ret = builder.new_block();
+    builder.current = entry;
+    builder.ret = ret;
+    builder.add_stmt(body);
+    let exit = builder.new_block();
+    builder.edge(builder.current, exit);
+
+    Cfg {
+      blocks: builder.blocks,
+      entry,
+      exit,
+      ret,
+      stmt_block: builder.stmt_block,
+    }
+  }
+  pub fn reachable(&self) -> Vec<bool> {
+    let mut seen = vec![false; self.blocks.len()];
+    let mut stk = vec![self.entry];
+    while let Some(block) = stk.pop() {
+      if seen[block] {
+        continue;
+      }
+      seen[block] = true;
+      stk.extend(self.blocks[block].succs.iter());
+    }
+    seen
+  }
+  pub fn block_of(&self, stmt: &AstNode) -> Option<usize> {
+    self.stmt_block.get(&stmt.get_token().get_pos()).copied()
+  }
+}
+
+struct CfgBuilder<'d> {
+  blocks: Vec<BasicBlock>,
+  current: usize,
+  ret: usize,
+  // (continue target, break target) of the enclosing loops
+  loop_stk: Vec<(usize, usize)>,
+  stmt_block: BTreeMap<usize, usize>,
+  diagnostics: &'d mut Vec<Diagnostic>,
+}
+
+impl CfgBuilder<'_> {
+  fn new_block(&mut self) -> usize {
+    self.blocks.push(BasicBlock { stmts: Vec::new(), succs: Vec::new() });
+    self.blocks.len() - 1
+  }
+  fn edge(&mut self, from: usize, to: usize) {
+    self.blocks[from].succs.push(to);
+  }
+  // Continue in a fresh block that nothing jumps to
+  fn jump(&mut self, to: usize) {
+    self.edge(self.current, to);
+    self.current = self.new_block();
+  }
+  fn add_stmt(&mut self, node: &AstNode) {
+    let pos = node.get_token().get_pos();
+    self.stmt_block.insert(pos, self.current);
+    self.blocks[self.current].stmts.push(pos);
+    match node.get_kind() {
+      AstKind::Block => {
+        for stmt in node.get_children() {
+          self.add_stmt(stmt);
+        }
+      },
+      AstKind::If if node.child_count() >= 2 => {
+        let cond = self.current;
+        let join = self.new_block();
+        let then_block = self.new_block();
+        self.edge(cond, then_block);
+        self.current = then_block;
+        self.add_stmt(&node[1]);
+        self.edge(self.current, join);
+        if node.child_count() > 2 && node[2].child_count() > 0 {
+          let else_block = self.new_block();
+          self.edge(cond, else_block);
+          self.current = else_block;
+          self.add_stmt(&node[2][0]);
+          self.edge(self.current, join);
+        } else {
+          self.edge(cond, join);
+        }
+        self.current = join;
+      },
+      AstKind::While | AstKind::For if node.child_count() >= loop_arity(node) => {
+        let header = self.new_block();
+        let exit = self.new_block();
+        let body = self.new_block();
+        self.edge(self.current, header);
+        self.edge(header, body);
+        // `while (true)` only ends through `break`
+        let endless = node.get_kind() == AstKind::While && node[0].get_token().get_kind() == TokenKind::True;
+        if !endless {
+          self.edge(header, exit);
+        }
+        self.loop_stk.push((header, exit));
+        self.current = body;
+        self.add_stmt(&node[node.child_count() - 1]);
+        self.edge(self.current, header);
+        self.loop_stk.pop();
+        self.current = exit;
+      },
+      AstKind::Return => {
+        self.jump(self.ret);
+      },
+      AstKind::Break | AstKind::Continue => {
+        let keyword = node.get_token().get_value();
+        match self.loop_stk.last() {
+          Some((head, exit)) => {
+            let target = if node.get_kind() == AstKind::Break { *exit } else { *head };
+            self.jump(target);
+          },
+          None => {
+            self.diagnostics.push(Diagnostic::error(
+              Span::of_token(node), format!("`{}` outside of a loop", keyword)
+            ));
+          },
+        }
+      },
+      _ => {},
+    }
+  }
+}
+
+// Checks every function and lambda body for misplaced `break`/`continue`,
+// missing returns and statements that can never run
+pub fn check_flow(ast: &Ast) -> Vec<Diagnostic> {
+  let mut diagnostics = Vec::new();
+  let funs = ast.get_root().get_children().iter().flat_map(|item| match item.get_kind() {
+    AstKind::Impl => item.get_children().iter().skip(1).collect(),
+    _ => vec![item],
+  });
+  for item in funs {
+    if item.get_kind() != AstKind::Fun || item.child_count() < 4 {
+      continue;
+    }
+    let what = format!("function `{}`", item[0].get_token().get_value());
+    check_body(&item[3], &item[2], Span::of_token(&item[0]), &what, &mut diagnostics);
+  }
+  diagnostics.extend(check_lambdas(ast.get_root()));
+  diagnostics.sort_by_key(|d| d.span);
+
+  diagnostics
+}
+
+// The same checks for the bodies of the lambdas anywhere in `node`
+pub fn check_lambdas(node: &AstNode) -> Vec<Diagnostic> {
+  let mut diagnostics = Vec::new();
+  let mut lambdas = Vec::new();
+  collect_lambdas(node, &mut lambdas);
+  for lambda in lambdas {
+    check_body(&lambda[2], &lambda[1], Span::of_token(lambda), "lambda", &mut diagnostics);
+  }
+  diagnostics
+}
+
+fn collect_lambdas<'n, 'a>(node: &'n AstNode<'a>, out: &mut Vec<&'n AstNode<'a>>) {
+  if node.get_kind() == AstKind::Lambda && node.child_count() == 3 {
+    out.push(node);
+  }
+  for child in node.get_children() {
+    collect_lambdas(child, out);
+  }
+}
+
+fn check_body(body: &AstNode, ret: &AstNode, name: Span, what: &str, diagnostics: &mut Vec<Diagnostic>) {
+  let cfg = Cfg::build(body, diagnostics);
+  let reachable = cfg.reachable();
+  report_unreachable(&cfg, &reachable, body, diagnostics);
+  let ret_type = printer::print_type(ret);
+  if reachable[cfg.exit] && ret_type != "unit" {
+    diagnostics.push(Diagnostic::error(name, format!(
+      "{} returns `{}` but can reach the end of its body without `return`", what, ret_type
+    )));
+  }
+}
+
+// Report the first unreachable statement of each sequence and skip what follows it
+fn report_unreachable(cfg: &Cfg, reachable: &[bool], node: &AstNode, diagnostics: &mut Vec<Diagnostic>) {
+  let is_reachable = |stmt: &AstNode| cfg.block_of(stmt).is_none_or(|block| reachable[block]);
+  let children: Vec<&AstNode> = match node.get_kind() {
+    AstKind::Block => node.get_children().iter().collect(),
+    AstKind::If if node.child_count() >= 2 => {
+      let mut branches = vec![&node[1]];
+      if node.child_count() > 2 && node[2].child_count() > 0 {
+        branches.push(&node[2][0]);
+      }
+      branches
+    },
+    AstKind::While | AstKind::For if node.child_count() >= loop_arity(node) => vec![&node[node.child_count() - 1]],
+    _ => Vec::new(),
+  };
+  for stmt in children {
+    if !is_reachable(stmt) {
+      diagnostics.push(Diagnostic::warning(Span::of(stmt), "unreachable statement".to_string()));
+      if node.get_kind() == AstKind::Block {
+        break;
+      }
+      continue;
+    }
+    report_unreachable(cfg, reachable, stmt, diagnostics);
+  }
+}
+
+// Children of a well formed loop, the body being the last one
+fn loop_arity(node: &AstNode) -> usize {
+  match node.get_kind() {
+    AstKind::For => 3,
+    _ => 2,
+  }
+}
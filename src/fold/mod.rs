@@ -0,0 +1,216 @@
+use crate::ast::token::{Token, TokenKind};
+use crate::ast::{Ast, AstKind, AstNode};
+use crate::diagnostic::{Diagnostic, Span};
+use crate::utilities::intern::intern;
+use crate::utilities::literal;
+
+// Value of a literal node
+#[derive(Clone, PartialEq, Debug)]
+pub enum Const {
+  Int(i64),
+  Float(f64),
+  Char(char),
+  Str(String),
+  Bool(bool),
+}
+
+impl Const {
+  pub fn from_literal(node: &AstNode) -> Option<Const> {
+    if node.get_kind() != AstKind::Literal {
+      return None;
+    }
+    let text = node.get_token().get_value();
+    match node.get_token().get_kind() {
+      TokenKind::Int => literal::parse_int(text).map(Const::Int),
+      TokenKind::Float => literal::parse_float(text).map(Const::Float),
+      TokenKind::Char => literal::parse_char(text).map(Const::Char),
+      TokenKind::String => literal::parse_string(text).map(Const::Str),
+      TokenKind::True => Some(Const::Bool(true)),
+      TokenKind::False => Some(Const::Bool(false)),
+      _ => None,
+    }
+  }
+  // Literal node for the value, placed at `pos`
+  pub fn into_node<'a>(self, pos: usize) -> AstNode<'a> {
+    let (kind, text) = match self {
+      Const::Int(n) => (TokenKind::Int, n.to_string()),
+      Const::Float(f) => (TokenKind::Float, literal::format_float(f)),
+      Const::Char(c) => (TokenKind::Char, literal::quote_char(c)),
+      Const::Str(s) => (TokenKind::String, literal::quote_string(&s)),
+      Const::Bool(true) => (TokenKind::True, "true".to_string()),
+      Const::Bool(false) => (TokenKind::False, "false".to_string()),
+    };
+    AstNode::new(Token::new(kind, intern(&text), pos), AstKind::Literal)
+  }
+}
+
+// Folds constant subexpressions and trivial identities in place, reporting
+// constant expressions that could never run (division by zero, overflow)
+pub fn fold(ast: &mut Ast) -> Vec<Diagnostic> {
+  let mut folder = Folder {
+    diagnostics: Vec::new(),
+  };
+  folder.fold_node(ast.get_mut_root());
+  folder.diagnostics.sort_by_key(|d| d.span);
+
+  folder.diagnostics
+}
+
+struct Folder {
+  diagnostics: Vec<Diagnostic>,
+}
+
+impl Folder {
+  fn fold_node(&mut self, node: &mut AstNode) {
+    for child in node.get_mut_children().iter_mut() {
+      self.fold_node(child);
+    }
+    match node.get_kind() {
+      AstKind::Expr if node.child_count() == 1 && node[0].get_kind() == AstKind::Literal => {
+        replace_with_child(node, 0);
+      },
+      AstKind::UnOper if node.child_count() == 1 => self.fold_unary(node),
+      AstKind::BinOper if node.child_count() == 2 => self.fold_binary(node),
+      _ => {},
+    }
+  }
+  fn fold_unary(&mut self, node: &mut AstNode) {
+    let kind = node.get_token().get_kind();
+    // `!!b`, but not `- -x`, which overflows when `x` is the least `int`
+    if kind == TokenKind::Not && node[0].get_kind() == AstKind::UnOper && node[0].get_token().get_kind() == kind && node[0].child_count() == 1 {
+      let inner = node.get_mut_children().remove(0).get_mut_children().remove(0);
+      *node = inner;
+      return;
+    }
+    let value = match (kind, Const::from_literal(&node[0])) {
+      (TokenKind::Minus, Some(Const::Int(n))) => match n.checked_neg() {
+        Some(n) => Const::Int(n),
+        None => {
+          self.overflow(node);
+          return;
+        },
+      },
+      (TokenKind::Minus, Some(Const::Float(f))) => Const::Float(-f),
+      (TokenKind::Not, Some(Const::Bool(b))) => Const::Bool(!b),
+      _ => return,
+    };
+    *node = value.into_node(node.get_token().get_pos());
+  }
+  fn fold_binary(&mut self, node: &mut AstNode) {
+    use TokenKind::*;
+    let kind = node.get_token().get_kind();
+    let lhs = Const::from_literal(&node[0]);
+    let rhs = Const::from_literal(&node[1]);
+    // A constant right operand can be wrong whatever the left one is
+    match (kind, &rhs) {
+      (Slash | SlashEq, Some(Const::Int(0))) => {
+        self.error(node, "division by zero".to_string());
+        return;
+      },
+      (Mod | ModEq, Some(Const::Int(0))) => {
+        self.error(node, "remainder by zero".to_string());
+        return;
+      },
+      (LShift | RShift | LShiftEq | RShiftEq, Some(Const::Int(n))) if !(0..64).contains(n) => {
+        self.error(node, format!("shift by {} bits overflows a 64-bit `int`", n));
+        return;
+      },
+      _ => {},
+    }
+    match (lhs, rhs) {
+      (Some(Const::Int(lo)), Some(Const::Int(hi))) if kind == DDot => {
+        if lo > hi {
+          self.diagnostics.push(Diagnostic::warning(Span::of(node), format!("range `{}..{}` is empty", lo, hi)));
+        }
+      },
+      (Some(l), Some(r)) => match eval_binary(kind, l, r) {
+        Ok(Some(value)) => *node = value.into_node(node.get_token().get_pos()),
+        Ok(None) => {},
+        Err(()) => self.overflow(node),
+      },
+      (lhs, rhs) => self.simplify(node, kind, lhs, rhs),
+    }
+  }
+  // Identities with one constant operand, never dropping the other operand
+  fn simplify(&mut self, node: &mut AstNode, kind: TokenKind, lhs: Option<Const>, rhs: Option<Const>) {
+    use TokenKind::*;
+    let keep = match (kind, lhs, rhs) {
+      (Plus | Minus | Or | Xor | LShift | RShift, _, Some(Const::Int(0))) => 0,
+      (Asterisk | Slash, _, Some(Const::Int(1))) => 0,
+      (DAnd, _, Some(Const::Bool(true))) | (DOr, _, Some(Const::Bool(false))) => 0,
+      (Plus | Or | Xor, Some(Const::Int(0)), _) => 1,
+      (Asterisk, Some(Const::Int(1)), _) => 1,
+      (DAnd, Some(Const::Bool(true)), _) | (DOr, Some(Const::Bool(false)), _) => 1,
+      // The right operand of a short-circuit is never evaluated here
+      (DAnd, Some(Const::Bool(false)), _) | (DOr, Some(Const::Bool(true)), _) => 0,
+      _ => return,
+    };
+    replace_with_child(node, keep);
+  }
+  fn error(&mut self, node: &AstNode, message: String) {
+    self.diagnostics.push(Diagnostic::error(Span::of(node), message));
+  }
+  fn overflow(&mut self, node: &AstNode) {
+    self.error(node, "arithmetic overflow in constant expression".to_string());
+  }
+}
+
+fn replace_with_child(node: &mut AstNode, index: usize) {
+  let child = node.get_mut_children().swap_remove(index);
+  *node = child;
+}
+
+// Ok(None) when the operator does not fold for these operands, Err on overflow
+fn eval_binary(kind: TokenKind, lhs: Const, rhs: Const) -> Result<Option<Const>, ()> {
+  use TokenKind::*;
+  let value = match (lhs, rhs) {
+    (Const::Int(l), Const::Int(r)) => match kind {
+      Plus => Const::Int(l.checked_add(r).ok_or(())?),
+      Minus => Const::Int(l.checked_sub(r).ok_or(())?),
+      Asterisk => Const::Int(l.checked_mul(r).ok_or(())?),
+      Slash => Const::Int(l.checked_div(r).ok_or(())?),
+      Mod => Const::Int(l.checked_rem(r).ok_or(())?),
+      And => Const::Int(l & r),
+      Or => Const::Int(l | r),
+      Xor => Const::Int(l ^ r),
+      LShift => Const::Int(l << r),
+      RShift => Const::Int(l >> r),
+      _ => return Ok(compare(kind, &l, &r)),
+    },
+    (Const::Float(l), Const::Float(r)) => match kind {
+      Plus => Const::Float(l + r),
+      Minus => Const::Float(l - r),
+      Asterisk => Const::Float(l * r),
+      Slash => Const::Float(l / r),
+      _ => return Ok(l.partial_cmp(&r).and_then(|_| compare(kind, &l, &r))),
+    },
+    (Const::Str(l), Const::Str(r)) => match kind {
+      Plus => Const::Str(l + &r),
+      _ => return Ok(compare(kind, &l, &r)),
+    },
+    (Const::Char(l), Const::Char(r)) => return Ok(compare(kind, &l, &r)),
+    (Const::Bool(l), Const::Bool(r)) => match kind {
+      DAnd => Const::Bool(l && r),
+      DOr => Const::Bool(l || r),
+      DEqual => Const::Bool(l == r),
+      NEqual => Const::Bool(l != r),
+      _ => return Ok(None),
+    },
+    _ => return Ok(None),
+  };
+  Ok(Some(value))
+}
+
+fn compare<T: PartialOrd>(kind: TokenKind, l: &T, r: &T) -> Option<Const> {
+  use TokenKind::*;
+  let result = match kind {
+    DEqual => l == r,
+    NEqual => l != r,
+    Less => l < r,
+    Greater => l > r,
+    LessEq => l <= r,
+    GreaterEq => l >= r,
+    _ => return None,
+  };
+  Some(Const::Bool(result))
+}
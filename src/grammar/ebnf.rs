@@ -0,0 +1,53 @@
+use crate::ast::token;
+
+use super::{rules, token_name, Term, GENERATED};
+
+// Where the grammar is kept, relative to the crate root
+pub const PATH: &str = "docs/grammar.ebnf";
+
+// W3C notation, the one railroad diagram tools read
+fn render(term: &Term, nested: bool) -> String {
+  let group = |term: &Term| match term {
+    Term::Seq(_) | Term::Choice(_) => format!("({})", render(term, true)),
+    _ => render(term, true),
+  };
+  match term {
+    Term::Text(text) if text.contains('\'') => format!("\"{}\"", text),
+    Term::Text(text) => format!("'{}'", text),
+    Term::Token(kind) => token_name(*kind).to_uppercase(),
+    Term::Rule(name) => name.to_string(),
+    Term::Seq(terms) => terms.iter()
+      .map(|t| match t {
+        Term::Choice(_) => group(t),
+        _ => render(t, true),
+      })
+      .collect::<Vec<_>>()
+      .join(" "),
+    Term::Choice(terms) => {
+      let alternatives: Vec<String> = terms.iter().map(|t| render(t, true)).collect();
+      match nested {
+        true => alternatives.join(" | "),
+        // Continuation lines are aligned by the caller
+        false => alternatives.join("\n| "),
+      }
+    },
+    Term::Opt(term) => format!("{}?", group(term)),
+    Term::Many(term) => format!("{}*", group(term)),
+  }
+}
+
+pub fn generate() -> String {
+  let rules = rules();
+  let width = rules.iter().map(|r| r.name.len()).max().unwrap_or(0);
+  let mut out = format!("/* {} */\n\n", GENERATED);
+  for rule in &rules {
+    let body = render(&rule.body, false).replace('\n', &format!("\n{}   ", " ".repeat(width)));
+    out.push_str(&format!("{:width$} ::= {}\n", rule.name, body, width = width));
+  }
+  out.push_str("\n/* Tokens, matched by pattern where no keyword or symbol does */\n");
+  for (kind, pattern) in token::LITERAL_PATTERNS.iter().rev() {
+    out.push_str(&format!("/* {:10} {} */\n", token_name(*kind).to_uppercase(), pattern));
+  }
+  out.push_str("/* Comments run from `//` to the end of the line */\n");
+  out
+}
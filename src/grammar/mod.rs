@@ -0,0 +1,249 @@
+pub mod textmate;
+pub mod tree_sitter;
+pub mod ebnf;
+pub mod railroad;
+
+use crate::ast::token::{self, TokenKind};
+use crate::lexer::Lexer;
+use crate::parser::{Parser, ITEM_KINDS};
+use crate::subparser::SubParser;
+
+const GENERATED: &str = "Generated from the carf token tables by `cargo test`, do not edit";
+
+// A piece of the grammar, shared by every format it is exported to
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Term {
+  // Keyword or symbol
+  Text(&'static str),
+  // Identifier or literal, matched by a pattern
+  Token(TokenKind),
+  // Nonterminal
+  Rule(&'static str),
+  Seq(Vec<Term>),
+  Choice(Vec<Term>),
+  Opt(Box<Term>),
+  Many(Box<Term>),
+}
+
+pub struct Rule {
+  pub name: &'static str,
+  // Id in the expect list, None for the rules that tie the list together
+  pub id: Option<&'static str>,
+  pub body: Term,
+}
+
+// Text of a keyword or symbol
+fn text_of(kind: TokenKind) -> Option<&'static str> {
+  token::KEYWORD_LIST.iter().chain(token::SYMBOL_LIST.iter()).copied()
+    .find(|t| token::keyword_token_map(t) == kind || token::sym_token_map(t) == kind)
+}
+
+// Name of the rule for an identifier or literal
+fn token_name(kind: TokenKind) -> &'static str {
+  match kind {
+    TokenKind::Identifier => "identifier",
+    TokenKind::Int => "integer",
+    TokenKind::Float => "float",
+    TokenKind::Char => "char",
+    TokenKind::String => "string",
+    _ => "unknown",
+  }
+}
+
+fn pattern_of(kind: TokenKind) -> Option<&'static str> {
+  token::LITERAL_PATTERNS.iter().find(|(k, _)| *k == kind).map(|(_, p)| *p)
+}
+
+// What a Union slot of a rule holds. Slots not named here hold an expression.
+fn slot(id: &str, n: u8) -> Term {
+  let rule = Term::Rule;
+  let opt = |term: Term| Term::Opt(Box::new(term));
+  match (id, n) {
+    ("fun", 0) | ("expr_lambda", 0) => opt(rule("params")),
+    ("fun", 1) | ("expr_lambda", 1) => rule("stmt_multi"),
+    ("fun", 2) | ("expr_lambda", 2) => rule("type"),
+    ("stmt_multi", 0) => Term::Many(Box::new(rule("stmt"))),
+    ("stmt_let", 0) => opt(Term::Seq(vec![Term::Text("="), rule("expr")])),
+    ("stmt_let", 1) => opt(Term::Seq(vec![Term::Text(":"), rule("type")])),
+    ("stmt_while", 1) | ("stmt_for", 1) | ("stmt_else", 0) => rule("stmt"),
+    ("stmt_if", 1) => Term::Seq(vec![rule("stmt"), opt(rule("stmt_else"))]),
+    ("stmt_return", 0) => opt(rule("expr")),
+    ("expr_call", 1) => opt(rule("args")),
+    ("expr_args", 0) => rule("args"),
+    ("expr_array", 0) => opt(rule("elements")),
+    ("struct_def", 0) => opt(rule("fields")),
+    ("impl_def", 0) => Term::Many(Box::new(rule("fun"))),
+    ("enum_def", 0) => opt(rule("variants")),
+    ("import_def", 0) => Term::Many(Box::new(Term::Seq(vec![Term::Text("::"), Term::Token(TokenKind::Identifier)]))),
+    ("pub_def", 0) => Term::Choice(vec![rule("fun"), rule("stmt_let"), rule("struct_def"), rule("enum_def")]),
+    ("expr_path", 0) | ("expr_struct", 0) => Term::Token(TokenKind::Identifier),
+    ("expr_match", 1) => opt(rule("arms")),
+    ("expr_struct", 1) => opt(rule("field_inits")),
+    _ => rule("expr"),
+  }
+}
+
+// The rule for one entry of the expect list
+fn expect_rule(id: &'static str, kinds: &[TokenKind]) -> Term {
+  let mut terms = Vec::new();
+  for kind in kinds {
+    let term = match kind {
+      TokenKind::Union(n) => slot(id, *n),
+      kind => match text_of(*kind) {
+        Some(text) => Term::Text(text),
+        None => Term::Token(*kind),
+      },
+    };
+    match term {
+      Term::Seq(inner) => terms.extend(inner),
+      term => terms.push(term),
+    }
+  }
+  // Prefix operators read their operand themselves
+  if id.starts_with("expr_u_") && kinds[0] != TokenKind::Union(0) {
+    terms.push(Term::Rule("expr"));
+  }
+  match terms.len() {
+    1 => terms.pop().unwrap(),
+    _ => Term::Seq(terms),
+  }
+}
+
+// The whole grammar, starting from `program`
+pub fn rules() -> Vec<Rule> {
+  let parser = Parser::new(Lexer::new(""));
+  let expect_list = Parser::get_expect_list();
+  let ids: Vec<&'static str> = expect_list.keys().copied().collect();
+  let choice = |filter: &dyn Fn(&str) -> bool| Term::Choice(ids.iter().filter(|id| filter(id)).map(|id| Term::Rule(id)).collect());
+  let tied = |name, body| Rule { name, id: None, body };
+
+  let items = ITEM_KINDS.iter().map(|kind| Term::Rule(parser.get_kind_id(kind))).collect();
+  let mut stmt = choice(&|id| id.starts_with("stmt_") && id != "stmt_else");
+  if let Term::Choice(alternatives) = &mut stmt {
+    alternatives.push(Term::Seq(vec![Term::Rule("expr"), Term::Text(";")]));
+  }
+  let mut rules = vec![
+    tied("program", Term::Many(Box::new(Term::Choice(items)))),
+    tied("stmt", stmt),
+    tied("expr", choice(&|id| id.starts_with("expr_") && id != "expr_args")),
+  ];
+  for (id, kinds) in &expect_list {
+    rules.push(Rule { name: id, id: Some(id), body: expect_rule(id, kinds) });
+  }
+  let param = Term::Rule("param");
+  rules.push(tied("params", Term::Seq(vec![
+    param.clone(),
+    Term::Many(Box::new(Term::Seq(vec![Term::Text(","), param]))),
+  ])));
+  // Only the first parameter of a method can be `self`, which the checker enforces
+  rules.push(tied("param", Term::Choice(vec![
+    Term::Text("self"),
+    Term::Seq(vec![Term::Token(TokenKind::Identifier), Term::Text(":"), Term::Rule("type")]),
+  ])));
+  // `item, ...` with an optional trailing `,`
+  let commas = |item: Term| Term::Seq(vec![
+    item.clone(),
+    Term::Many(Box::new(Term::Seq(vec![Term::Text(","), item]))),
+    Term::Opt(Box::new(Term::Text(","))),
+  ]);
+  // `name: part, ...`
+  let list = |item: &'static str, part: Term| {
+    let item_body = Term::Seq(vec![Term::Token(TokenKind::Identifier), Term::Text(":"), part]);
+    (commas(Term::Rule(item)), item_body)
+  };
+  let (fields, field) = list("field", Term::Rule("type"));
+  rules.push(tied("fields", fields));
+  rules.push(tied("field", field));
+  let (inits, init) = list("field_init", Term::Rule("expr"));
+  rules.push(tied("field_inits", inits));
+  rules.push(tied("field_init", init));
+  let parens = |item: &'static str| Term::Opt(Box::new(Term::Seq(vec![
+    Term::Text("("),
+    Term::Opt(Box::new(commas(Term::Rule(item)))),
+    Term::Text(")"),
+  ])));
+  rules.push(tied("variants", commas(Term::Rule("variant"))));
+  rules.push(tied("variant", Term::Seq(vec![Term::Token(TokenKind::Identifier), parens("type")])));
+  rules.push(tied("arms", commas(Term::Rule("arm"))));
+  rules.push(tied("arm", Term::Seq(vec![Term::Rule("pattern"), Term::Text("=>"), Term::Rule("expr")])));
+  // `_` is an identifier to the lexer, the parser reads it as a wildcard
+  let literal = Term::Choice(vec![
+    Term::Seq(vec![Term::Opt(Box::new(Term::Text("-"))), Term::Choice(vec![Term::Token(TokenKind::Int), Term::Token(TokenKind::Float)])]),
+    Term::Token(TokenKind::Char),
+    Term::Token(TokenKind::String),
+    Term::Text("true"),
+    Term::Text("false"),
+  ]);
+  rules.push(tied("pattern", Term::Choice(vec![
+    Term::Seq(vec![Term::Token(TokenKind::Identifier), Term::Text("::"), Term::Token(TokenKind::Identifier), parens("pattern")]),
+    Term::Token(TokenKind::Identifier),
+    Term::Seq(vec![literal.clone(), Term::Opt(Box::new(Term::Seq(vec![Term::Text(".."), literal])))]),
+  ])));
+  rules.push(tied("args", Term::Choice(vec![Term::Rule("expr_args"), Term::Rule("expr")])));
+  let expr = Term::Rule("expr");
+  rules.push(tied("elements", Term::Seq(vec![expr.clone(), Term::Choice(vec![
+    Term::Seq(vec![Term::Text(";"), expr.clone()]),
+    Term::Seq(vec![
+      Term::Many(Box::new(Term::Seq(vec![Term::Text(","), expr]))),
+      Term::Opt(Box::new(Term::Text(","))),
+    ]),
+  ])])));
+  rules.push(tied("type", Term::Choice(vec![
+    Term::Token(TokenKind::Identifier),
+    Term::Seq(vec![
+      Term::Text("["),
+      Term::Rule("type"),
+      Term::Opt(Box::new(Term::Seq(vec![Term::Text(";"), Term::Token(TokenKind::Int)]))),
+      Term::Text("]"),
+    ]),
+    Term::Seq(vec![
+      Term::Text("fun"),
+      Term::Text("("),
+      Term::Opt(Box::new(commas(Term::Rule("type")))),
+      Term::Text(")"),
+      Term::Text(":"),
+      Term::Rule("type"),
+    ]),
+  ])));
+  rules
+}
+
+fn references(term: &Term, out: &mut Vec<&'static str>) {
+  match term {
+    Term::Rule(name) => out.push(name),
+    Term::Seq(terms) | Term::Choice(terms) => terms.iter().for_each(|t| references(t, out)),
+    Term::Opt(term) | Term::Many(term) => references(term, out),
+    Term::Text(_) | Term::Token(_) => {},
+  }
+}
+
+// Problems that keep the expect list, the subparsers and the exported grammar
+// from agreeing, empty when they are consistent
+pub fn check_rules() -> Vec<String> {
+  let mut problems = Vec::new();
+  let expect_list = Parser::get_expect_list();
+  let subparsers = SubParser::get_subparser_list();
+  for id in expect_list.keys().filter(|id| !subparsers.contains_key(*id)) {
+    problems.push(format!("rule `{}` has no subparser", id));
+  }
+  for id in subparsers.keys().filter(|id| !expect_list.contains_key(*id)) {
+    problems.push(format!("subparser `{}` has no rule", id));
+  }
+  for (id, kinds) in &expect_list {
+    for kind in kinds {
+      let known = matches!(kind, TokenKind::Union(_)) || text_of(*kind).is_some() || pattern_of(*kind).is_some();
+      if !known {
+        problems.push(format!("rule `{}` uses {:?}, which has no spelling or pattern", id, kind));
+      }
+    }
+  }
+  let rules = rules();
+  for rule in &rules {
+    let mut names = Vec::new();
+    references(&rule.body, &mut names);
+    for name in names.iter().filter(|name| !rules.iter().any(|r| r.name == **name)) {
+      problems.push(format!("rule `{}` refers to the undefined `{}`", rule.name, name));
+    }
+  }
+  problems
+}
@@ -0,0 +1,166 @@
+use super::{rules, token_name, Term, GENERATED};
+
+// Where the diagrams are kept, relative to the crate root
+pub const PATH: &str = "docs/grammar.svg";
+
+const CHAR_WIDTH: i32 = 8;
+// Half the height of a box
+const HALF: i32 = 11;
+const GAP: i32 = 10;
+const ARC: i32 = 10;
+
+// Layout of a term: optional parts become a choice with an empty branch
+enum Node {
+  Terminal(String),
+  NonTerminal(String),
+  Seq(Vec<Node>),
+  Choice(Vec<Node>),
+  // One or more, with a path back under it
+  Loop(Box<Node>),
+}
+
+impl Node {
+  fn from(term: &Term) -> Node {
+    match term {
+      Term::Text(text) => Node::Terminal(text.to_string()),
+      Term::Token(kind) => Node::Terminal(token_name(*kind).to_uppercase()),
+      Term::Rule(name) => Node::NonTerminal(name.to_string()),
+      Term::Seq(terms) => Node::Seq(terms.iter().map(Node::from).collect()),
+      Term::Choice(terms) => Node::Choice(terms.iter().map(Node::from).collect()),
+      Term::Opt(term) => Node::Choice(vec![Node::Seq(Vec::new()), Node::from(term)]),
+      Term::Many(term) => Node::Choice(vec![Node::Seq(Vec::new()), Node::Loop(Box::new(Node::from(term)))]),
+    }
+  }
+  // Width, and extent above and below the line that runs through it
+  fn size(&self) -> (i32, i32, i32) {
+    match self {
+      Node::Terminal(label) | Node::NonTerminal(label) => (label.chars().count() as i32 * CHAR_WIDTH + 2 * GAP, HALF, HALF),
+      Node::Seq(nodes) => {
+        let sizes: Vec<_> = nodes.iter().map(|n| n.size()).collect();
+        let width = sizes.iter().map(|s| s.0).sum::<i32>() + GAP * (sizes.len() as i32 - 1).max(0);
+        (width, sizes.iter().map(|s| s.1).max().unwrap_or(0), sizes.iter().map(|s| s.2).max().unwrap_or(0))
+      },
+      Node::Choice(nodes) => {
+        let sizes: Vec<_> = nodes.iter().map(|n| n.size()).collect();
+        let width = sizes.iter().map(|s| s.0).max().unwrap_or(0) + 4 * ARC;
+        let below = sizes.iter().skip(1).map(|s| GAP + s.1 + s.2).sum::<i32>();
+        (width, sizes[0].1, sizes[0].2 + below)
+      },
+      Node::Loop(node) => {
+        let (width, up, down) = node.size();
+        (width + 2 * ARC, up, down + GAP)
+      },
+    }
+  }
+  // Draw with the line entering at (x, y) and leaving on the right
+  fn draw(&self, x: i32, y: i32, out: &mut String) {
+    let (width, _, _) = self.size();
+    match self {
+      Node::Terminal(label) | Node::NonTerminal(label) => {
+        let (class, rx) = match self {
+          Node::Terminal(_) => ("terminal", HALF),
+          _ => ("nonterminal", 0),
+        };
+        out.push_str(&format!(
+          "<rect class=\"{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\"/>\n<text x=\"{}\" y=\"{}\">{}</text>\n",
+          class, x, y - HALF, width, 2 * HALF, rx, x + width / 2, y + 4, escape(label)
+        ));
+      },
+      Node::Seq(nodes) => {
+        let mut cursor = x;
+        for (i, node) in nodes.iter().enumerate() {
+          node.draw(cursor, y, out);
+          cursor += node.size().0;
+          if i + 1 < nodes.len() {
+            line(out, cursor, cursor + GAP, y);
+            cursor += GAP;
+          }
+        }
+      },
+      Node::Choice(nodes) => {
+        let inner = width - 4 * ARC;
+        let (left, right) = (x + 2 * ARC, x + 2 * ARC + inner);
+        let mut branch_y = y;
+        for (i, node) in nodes.iter().enumerate() {
+          let (w, up, down) = node.size();
+          if i > 0 {
+            branch_y += up;
+            out.push_str(&format!(
+              "<path d=\"M{} {} Q{} {} {} {} V{} Q{} {} {} {}\"/>\n",
+              x, y, x + ARC, y, x + ARC, y + ARC, branch_y - ARC, x + ARC, branch_y, left, branch_y
+            ));
+            out.push_str(&format!(
+              "<path d=\"M{} {} Q{} {} {} {} V{} Q{} {} {} {}\"/>\n",
+              right, branch_y, x + width - ARC, branch_y, x + width - ARC, branch_y - ARC, y + ARC, x + width - ARC, y, x + width, y
+            ));
+          } else {
+            line(out, x, left, y);
+            line(out, right, x + width, y);
+          }
+          let start = left + (inner - w) / 2;
+          line(out, left, start, branch_y);
+          node.draw(start, branch_y, out);
+          line(out, start + w, right, branch_y);
+          branch_y += down + GAP;
+        }
+      },
+      Node::Loop(node) => {
+        let (w, _, down) = node.size();
+        let back = y + down + GAP;
+        line(out, x, x + ARC, y);
+        node.draw(x + ARC, y, out);
+        line(out, x + ARC + w, x + width, y);
+        out.push_str(&format!(
+          "<path d=\"M{} {} Q{} {} {} {} V{} Q{} {} {} {} H{} Q{} {} {} {} V{} Q{} {} {} {}\"/>\n",
+          x + width - ARC, y, x + width, y, x + width, y + ARC, back - ARC, x + width, back, x + width - ARC, back,
+          x + ARC, x, back, x, back - ARC, y + ARC, x, y, x + ARC, y
+        ));
+      },
+    }
+  }
+}
+
+fn line(out: &mut String, from: i32, to: i32, y: i32) {
+  if from < to {
+    out.push_str(&format!("<path d=\"M{} {} H{}\"/>\n", from, y, to));
+  }
+}
+
+fn escape(text: &str) -> String {
+  text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Every rule as a railroad diagram, one under the other
+pub fn generate() -> String {
+  let mut body = String::new();
+  let mut top = 0;
+  let mut total_width = 0;
+  for rule in rules() {
+    let node = Node::from(&rule.body);
+    let (width, up, down) = node.size();
+    let y = top + 30 + up;
+    body.push_str(&format!("<text class=\"title\" x=\"10\" y=\"{}\">{}</text>\n", top + 20, escape(rule.name)));
+    // Start and end marks
+    body.push_str(&format!("<path d=\"M10 {} V{} M10 {} H20\"/>\n", y - 6, y + 6, y));
+    node.draw(20, y, &mut body);
+    body.push_str(&format!("<path d=\"M{} {} H{} M{} {} V{}\"/>\n", 20 + width, y, 30 + width, 30 + width, y - 6, y + 6));
+    total_width = total_width.max(width + 50);
+    top = y + down + 20;
+  }
+  let mut out = format!(
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+    total_width, top
+  );
+  out.push_str(&format!("<!-- {} -->\n", GENERATED));
+  out.push_str("<style>\n");
+  out.push_str("path { fill: none; stroke: #333; stroke-width: 1.5; }\n");
+  out.push_str("rect { stroke: #333; stroke-width: 1.5; }\n");
+  out.push_str("rect.terminal { fill: #e8f0fe; }\n");
+  out.push_str("rect.nonterminal { fill: #fdf6e3; }\n");
+  out.push_str("text { font: 13px monospace; text-anchor: middle; }\n");
+  out.push_str("text.title { font-weight: bold; text-anchor: start; }\n");
+  out.push_str("</style>\n");
+  out.push_str(&body);
+  out.push_str("</svg>\n");
+  out
+}
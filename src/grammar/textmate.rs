@@ -0,0 +1,96 @@
+use crate::ast::token::{self, TokenKind};
+use crate::checker::types::TYPE_NAMES;
+use crate::lexer::Lexer;
+use crate::parser::{Parser, ITEM_KINDS};
+use crate::utilities::json::Json;
+
+use super::{pattern_of, GENERATED};
+
+// Where the grammar is kept, relative to the crate root
+pub const PATH: &str = "editors/textmate/carf.tmLanguage.json";
+
+fn regex_escape(text: &str) -> String {
+  text.chars().map(|c| match c {
+    '\\' | '^' | '$' | '.' | '|' | '?' | '*' | '+' | '(' | ')' | '[' | ']' | '{' | '}' | '/' => format!("\\{}", c),
+    c => c.to_string(),
+  }).collect()
+}
+
+fn words(words: &[&str]) -> String {
+  format!(r"\b(?:{})\b", words.join("|"))
+}
+
+// Keywords split into constants, item keywords and the rest
+fn keyword_groups() -> [Vec<&'static str>; 3] {
+  let parser = Parser::new(Lexer::new(""));
+  let mut groups = [Vec::new(), Vec::new(), Vec::new()];
+  for keyword in token::KEYWORD_LIST {
+    let kind = token::keyword_token_map(keyword);
+    let group = match kind {
+      _ if ITEM_KINDS.contains(&kind) => 1,
+      _ if parser.is_atom(&kind) => 0,
+      _ => 2,
+    };
+    groups[group].push(keyword);
+  }
+  groups
+}
+
+// Operators first, longest first so `<<=` is not read as `<` and `<=`
+fn symbol_groups() -> [Vec<&'static str>; 2] {
+  let (mut operators, punctuation): (Vec<&str>, Vec<&str>) = token::SYMBOL_LIST.iter()
+    .partition(|s| token::is_operator(token::sym_token_map(s)));
+  operators.sort_by_key(|s| std::cmp::Reverse(s.len()));
+  [operators, punctuation]
+}
+
+pub fn generate() -> String {
+  let rule = |name: &str, pattern: String| Json::object(vec![
+    ("name", Json::String(format!("{}.carf", name))),
+    ("match", Json::String(pattern)),
+  ]);
+  let patterns = |rules: Vec<Json>| Json::object(vec![("patterns", Json::Array(rules))]);
+  let [constants, items, control] = keyword_groups();
+  let [operators, punctuation] = symbol_groups();
+  let alternatives = |symbols: &[&str]| symbols.iter().map(|s| regex_escape(s)).collect::<Vec<_>>().join("|");
+
+  let repository = vec![
+    ("comment", rule("comment.line.double-slash", "//.*".to_string())),
+    ("string", Json::object(vec![
+      ("name", Json::str("string.quoted.double.carf")),
+      ("begin", Json::str("\"")),
+      // The lexer ends an unterminated string at the end of the line
+      ("end", Json::str("\"|$")),
+      ("patterns", Json::Array(vec![rule("constant.character.escape", r"\\.".to_string())])),
+    ])),
+    ("char", rule("string.quoted.single", pattern_of(TokenKind::Char).unwrap().to_string())),
+    ("keyword", patterns(vec![
+      rule("constant.language", words(&constants)),
+      rule("storage.type", words(&items)),
+      rule("keyword.control", words(&control)),
+    ])),
+    ("type", rule("support.type.primitive", words(&TYPE_NAMES))),
+    ("number", patterns(vec![
+      rule("constant.numeric.float", format!(r"\b(?:{})\b", pattern_of(TokenKind::Float).unwrap())),
+      rule("constant.numeric.integer", format!(r"\b{}\b", pattern_of(TokenKind::Int).unwrap())),
+    ])),
+    ("function", Json::object(vec![
+      ("match", Json::String(format!(r"({})(?=\s*\()", pattern_of(TokenKind::Identifier).unwrap()))),
+      ("captures", Json::object(vec![("1", Json::object(vec![("name", Json::str("entity.name.function.carf"))]))])),
+    ])),
+    ("operator", rule("keyword.operator", alternatives(&operators))),
+    ("punctuation", rule("punctuation", alternatives(&punctuation))),
+  ];
+  let includes = repository.iter()
+    .map(|(name, _)| Json::object(vec![("include", Json::String(format!("#{}", name)))]))
+    .collect();
+  let grammar = Json::object(vec![
+    ("comment", Json::str(GENERATED)),
+    ("name", Json::str("carf")),
+    ("scopeName", Json::str("source.carf")),
+    ("fileTypes", Json::Array(vec![Json::str("carf")])),
+    ("patterns", Json::Array(includes)),
+    ("repository", Json::object(repository)),
+  ]);
+  format!("{}\n", grammar.pretty())
+}
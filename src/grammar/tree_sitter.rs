@@ -0,0 +1,76 @@
+use crate::ast::token::{self, TokenKind};
+use crate::lexer::Lexer;
+use crate::parser::{Parser, UNARY_PREC};
+
+use super::{pattern_of, rules, token_name, Term, GENERATED};
+
+// Where the grammar is kept, relative to the crate root
+pub const PATH: &str = "editors/tree-sitter-carf/grammar.js";
+
+fn js_string(text: &str) -> String {
+  format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+// Rules that only group others are hidden from the syntax tree
+fn rule_name(name: &str) -> String {
+  match name {
+    "program" => "source_file".to_string(),
+    "stmt" => "_statement".to_string(),
+    "expr" => "_expression".to_string(),
+    "args" => "_args".to_string(),
+    name => name.to_string(),
+  }
+}
+
+// A choice at the top of a rule gets one alternative per line
+fn render(term: &Term, top: bool) -> String {
+  let all = |terms: &[Term]| terms.iter().map(|t| render(t, false)).collect::<Vec<_>>();
+  match term {
+    Term::Text(text) => js_string(text),
+    Term::Token(kind) => format!("$.{}", token_name(*kind)),
+    Term::Rule(name) => format!("$.{}", rule_name(name)),
+    Term::Seq(terms) => format!("seq({})", all(terms).join(", ")),
+    Term::Choice(terms) if top => {
+      let lines: Vec<String> = all(terms).iter().map(|t| format!("      {},\n", t)).collect();
+      format!("choice(\n{}    )", lines.concat())
+    },
+    Term::Choice(terms) => format!("choice({})", all(terms).join(", ")),
+    Term::Opt(term) => format!("optional({})", render(term, top)),
+    Term::Many(term) => format!("repeat({})", render(term, top)),
+  }
+}
+
+pub fn generate() -> String {
+  let parser = Parser::new(Lexer::new(""));
+  let expect_list = Parser::get_expect_list();
+  let mut out = format!("// {}\n\n", GENERATED);
+  out.push_str("module.exports = grammar({\n  name: 'carf',\n\n");
+  out.push_str("  extras: $ => [/\\s/, $.comment],\n\n");
+  out.push_str("  word: $ => $.identifier,\n\n");
+  out.push_str("  rules: {\n");
+  let mut rule = |name: &str, body: String| out.push_str(&format!("    {}: $ => {},\n\n", name, body));
+  for r in rules() {
+    let mut body = render(&r.body, true);
+    let kinds = r.id.map(|id| &expect_list[id]);
+    if let Some(kinds) = kinds.filter(|kinds| kinds[0] == TokenKind::Union(0) && kinds.len() > 1) {
+      body = match parser.get_prec(&kinds[1]) {
+        Some((prec, true)) => format!("prec.right({}, {})", prec, body),
+        Some((prec, false)) => format!("prec.left({}, {})", prec, body),
+        None => body,
+      };
+    } else if r.name.starts_with("expr_u_") {
+      body = format!("prec({}, {})", UNARY_PREC, body);
+    } else if matches!(&r.body, Term::Seq(terms) if matches!(terms.last(), Some(Term::Opt(_)))) {
+      // A trailing optional part, like `else`, belongs to the innermost rule
+      body = format!("prec.right({})", body);
+    }
+    rule(&rule_name(r.name), body);
+  }
+  for (kind, _) in token::LITERAL_PATTERNS.iter().rev() {
+    rule(token_name(*kind), format!("/{}/", pattern_of(*kind).unwrap().replace('/', "\\/")));
+  }
+  rule("comment", "token(seq('//', /.*/))".to_string());
+  out.truncate(out.len() - 1);
+  out.push_str("  },\n});\n");
+  out
+}
@@ -0,0 +1,139 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::ast::AstNode;
+use crate::diagnostic::{Diagnostic, Span};
+
+// Calls a script may nest by default
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+// Bytes of stack a run may take by default, half of what a spawned thread gets
+pub const DEFAULT_MAX_STACK: usize = 1 << 20;
+// Steps between two looks at the clock and the cancel flag
+const CHECK_EVERY: u64 = 256;
+
+// Stops a running script from another thread. Clones share the flag.
+#[derive(Clone, Default, Debug)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+  pub fn new() -> CancelHandle {
+    CancelHandle::default()
+  }
+  // The script stops at its next check, and any later run stops at once
+  pub fn cancel(&self) {
+    self.0.store(true, Ordering::Relaxed);
+  }
+  pub fn is_cancelled(&self) -> bool {
+    self.0.load(Ordering::Relaxed)
+  }
+}
+
+// How much one run may do. `None` leaves a resource unbounded.
+#[derive(Clone, Debug)]
+pub struct Limits {
+  // Statements and expressions evaluated
+  pub max_steps: Option<u64>,
+  // Calls active at once
+  pub max_depth: usize,
+  // Bytes of stack the calls may take, which stops them before `max_depth` when
+  // they would overflow the stack of the thread running them
+  pub max_stack: usize,
+  // Bytes taken by the strings, arrays, structs and variants created
+  pub max_heap: Option<usize>,
+  // Time from the start of the run
+  pub timeout: Option<Duration>,
+  pub cancel: Option<CancelHandle>,
+}
+
+impl Default for Limits {
+  fn default() -> Limits {
+    Limits {
+      max_steps: None,
+      max_depth: DEFAULT_MAX_DEPTH,
+      max_stack: DEFAULT_MAX_STACK,
+      max_heap: None,
+      timeout: None,
+      cancel: None,
+    }
+  }
+}
+
+// What a run has used of its limits
+pub struct Budget {
+  limits: Limits,
+  steps: u64,
+  heap: usize,
+  deadline: Option<Instant>,
+  // Stack address the first call of the run started at
+  stack_base: Option<usize>,
+  // Set once a limit stopped the run
+  exceeded: bool,
+}
+
+impl Budget {
+  pub fn new(limits: Limits) -> Budget {
+    Budget {
+      deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+      limits,
+      steps: 0,
+      heap: 0,
+      stack_base: None,
+      exceeded: false,
+    }
+  }
+  pub fn is_exceeded(&self) -> bool {
+    self.exceeded
+  }
+  // Start over with nothing used and the clock restarted
+  pub fn reset(&mut self) {
+    *self = Budget::new(self.limits.clone());
+  }
+  // Count one statement or expression
+  pub fn step(&mut self, node: &AstNode) -> Result<(), Diagnostic> {
+    self.steps += 1;
+    if let Some(max) = self.limits.max_steps && self.steps > max {
+      return Err(self.exceed(node, format!("step limit of {} exceeded", max)));
+    }
+    if !self.steps.is_multiple_of(CHECK_EVERY) {
+      return Ok(());
+    }
+    if self.limits.cancel.as_ref().is_some_and(|cancel| cancel.is_cancelled()) {
+      return Err(self.exceed(node, "execution cancelled".to_string()));
+    }
+    match (self.deadline, self.limits.timeout) {
+      (Some(deadline), Some(timeout)) if Instant::now() >= deadline => {
+        Err(self.exceed(node, format!("time limit of {}ms exceeded", timeout.as_millis())))
+      },
+      _ => Ok(()),
+    }
+  }
+  // Check the depth a call is about to run at, and the stack the calls below it took
+  pub fn call(&mut self, depth: usize, span: Span) -> Result<(), Diagnostic> {
+    let here = &depth as *const usize as usize;
+    let base = *self.stack_base.get_or_insert(here);
+    if depth > self.limits.max_depth {
+      self.exceeded = true;
+      return Err(Diagnostic::error(span, format!("call depth limit of {} exceeded", self.limits.max_depth)));
+    }
+    if here.abs_diff(base) > self.limits.max_stack {
+      self.exceeded = true;
+      return Err(Diagnostic::error(span, format!(
+        "call depth limit exceeded, {} calls take more than {} bytes of stack", depth, self.limits.max_stack
+      )));
+    }
+    Ok(())
+  }
+  // Count memory about to be, or just, taken
+  pub fn allocate(&mut self, bytes: usize, node: &AstNode) -> Result<(), Diagnostic> {
+    self.heap = self.heap.saturating_add(bytes);
+    match self.limits.max_heap {
+      Some(max) if self.heap > max => Err(self.exceed(node, format!("heap limit of {} bytes exceeded", max))),
+      _ => Ok(()),
+    }
+  }
+  fn exceed(&mut self, node: &AstNode, message: String) -> Diagnostic {
+    self.exceeded = true;
+    Diagnostic::error(Span::of(node), message)
+  }
+}
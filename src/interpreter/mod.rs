@@ -0,0 +1,866 @@
+pub mod limits;
+pub mod prelude;
+pub mod value;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::ast::token::TokenKind;
+use crate::ast::{Ast, AstKind, AstNode};
+use crate::checker::patterns::{in_range, pattern_const};
+use crate::checker::strip_assign;
+use crate::diagnostic::{Diagnostic, Span};
+use crate::utilities::literal;
+use crate::fold::Const;
+use crate::resolve::Resolution;
+use limits::{Budget, Limits};
+use prelude::Console;
+use value::{Cell, Fun, Record, Value, Variant};
+
+// How a statement finished
+enum Flow {
+  Next,
+  Break,
+  Continue,
+  Return(Value),
+}
+
+// Assignable location found by evaluating the left side of an assignment once
+enum Place<'a> {
+  Var(&'a str),
+  Elem(Rc<RefCell<Vec<Value>>>, usize),
+  Field(Rc<RefCell<Record>>, usize),
+  // Strings are values, so writing a char rebuilds the string held by the inner place
+  StrChar(Box<Place<'a>>, usize),
+}
+
+// A `let` without initializer holds None until assigned
+type Globals<'a> = BTreeMap<&'a str, Option<Value>>;
+
+// A local moves into a cell once a lambda captures it by reference
+enum Slot {
+  Own(Option<Value>),
+  Shared(Cell),
+}
+
+type Scope<'a> = BTreeMap<&'a str, Slot>;
+
+// A lambda node with the names it captures and whether each is captured by reference
+type Lambda<'n, 'a> = (&'n AstNode<'a>, Vec<(&'a str, bool)>);
+
+// Function of the host program, given the arguments the checker let through
+pub type Native = Rc<dyn Fn(Vec<Value>) -> Result<Value, String>>;
+
+// Tree-walking evaluator over a checked tree
+pub struct Interpreter<'n, 'a> {
+  funs: BTreeMap<&'a str, &'n AstNode<'a>>,
+  natives: BTreeMap<&'a str, Native>,
+  structs: BTreeMap<&'a str, &'n AstNode<'a>>,
+  // Keyed by the implemented type and the method name
+  methods: BTreeMap<(String, &'a str), &'n AstNode<'a>>,
+  // Keyed by the address of the lambda node
+  lambdas: BTreeMap<usize, Lambda<'n, 'a>>,
+  globals: Globals<'a>,
+  // Scopes of each active call, innermost last
+  frames: Vec<Vec<Scope<'a>>>,
+  console: Console<'n>,
+  budget: Budget,
+}
+
+impl<'n, 'a> Interpreter<'n, 'a> {
+  pub fn empty() -> Interpreter<'n, 'a> {
+    Interpreter {
+      funs: BTreeMap::new(),
+      natives: BTreeMap::new(),
+      structs: BTreeMap::new(),
+      methods: BTreeMap::new(),
+      lambdas: BTreeMap::new(),
+      globals: BTreeMap::new(),
+      frames: vec![vec![BTreeMap::new()]],
+      console: Console::std(),
+      budget: Budget::new(Limits::default()),
+    }
+  }
+  // Declares every function and evaluates the globals in source order
+  pub fn new(ast: &'n Ast<'a>) -> Result<Interpreter<'n, 'a>, Diagnostic> {
+    Interpreter::with_console(ast, Console::std())
+  }
+  // Same as `new`, printing to and reading from `console`
+  pub fn with_console(ast: &'n Ast<'a>, console: Console<'n>) -> Result<Interpreter<'n, 'a>, Diagnostic> {
+    let mut interpreter = Interpreter::empty();
+    interpreter.set_console(console);
+    interpreter.load(ast)?;
+    Ok(interpreter)
+  }
+  // Define every item of the tree, globals last so they can call any function
+  pub fn load(&mut self, ast: &'n Ast<'a>) -> Result<(), Diagnostic> {
+    let items = ast.get_root().get_children();
+    for item in items.iter().filter(|item| matches!(item.get_kind(), AstKind::Fun | AstKind::Struct | AstKind::Impl)) {
+      self.define(item)?;
+    }
+    for item in items.iter().filter(|item| item.get_kind() == AstKind::Let) {
+      self.define(item)?;
+    }
+    Ok(())
+  }
+  pub fn define_native(&mut self, name: &'a str, native: Native) {
+    self.natives.insert(name, native);
+  }
+  pub fn define_global(&mut self, name: &'a str, value: Value) {
+    self.globals.insert(name, Some(value));
+  }
+  // Add a root level item, replacing an earlier one of the same name
+  pub fn define(&mut self, item: &'n AstNode<'a>) -> Result<(), Diagnostic> {
+    self.add_lambdas(item, &Resolution::resolve_stmt(item));
+    match item.get_kind() {
+      AstKind::Fun if item.child_count() == 4 => {
+        self.funs.insert(item[0].get_token().get_value(), item);
+      },
+      AstKind::Struct if item.child_count() == 2 => {
+        self.structs.insert(item[0].get_token().get_value(), item);
+      },
+      AstKind::Impl if item.child_count() > 0 => {
+        let ty = item[0].get_token().get_value();
+        for method in item.get_children().iter().skip(1).filter(|m| m.child_count() == 4) {
+          self.methods.insert((ty.to_string(), method[0].get_token().get_value()), method);
+        }
+      },
+      AstKind::Let if item.child_count() > 2 => {
+        let value = self.eval(&item[2])?;
+        self.globals.insert(item[0].get_token().get_value(), Some(value));
+      },
+      _ => {},
+    }
+    Ok(())
+  }
+  // Run a statement outside of any function and give the value of its expression
+  pub fn exec_root_stmt(&mut self, node: &'n AstNode<'a>) -> Result<Value, Diagnostic> {
+    self.add_lambdas(node, &Resolution::resolve_stmt(node));
+    if node.get_kind() == AstKind::Stmt && node.child_count() > 0 {
+      return self.eval(&node[0]);
+    }
+    self.exec(node)?;
+    Ok(Value::Unit)
+  }
+  pub fn run_main(&mut self) -> Result<Value, Diagnostic> {
+    match self.funs.get("main") {
+      Some(main) if main[1].child_count() > 0 => {
+        Err(Diagnostic::error(Span::of_token(&main[0]), "`main` must not take parameters".to_string()))
+      },
+      Some(_) => self.call("main", Vec::new()),
+      None => Err(Diagnostic::error(Span::new(0, 0), "no `main` function to run".to_string())),
+    }
+  }
+  pub fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value, Diagnostic> {
+    let fun = match self.funs.get(name) {
+      Some(fun) => *fun,
+      None => return Err(Diagnostic::error(Span::new(0, 0), format!("cannot find function `{}`", name))),
+    };
+    self.invoke(fun, args, Span::of_token(&fun[0]))
+  }
+  pub fn set_console(&mut self, console: Console<'n>) {
+    self.console = console;
+  }
+  // Limits of what follows, counted from now
+  pub fn set_limits(&mut self, limits: Limits) {
+    self.budget = Budget::new(limits);
+  }
+  // Same limits again in full, e.g. for the next call of a host
+  pub fn reset_budget(&mut self) {
+    self.budget.reset();
+  }
+  // Whether the last error came from a limit rather than from the script
+  pub fn limit_exceeded(&self) -> bool {
+    self.budget.is_exceeded()
+  }
+  pub fn get_global(&self, name: &str) -> Option<&Value> {
+    self.globals.get(name).and_then(|v| v.as_ref())
+  }
+  fn add_lambdas(&mut self, node: &'n AstNode<'a>, resolution: &Resolution<'a>) {
+    if node.get_kind() == AstKind::Lambda {
+      let captures = resolution.captures_of(node.get_token().get_pos());
+      self.lambdas.insert(address(node), (node, captures.iter().map(|c| (c.name, c.by_ref)).collect()));
+    }
+    for child in node.get_children() {
+      self.add_lambdas(child, resolution);
+    }
+  }
+}
+
+// Runs `main` of a checked program and returns what it returned
+pub fn run(ast: &Ast) -> Result<Value, Diagnostic> {
+  run_with(ast, Console::std())
+}
+
+// Run `main` with `print` and `read_line` going through `console`
+pub fn run_with<'n>(ast: &'n Ast, console: Console<'n>) -> Result<Value, Diagnostic> {
+  Interpreter::with_console(ast, console)?.run_main()
+}
+
+impl<'n, 'a> Interpreter<'n, 'a> {
+  fn invoke(&mut self, fun: &'n AstNode<'a>, args: Vec<Value>, span: Span) -> Result<Value, Diagnostic> {
+    let what = format!("function `{}`", fun[0].get_token().get_value());
+    self.enter(what, &fun[1], &fun[3], BTreeMap::new(), args, span)
+  }
+  fn invoke_value(&mut self, fun: &Value, args: Vec<Value>, node: &AstNode<'a>) -> Result<Value, Diagnostic> {
+    let fun = match fun {
+      Value::Fun(fun) => fun,
+      other => return Err(error(&node[0], format!("cannot call a `{}`", other.type_name()))),
+    };
+    match &**fun {
+      Fun::Named(name) => match (self.funs.get(&**name), self.natives.get(&**name)) {
+        (Some(fun), _) => self.invoke(fun, args, Span::of(node)),
+        (None, Some(native)) => native(args).map_err(|message| error(node, message)),
+        (None, None) => Err(error(&node[0], format!("cannot find function `{}`", name))),
+      },
+      Fun::Lambda(addr, cells) => {
+        let (lambda, names) = match self.lambdas.get(addr) {
+          Some((lambda, names)) => (*lambda, names),
+          None => return Err(error(&node[0], "cannot find the body of this lambda".to_string())),
+        };
+        let captured = names.iter().zip(cells).map(|((name, _), cell)| (*name, Slot::Shared(cell.clone()))).collect();
+        self.enter("lambda".to_string(), &lambda[0], &lambda[2], captured, args, Span::of(node))
+      },
+    }
+  }
+  // Runs a body in a new frame holding the captured variables and the arguments
+  fn enter(
+    &mut self, what: String, params: &'n AstNode<'a>, body: &'n AstNode<'a>, mut scope: Scope<'a>, args: Vec<Value>, span: Span,
+  ) -> Result<Value, Diagnostic> {
+    let params = params.get_children();
+    if params.len() != args.len() {
+      return Err(Diagnostic::error(span, format!(
+        "{} takes {} arguments but {} were supplied", what, params.len(), args.len()
+      )));
+    }
+    for (param, arg) in params.iter().zip(args) {
+      scope.insert(param.get_token().get_value(), Slot::Own(Some(arg)));
+    }
+    self.budget.call(self.frames.len(), span)?;
+    self.frames.push(vec![scope]);
+    let flow = self.exec(body);
+    self.frames.pop();
+    match flow? {
+      Flow::Return(value) => Ok(value),
+      _ => Ok(Value::Unit),
+    }
+  }
+  // A lambda copies what it captures by value and shares the rest with this frame
+  fn eval_lambda(&mut self, node: &'n AstNode<'a>) -> Result<Value, Diagnostic> {
+    let names = self.lambdas.get(&address(node)).map(|(_, names)| names.clone()).unwrap_or_default();
+    let mut cells = Vec::new();
+    for (name, by_ref) in names {
+      let cell = match self.slot(name) {
+        Some(slot) if by_ref => match slot {
+          Slot::Shared(cell) => cell.clone(),
+          Slot::Own(value) => {
+            let cell = Rc::new(RefCell::new(value.take()));
+            *slot = Slot::Shared(cell.clone());
+            cell
+          },
+        },
+        Some(Slot::Own(value)) => Rc::new(RefCell::new(value.clone())),
+        Some(Slot::Shared(cell)) => Rc::new(RefCell::new(cell.borrow().clone())),
+        None => return Err(error(node, format!("cannot find value `{}`", name))),
+      };
+      cells.push(cell);
+    }
+    Ok(Value::Fun(Rc::new(Fun::Lambda(address(node), cells))))
+  }
+  fn scopes(&mut self) -> &mut Vec<Scope<'a>> {
+    self.frames.last_mut().unwrap()
+  }
+  fn declare(&mut self, name: &'a str, value: Option<Value>) {
+    self.scopes().last_mut().unwrap().insert(name, Slot::Own(value));
+  }
+  // Local of the running call, globals are kept apart
+  fn slot(&mut self, name: &str) -> Option<&mut Slot> {
+    let frame = self.frames.last_mut().unwrap();
+    frame.iter_mut().rev().find_map(|scope| scope.get_mut(name))
+  }
+  fn is_var(&mut self, name: &str) -> bool {
+    self.slot(name).is_some() || self.globals.contains_key(name)
+  }
+  // Variables shadow functions, a function used as a value is found by name
+  fn lookup(&mut self, name: &str, node: &AstNode<'a>) -> Result<Value, Diagnostic> {
+    let value = match self.slot(name) {
+      Some(Slot::Own(value)) => value.clone(),
+      Some(Slot::Shared(cell)) => cell.borrow().clone(),
+      None => match self.globals.get(name) {
+        Some(value) => value.clone(),
+        None if self.funs.contains_key(name) || self.natives.contains_key(name) => Some(Value::Fun(Rc::new(Fun::Named(name.into())))),
+        None => return Err(error(node, format!("cannot find value `{}`", name))),
+      },
+    };
+    value.ok_or_else(|| error(node, format!("use of uninitialized variable `{}`", name)))
+  }
+  fn assign(&mut self, name: &str, value: Value) {
+    match self.slot(name) {
+      Some(Slot::Own(slot)) => *slot = Some(value),
+      Some(Slot::Shared(cell)) => *cell.borrow_mut() = Some(value),
+      None => {
+        if let Some(slot) = self.globals.get_mut(name) {
+          *slot = Some(value);
+        }
+      },
+    }
+  }
+}
+
+impl<'n, 'a> Interpreter<'n, 'a> {
+  fn exec(&mut self, node: &'n AstNode<'a>) -> Result<Flow, Diagnostic> {
+    self.budget.step(node)?;
+    match node.get_kind() {
+      AstKind::Block => {
+        self.scopes().push(BTreeMap::new());
+        let mut flow = Ok(Flow::Next);
+        for stmt in node.get_children() {
+          flow = self.exec(stmt);
+          if !matches!(flow, Ok(Flow::Next)) {
+            break;
+          }
+        }
+        self.scopes().pop();
+        flow
+      },
+      AstKind::Let => {
+        let value = match node.child_count() > 2 {
+          true => Some(self.eval(&node[2])?),
+          false => None,
+        };
+        self.declare(node[0].get_token().get_value(), value);
+        Ok(Flow::Next)
+      },
+      AstKind::Stmt => {
+        if node.child_count() > 0 {
+          self.eval(&node[0])?;
+        }
+        Ok(Flow::Next)
+      },
+      AstKind::If => {
+        if self.eval_bool(&node[0])? {
+          self.exec(&node[1])
+        } else if node.child_count() > 2 && node[2].child_count() > 0 {
+          self.exec(&node[2][0])
+        } else {
+          Ok(Flow::Next)
+        }
+      },
+      AstKind::While => {
+        while self.eval_bool(&node[0])? {
+          match self.exec(&node[1])? {
+            Flow::Break => break,
+            Flow::Return(value) => return Ok(Flow::Return(value)),
+            Flow::Next | Flow::Continue => {},
+          }
+        }
+        Ok(Flow::Next)
+      },
+      AstKind::For => self.exec_for(node),
+      AstKind::Return => {
+        let value = match node.child_count() {
+          0 => Value::Unit,
+          _ => self.eval(&node[0])?,
+        };
+        Ok(Flow::Return(value))
+      },
+      AstKind::Break => Ok(Flow::Break),
+      AstKind::Continue => Ok(Flow::Continue),
+      _ => Err(error(node, "cannot execute this statement".to_string())),
+    }
+  }
+  fn exec_for(&mut self, node: &'n AstNode<'a>) -> Result<Flow, Diagnostic> {
+    let name = node[0].get_token().get_value();
+    let iter = self.eval(&node[1])?;
+    let mut i = 0_usize;
+    loop {
+      // Arrays are read as the loop goes, so the body sees its own writes
+      let item = match &iter {
+        Value::Range(lo, hi) => match lo.checked_add(i as i64) {
+          Some(n) if n < *hi => Value::Int(n),
+          _ => break,
+        },
+        Value::Array(items) => match items.borrow().get(i) {
+          Some(item) => item.clone(),
+          None => break,
+        },
+        Value::Str(s) => match s.chars().nth(i) {
+          Some(c) => Value::Char(c),
+          None => break,
+        },
+        other => return Err(error(&node[1], format!("cannot iterate over a `{}`", other.type_name()))),
+      };
+      i += 1;
+      self.scopes().push(BTreeMap::from([(name, Slot::Own(Some(item)))]));
+      let flow = self.exec(&node[2]);
+      self.scopes().pop();
+      match flow? {
+        Flow::Break => break,
+        Flow::Return(value) => return Ok(Flow::Return(value)),
+        Flow::Next | Flow::Continue => {},
+      }
+    }
+    Ok(Flow::Next)
+  }
+}
+
+impl<'n, 'a> Interpreter<'n, 'a> {
+  fn eval_bool(&mut self, node: &'n AstNode<'a>) -> Result<bool, Diagnostic> {
+    match self.eval(node)? {
+      Value::Bool(b) => Ok(b),
+      other => Err(error(node, format!("expected a `bool`, found a `{}`", other.type_name()))),
+    }
+  }
+  fn eval(&mut self, node: &'n AstNode<'a>) -> Result<Value, Diagnostic> {
+    self.budget.step(node)?;
+    let token = node.get_token();
+    match node.get_kind() {
+      AstKind::Literal => {
+        let text = token.get_value();
+        let value = match token.get_kind() {
+          TokenKind::Int => literal::parse_int(text).map(Value::Int),
+          TokenKind::Float => literal::parse_float(text).map(Value::Float),
+          TokenKind::Char => literal::parse_char(text).map(Value::Char),
+          TokenKind::String => literal::parse_string(text).map(|s| Value::Str(s.into())),
+          TokenKind::True => Some(Value::Bool(true)),
+          TokenKind::False => Some(Value::Bool(false)),
+          _ => None,
+        };
+        let value = value.ok_or_else(|| error(node, format!("invalid literal `{}`", text)))?;
+        self.allocated(value, node)
+      },
+      AstKind::Identifier => self.lookup(token.get_value(), node),
+      AstKind::Expr => self.eval(&node[0]),
+      AstKind::UnOper => {
+        let operand = self.eval(&node[0])?;
+        match (token.get_kind(), operand) {
+          (TokenKind::Minus, Value::Int(n)) => n.checked_neg().map(Value::Int).ok_or_else(|| overflow(node)),
+          (TokenKind::Minus, Value::Float(x)) => Ok(Value::Float(-x)),
+          (TokenKind::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+          (_, operand) => Err(error(node, format!(
+            "cannot apply unary operator `{}` to a `{}`", token.get_value(), operand.type_name()
+          ))),
+        }
+      },
+      // `x++` and `x--` give the value from before the step, `++x` and `--x`
+      // the one after it
+      AstKind::PreStep | AstKind::PostStep => {
+        let place = self.place(&node[0])?;
+        let old = self.read(&place, &node[0])?;
+        let new = match (token.get_kind(), &old) {
+          (TokenKind::DPlus, Value::Int(n)) => n.checked_add(1),
+          (TokenKind::DMinus, Value::Int(n)) => n.checked_sub(1),
+          _ => return Err(error(node, format!("cannot apply `{}` to a `{}`", token.get_value(), old.type_name()))),
+        };
+        let new = Value::Int(new.ok_or_else(|| overflow(node))?);
+        self.write(place, new.clone(), &node[0])?;
+        match node.get_kind() {
+          AstKind::PreStep => Ok(new),
+          _ => Ok(old),
+        }
+      },
+      AstKind::BinOper => self.eval_binary(node),
+      AstKind::Call => {
+        let callee = &node[0];
+        let name = callee.get_token().get_value();
+        // Anything but a declared function is a value to call
+        let fun = match callee.get_kind() {
+          AstKind::Path => None,
+          AstKind::Identifier if !self.is_var(name) => None,
+          _ => Some(self.eval(callee)?),
+        };
+        let mut args = Vec::new();
+        for arg in node[1].get_children() {
+          args.push(self.eval(arg)?);
+        }
+        if let Some(fun) = fun {
+          return self.invoke_value(&fun, args, node);
+        }
+        if callee.get_kind() == AstKind::Path {
+          return self.allocated(variant(callee, args), node);
+        }
+        let value = match self.funs.get(name) {
+          Some(fun) => return self.invoke(fun, args, Span::of(node)),
+          None => match self.natives.get(name) {
+            Some(native) => native(args).map_err(|message| error(node, message))?,
+            None => prelude::call(&mut self.console, name, args, node)?,
+          },
+        };
+        self.allocated_by(name, value, node)
+      },
+      AstKind::MethodCall => {
+        let mut args = vec![self.eval(&node[0])?];
+        for arg in node[2].get_children() {
+          args.push(self.eval(arg)?);
+        }
+        let name = node[1].get_token().get_value();
+        match self.methods.get(&(args[0].method_key(), name)) {
+          Some(method) => self.invoke(method, args, Span::of(node)),
+          None => {
+            let value = builtin_method(name, args, node)?;
+            self.allocated_by(name, value, node)
+          },
+        }
+      },
+      AstKind::Array => {
+        let mut items = Vec::new();
+        for item in node.get_children() {
+          items.push(self.eval(item)?);
+        }
+        self.allocated(Value::array(items), node)
+      },
+      // Every element gets its own copy, so `[[0; 2]; 2]` has two distinct rows
+      AstKind::Repeat => {
+        let elem = self.eval(&node[0])?;
+        let count = match self.eval(&node[1])? {
+          Value::Int(n) => usize::try_from(n).map_err(|_| error(&node[1], format!("negative array repeat count {}", n)))?,
+          other => return Err(error(&node[1], format!("expected an `int` repeat count, found a `{}`", other.type_name()))),
+        };
+        // Counted before allocating, so a huge count fails cleanly
+        let size = count.saturating_mul(std::mem::size_of::<Value>().saturating_add(elem.copy_size()));
+        self.budget.allocate(size, node)?;
+        Ok(Value::array((0..count).map(|_| elem.deep_copy()).collect()))
+      },
+      AstKind::StructLit => self.eval_struct_lit(node),
+      AstKind::Path => self.allocated(variant(node, Vec::new()), node),
+      AstKind::Match => self.eval_match(node),
+      AstKind::Lambda => {
+        let lambda = self.eval_lambda(node)?;
+        self.allocated(lambda, node)
+      },
+      AstKind::Member => {
+        let place = self.place(node)?;
+        self.read(&place, node)
+      },
+      AstKind::Index => {
+        let base = self.eval(&node[0])?;
+        let index = self.eval_index(&node[1])?;
+        index_into(&base, index, node)
+      },
+      _ => Err(error(node, "cannot evaluate this expression".to_string())),
+    }
+  }
+  // Count the memory of a value just created
+  fn allocated(&mut self, value: Value, node: &AstNode<'a>) -> Result<Value, Diagnostic> {
+    self.budget.allocate(value.size(), node)?;
+    Ok(value)
+  }
+  // Same for the result of a built-in or native function, `push` growing its array
+  fn allocated_by(&mut self, name: &str, value: Value, node: &AstNode<'a>) -> Result<Value, Diagnostic> {
+    if name == "push" {
+      self.budget.allocate(std::mem::size_of::<Value>(), node)?;
+    }
+    self.allocated(value, node)
+  }
+  // The first arm whose pattern matches gives the value, with its bindings in scope
+  fn eval_match(&mut self, node: &'n AstNode<'a>) -> Result<Value, Diagnostic> {
+    let value = self.eval(&node[0])?;
+    for arm in node.get_children().iter().skip(1) {
+      let mut binds = Vec::new();
+      if !matches(&arm[0], &value, &mut binds) {
+        continue;
+      }
+      self.scopes().push(binds.into_iter().map(|(name, value)| (name, Slot::Own(Some(value)))).collect());
+      let result = self.eval(&arm[1]);
+      self.scopes().pop();
+      return result;
+    }
+    Err(error(node, format!("no `match` arm matches `{}`", value.repr())))
+  }
+  // Initializers run in source order, the fields are stored in declaration order
+  fn eval_struct_lit(&mut self, node: &'n AstNode<'a>) -> Result<Value, Diagnostic> {
+    let name = node[0].get_token().get_value();
+    let decl = match self.structs.get(name) {
+      Some(decl) => *decl,
+      None => return Err(error(&node[0], format!("cannot find struct `{}`", name))),
+    };
+    let mut given = Vec::new();
+    for init in node.get_children().iter().skip(1) {
+      given.push((init.get_token().get_value(), self.eval(&init[0])?));
+    }
+    let mut fields = Vec::new();
+    for field in decl[1].get_children() {
+      let field = field.get_token().get_value();
+      match given.iter().position(|(name, _)| *name == field) {
+        Some(i) => fields.push((field.into(), given.swap_remove(i).1)),
+        None => return Err(error(node, format!("missing field `{}` in `{}` literal", field, name))),
+      }
+    }
+    self.allocated(Value::Struct(Rc::new(RefCell::new(Record { name: name.into(), fields }))), node)
+  }
+  fn eval_index(&mut self, node: &'n AstNode<'a>) -> Result<i64, Diagnostic> {
+    match self.eval(node)? {
+      Value::Int(n) => Ok(n),
+      other => Err(error(node, format!("expected an `int` index, found a `{}`", other.type_name()))),
+    }
+  }
+  fn eval_binary(&mut self, node: &'n AstNode<'a>) -> Result<Value, Diagnostic> {
+    use TokenKind::*;
+    let kind = node.get_token().get_kind();
+    match kind {
+      Equal => {
+        let place = self.place(&node[0])?;
+        let value = self.eval(&node[1])?;
+        self.write(place, value, &node[0])?;
+        Ok(Value::Unit)
+      },
+      PlusEq | MinusEq | AsteriskEq | SlashEq | ModEq | AndEq | OrEq | XorEq | LShiftEq | RShiftEq => {
+        let place = self.place(&node[0])?;
+        let old = self.read(&place, &node[0])?;
+        let rhs = self.eval(&node[1])?;
+        let value = binary(strip_assign(kind), old, rhs, node)?;
+        self.budget.allocate(value.size(), node)?;
+        self.write(place, value, &node[0])?;
+        Ok(Value::Unit)
+      },
+      DAnd => Ok(Value::Bool(self.eval_bool(&node[0])? && self.eval_bool(&node[1])?)),
+      DOr => Ok(Value::Bool(self.eval_bool(&node[0])? || self.eval_bool(&node[1])?)),
+      _ => {
+        let lhs = self.eval(&node[0])?;
+        let rhs = self.eval(&node[1])?;
+        let value = binary(kind, lhs, rhs, node)?;
+        self.allocated(value, node)
+      },
+    }
+  }
+}
+
+impl<'n, 'a> Interpreter<'n, 'a> {
+  fn place(&mut self, node: &'n AstNode<'a>) -> Result<Place<'a>, Diagnostic> {
+    match node.get_kind() {
+      AstKind::Identifier => {
+        let name = node.get_token().get_value();
+        match self.is_var(name) {
+          true => Ok(Place::Var(name)),
+          false => Err(error(node, format!("cannot find value `{}`", name))),
+        }
+      },
+      AstKind::Expr => self.place(&node[0]),
+      AstKind::Member => {
+        let field = node[1].get_token().get_value();
+        match self.eval(&node[0])? {
+          Value::Struct(record) => {
+            let i = record.borrow().fields.iter().position(|(name, _)| &**name == field);
+            match i {
+              Some(i) => Ok(Place::Field(record, i)),
+              None => Err(error(&node[1], format!("no field `{}` on `{}`", field, record.borrow().name))),
+            }
+          },
+          other => Err(error(&node[0], format!("no field `{}` on a `{}`", field, other.type_name()))),
+        }
+      },
+      AstKind::Index => {
+        let base = &node[0];
+        let (base_place, base_value) = match base.get_kind() {
+          AstKind::Identifier | AstKind::Index | AstKind::Expr | AstKind::Member => {
+            let place = self.place(base)?;
+            let value = self.read(&place, base)?;
+            (Some(place), value)
+          },
+          _ => (None, self.eval(base)?),
+        };
+        let index = self.eval_index(&node[1])?;
+        match (base_value, base_place) {
+          (Value::Array(items), _) => {
+            let len = items.borrow().len();
+            Ok(Place::Elem(items, checked_index(index, len, node)?))
+          },
+          (Value::Str(s), Some(place)) => {
+            let len = s.chars().count();
+            Ok(Place::StrChar(Box::new(place), checked_index(index, len, node)?))
+          },
+          (other, _) => Err(error(base, format!("cannot assign into a `{}`", other.type_name()))),
+        }
+      },
+      _ => Err(error(node, "cannot assign to this expression".to_string())),
+    }
+  }
+  fn read(&mut self, place: &Place<'a>, node: &AstNode<'a>) -> Result<Value, Diagnostic> {
+    match place {
+      Place::Var(name) => self.lookup(name, node),
+      Place::Elem(items, i) => Ok(items.borrow()[*i].clone()),
+      Place::Field(record, i) => Ok(record.borrow().fields[*i].1.clone()),
+      Place::StrChar(base, i) => match self.read(base, node)? {
+        Value::Str(s) => Ok(Value::Char(s.chars().nth(*i).unwrap())),
+        other => Err(error(node, format!("cannot index into a `{}`", other.type_name()))),
+      },
+    }
+  }
+  fn write(&mut self, place: Place<'a>, value: Value, node: &AstNode<'a>) -> Result<(), Diagnostic> {
+    match place {
+      Place::Var(name) => self.assign(name, value),
+      Place::Elem(items, i) => items.borrow_mut()[i] = value,
+      Place::Field(record, i) => record.borrow_mut().fields[i].1 = value,
+      Place::StrChar(base, i) => {
+        let c = match value {
+          Value::Char(c) => c,
+          other => return Err(error(node, format!("cannot store a `{}` in a string", other.type_name()))),
+        };
+        let s = match self.read(&base, node)? {
+          Value::Str(s) => s,
+          other => return Err(error(node, format!("cannot index into a `{}`", other.type_name()))),
+        };
+        let updated: String = s.chars().enumerate().map(|(j, old)| if j == i { c } else { old }).collect();
+        self.budget.allocate(updated.len(), node)?;
+        self.write(*base, Value::Str(updated.into()), node)?;
+      },
+    }
+    Ok(())
+  }
+}
+
+fn error(node: &AstNode, message: String) -> Diagnostic {
+  Diagnostic::error(Span::of(node), message)
+}
+
+fn address(node: &AstNode) -> usize {
+  node as *const AstNode as usize
+}
+
+fn overflow(node: &AstNode) -> Diagnostic {
+  error(node, "arithmetic overflow".to_string())
+}
+
+fn checked_index(index: i64, len: usize, node: &AstNode) -> Result<usize, Diagnostic> {
+  match usize::try_from(index) {
+    Ok(i) if i < len => Ok(i),
+    _ => Err(error(node, format!("index {} out of bounds for length {}", index, len))),
+  }
+}
+
+// Methods every value of a built-in type has, the receiver is `args[0]`
+fn builtin_method(name: &str, args: Vec<Value>, node: &AstNode) -> Result<Value, Diagnostic> {
+  match (name, &args[..]) {
+    ("len", [Value::Str(s)]) => Ok(Value::Int(s.chars().count() as i64)),
+    ("len", [Value::Array(items)]) => Ok(Value::Int(items.borrow().len() as i64)),
+    ("push", [Value::Array(items), item]) => {
+      items.borrow_mut().push(item.clone());
+      Ok(Value::Unit)
+    },
+    ("pop", [Value::Array(items)]) => items.borrow_mut().pop().ok_or_else(|| error(node, "cannot pop from an empty array".to_string())),
+    _ => Err(error(&node[1], format!("no method `{}` on a `{}`", name, args[0].type_name()))),
+  }
+}
+
+fn variant(path: &AstNode, fields: Vec<Value>) -> Value {
+  Value::Variant(Rc::new(Variant {
+    enum_name: path[0].get_token().get_value().into(),
+    name: path[1].get_token().get_value().into(),
+    fields,
+  }))
+}
+
+// Whether `value` matches a pattern, collecting what the pattern binds
+fn matches<'a>(pattern: &AstNode<'a>, value: &Value, binds: &mut Vec<(&'a str, Value)>) -> bool {
+  match pattern.get_kind() {
+    AstKind::Wildcard => true,
+    AstKind::Binding => {
+      binds.push((pattern.get_token().get_value(), value.clone()));
+      true
+    },
+    AstKind::RangePat => match (pattern_const(&pattern[0]), pattern_const(&pattern[1]), const_of(value)) {
+      (Some(lo), Some(hi), Some(c)) => in_range(&lo, &hi, &c),
+      _ => false,
+    },
+    AstKind::VariantPat => match value {
+      Value::Variant(variant) => {
+        *variant.enum_name == *pattern[0].get_token().get_value()
+          && *variant.name == *pattern[1].get_token().get_value()
+          && pattern.get_children()[2..].iter().zip(variant.fields.iter()).all(|(sub, field)| matches(sub, field, binds))
+      },
+      _ => false,
+    },
+    _ => pattern_const(pattern).is_some_and(|c| const_of(value) == Some(c)),
+  }
+}
+
+fn const_of(value: &Value) -> Option<Const> {
+  match value {
+    Value::Int(n) => Some(Const::Int(*n)),
+    Value::Float(x) => Some(Const::Float(*x)),
+    Value::Char(c) => Some(Const::Char(*c)),
+    Value::Str(s) => Some(Const::Str(s.to_string())),
+    Value::Bool(b) => Some(Const::Bool(*b)),
+    _ => None,
+  }
+}
+
+fn index_into(base: &Value, index: i64, node: &AstNode) -> Result<Value, Diagnostic> {
+  match base {
+    Value::Array(items) => {
+      let items = items.borrow();
+      let i = checked_index(index, items.len(), node)?;
+      Ok(items[i].clone())
+    },
+    Value::Str(s) => {
+      let i = checked_index(index, s.chars().count(), node)?;
+      Ok(Value::Char(s.chars().nth(i).unwrap()))
+    },
+    other => Err(error(node, format!("cannot index into a `{}`", other.type_name()))),
+  }
+}
+
+fn binary(kind: TokenKind, lhs: Value, rhs: Value, node: &AstNode) -> Result<Value, Diagnostic> {
+  use TokenKind::*;
+  let value = match (&lhs, &rhs) {
+    (Value::Int(l), Value::Int(r)) => {
+      let (l, r) = (*l, *r);
+      let result = match kind {
+        Plus => l.checked_add(r),
+        Minus => l.checked_sub(r),
+        Asterisk => l.checked_mul(r),
+        Slash if r == 0 => return Err(error(node, "division by zero".to_string())),
+        Mod if r == 0 => return Err(error(node, "remainder by zero".to_string())),
+        Slash => l.checked_div(r),
+        Mod => l.checked_rem(r),
+        And => Some(l & r),
+        Or => Some(l | r),
+        Xor => Some(l ^ r),
+        LShift | RShift if !(0..64).contains(&r) => {
+          return Err(error(node, format!("shift by {} bits overflows a 64-bit `int`", r)));
+        },
+        LShift => Some(l << r),
+        RShift => Some(l >> r),
+        DDot => return Ok(Value::Range(l, r)),
+        _ => return compare(kind, &l, &r, node),
+      };
+      Value::Int(result.ok_or_else(|| overflow(node))?)
+    },
+    (Value::Float(l), Value::Float(r)) => match kind {
+      Plus => Value::Float(l + r),
+      Minus => Value::Float(l - r),
+      Asterisk => Value::Float(l * r),
+      Slash => Value::Float(l / r),
+      _ => return compare(kind, l, r, node),
+    },
+    (Value::Str(l), Value::Str(r)) => match kind {
+      Plus => Value::Str(format!("{}{}", l, r).into()),
+      _ => return compare(kind, l, r, node),
+    },
+    (Value::Char(l), Value::Char(r)) => return compare(kind, l, r, node),
+    _ => match kind {
+      DEqual => Value::Bool(lhs == rhs),
+      NEqual => Value::Bool(lhs != rhs),
+      _ => return Err(error(node, format!(
+        "cannot apply binary operator `{}` to a `{}` and a `{}`",
+        node.get_token().get_value(), lhs.type_name(), rhs.type_name()
+      ))),
+    },
+  };
+  Ok(value)
+}
+
+fn compare<T: PartialOrd + ?Sized>(kind: TokenKind, l: &T, r: &T, node: &AstNode) -> Result<Value, Diagnostic> {
+  use TokenKind::*;
+  let result = match kind {
+    DEqual => l == r,
+    NEqual => l != r,
+    Less => l < r,
+    Greater => l > r,
+    LessEq => l <= r,
+    GreaterEq => l >= r,
+    _ => return Err(error(node, format!("cannot apply binary operator `{}` here", node.get_token().get_value()))),
+  };
+  Ok(Value::Bool(result))
+}
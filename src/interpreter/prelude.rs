@@ -0,0 +1,112 @@
+use std::cmp::Ordering;
+use std::io::{BufRead, BufReader, Write};
+
+use crate::ast::AstNode;
+use crate::diagnostic::{Diagnostic, Span};
+use super::value::Value;
+
+// Where `print` writes and `read_line` reads
+pub struct Console<'c> {
+  input: Box<dyn BufRead + 'c>,
+  output: Box<dyn Write + 'c>,
+}
+
+impl<'c> Console<'c> {
+  pub fn new(input: Box<dyn BufRead + 'c>, output: Box<dyn Write + 'c>) -> Console<'c> {
+    Console {
+      input,
+      output,
+    }
+  }
+  // The standard input and output of the process
+  pub fn std() -> Console<'c> {
+    Console::new(Box::new(BufReader::new(std::io::stdin())), Box::new(std::io::stdout()))
+  }
+  fn write(&mut self, text: &str, node: &AstNode) -> Result<Value, Diagnostic> {
+    // Flushed at once, so a prompt shows before `read_line` waits
+    self.output.write_all(text.as_bytes()).and_then(|_| self.output.flush())
+      .map_err(|e| error(node, format!("cannot write output: {}", e)))?;
+    Ok(Value::Unit)
+  }
+  // The next line without its line break, empty once the input has ended
+  fn read_line(&mut self, node: &AstNode) -> Result<Value, Diagnostic> {
+    let mut line = String::new();
+    self.input.read_line(&mut line).map_err(|e| error(node, format!("cannot read input: {}", e)))?;
+    let line = line.strip_suffix('\n').unwrap_or(&line);
+    Ok(Value::Str(line.strip_suffix('\r').unwrap_or(line).into()))
+  }
+}
+
+// Run a prelude function, the checker made sure the arguments fit
+pub fn call(console: &mut Console, name: &str, args: Vec<Value>, node: &AstNode) -> Result<Value, Diagnostic> {
+  match (name, &args[..]) {
+    ("print", [value]) => console.write(&value.to_string(), node),
+    ("println", [value]) => console.write(&format!("{}\n", value), node),
+    ("read_line", []) => console.read_line(node),
+    ("abs", [Value::Int(n)]) => n.checked_abs().map(Value::Int).ok_or_else(|| error(node, "arithmetic overflow".to_string())),
+    ("abs", [Value::Float(x)]) => Ok(Value::Float(x.abs())),
+    ("min", [a, b]) => Ok(if compare(b, a) == Ordering::Less { b.clone() } else { a.clone() }),
+    ("max", [a, b]) => Ok(if compare(b, a) == Ordering::Greater { b.clone() } else { a.clone() }),
+    ("pow", [Value::Int(_), Value::Int(exp)]) if *exp < 0 => Err(error(node, format!("negative exponent {} in `pow`", exp))),
+    ("pow", [Value::Int(base), Value::Int(exp)]) => match (u32::try_from(*exp), base) {
+      (Ok(exp), _) => base.checked_pow(exp).map(Value::Int).ok_or_else(|| error(node, "arithmetic overflow".to_string())),
+      // Past `u32::MAX` only these bases stay in range
+      (Err(_), 0 | 1) => Ok(Value::Int(*base)),
+      (Err(_), -1) => Ok(Value::Int(if exp % 2 == 0 { 1 } else { -1 })),
+      (Err(_), _) => Err(error(node, "arithmetic overflow".to_string())),
+    },
+    ("pow", [Value::Float(base), Value::Float(exp)]) => Ok(Value::Float(base.powf(*exp))),
+    ("sqrt", [Value::Float(x)]) => Ok(Value::Float(x.sqrt())),
+    ("floor", [Value::Float(x)]) => to_int(x.floor(), node),
+    ("len", [Value::Str(s)]) => Ok(Value::Int(s.chars().count() as i64)),
+    ("len", [Value::Array(items)]) => Ok(Value::Int(items.borrow().len() as i64)),
+    ("substr", [Value::Str(s), Value::Int(start), Value::Int(count)]) => {
+      let len = s.chars().count();
+      match (usize::try_from(*start), usize::try_from(*count)) {
+        (Ok(start), Ok(count)) if start.checked_add(count).is_some_and(|end| end <= len) => {
+          Ok(Value::Str(s.chars().skip(start).take(count).collect::<String>().into()))
+        },
+        _ => Err(error(node, format!("substring of {} chars at {} out of bounds for length {}", count, start, len))),
+      }
+    },
+    ("split", [Value::Str(_), Value::Str(sep)]) if sep.is_empty() => Err(error(node, "cannot split on an empty separator".to_string())),
+    ("split", [Value::Str(s), Value::Str(sep)]) => Ok(Value::array(s.split(&**sep).map(|part| Value::Str(part.into())).collect())),
+    ("to_int", [Value::Str(s)]) => s.trim().parse().map(Value::Int).map_err(|_| error(node, format!("cannot parse {} as an int", Value::Str(s.clone()).repr()))),
+    ("to_int", [Value::Float(x)]) => to_int(x.trunc(), node),
+    ("to_int", [Value::Char(c)]) => Ok(Value::Int(*c as i64)),
+    ("to_string", [value]) => Ok(Value::Str(value.to_string().into())),
+    ("push", [Value::Array(items), item]) => {
+      items.borrow_mut().push(item.clone());
+      Ok(Value::Unit)
+    },
+    ("pop", [Value::Array(items)]) => items.borrow_mut().pop().ok_or_else(|| error(node, "cannot pop from an empty array".to_string())),
+    ("sort", [Value::Array(items)]) => {
+      items.borrow_mut().sort_by(compare);
+      Ok(Value::Unit)
+    },
+    _ => Err(error(&node[0], format!("cannot find function `{}`", name))),
+  }
+}
+
+// Order of two values of a type `<` accepts
+fn compare(a: &Value, b: &Value) -> Ordering {
+  match (a, b) {
+    (Value::Int(a), Value::Int(b)) => a.cmp(b),
+    (Value::Float(a), Value::Float(b)) => a.total_cmp(b),
+    (Value::Char(a), Value::Char(b)) => a.cmp(b),
+    (Value::Str(a), Value::Str(b)) => a.cmp(b),
+    _ => Ordering::Equal,
+  }
+}
+
+// A whole float as an int, when it fits
+fn to_int(x: f64, node: &AstNode) -> Result<Value, Diagnostic> {
+  match x.is_finite() && x >= i64::MIN as f64 && x < i64::MAX as f64 {
+    true => Ok(Value::Int(x as i64)),
+    false => Err(error(node, format!("{} does not fit in an int", Value::Float(x)))),
+  }
+}
+
+fn error(node: &AstNode, message: String) -> Diagnostic {
+  Diagnostic::error(Span::of(node), message)
+}
@@ -0,0 +1,190 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::utilities::literal;
+
+// Runtime value. Arrays are shared by reference, everything else is copied.
+#[derive(Clone, Debug)]
+pub enum Value {
+  Int(i64),
+  Float(f64),
+  Char(char),
+  Str(Rc<str>),
+  Bool(bool),
+  Unit,
+  Array(Rc<RefCell<Vec<Value>>>),
+  // Shared by reference like arrays
+  Struct(Rc<RefCell<Record>>),
+  // Variant of an `enum`, its fields are never written
+  Variant(Rc<Variant>),
+  // `lo..hi`, `hi` excluded
+  Range(i64, i64),
+  Fun(Rc<Fun>),
+}
+
+// Variable captured by a lambda. A capture by reference shares the cell with
+// the scope it was captured from, a capture by value gets a cell of its own.
+pub type Cell = Rc<RefCell<Option<Value>>>;
+
+#[derive(Debug)]
+pub enum Fun {
+  // A function declared with `fun name`
+  Named(Rc<str>),
+  // Address of the lambda node and its captures in the order the resolver lists them
+  Lambda(usize, Vec<Cell>),
+}
+
+// Instance of a `struct`, fields in declaration order
+#[derive(Debug, PartialEq)]
+pub struct Record {
+  pub name: Rc<str>,
+  pub fields: Vec<(Rc<str>, Value)>,
+}
+
+// Instance of an `enum` variant, fields in declaration order
+#[derive(Debug, PartialEq)]
+pub struct Variant {
+  pub enum_name: Rc<str>,
+  pub name: Rc<str>,
+  pub fields: Vec<Value>,
+}
+
+impl Value {
+  pub fn array(items: Vec<Value>) -> Value {
+    Value::Array(Rc::new(RefCell::new(items)))
+  }
+  // Copy that shares nothing with the original, down through nested arrays
+  pub fn deep_copy(&self) -> Value {
+    match self {
+      Value::Array(items) => Value::array(items.borrow().iter().map(|v| v.deep_copy()).collect()),
+      Value::Struct(record) => {
+        let record = record.borrow();
+        let fields = record.fields.iter().map(|(name, v)| (name.clone(), v.deep_copy())).collect();
+        Value::Struct(Rc::new(RefCell::new(Record { name: record.name.clone(), fields })))
+      },
+      Value::Variant(variant) => Value::Variant(Rc::new(Variant {
+        enum_name: variant.enum_name.clone(),
+        name: variant.name.clone(),
+        fields: variant.fields.iter().map(|v| v.deep_copy()).collect(),
+      })),
+      other => other.clone(),
+    }
+  }
+  // Bytes the value holds on the heap itself, not counting what it refers to
+  pub fn size(&self) -> usize {
+    let value = std::mem::size_of::<Value>();
+    match self {
+      Value::Str(s) => s.len(),
+      Value::Array(items) => items.borrow().len() * value,
+      Value::Struct(record) => record.borrow().fields.len() * std::mem::size_of::<(Rc<str>, Value)>(),
+      Value::Variant(variant) => variant.fields.len() * value,
+      Value::Fun(fun) => match &**fun {
+        Fun::Named(name) => name.len(),
+        Fun::Lambda(_, cells) => cells.len() * std::mem::size_of::<Cell>(),
+      },
+      _ => 0,
+    }
+  }
+  // Bytes `deep_copy` allocates, strings and functions being shared
+  pub fn copy_size(&self) -> usize {
+    let nested: usize = match self {
+      Value::Array(items) => items.borrow().iter().map(Value::copy_size).sum(),
+      Value::Struct(record) => record.borrow().fields.iter().map(|(_, v)| v.copy_size()).sum(),
+      Value::Variant(variant) => variant.fields.iter().map(Value::copy_size).sum(),
+      _ => return 0,
+    };
+    self.size() + nested
+  }
+  pub fn type_name(&self) -> &'static str {
+    match self {
+      Value::Int(_) => "int",
+      Value::Float(_) => "float",
+      Value::Char(_) => "char",
+      Value::Str(_) => "string",
+      Value::Bool(_) => "bool",
+      Value::Unit => "unit",
+      Value::Array(_) => "array",
+      Value::Struct(_) => "struct",
+      Value::Variant(_) => "enum",
+      Value::Range(..) => "range",
+      Value::Fun(_) => "fun",
+    }
+  }
+  // Name the methods of an `impl` on this value's type are found under
+  pub fn method_key(&self) -> String {
+    match self {
+      Value::Struct(record) => record.borrow().name.to_string(),
+      Value::Variant(variant) => variant.enum_name.to_string(),
+      other => other.type_name().to_string(),
+    }
+  }
+  // Source-like text, quoting strings and chars, as used inside arrays
+  pub fn repr(&self) -> String {
+    match self {
+      Value::Char(c) => literal::quote_char(*c),
+      Value::Str(s) => literal::quote_string(s),
+      other => other.to_string(),
+    }
+  }
+}
+
+impl PartialEq for Value {
+  fn eq(&self, other: &Value) -> bool {
+    match (self, other) {
+      (Value::Int(a), Value::Int(b)) => a == b,
+      (Value::Float(a), Value::Float(b)) => a == b,
+      (Value::Char(a), Value::Char(b)) => a == b,
+      (Value::Str(a), Value::Str(b)) => a == b,
+      (Value::Bool(a), Value::Bool(b)) => a == b,
+      (Value::Unit, Value::Unit) => true,
+      (Value::Array(a), Value::Array(b)) => Rc::ptr_eq(a, b) || *a.borrow() == *b.borrow(),
+      (Value::Struct(a), Value::Struct(b)) => Rc::ptr_eq(a, b) || *a.borrow() == *b.borrow(),
+      (Value::Variant(a), Value::Variant(b)) => a == b,
+      (Value::Range(a, b), Value::Range(c, d)) => a == c && b == d,
+      (Value::Fun(a), Value::Fun(b)) => match (&**a, &**b) {
+        (Fun::Named(a), Fun::Named(b)) => a == b,
+        _ => Rc::ptr_eq(a, b),
+      },
+      _ => false,
+    }
+  }
+}
+
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Value::Int(n) => write!(f, "{}", n),
+      Value::Float(x) => write!(f, "{}", literal::format_float(*x)),
+      Value::Char(c) => write!(f, "{}", c),
+      Value::Str(s) => write!(f, "{}", s),
+      Value::Bool(b) => write!(f, "{}", b),
+      Value::Unit => write!(f, "()"),
+      Value::Array(items) => {
+        let items: Vec<String> = items.borrow().iter().map(|v| v.repr()).collect();
+        write!(f, "[{}]", items.join(", "))
+      },
+      Value::Struct(record) => {
+        let record = record.borrow();
+        let fields: Vec<String> = record.fields.iter().map(|(name, v)| format!("{}: {}", name, v.repr())).collect();
+        match fields.is_empty() {
+          true => write!(f, "{} {{}}", record.name),
+          false => write!(f, "{} {{ {} }}", record.name, fields.join(", ")),
+        }
+      },
+      Value::Variant(variant) => {
+        write!(f, "{}::{}", variant.enum_name, variant.name)?;
+        if !variant.fields.is_empty() {
+          let fields: Vec<String> = variant.fields.iter().map(|v| v.repr()).collect();
+          write!(f, "({})", fields.join(", "))?;
+        }
+        Ok(())
+      },
+      Value::Range(lo, hi) => write!(f, "{}..{}", lo, hi),
+      Value::Fun(fun) => match &**fun {
+        Fun::Named(name) => write!(f, "<fun {}>", name),
+        Fun::Lambda(..) => write!(f, "<lambda>"),
+      },
+    }
+  }
+}
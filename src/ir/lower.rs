@@ -0,0 +1,632 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{BinOp, Block, Function, Module, Op, Term, Ty, UnOp, Value};
+use crate::ast::token::TokenKind;
+use crate::ast::{Ast, AstKind, AstNode};
+use crate::checker::types::{FunSig, Type};
+use crate::checker::{Checker, strip_assign};
+use crate::diagnostic::{Diagnostic, Span};
+use crate::fold::Const;
+
+// Lower a checked program to IR. Only scalar values have a lowering yet:
+// arrays, structs, enums, `match` and function values are reported as errors.
+// The code of the global initializers becomes the function `@.init`, a name no
+// carf function can have.
+pub fn lower<'a>(ast: &Ast<'a>, checker: &Checker<'a>) -> Result<Module, Vec<Diagnostic>> {
+  let mut lowerer = Lowerer {
+    checker,
+    methods: BTreeSet::new(),
+    globals: BTreeMap::new(),
+    blocks: Vec::new(),
+    current: None,
+    vars: Vec::new(),
+    edges: BTreeMap::new(),
+    loops: Vec::new(),
+    next: 0,
+    diagnostics: Vec::new(),
+  };
+  let module = lowerer.program(ast.get_root());
+  match lowerer.diagnostics.is_empty() {
+    true => Ok(module),
+    false => {
+      lowerer.diagnostics.sort_by_key(|d| d.span);
+      Err(lowerer.diagnostics)
+    },
+  }
+}
+
+// A local variable in scope and the value it holds at the current point
+struct Var<'a> {
+  name: &'a str,
+  value: Value,
+  ty: Ty,
+}
+
+// Variable an assignment writes
+enum Target {
+  Local(usize),
+  Global(String, Ty),
+}
+
+struct Lowerer<'g, 'a> {
+  checker: &'g Checker<'a>,
+  // Methods declared by an `impl`, by method key and name
+  methods: BTreeSet<(String, &'a str)>,
+  globals: BTreeMap<&'a str, Ty>,
+  // Blocks of the function being lowered, and the one instructions go to,
+  // None after a jump until the next block is entered
+  blocks: Vec<Block>,
+  current: Option<usize>,
+  // Innermost variable last. Blocks end by truncating it back.
+  vars: Vec<Var<'a>>,
+  // Jumps to each block not entered yet, with the values of `vars` at the jump
+  edges: BTreeMap<usize, Vec<(usize, Vec<Value>)>>,
+  // Blocks `continue` and `break` go to in the enclosing loops
+  loops: Vec<(usize, usize)>,
+  next: usize,
+  diagnostics: Vec<Diagnostic>,
+}
+
+// Items
+impl<'g, 'a> Lowerer<'g, 'a> {
+  fn program(&mut self, root: &'g AstNode<'a>) -> Module {
+    let mut module = Module::default();
+    let items = root.get_children();
+    for item in items {
+      match item.get_kind() {
+        AstKind::Impl => {
+          let key = self.resolve(&item[0]).method_key().unwrap_or_default();
+          for method in item.get_children().iter().skip(1) {
+            self.methods.insert((key.clone(), method[0].get_token().get_value()));
+          }
+        },
+        AstKind::Let => {
+          let name = item[0].get_token().get_value();
+          match self.ty(&self.binding_type(&item[0]), &item[0]) {
+            Ok(ty) => {
+              self.globals.insert(name, ty);
+              module.globals.push((name.to_string(), ty));
+            },
+            Err(d) => self.diagnostics.push(d),
+          }
+        },
+        _ => {},
+      }
+    }
+    let globals: Vec<&'g AstNode<'a>> = items.iter().filter(|item| item.get_kind() == AstKind::Let).collect();
+    if !globals.is_empty() {
+      match self.init(&globals) {
+        Ok(fun) => module.funs.push(fun),
+        Err(d) => self.diagnostics.push(d),
+      }
+    }
+    for item in items {
+      let result = match item.get_kind() {
+        AstKind::Fun => self.fun_item(item),
+        AstKind::Impl => self.impl_item(item),
+        _ => Ok(Vec::new()),
+      };
+      match result {
+        Ok(funs) => module.funs.extend(funs),
+        Err(d) => self.diagnostics.push(d),
+      }
+    }
+    module
+  }
+  fn fun_item(&mut self, item: &'g AstNode<'a>) -> Result<Vec<Function>, Diagnostic> {
+    let name = item[0].get_token().get_value();
+    match self.checker.get_fun(name) {
+      Some(sig) => Ok(vec![self.function(name.to_string(), item, &sig.clone())?]),
+      None => Ok(Vec::new()),
+    }
+  }
+  // Methods are named `@Key.name`, e.g. `@int.double`
+  fn impl_item(&mut self, item: &'g AstNode<'a>) -> Result<Vec<Function>, Diagnostic> {
+    let receiver = self.resolve(&item[0]);
+    let key = match receiver.method_key() {
+      Some(key) if !matches!(receiver, Type::Enum(_)) => key,
+      _ => return Err(unsupported(item, "methods of enums")),
+    };
+    let mut funs = Vec::new();
+    for method in item.get_children().iter().skip(1) {
+      let name = method[0].get_token().get_value();
+      if let Some(sig) = self.checker.get_method(&receiver, name) {
+        funs.push(self.function(format!("{}.{}", key, name), method, &sig)?);
+      }
+    }
+    Ok(funs)
+  }
+  fn function(&mut self, name: String, fun: &'g AstNode<'a>, sig: &FunSig) -> Result<Function, Diagnostic> {
+    let ret = match sig.ret {
+      Type::Unit => Ty::Unit,
+      _ => self.ty(&sig.ret, &fun[2])?,
+    };
+    self.start();
+    let mut params = Vec::new();
+    for (param, ty) in fun[1].get_children().iter().zip(&sig.params) {
+      let ty = self.ty(ty, param)?;
+      let value = self.fresh();
+      self.vars.push(Var { name: param.get_token().get_value(), value, ty });
+      params.push((value, ty));
+    }
+    self.body(&fun[3])?;
+    Ok(self.finish(name, params, ret))
+  }
+  // Globals are set in source order, as the evaluator does before `main` runs
+  fn init(&mut self, globals: &[&'g AstNode<'a>]) -> Result<Function, Diagnostic> {
+    self.start();
+    for item in globals {
+      let name = item[0].get_token().get_value();
+      if item.child_count() > 2 && let Some(&ty) = self.globals.get(name) {
+        let value = self.value(&item[2])?;
+        self.effect(ty, Op::Store(name.to_string(), value));
+      }
+    }
+    Ok(self.finish(".init".to_string(), Vec::new(), Ty::Unit))
+  }
+  fn start(&mut self) {
+    self.blocks.clear();
+    self.vars.clear();
+    self.edges.clear();
+    self.loops.clear();
+    self.next = 0;
+    let entry = self.new_block();
+    self.current = Some(entry);
+  }
+  // Falling off the end returns from a `unit` function, the checker made sure
+  // no other function can
+  fn finish(&mut self, name: String, params: Vec<(Value, Ty)>, ret: Ty) -> Function {
+    if self.current.is_some() {
+      self.terminate(match ret {
+        Ty::Unit => Term::Return(None),
+        _ => Term::Unreachable,
+      });
+    }
+    let mut fun = Function { name, params, ret, blocks: std::mem::take(&mut self.blocks) };
+    fun.remove_unreachable();
+    fun.remove_trivial_phis();
+    fun
+  }
+}
+
+// Statements. Once a statement jumps away, the rest of its block cannot run
+// and is not lowered.
+impl<'g, 'a> Lowerer<'g, 'a> {
+  fn body(&mut self, node: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    let mark = self.vars.len();
+    let result = match node.get_kind() {
+      AstKind::Block => node.get_children().iter().try_for_each(|stmt| match self.current {
+        Some(_) => self.stmt(stmt),
+        None => Ok(()),
+      }),
+      _ => self.stmt(node),
+    };
+    self.vars.truncate(mark);
+    result
+  }
+  fn stmt(&mut self, node: &'g AstNode<'a>) -> Result<(), Diagnostic> {
+    match node.get_kind() {
+      AstKind::Let => {
+        let ty = self.ty(&self.binding_type(&node[0]), &node[0])?;
+        // An unset variable is never read, the zero only keeps the merges defined
+        let value = match node.child_count() > 2 {
+          true => self.value(&node[2])?,
+          false => self.emit(ty, Op::Const(zero(ty))),
+        };
+        self.vars.push(Var { name: node[0].get_token().get_value(), value, ty });
+      },
+      AstKind::Stmt if node.child_count() > 0 => {
+        self.expr(&node[0])?;
+      },
+      AstKind::Stmt => {},
+      AstKind::Block => self.body(node)?,
+      AstKind::If => {
+        let cond = self.value(&node[0])?;
+        let then = self.new_block();
+        let other = match node.child_count() > 2 && node[2].child_count() > 0 {
+          true => Some(self.new_block()),
+          false => None,
+        };
+        let join = self.new_block();
+        self.branch(cond, then, other.unwrap_or(join));
+        self.enter(then);
+        self.body(&node[1])?;
+        self.jump(join);
+        if let Some(other) = other {
+          self.enter(other);
+          self.body(&node[2][0])?;
+          self.jump(join);
+        }
+        self.enter(join);
+      },
+      AstKind::While => {
+        let header = self.new_block();
+        self.jump(header);
+        let phis = self.open_loop(header);
+        let cond = self.value(&node[0])?;
+        let body = self.new_block();
+        let exit = self.new_block();
+        self.branch(cond, body, exit);
+        self.enter(body);
+        self.loop_body(&node[1], (header, exit))?;
+        self.jump(header);
+        self.close_loop(header, &phis);
+        self.enter(exit);
+      },
+      // The desugaring pass turns loops over ranges into `while`
+      AstKind::For => return Err(unsupported(&node[1], "this iterator")),
+      AstKind::Return => {
+        let value = match node.child_count() > 0 {
+          true => self.expr(&node[0])?,
+          false => None,
+        };
+        self.terminate(Term::Return(value));
+      },
+      AstKind::Break => {
+        let (_, exit) = *self.loops.last().unwrap();
+        self.jump(exit);
+      },
+      AstKind::Continue => {
+        let (next, _) = *self.loops.last().unwrap();
+        self.jump(next);
+      },
+      _ => return Err(unsupported(node, "this statement")),
+    }
+    Ok(())
+  }
+  // Where `continue` and `break` in the body go
+  fn loop_body(&mut self, node: &'g AstNode<'a>, targets: (usize, usize)) -> Result<(), Diagnostic> {
+    self.loops.push(targets);
+    let result = self.body(node);
+    self.loops.pop();
+    result
+  }
+}
+
+// Expressions, None being the value of a `unit` one
+impl<'g, 'a> Lowerer<'g, 'a> {
+  fn value(&mut self, node: &'g AstNode<'a>) -> Result<Value, Diagnostic> {
+    match self.expr(node)? {
+      Some(value) => Ok(value),
+      None => Err(unsupported(node, "values of type `unit`")),
+    }
+  }
+  fn expr(&mut self, node: &'g AstNode<'a>) -> Result<Option<Value>, Diagnostic> {
+    let token = node.get_token();
+    let value = match node.get_kind() {
+      AstKind::Literal => match Const::from_literal(node) {
+        Some(c) => {
+          let ty = match c {
+            Const::Int(_) => Ty::Int,
+            Const::Float(_) => Ty::Float,
+            Const::Char(_) => Ty::Char,
+            Const::Str(_) => Ty::String,
+            Const::Bool(_) => Ty::Bool,
+          };
+          self.emit(ty, Op::Const(c))
+        },
+        None => return Err(Diagnostic::error(Span::of(node), format!("invalid literal `{}`", token.get_value()))),
+      },
+      AstKind::Identifier => match self.target(node)? {
+        Target::Local(i) => self.vars[i].value,
+        Target::Global(name, ty) => self.emit(ty, Op::Load(name)),
+      },
+      AstKind::Expr => return self.expr(&node[0]),
+      AstKind::UnOper => {
+        let operand = self.value(&node[0])?;
+        let ty = self.ty(&self.type_of(node), node)?;
+        let op = match token.get_kind() {
+          TokenKind::Minus => UnOp::Neg,
+          _ => UnOp::Not,
+        };
+        self.emit(ty, Op::Unary(op, operand))
+      },
+      // `x++` and `x--` give the value from before the step, `++x` and `--x`
+      // the one after it
+      AstKind::PreStep | AstKind::PostStep => {
+        let target = self.target(&node[0])?;
+        let old = self.read(&target);
+        let one = self.emit(Ty::Int, Op::Const(Const::Int(1)));
+        let op = match token.get_kind() {
+          TokenKind::DPlus => BinOp::Add,
+          _ => BinOp::Sub,
+        };
+        let new = self.emit(Ty::Int, Op::Binary(op, old, one));
+        self.write(&target, new);
+        match node.get_kind() {
+          AstKind::PreStep => new,
+          _ => old,
+        }
+      },
+      AstKind::BinOper => return self.binary(node),
+      AstKind::Call => return self.call(node),
+      AstKind::MethodCall => return self.method_call(node),
+      AstKind::Array | AstKind::Repeat => return Err(unsupported(node, "arrays")),
+      AstKind::StructLit | AstKind::Member => return Err(unsupported(node, "structs")),
+      AstKind::Index => return Err(unsupported(node, "indexing")),
+      AstKind::Path => return Err(unsupported(node, "enums")),
+      AstKind::Match => return Err(unsupported(node, "`match`")),
+      AstKind::Lambda => return Err(unsupported(node, "lambdas")),
+      _ => return Err(unsupported(node, "this expression")),
+    };
+    Ok(Some(value))
+  }
+  fn binary(&mut self, node: &'g AstNode<'a>) -> Result<Option<Value>, Diagnostic> {
+    use TokenKind::*;
+    let kind = node.get_token().get_kind();
+    match kind {
+      Equal => {
+        let target = self.target(&node[0])?;
+        let value = self.value(&node[1])?;
+        self.write(&target, value);
+        Ok(None)
+      },
+      // Left by the desugaring pass when the target is not pure, which is evaluated once
+      PlusEq | MinusEq | AsteriskEq | SlashEq | ModEq | AndEq | OrEq | XorEq | LShiftEq | RShiftEq => {
+        let target = self.target(&node[0])?;
+        let old = self.read(&target);
+        let rhs = self.value(&node[1])?;
+        let ty = self.ty(&self.type_of(&node[0]), node)?;
+        let new = self.emit(ty, Op::Binary(bin_op(strip_assign(kind)), old, rhs));
+        self.write(&target, new);
+        Ok(None)
+      },
+      // The right operand only runs when the left one does not decide, which
+      // is also the value the result takes when it comes from the left
+      DAnd | DOr => {
+        let lhs = self.value(&node[0])?;
+        let from = self.current.unwrap();
+        let rhs_block = self.new_block();
+        let join = self.new_block();
+        match kind {
+          DAnd => self.branch(lhs, rhs_block, join),
+          _ => self.branch(lhs, join, rhs_block),
+        }
+        self.enter(rhs_block);
+        let rhs = self.value(&node[1])?;
+        let to = self.current.unwrap();
+        self.jump(join);
+        self.enter(join);
+        Ok(Some(self.emit(Ty::Bool, Op::Phi(vec![(from, lhs), (to, rhs)]))))
+      },
+      DDot => Err(unsupported(node, "ranges outside of `for`")),
+      _ => {
+        let lhs = self.value(&node[0])?;
+        let rhs = self.value(&node[1])?;
+        let ty = self.ty(&self.type_of(node), node)?;
+        Ok(Some(self.emit(ty, Op::Binary(bin_op(kind), lhs, rhs))))
+      },
+    }
+  }
+  fn call(&mut self, node: &'g AstNode<'a>) -> Result<Option<Value>, Diagnostic> {
+    let callee = &node[0];
+    let name = callee.get_token().get_value();
+    if callee.get_kind() == AstKind::Path {
+      return Err(unsupported(node, "enums"));
+    }
+    if callee.get_kind() != AstKind::Identifier || self.local(name).is_some() {
+      return Err(unsupported(node, "calls of function values"));
+    }
+    let mut args = Vec::new();
+    for arg in node[1].get_children() {
+      args.push(self.value(arg)?);
+    }
+    self.emit_call(name.to_string(), args, node)
+  }
+  // Built-in methods are prelude functions taking the receiver first
+  fn method_call(&mut self, node: &'g AstNode<'a>) -> Result<Option<Value>, Diagnostic> {
+    let receiver = self.type_of(&node[0]);
+    let name = node[1].get_token().get_value();
+    let mut args = vec![self.value(&node[0])?];
+    for arg in node[2].get_children() {
+      args.push(self.value(arg)?);
+    }
+    let key = receiver.method_key().unwrap_or_default();
+    match self.methods.contains(&(key.clone(), name)) {
+      true => self.emit_call(format!("{}.{}", key, name), args, node),
+      false => self.emit_call(name.to_string(), args, node),
+    }
+  }
+  fn emit_call(&mut self, name: String, args: Vec<Value>, node: &'g AstNode<'a>) -> Result<Option<Value>, Diagnostic> {
+    match self.type_of(node) {
+      Type::Unit => {
+        self.effect(Ty::Unit, Op::Call(name, args));
+        Ok(None)
+      },
+      ty => {
+        let ty = self.ty(&ty, node)?;
+        Ok(Some(self.emit(ty, Op::Call(name, args))))
+      },
+    }
+  }
+}
+
+// Variables
+impl<'g, 'a> Lowerer<'g, 'a> {
+  fn target(&self, node: &'g AstNode<'a>) -> Result<Target, Diagnostic> {
+    match node.get_kind() {
+      AstKind::Expr => self.target(&node[0]),
+      AstKind::Identifier => {
+        let name = node.get_token().get_value();
+        if let Some(i) = self.local(name) {
+          return Ok(Target::Local(i));
+        }
+        match self.globals.get(name) {
+          Some(&ty) => Ok(Target::Global(name.to_string(), ty)),
+          None => Err(unsupported(node, "function values")),
+        }
+      },
+      _ => Err(unsupported(node, "this assignment")),
+    }
+  }
+  fn read(&mut self, target: &Target) -> Value {
+    match target {
+      Target::Local(i) => self.vars[*i].value,
+      Target::Global(name, ty) => self.emit(*ty, Op::Load(name.clone())),
+    }
+  }
+  fn write(&mut self, target: &Target, value: Value) {
+    match target {
+      Target::Local(i) => self.vars[*i].value = value,
+      Target::Global(name, ty) => self.effect(*ty, Op::Store(name.clone(), value)),
+    }
+  }
+  fn local(&self, name: &str) -> Option<usize> {
+    self.vars.iter().rposition(|var| var.name == name)
+  }
+}
+
+// Blocks and the SSA construction. The value of each variable is tracked as
+// instructions are emitted, a block entered from several others gets a phi
+// for each variable whose value differs between them. A loop header is
+// entered before its back edges are known, so it starts with a phi for every
+// variable, completed when the loop is closed. Phis that turn out to merge one
+// value are removed when the function is finished.
+impl<'g, 'a> Lowerer<'g, 'a> {
+  fn new_block(&mut self) -> usize {
+    self.blocks.push(Block { insts: Vec::new(), term: Term::Unreachable });
+    self.blocks.len() - 1
+  }
+  fn fresh(&mut self) -> Value {
+    self.next += 1;
+    Value(self.next - 1)
+  }
+  fn emit(&mut self, ty: Ty, op: Op) -> Value {
+    let dest = self.fresh();
+    let block = self.current.unwrap();
+    self.blocks[block].insts.push(super::Inst { dest: Some(dest), ty, op });
+    dest
+  }
+  fn effect(&mut self, ty: Ty, op: Op) {
+    let block = self.current.unwrap();
+    self.blocks[block].insts.push(super::Inst { dest: None, ty, op });
+  }
+  fn terminate(&mut self, term: Term) {
+    if let Some(block) = self.current.take() {
+      self.blocks[block].term = term;
+    }
+  }
+  fn snapshot(&self) -> Vec<Value> {
+    self.vars.iter().map(|var| var.value).collect()
+  }
+  fn jump(&mut self, target: usize) {
+    if let Some(block) = self.current {
+      let values = self.snapshot();
+      self.edges.entry(target).or_default().push((block, values));
+      self.terminate(Term::Jump(target));
+    }
+  }
+  fn branch(&mut self, cond: Value, then: usize, other: usize) {
+    let block = self.current.unwrap();
+    for target in [then, other] {
+      let values = self.snapshot();
+      self.edges.entry(target).or_default().push((block, values));
+    }
+    self.terminate(Term::Branch(cond, then, other));
+  }
+  // Start emitting into a block all jumps to it are known for. Nothing can
+  // reach a block no jump goes to, so nothing is emitted until the next one.
+  fn enter(&mut self, block: usize) {
+    let edges = self.edges.remove(&block).unwrap_or_default();
+    if edges.is_empty() {
+      self.current = None;
+      return;
+    }
+    self.current = Some(block);
+    for i in 0..self.vars.len() {
+      let first = edges[0].1[i];
+      self.vars[i].value = match edges.iter().all(|(_, values)| values[i] == first) {
+        true => first,
+        false => {
+          let incoming = edges.iter().map(|(from, values)| (*from, values[i])).collect();
+          self.emit(self.vars[i].ty, Op::Phi(incoming))
+        },
+      };
+    }
+  }
+  // Enter a loop header, giving the phi of each variable by its index
+  fn open_loop(&mut self, header: usize) -> Vec<(usize, Value)> {
+    let edges = self.edges.remove(&header).unwrap_or_default();
+    self.current = Some(header);
+    let mut phis = Vec::new();
+    for i in 0..self.vars.len() {
+      let incoming = edges.iter().map(|(from, values)| (*from, values[i])).collect();
+      let phi = self.emit(self.vars[i].ty, Op::Phi(incoming));
+      self.vars[i].value = phi;
+      phis.push((i, phi));
+    }
+    phis
+  }
+  // Add the back edges taken so far to the header's phis
+  fn close_loop(&mut self, header: usize, phis: &[(usize, Value)]) {
+    let edges = self.edges.remove(&header).unwrap_or_default();
+    for inst in self.blocks[header].insts.iter_mut() {
+      let Some(&(i, _)) = phis.iter().find(|(_, phi)| Some(*phi) == inst.dest) else { continue };
+      if let Op::Phi(incoming) = &mut inst.op {
+        incoming.extend(edges.iter().map(|(from, values)| (*from, values[i])));
+      }
+    }
+  }
+}
+
+// Types
+impl<'g, 'a> Lowerer<'g, 'a> {
+  fn ty(&self, ty: &Type, node: &AstNode<'a>) -> Result<Ty, Diagnostic> {
+    match ty {
+      Type::Int => Ok(Ty::Int),
+      Type::Float => Ok(Ty::Float),
+      Type::Bool => Ok(Ty::Bool),
+      Type::Char => Ok(Ty::Char),
+      Type::String => Ok(Ty::String),
+      Type::Enum(_) => Err(unsupported(node, "enums")),
+      Type::Fun(..) => Err(unsupported(node, "function values")),
+      Type::Var(_) | Type::Error => Err(Diagnostic::error(Span::of(node), "type annotations needed to lower this to IR".to_string())),
+      _ => Err(unsupported(node, &format!("values of type `{}`", ty))),
+    }
+  }
+  fn type_of(&self, node: &AstNode<'a>) -> Type {
+    self.checker.get_type_at(node.get_token().get_pos()).cloned().unwrap_or(Type::Error)
+  }
+  fn binding_type(&self, name: &AstNode<'a>) -> Type {
+    self.checker.get_binding_type(name.get_token().get_pos()).cloned().unwrap_or(Type::Error)
+  }
+  fn resolve(&self, ty: &AstNode<'a>) -> Type {
+    Type::from_name(ty.get_token().get_value()).unwrap_or_else(|| Type::Struct(ty.get_token().get_value().to_string()))
+  }
+}
+
+fn unsupported(node: &AstNode, what: &str) -> Diagnostic {
+  Diagnostic::error(Span::of(node), format!("{} cannot be lowered to IR", what))
+}
+
+fn bin_op(kind: TokenKind) -> BinOp {
+  use TokenKind::*;
+  match kind {
+    Plus => BinOp::Add,
+    Minus => BinOp::Sub,
+    Asterisk => BinOp::Mul,
+    Slash => BinOp::Div,
+    Mod => BinOp::Rem,
+    And => BinOp::And,
+    Or => BinOp::Or,
+    Xor => BinOp::Xor,
+    LShift => BinOp::Shl,
+    RShift => BinOp::Shr,
+    DEqual => BinOp::Eq,
+    NEqual => BinOp::Ne,
+    Less => BinOp::Lt,
+    Greater => BinOp::Gt,
+    LessEq => BinOp::Le,
+    _ => BinOp::Ge,
+  }
+}
+
+fn zero(ty: Ty) -> Const {
+  match ty {
+    Ty::Float => Const::Float(0.0),
+    Ty::Bool => Const::Bool(false),
+    Ty::Char => Const::Char('\0'),
+    Ty::String => Const::Str(String::new()),
+    _ => Const::Int(0),
+  }
+}
@@ -0,0 +1,501 @@
+// Mid-level representation between the checked syntax tree and the backends.
+// A function is a list of basic blocks in SSA form: every value is defined by
+// one instruction, and where control flow joins, a `phi` at the top of the
+// block picks the value of the predecessor it came from. Block 0 is the entry.
+
+pub mod lower;
+pub mod parse;
+pub mod verify;
+pub mod passes;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::fold::Const;
+use crate::utilities::literal;
+
+// `%N`, numbered apart within a function, parameters first
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Value(pub usize);
+
+// The scalar carf types, `unit` being the type of instructions without a result
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Ty {
+  Int,
+  Float,
+  Bool,
+  Char,
+  String,
+  Unit,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnOp {
+  Neg,
+  Not,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BinOp {
+  Add,
+  Sub,
+  Mul,
+  Div,
+  Rem,
+  And,
+  Or,
+  Xor,
+  Shl,
+  Shr,
+  Eq,
+  Ne,
+  Lt,
+  Gt,
+  Le,
+  Ge,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Op {
+  Const(Const),
+  Copy(Value),
+  Unary(UnOp, Value),
+  Binary(BinOp, Value, Value),
+  // The value coming from each predecessor block
+  Phi(Vec<(usize, Value)>),
+  // A function of the module or of the prelude, by name
+  Call(String, Vec<Value>),
+  Load(String),
+  Store(String, Value),
+}
+
+// `ty` is the type of the result, or of the stored value for `store`
+#[derive(Clone, PartialEq, Debug)]
+pub struct Inst {
+  pub dest: Option<Value>,
+  pub ty: Ty,
+  pub op: Op,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Term {
+  Jump(usize),
+  // Condition, then the blocks taken when it is true and false
+  Branch(Value, usize, usize),
+  Return(Option<Value>),
+  Unreachable,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Block {
+  pub insts: Vec<Inst>,
+  pub term: Term,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Function {
+  pub name: String,
+  pub params: Vec<(Value, Ty)>,
+  pub ret: Ty,
+  pub blocks: Vec<Block>,
+}
+
+// Globals are not in SSA form, functions `load` and `store` them by name
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Module {
+  pub globals: Vec<(String, Ty)>,
+  pub funs: Vec<Function>,
+}
+
+impl Ty {
+  pub fn from_name(name: &str) -> Option<Ty> {
+    [Ty::Int, Ty::Float, Ty::Bool, Ty::Char, Ty::String, Ty::Unit].into_iter().find(|ty| ty.name() == name)
+  }
+  pub fn name(self) -> &'static str {
+    match self {
+      Ty::Int => "int",
+      Ty::Float => "float",
+      Ty::Bool => "bool",
+      Ty::Char => "char",
+      Ty::String => "string",
+      Ty::Unit => "unit",
+    }
+  }
+}
+
+impl UnOp {
+  pub fn name(self) -> &'static str {
+    match self {
+      UnOp::Neg => "neg",
+      UnOp::Not => "not",
+    }
+  }
+}
+
+impl BinOp {
+  pub const ALL: [BinOp; 16] = [
+    BinOp::Add, BinOp::Sub, BinOp::Mul, BinOp::Div, BinOp::Rem, BinOp::And, BinOp::Or, BinOp::Xor,
+    BinOp::Shl, BinOp::Shr, BinOp::Eq, BinOp::Ne, BinOp::Lt, BinOp::Gt, BinOp::Le, BinOp::Ge,
+  ];
+
+  pub fn from_name(name: &str) -> Option<BinOp> {
+    BinOp::ALL.into_iter().find(|op| op.name() == name)
+  }
+  pub fn name(self) -> &'static str {
+    match self {
+      BinOp::Add => "add",
+      BinOp::Sub => "sub",
+      BinOp::Mul => "mul",
+      BinOp::Div => "div",
+      BinOp::Rem => "rem",
+      BinOp::And => "and",
+      BinOp::Or => "or",
+      BinOp::Xor => "xor",
+      BinOp::Shl => "shl",
+      BinOp::Shr => "shr",
+      BinOp::Eq => "eq",
+      BinOp::Ne => "ne",
+      BinOp::Lt => "lt",
+      BinOp::Gt => "gt",
+      BinOp::Le => "le",
+      BinOp::Ge => "ge",
+    }
+  }
+  pub fn is_comparison(self) -> bool {
+    matches!(self, BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge)
+  }
+  // `add` joins strings, which does not commute
+  pub fn is_commutative(self, ty: Ty) -> bool {
+    match self {
+      BinOp::Add => ty != Ty::String,
+      _ => matches!(self, BinOp::Mul | BinOp::And | BinOp::Or | BinOp::Xor | BinOp::Eq | BinOp::Ne),
+    }
+  }
+}
+
+impl Inst {
+  pub fn operands(&self) -> Vec<Value> {
+    match &self.op {
+      Op::Const(_) | Op::Load(_) => Vec::new(),
+      Op::Copy(v) | Op::Unary(_, v) | Op::Store(_, v) => vec![*v],
+      Op::Binary(_, a, b) => vec![*a, *b],
+      Op::Phi(incoming) => incoming.iter().map(|(_, v)| *v).collect(),
+      Op::Call(_, args) => args.clone(),
+    }
+  }
+  pub fn operands_mut(&mut self) -> Vec<&mut Value> {
+    match &mut self.op {
+      Op::Const(_) | Op::Load(_) => Vec::new(),
+      Op::Copy(v) | Op::Unary(_, v) | Op::Store(_, v) => vec![v],
+      Op::Binary(_, a, b) => vec![a, b],
+      Op::Phi(incoming) => incoming.iter_mut().map(|(_, v)| v).collect(),
+      Op::Call(_, args) => args.iter_mut().collect(),
+    }
+  }
+  pub fn is_phi(&self) -> bool {
+    matches!(self.op, Op::Phi(_))
+  }
+  // Calls and stores, and `int` arithmetic, which stops the program when it
+  // overflows or divides by zero. Nothing else may be removed when unused.
+  pub fn has_effects(&self) -> bool {
+    match self.op {
+      Op::Call(..) | Op::Store(..) => true,
+      Op::Unary(UnOp::Neg, _) => self.ty == Ty::Int,
+      Op::Binary(op, ..) => self.ty == Ty::Int && matches!(op, BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem | BinOp::Shl | BinOp::Shr),
+      _ => false,
+    }
+  }
+}
+
+impl Term {
+  pub fn succs(&self) -> Vec<usize> {
+    match self {
+      Term::Jump(target) => vec![*target],
+      Term::Branch(_, then, other) if then == other => vec![*then],
+      Term::Branch(_, then, other) => vec![*then, *other],
+      Term::Return(_) | Term::Unreachable => Vec::new(),
+    }
+  }
+  pub fn operands(&self) -> Vec<Value> {
+    match self {
+      Term::Branch(cond, ..) => vec![*cond],
+      Term::Return(Some(v)) => vec![*v],
+      _ => Vec::new(),
+    }
+  }
+  pub fn operands_mut(&mut self) -> Vec<&mut Value> {
+    match self {
+      Term::Branch(cond, ..) => vec![cond],
+      Term::Return(Some(v)) => vec![v],
+      _ => Vec::new(),
+    }
+  }
+}
+
+impl Function {
+  // Predecessors of each block, in block order
+  pub fn preds(&self) -> Vec<Vec<usize>> {
+    let mut preds = vec![Vec::new(); self.blocks.len()];
+    for (b, block) in self.blocks.iter().enumerate() {
+      for succ in block.term.succs() {
+        preds[succ].push(b);
+      }
+    }
+    preds
+  }
+  // Blocks in reverse postorder from the entry, unreachable ones left out
+  pub fn reverse_postorder(&self) -> Vec<usize> {
+    let mut seen = vec![false; self.blocks.len()];
+    let mut order = Vec::new();
+    let mut stk = vec![(0, 0)];
+    seen[0] = true;
+    while let Some((block, next)) = stk.pop() {
+      let succs = self.blocks[block].term.succs();
+      match succs.get(next) {
+        Some(&succ) => {
+          stk.push((block, next + 1));
+          if !seen[succ] {
+            seen[succ] = true;
+            stk.push((succ, 0));
+          }
+        },
+        None => order.push(block),
+      }
+    }
+    order.reverse();
+    order
+  }
+  // Immediate dominator of each reachable block, the entry being its own
+  pub fn dominators(&self) -> Vec<Option<usize>> {
+    let order = self.reverse_postorder();
+    let mut rank = vec![usize::MAX; self.blocks.len()];
+    for (i, &b) in order.iter().enumerate() {
+      rank[b] = i;
+    }
+    let preds = self.preds();
+    let mut idom = vec![None; self.blocks.len()];
+    idom[0] = Some(0);
+    let mut changed = true;
+    while changed {
+      changed = false;
+      for &b in order.iter().skip(1) {
+        let mut new = None;
+        for &p in preds[b].iter().filter(|&&p| idom[p].is_some()) {
+          new = Some(match new {
+            None => p,
+            Some(mut q) => {
+              let mut p = p;
+              while p != q {
+                while rank[p] > rank[q] {
+                  p = idom[p].unwrap();
+                }
+                while rank[q] > rank[p] {
+                  q = idom[q].unwrap();
+                }
+              }
+              p
+            },
+          });
+        }
+        if new != idom[b] {
+          idom[b] = new;
+          changed = true;
+        }
+      }
+    }
+    idom
+  }
+  // Rewrite every use of a key of `map` to its value, following chains
+  pub fn replace_uses(&mut self, map: &BTreeMap<Value, Value>) {
+    let resolve = |mut v: Value| {
+      while let Some(&to) = map.get(&v) {
+        v = to;
+      }
+      v
+    };
+    for block in self.blocks.iter_mut() {
+      for inst in block.insts.iter_mut() {
+        for v in inst.operands_mut() {
+          *v = resolve(*v);
+        }
+      }
+      for v in block.term.operands_mut() {
+        *v = resolve(*v);
+      }
+    }
+  }
+  // Remove the phis that merge one value besides their own, using that value
+  // instead, whether any was removed
+  pub fn remove_trivial_phis(&mut self) -> bool {
+    let mut removed = false;
+    loop {
+      let mut map = BTreeMap::new();
+      for block in self.blocks.iter_mut() {
+        block.insts.retain(|inst| {
+          let (Op::Phi(incoming), Some(dest)) = (&inst.op, inst.dest) else { return true };
+          let mut others = incoming.iter().map(|(_, v)| *v).filter(|v| *v != dest);
+          match others.next() {
+            // Two phis standing for each other are only removed one at a time
+            Some(v) if others.all(|w| w == v) && !map.contains_key(&v) => {
+              map.insert(dest, v);
+              false
+            },
+            _ => true,
+          }
+        });
+      }
+      if map.is_empty() {
+        return removed;
+      }
+      self.replace_uses(&map);
+      removed = true;
+    }
+  }
+  // Drop the blocks the entry cannot reach and renumber the others, whether any was dropped
+  pub fn remove_unreachable(&mut self) -> bool {
+    let mut reachable = vec![false; self.blocks.len()];
+    for b in self.reverse_postorder() {
+      reachable[b] = true;
+    }
+    if reachable.iter().all(|&r| r) {
+      return false;
+    }
+    let mut number = vec![usize::MAX; self.blocks.len()];
+    let mut next = 0;
+    for (b, &r) in reachable.iter().enumerate() {
+      if r {
+        number[b] = next;
+        next += 1;
+      }
+    }
+    let blocks = std::mem::take(&mut self.blocks);
+    for (b, mut block) in blocks.into_iter().enumerate() {
+      if !reachable[b] {
+        continue;
+      }
+      for inst in block.insts.iter_mut() {
+        if let Op::Phi(incoming) = &mut inst.op {
+          incoming.retain(|(p, _)| reachable[*p]);
+          for (p, _) in incoming.iter_mut() {
+            *p = number[*p];
+          }
+        }
+      }
+      match &mut block.term {
+        Term::Jump(target) => *target = number[*target],
+        Term::Branch(_, then, other) => {
+          *then = number[*then];
+          *other = number[*other];
+        },
+        Term::Return(_) | Term::Unreachable => {},
+      }
+      self.blocks.push(block);
+    }
+    true
+  }
+}
+
+// Whether block `a` dominates block `b`, given the immediate dominators
+pub fn dominates(idom: &[Option<usize>], a: usize, mut b: usize) -> bool {
+  loop {
+    if a == b {
+      return true;
+    }
+    match idom[b] {
+      Some(up) if up != b => b = up,
+      _ => return false,
+    }
+  }
+}
+
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "%{}", self.0)
+  }
+}
+
+impl fmt::Display for Ty {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+// Constants are written like carf literals
+pub fn format_const(c: &Const) -> String {
+  match c {
+    Const::Int(n) => n.to_string(),
+    Const::Float(x) => literal::format_float(*x),
+    Const::Char(c) => literal::quote_char(*c),
+    Const::Str(s) => literal::quote_string(s),
+    Const::Bool(b) => b.to_string(),
+  }
+}
+
+fn join(values: &[Value]) -> String {
+  values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+impl fmt::Display for Inst {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if let Some(dest) = self.dest {
+      write!(f, "{} = ", dest)?;
+    }
+    let ty = self.ty;
+    match &self.op {
+      Op::Const(c) => write!(f, "const {} {}", ty, format_const(c)),
+      Op::Copy(v) => write!(f, "copy {} {}", ty, v),
+      Op::Unary(op, v) => write!(f, "{} {} {}", op.name(), ty, v),
+      Op::Binary(op, a, b) => write!(f, "{} {} {}, {}", op.name(), ty, a, b),
+      Op::Phi(incoming) => {
+        let incoming: Vec<String> = incoming.iter().map(|(b, v)| format!("[b{}: {}]", b, v)).collect();
+        write!(f, "phi {} {}", ty, incoming.join(", "))
+      },
+      Op::Call(name, args) => write!(f, "call {} @{}({})", ty, name, join(args)),
+      Op::Load(name) => write!(f, "load {} @{}", ty, name),
+      Op::Store(name, v) => write!(f, "store {} @{}, {}", ty, name, v),
+    }
+  }
+}
+
+impl fmt::Display for Term {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Term::Jump(target) => write!(f, "jump b{}", target),
+      Term::Branch(cond, then, other) => write!(f, "br {}, b{}, b{}", cond, then, other),
+      Term::Return(Some(v)) => write!(f, "ret {}", v),
+      Term::Return(None) => write!(f, "ret"),
+      Term::Unreachable => write!(f, "unreachable"),
+    }
+  }
+}
+
+impl fmt::Display for Function {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let params: Vec<String> = self.params.iter().map(|(v, ty)| format!("{}: {}", v, ty)).collect();
+    writeln!(f, "fun @{}({}): {} {{", self.name, params.join(", "), self.ret)?;
+    for (b, block) in self.blocks.iter().enumerate() {
+      writeln!(f, "b{}:", b)?;
+      for inst in &block.insts {
+        writeln!(f, "  {}", inst)?;
+      }
+      writeln!(f, "  {}", block.term)?;
+    }
+    writeln!(f, "}}")
+  }
+}
+
+// The text `parse::parse` reads back
+impl fmt::Display for Module {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (name, ty) in &self.globals {
+      writeln!(f, "global @{}: {}", name, ty)?;
+    }
+    for (i, fun) in self.funs.iter().enumerate() {
+      if i > 0 || !self.globals.is_empty() {
+        writeln!(f)?;
+      }
+      write!(f, "{}", fun)?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,178 @@
+use super::{BinOp, Block, Function, Inst, Module, Op, Term, Ty, UnOp, Value};
+use crate::fold::Const;
+use crate::utilities::literal;
+
+// Read back the text a module prints as. Blocks are numbered in the order
+// they appear. Errors name the line, e.g. "line 3: unknown instruction `mov`".
+pub fn parse(text: &str) -> Result<Module, String> {
+  let mut reader = Reader {
+    module: Module::default(),
+    fun: None,
+    insts: None,
+  };
+  for (i, line) in text.lines().enumerate() {
+    reader.line(line.trim()).map_err(|e| format!("line {}: {}", i + 1, e))?;
+  }
+  match reader.fun {
+    Some(fun) => Err(format!("`@{}` is not closed", fun.name)),
+    None => Ok(reader.module),
+  }
+}
+
+struct Reader {
+  module: Module,
+  fun: Option<Function>,
+  // Instructions of the block being read, until its terminator
+  insts: Option<Vec<Inst>>,
+}
+
+impl Reader {
+  fn line(&mut self, line: &str) -> Result<(), String> {
+    if line.is_empty() {
+      return Ok(());
+    }
+    let Some(fun) = &mut self.fun else {
+      if let Some(rest) = line.strip_prefix("global ") {
+        let (name, ty) = rest.split_once(": ").ok_or("expected `global @name: type`")?;
+        self.module.globals.push((global(name)?.to_string(), ty_of(ty)?));
+        return Ok(());
+      }
+      let head = line.strip_prefix("fun ").and_then(|rest| rest.strip_suffix(" {")).ok_or("expected `global` or `fun`")?;
+      self.fun = Some(fun_head(head)?);
+      return Ok(());
+    };
+    if line == "}" {
+      if self.insts.is_some() {
+        return Err(format!("b{} has no terminator", fun.blocks.len()));
+      }
+      if fun.blocks.is_empty() {
+        return Err(format!("`@{}` has no blocks", fun.name));
+      }
+      self.module.funs.push(self.fun.take().unwrap());
+      return Ok(());
+    }
+    if let Some(label) = line.strip_suffix(':') {
+      if self.insts.is_some() {
+        return Err(format!("b{} has no terminator", fun.blocks.len()));
+      }
+      if block(label)? != fun.blocks.len() {
+        return Err(format!("expected block b{}", fun.blocks.len()));
+      }
+      self.insts = Some(Vec::new());
+      return Ok(());
+    }
+    let insts = self.insts.as_mut().ok_or("instruction outside of a block")?;
+    match term(line)? {
+      Some(term) => fun.blocks.push(Block { insts: self.insts.take().unwrap(), term }),
+      None => insts.push(inst(line)?),
+    }
+    Ok(())
+  }
+}
+
+// `@name(%0: int, %1: float): int`
+fn fun_head(head: &str) -> Result<Function, String> {
+  let (name, rest) = head.split_once('(').ok_or("expected `(` after the function name")?;
+  let (params, ret) = rest.split_once("): ").ok_or("expected `): type` after the parameters")?;
+  let mut fun = Function { name: global(name)?.to_string(), params: Vec::new(), ret: ty_of(ret)?, blocks: Vec::new() };
+  for param in list(params) {
+    let (v, ty) = param.split_once(": ").ok_or("expected `%N: type` for a parameter")?;
+    fun.params.push((value(v)?, ty_of(ty)?));
+  }
+  Ok(fun)
+}
+
+fn term(line: &str) -> Result<Option<Term>, String> {
+  let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+  let term = match word {
+    "jump" => Term::Jump(block(rest)?),
+    "br" => match list(rest)[..] {
+      [cond, then, other] => Term::Branch(value(cond)?, block(then)?, block(other)?),
+      _ => return Err("expected `br %N, bN, bN`".to_string()),
+    },
+    "ret" if rest.is_empty() => Term::Return(None),
+    "ret" => Term::Return(Some(value(rest)?)),
+    "unreachable" => Term::Unreachable,
+    _ => return Ok(None),
+  };
+  Ok(Some(term))
+}
+
+// `%N = op type operands`, or `op type operands` for a store or a call without result
+fn inst(line: &str) -> Result<Inst, String> {
+  let (dest, line) = match line.split_once(" = ") {
+    Some((dest, rest)) if dest.starts_with('%') => (Some(value(dest)?), rest),
+    _ => (None, line),
+  };
+  let mut words = line.splitn(3, ' ');
+  let name = words.next().unwrap_or_default();
+  let ty = ty_of(words.next().ok_or("expected a type after the instruction")?)?;
+  let rest = words.next().unwrap_or_default();
+  let one = |rest: &str| value(rest);
+  let op = match name {
+    "const" => Op::Const(constant(ty, rest)?),
+    "copy" => Op::Copy(one(rest)?),
+    "neg" => Op::Unary(UnOp::Neg, one(rest)?),
+    "not" => Op::Unary(UnOp::Not, one(rest)?),
+    "phi" => {
+      let mut incoming = Vec::new();
+      for part in list(rest) {
+        let part = part.strip_prefix('[').and_then(|p| p.strip_suffix(']')).ok_or("expected `[bN: %N]`")?;
+        let (b, v) = part.split_once(": ").ok_or("expected `[bN: %N]`")?;
+        incoming.push((block(b)?, value(v)?));
+      }
+      Op::Phi(incoming)
+    },
+    "call" => {
+      let (callee, args) = rest.strip_suffix(')').and_then(|r| r.split_once('(')).ok_or("expected `@name(args)`")?;
+      let args = list(args).into_iter().map(value).collect::<Result<_, _>>()?;
+      Op::Call(global(callee)?.to_string(), args)
+    },
+    "load" => Op::Load(global(rest)?.to_string()),
+    "store" => match list(rest)[..] {
+      [name, v] => Op::Store(global(name)?.to_string(), value(v)?),
+      _ => return Err("expected `store type @name, %N`".to_string()),
+    },
+    _ => match (BinOp::from_name(name), &list(rest)[..]) {
+      (Some(op), [a, b]) => Op::Binary(op, value(a)?, value(b)?),
+      (Some(_), _) => return Err(format!("expected `{} type %N, %N`", name)),
+      (None, _) => return Err(format!("unknown instruction `{}`", name)),
+    },
+  };
+  Ok(Inst { dest, ty, op })
+}
+
+fn constant(ty: Ty, text: &str) -> Result<Const, String> {
+  let c = match ty {
+    Ty::Int => text.parse().ok().map(Const::Int),
+    Ty::Float => literal::parse_float(text).map(Const::Float),
+    Ty::Bool => text.parse().ok().map(Const::Bool),
+    Ty::Char => literal::parse_char(text).map(Const::Char),
+    Ty::String => literal::parse_string(text).map(Const::Str),
+    Ty::Unit => None,
+  };
+  c.ok_or_else(|| format!("invalid `{}` constant `{}`", ty, text))
+}
+
+fn list(text: &str) -> Vec<&str> {
+  match text.is_empty() {
+    true => Vec::new(),
+    false => text.split(", ").collect(),
+  }
+}
+
+fn value(text: &str) -> Result<Value, String> {
+  text.strip_prefix('%').and_then(|n| n.parse().ok()).map(Value).ok_or_else(|| format!("expected a value, not `{}`", text))
+}
+
+fn block(text: &str) -> Result<usize, String> {
+  text.strip_prefix('b').and_then(|n| n.parse().ok()).ok_or_else(|| format!("expected a block, not `{}`", text))
+}
+
+fn global(text: &str) -> Result<&str, String> {
+  text.strip_prefix('@').filter(|name| !name.is_empty()).ok_or_else(|| format!("expected `@name`, not `{}`", text))
+}
+
+fn ty_of(text: &str) -> Result<Ty, String> {
+  Ty::from_name(text).ok_or_else(|| format!("unknown type `{}`", text))
+}
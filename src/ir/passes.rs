@@ -0,0 +1,228 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{Function, Module, Op, Value, verify};
+
+// An optimization over one function at a time
+pub trait Pass {
+  // What the pass is called in a pipeline, e.g. `dce`
+  fn name(&self) -> &'static str;
+  // Whether the function changed
+  fn run(&self, fun: &mut Function) -> bool;
+}
+
+// Names `PassManager::parse` accepts
+pub const PASS_NAMES: [&str; 3] = ["dce", "copy-prop", "cse"];
+
+pub fn pass_named(name: &str) -> Option<Box<dyn Pass>> {
+  match name {
+    "dce" => Some(Box::new(DeadCode)),
+    "copy-prop" => Some(Box::new(CopyProp)),
+    "cse" => Some(Box::new(CommonSubexpr)),
+    _ => None,
+  }
+}
+
+// Passes run in order over every function of a module, each once
+pub struct PassManager {
+  passes: Vec<Box<dyn Pass>>,
+  verify: bool,
+}
+
+impl Default for PassManager {
+  fn default() -> Self {
+    PassManager::new()
+  }
+}
+
+impl PassManager {
+  pub fn new() -> PassManager {
+    PassManager {
+      passes: Vec::new(),
+      verify: true,
+    }
+  }
+  // A pipeline of pass names separated by commas, e.g. `cse,copy-prop,dce`.
+  // The same pass may come more than once.
+  pub fn parse(pipeline: &str) -> Result<PassManager, String> {
+    let mut manager = PassManager::new();
+    for name in pipeline.split(',').filter(|name| !name.is_empty()) {
+      match pass_named(name) {
+        Some(pass) => manager.add(pass),
+        None => return Err(format!("unknown pass `{}`, expected one of {}", name, PASS_NAMES.join(", "))),
+      }
+    }
+    Ok(manager)
+  }
+  pub fn add(&mut self, pass: Box<dyn Pass>) {
+    self.passes.push(pass);
+  }
+  // Verify the module before the pipeline and after every pass that changed
+  // it, on by default
+  pub fn set_verify(&mut self, verify: bool) {
+    self.verify = verify;
+  }
+  pub fn get_names(&self) -> Vec<&'static str> {
+    self.passes.iter().map(|pass| pass.name()).collect()
+  }
+  // Run the pipeline, giving the names of the passes that changed something,
+  // or the problem the verifier found
+  pub fn run(&self, module: &mut Module) -> Result<Vec<&'static str>, String> {
+    if self.verify {
+      verify::verify(module)?;
+    }
+    let mut changed = Vec::new();
+    for pass in &self.passes {
+      let mut any = false;
+      for fun in module.funs.iter_mut() {
+        any |= pass.run(fun);
+      }
+      if !any {
+        continue;
+      }
+      changed.push(pass.name());
+      if self.verify {
+        verify::verify(module).map_err(|e| format!("after `{}`: {}", pass.name(), e))?;
+      }
+    }
+    Ok(changed)
+  }
+}
+
+// Dead code elimination: drops unreachable blocks, then every instruction
+// whose result is unused and that has no effect. Phis that only feed each
+// other around a loop go too.
+pub struct DeadCode;
+
+impl Pass for DeadCode {
+  fn name(&self) -> &'static str {
+    "dce"
+  }
+  fn run(&self, fun: &mut Function) -> bool {
+    let mut changed = fun.remove_unreachable();
+    let mut defs = BTreeMap::new();
+    for (b, block) in fun.blocks.iter().enumerate() {
+      for (i, inst) in block.insts.iter().enumerate() {
+        if let Some(dest) = inst.dest {
+          defs.insert(dest, (b, i));
+        }
+      }
+    }
+    // Live values, from the effects and terminators back to what they use
+    let mut live = BTreeSet::new();
+    let mut work: Vec<Value> = Vec::new();
+    for block in &fun.blocks {
+      for inst in block.insts.iter().filter(|inst| inst.has_effects()) {
+        work.extend(inst.operands());
+      }
+      work.extend(block.term.operands());
+    }
+    while let Some(v) = work.pop() {
+      if !live.insert(v) {
+        continue;
+      }
+      if let Some(&(b, i)) = defs.get(&v) {
+        work.extend(fun.blocks[b].insts[i].operands());
+      }
+    }
+    for block in fun.blocks.iter_mut() {
+      let before = block.insts.len();
+      block.insts.retain(|inst| inst.has_effects() || inst.dest.is_some_and(|dest| live.contains(&dest)));
+      changed |= block.insts.len() != before;
+    }
+    changed
+  }
+}
+
+// Copy propagation: uses of a `copy` read its operand instead, and the copy
+// goes. A phi merging one value is a copy of it.
+pub struct CopyProp;
+
+impl Pass for CopyProp {
+  fn name(&self) -> &'static str {
+    "copy-prop"
+  }
+  fn run(&self, fun: &mut Function) -> bool {
+    let mut map = BTreeMap::new();
+    for block in fun.blocks.iter_mut() {
+      block.insts.retain(|inst| match (&inst.op, inst.dest) {
+        (Op::Copy(v), Some(dest)) => {
+          map.insert(dest, *v);
+          false
+        },
+        _ => true,
+      });
+    }
+    fun.replace_uses(&map);
+    let phis = fun.remove_trivial_phis();
+    !map.is_empty() || phis
+  }
+}
+
+// Common subexpression elimination: an instruction computing what one in a
+// dominating position already did becomes a copy of it, for `copy-prop` and
+// `dce` to clean up. Loads and calls may give a different result each time
+// and are left alone.
+pub struct CommonSubexpr;
+
+impl Pass for CommonSubexpr {
+  fn name(&self) -> &'static str {
+    "cse"
+  }
+  fn run(&self, fun: &mut Function) -> bool {
+    let idom = fun.dominators();
+    let mut children = vec![Vec::new(); fun.blocks.len()];
+    for (b, up) in idom.iter().enumerate().skip(1) {
+      if let Some(up) = up {
+        children[*up].push(b);
+      }
+    }
+    // Available computations by their text, with their operands replaced by
+    // the values they copy, scoped to the dominator subtree being walked
+    let mut available: BTreeMap<String, Value> = BTreeMap::new();
+    let mut copies: BTreeMap<Value, Value> = BTreeMap::new();
+    let mut changed = false;
+    // Blocks to enter, or the keys to forget on leaving one
+    let mut stk = vec![Ok(0)];
+    while let Some(next) = stk.pop() {
+      let b = match next {
+        Ok(b) => b,
+        Err(keys) => {
+          for key in keys {
+            available.remove(&key);
+          }
+          continue;
+        },
+      };
+      let mut added = Vec::new();
+      for inst in fun.blocks[b].insts.iter_mut() {
+        let Some(dest) = inst.dest else { continue };
+        if !matches!(inst.op, Op::Const(_) | Op::Unary(..) | Op::Binary(..)) {
+          continue;
+        }
+        let mut key = inst.clone();
+        for v in key.operands_mut() {
+          *v = copies.get(v).copied().unwrap_or(*v);
+        }
+        key.dest = None;
+        if let Op::Binary(op, lhs, rhs) = &mut key.op && op.is_commutative(key.ty) && rhs < lhs {
+          std::mem::swap(lhs, rhs);
+        }
+        let key = key.to_string();
+        match available.get(&key) {
+          Some(&earlier) => {
+            inst.op = Op::Copy(earlier);
+            copies.insert(dest, earlier);
+            changed = true;
+          },
+          None => {
+            available.insert(key.clone(), dest);
+            added.push(key);
+          },
+        }
+      }
+      stk.push(Err(added));
+      stk.extend(children[b].iter().rev().map(|&child| Ok(child)));
+    }
+    changed
+  }
+}
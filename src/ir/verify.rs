@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+
+use super::{BinOp, Function, Inst, Module, Op, Term, Ty, UnOp, Value, dominates};
+use crate::fold::Const;
+
+// Check that the module is well formed SSA whose types agree, giving the first
+// problem found, e.g. "@main b2: %4 is used where its definition does not dominate"
+pub fn verify(module: &Module) -> Result<(), String> {
+  let globals: BTreeMap<&str, Ty> = module.globals.iter().map(|(name, ty)| (name.as_str(), *ty)).collect();
+  let funs: BTreeMap<&str, &Function> = module.funs.iter().map(|fun| (fun.name.as_str(), fun)).collect();
+  for fun in &module.funs {
+    let mut verifier = Verifier {
+      globals: &globals,
+      funs: &funs,
+      fun,
+      defs: BTreeMap::new(),
+      idom: Vec::new(),
+    };
+    verifier.run().map_err(|(block, e)| match block {
+      Some(b) => format!("@{} b{}: {}", fun.name, b, e),
+      None => format!("@{}: {}", fun.name, e),
+    })?;
+  }
+  Ok(())
+}
+
+// Where a value is defined: parameters in no block, then block and index
+type Def = (Option<(usize, usize)>, Ty);
+
+struct Verifier<'v> {
+  globals: &'v BTreeMap<&'v str, Ty>,
+  funs: &'v BTreeMap<&'v str, &'v Function>,
+  fun: &'v Function,
+  defs: BTreeMap<Value, Def>,
+  idom: Vec<Option<usize>>,
+}
+
+// An error and the block it is in
+type Error = (Option<usize>, String);
+
+impl Verifier<'_> {
+  fn run(&mut self) -> Result<(), Error> {
+    let fun = self.fun;
+    if fun.blocks.is_empty() {
+      return Err((None, "has no blocks".to_string()));
+    }
+    for (b, block) in fun.blocks.iter().enumerate() {
+      for target in block.term.succs() {
+        if target >= fun.blocks.len() {
+          return Err((Some(b), format!("jumps to b{}, which does not exist", target)));
+        }
+      }
+    }
+    self.idom = fun.dominators();
+    if let Some(b) = self.idom.iter().position(|idom| idom.is_none()) {
+      return Err((Some(b), "is unreachable".to_string()));
+    }
+    for &(v, ty) in &fun.params {
+      self.define(v, None, ty)?;
+    }
+    for (b, block) in fun.blocks.iter().enumerate() {
+      for (i, inst) in block.insts.iter().enumerate() {
+        if let Some(dest) = inst.dest {
+          self.define(dest, Some((b, i)), inst.ty).map_err(|(_, e)| (Some(b), e))?;
+        }
+      }
+    }
+    let preds = fun.preds();
+    for (b, block) in fun.blocks.iter().enumerate() {
+      for (i, inst) in block.insts.iter().enumerate() {
+        self.inst(b, i, inst, &preds[b]).map_err(|e| (Some(b), e))?;
+      }
+      self.term(b, &block.term).map_err(|e| (Some(b), e))?;
+    }
+    Ok(())
+  }
+  fn define(&mut self, v: Value, at: Option<(usize, usize)>, ty: Ty) -> Result<(), Error> {
+    match self.defs.insert(v, (at, ty)) {
+      Some(_) => Err((None, format!("{} is defined twice", v))),
+      None => Ok(()),
+    }
+  }
+  fn inst(&self, b: usize, i: usize, inst: &Inst, preds: &[usize]) -> Result<(), String> {
+    let block = &self.fun.blocks[b];
+    if inst.is_phi() && i > 0 && !block.insts[i - 1].is_phi() {
+      return Err(format!("phi {} comes after other instructions", inst.dest.map(|v| v.to_string()).unwrap_or_default()));
+    }
+    match (&inst.op, inst.dest) {
+      (Op::Store(..), Some(dest)) => return Err(format!("store cannot define {}", dest)),
+      (Op::Store(..), None) => {},
+      (Op::Call(..), dest) if dest.is_none() != (inst.ty == Ty::Unit) => {
+        return Err("a call has a result unless its type is `unit`".to_string());
+      },
+      (Op::Call(..), _) => {},
+      (_, None) => return Err(format!("`{}` needs a result", inst)),
+      (_, Some(dest)) if inst.ty == Ty::Unit => return Err(format!("{} cannot have type `unit`", dest)),
+      _ => {},
+    }
+    let ty = inst.ty;
+    match &inst.op {
+      Op::Const(c) => {
+        let matches = matches!(
+          (c, ty),
+          (Const::Int(_), Ty::Int) | (Const::Float(_), Ty::Float) | (Const::Bool(_), Ty::Bool) | (Const::Char(_), Ty::Char) | (Const::Str(_), Ty::String)
+        );
+        if !matches {
+          return Err(format!("constant of {} is not of type `{}`", inst.dest.unwrap(), ty));
+        }
+      },
+      Op::Copy(v) => self.operand(b, i, *v, Some(ty))?,
+      Op::Unary(op, v) => {
+        let accepts = match op {
+          UnOp::Neg => matches!(ty, Ty::Int | Ty::Float),
+          UnOp::Not => ty == Ty::Bool,
+        };
+        if !accepts {
+          return Err(format!("`{}` does not apply to `{}`", op.name(), ty));
+        }
+        self.operand(b, i, *v, Some(ty))?;
+      },
+      Op::Binary(op, lhs, rhs) => {
+        let operand = match op.is_comparison() {
+          true => self.operand(b, i, *lhs, None).map(|_| self.defs[lhs].1)?,
+          false => ty,
+        };
+        let accepts = match op {
+          BinOp::Add => matches!(operand, Ty::Int | Ty::Float | Ty::String),
+          BinOp::Sub | BinOp::Mul | BinOp::Div => matches!(operand, Ty::Int | Ty::Float),
+          BinOp::Rem | BinOp::And | BinOp::Or | BinOp::Xor | BinOp::Shl | BinOp::Shr => operand == Ty::Int,
+          BinOp::Eq | BinOp::Ne => ty == Ty::Bool && operand != Ty::Unit,
+          BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => ty == Ty::Bool && operand != Ty::Unit && operand != Ty::Bool,
+        };
+        if !accepts {
+          return Err(format!("`{}` of `{}` operands cannot give `{}`", op.name(), operand, ty));
+        }
+        self.operand(b, i, *lhs, Some(operand))?;
+        self.operand(b, i, *rhs, Some(operand))?;
+      },
+      Op::Phi(incoming) => {
+        let mut from: Vec<usize> = incoming.iter().map(|(p, _)| *p).collect();
+        from.sort();
+        if from != preds {
+          return Err(format!("phi {} does not have one value for each predecessor", inst.dest.unwrap()));
+        }
+        for (p, v) in incoming {
+          // Defined on the way out of the predecessor
+          self.operand(*p, self.fun.blocks[*p].insts.len(), *v, Some(ty))?;
+        }
+      },
+      Op::Call(name, args) => {
+        for v in args {
+          self.operand(b, i, *v, None)?;
+        }
+        // Prelude functions are not checked
+        if let Some(callee) = self.funs.get(name.as_str()) {
+          let params: Vec<Ty> = callee.params.iter().map(|(_, ty)| *ty).collect();
+          let types: Vec<Ty> = args.iter().map(|v| self.defs[v].1).collect();
+          if params != types || callee.ret != ty {
+            return Err(format!("`{}` does not match the signature of `@{}`", inst, name));
+          }
+        }
+      },
+      Op::Load(name) => self.global(name, ty)?,
+      Op::Store(name, v) => {
+        self.global(name, ty)?;
+        self.operand(b, i, *v, Some(ty))?;
+      },
+    }
+    Ok(())
+  }
+  fn term(&self, b: usize, term: &Term) -> Result<(), String> {
+    let end = self.fun.blocks[b].insts.len();
+    match term {
+      Term::Branch(cond, ..) => self.operand(b, end, *cond, Some(Ty::Bool)),
+      Term::Return(Some(_)) if self.fun.ret == Ty::Unit => Err("a `unit` function returns no value".to_string()),
+      Term::Return(Some(v)) => self.operand(b, end, *v, Some(self.fun.ret)),
+      Term::Return(None) if self.fun.ret != Ty::Unit => Err(format!("`ret` needs a value of type `{}`", self.fun.ret)),
+      _ => Ok(()),
+    }
+  }
+  // A use at index `i` of block `b`, which the definition must come before
+  fn operand(&self, b: usize, i: usize, v: Value, ty: Option<Ty>) -> Result<(), String> {
+    let Some(&(at, def_ty)) = self.defs.get(&v) else {
+      return Err(format!("{} is not defined", v));
+    };
+    let dominated = match at {
+      None => true,
+      Some((def_b, def_i)) if def_b == b => def_i < i,
+      Some((def_b, _)) => dominates(&self.idom, def_b, b),
+    };
+    if !dominated {
+      return Err(format!("{} is used where its definition does not dominate", v));
+    }
+    match ty {
+      Some(ty) if ty != def_ty => Err(format!("{} has type `{}`, expected `{}`", v, def_ty, ty)),
+      _ => Ok(()),
+    }
+  }
+  fn global(&self, name: &str, ty: Ty) -> Result<(), String> {
+    match self.globals.get(name) {
+      Some(&global) if global == ty => Ok(()),
+      Some(&global) => Err(format!("`@{}` has type `{}`, not `{}`", name, global, ty)),
+      None => Err(format!("no global `@{}`", name)),
+    }
+  }
+}
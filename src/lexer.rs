@@ -1,11 +1,13 @@
-use core::panic;
 use std::collections::VecDeque;
 
 use crate::ast::token::{self, TokenKind};
+use crate::source::SourceFile;
 use crate::{ast::token::Token, utilities::trie::Trie};
 
 pub struct Lexer<'a> {
   tokens: VecDeque<Token<'a>>,
+  // Line comments with their position, which the parser never sees
+  comments: Vec<(usize, &'a str)>,
 }
 
 impl<'a> Lexer<'a> {
@@ -14,127 +16,169 @@ impl<'a> Lexer<'a> {
     for t in token::SYMBOL_LIST.iter() {
       token_trie.insert(t);
     }
-    for t in token::KEYWORD_LIST.iter() {
-      token_trie.insert(t);
-    }
 
     let mut tokens = VecDeque::new();
+    let mut comments = Vec::new();
     {
-      let mut last_pos = 0_usize;
-      let mut state = 0_u8;
       let s = source;
-      let deter_state = |i: usize, c: char| -> u8 {
-        if token_trie.contains(&s[i..=i]) { 
-          1 
-        } else if c == '"' { 
-          2 
-        } else if c == '\'' {
-          3
-        } else if c.is_digit(10) {
-          4
-        } else {
-          5
+      let chars: Vec<(usize, char)> = s.char_indices().collect();
+      let end_of = |i: usize| -> usize {
+        match chars.get(i) {
+          Some((pos, _)) => *pos,
+          None => s.len(),
         }
       };
-      for (i, c) in s.chars().enumerate() {
-        if state == 0 && (c == ' ' || c == '\n' || c == '\t' || c == '\r') {
+      let mut i = 0_usize;
+      while i < chars.len() {
+        let (pos, c) = chars[i];
+        if c.is_whitespace() {
+          i += 1;
           continue;
         }
-        match state {
-          0 => {
-            // Empty state
-            state = deter_state(i, c);
-            last_pos = i;
-          },
-          1 => {
-            // Symbol or Keyword
-            if !token_trie.contains(&s[last_pos..=i]) {
-              tokens.push_back(Token::from_token(&s[last_pos..=i]));
-              state = deter_state(i, c);
-              last_pos = i;
-            }
-          },
-          2 => {
-            // String Literal
-            if c == '"' {
-              match token::literal_token_map(&s[last_pos..=i]) {
-                TokenKind::String => {
-                  tokens.push_back(Token::new(TokenKind::String, &s[last_pos..=i]));
-                  state = 0;
-                },
-                _ => panic!("Compiler Error at lexing string literal"),
-              }
-            }
-          },
-          3 => {
-            // Char Literal
-            if c == '\'' {
-              match token::literal_token_map(&s[last_pos..=i]) {
-                TokenKind::Char => {
-                  tokens.push_back(Token::new(TokenKind::Char, &s[last_pos..=i]));
-                  state = 0;
-                },
-                _ => panic!("Compiler Error at lexing char literal"),
-              }
+        if c == '/' && matches!(chars.get(i + 1), Some((_, '/'))) {
+          // Line comment
+          while i < chars.len() && chars[i].1 != '\n' {
+            i += 1;
+          }
+          comments.push((pos, s[pos..end_of(i)].trim_end()));
+          continue;
+        }
+        let start = i;
+        if c == '"' || c == '\'' {
+          // String or Char Literal
+          i += 1;
+          let mut closed = false;
+          while i < chars.len() {
+            match chars[i].1 {
+              '\\' => i += 2,
+              '\n' => break,
+              q if q == c => {
+                i += 1;
+                closed = true;
+                break;
+              },
+              _ => i += 1,
             }
-          },
-          4 => {
-            // Number Literal
-            match token::literal_token_map(&s[last_pos..=i]) {
-              TokenKind::Other => {
-                match token::literal_token_map(&s[last_pos..i]) {
-                  TokenKind::Int => {
-                    tokens.push_back(Token::new(TokenKind::Int, &s[last_pos..i]));
-                    state = deter_state(i, c);
-                    last_pos = i;
-                  },
-                  TokenKind::Float => {
-                    tokens.push_back(Token::new(TokenKind::Float, &s[last_pos..i]));
-                    state = deter_state(i, c);
-                    last_pos = i;
-                  },
-                  _ => panic!("Compiler Error at lexing number literal (IM)"),
-                }
-              }
-              _ => panic!("Compiler Error at lexing number literal (OM)"),
+          }
+          let value = &s[pos..end_of(i.min(chars.len()))];
+          let expect = if c == '"' { TokenKind::String } else { TokenKind::Char };
+          if !closed {
+            tokens.push_back(Token::new(TokenKind::Bad(
+              if c == '"' { "Unterminated String Literal" } else { "Unterminated Char Literal" }
+            ), value, pos));
+          } else if token::literal_token_map(value) != expect {
+            tokens.push_back(Token::new(TokenKind::Bad("Invalid Char Literal"), value, pos));
+          } else {
+            tokens.push_back(Token::new(expect, value, pos));
+          }
+        } else if c.is_ascii_digit() {
+          // Number Literal
+          while i < chars.len() && chars[i].1.is_ascii_digit() {
+            i += 1;
+          }
+          // A dot only belongs to the number when a digit follows, so `0..n` stays a range
+          let has_fraction = matches!(chars.get(i), Some((_, '.')))
+            && matches!(chars.get(i + 1), Some((_, d)) if d.is_ascii_digit());
+          if has_fraction {
+            i += 1;
+            while i < chars.len() && chars[i].1.is_ascii_digit() {
+              i += 1;
             }
           }
-          5 => {
-            // Identifier
-            if token_trie.contains(&s[last_pos..=i]) {
-              tokens.push_back(Token::new(token::TokenKind::Identifier, &s[last_pos..i]));
-              state = deter_state(i, c);
-              last_pos = i;
+          if matches!(chars.get(i), Some((_, 'f'))) {
+            i += 1;
+          }
+          while i < chars.len() && (chars[i].1.is_alphanumeric() || chars[i].1 == '_') {
+            i += 1;
+          }
+          let value = &s[pos..end_of(i)];
+          match token::literal_token_map(value) {
+            TokenKind::Int => tokens.push_back(Token::new(TokenKind::Int, value, pos)),
+            TokenKind::Float => tokens.push_back(Token::new(TokenKind::Float, value, pos)),
+            _ => tokens.push_back(Token::new(TokenKind::Bad("Invalid Number Literal"), value, pos)),
+          }
+        } else if c.is_alphabetic() || c == '_' {
+          // Identifier or Keyword
+          while i < chars.len() && (chars[i].1.is_alphanumeric() || chars[i].1 == '_') {
+            i += 1;
+          }
+          let value = &s[pos..end_of(i)];
+          match token::keyword_token_map(value) {
+            TokenKind::Other => tokens.push_back(Token::new(TokenKind::Identifier, value, pos)),
+            kind => tokens.push_back(Token::new(kind, value, pos)),
+          }
+        } else if token_trie.starts_with(&s[pos..end_of(i + 1)]) {
+          // Symbol, longest match wins
+          let mut last_end = start;
+          while i < chars.len() && token_trie.starts_with(&s[pos..end_of(i + 1)]) {
+            i += 1;
+            if token_trie.contains(&s[pos..end_of(i)]) {
+              last_end = i;
             }
           }
-          _ => panic!("Invalid state"),
-        }
-        
-      }
-      // TODO: more detailed CE message
-      if state != 0 {
-        tokens.push_back(Token::new_bad(
-          match state {
-            1 => "Symbol or Keyword",
-            2 => "String Literal",
-            3 => "Char Literal",
-            4 => "Number Literal",
-            5 => "Identifier",
-            _ => "Unknown",
+          if last_end == start {
+            i = start + 1;
+            tokens.push_back(Token::new(TokenKind::Bad("Unknown Symbol"), &s[pos..end_of(i)], pos));
+          } else {
+            i = last_end;
+            tokens.push_back(Token::from_token(&s[pos..end_of(i)], pos));
           }
-        ));
+        } else {
+          i += 1;
+          tokens.push_back(Token::new(TokenKind::Bad("Unknown Character"), &s[pos..end_of(i)], pos));
+        }
       }
-      tokens.push_back(Token::new_eof());
+      tokens.push_back(Token::new_eof(s.len()));
     }
 
     Lexer {
       tokens,
+      comments,
     }
   }
+  // Tokens of a file in a SourceMap, positioned after the files before it
+  pub fn for_file(file: &'a SourceFile) -> Lexer<'a> {
+    let base = file.get_base();
+    let mut lexer = Lexer::new(file.get_text());
+    for token in lexer.tokens.iter_mut() {
+      *token = Token::new(token.get_kind(), token.get_value(), token.get_pos() + base);
+    }
+    for (pos, _) in lexer.comments.iter_mut() {
+      *pos += base;
+    }
+    lexer
+  }
+  // Not an Iterator, it never runs out
+  #[allow(clippy::should_implement_trait)]
   pub fn next(&mut self) -> Option<Token<'a>> {
-    self.tokens.pop_back()
+    // EOF is kept at the back so every later call keeps seeing it
+    if self.tokens.len() == 1 {
+      return self.tokens.front().copied();
+    }
+    self.tokens.pop_front()
   }
   pub fn peek(&self) -> Option<&Token<'a>> {
     self.tokens.front()
   }
-}
\ No newline at end of file
+  pub fn get_comments(&self) -> &Vec<(usize, &'a str)> {
+    &self.comments
+  }
+  pub fn peek_nth(&self, n: usize) -> Option<&Token<'a>> {
+    self.tokens.get(n).or(self.tokens.back())
+  }
+}
+
+// One `line:col Kind text` line per token, EOF included
+pub fn dump_tokens(source: &str) -> String {
+  let mut out = String::new();
+  let file = SourceFile::anonymous(source);
+  let mut lexer = Lexer::new(source);
+  while let Some(token) = lexer.next() {
+    let (line, col) = file.line_col(token.get_pos());
+    out.push_str(&format!("{}:{} {:?} {}\n", line, col, token.get_kind(), token.get_value()));
+    if token.get_kind() == TokenKind::EOF {
+      break;
+    }
+  }
+  out
+}
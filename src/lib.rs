@@ -1,10 +1,29 @@
 #![allow(dead_code)]
 
-mod utilities;
-mod ast;
-mod lexer;
-mod parser;
+pub mod utilities;
+pub mod ast;
+pub mod lexer;
+pub mod parser;
 mod subparser;
+pub mod diagnostic;
+pub mod source;
+pub mod checker;
+pub mod flow;
+pub mod fold;
+pub mod desugar;
+pub mod resolve;
+pub mod complete;
+pub mod grammar;
+pub mod interpreter;
+pub mod ir;
+pub mod backend;
+pub mod printer;
+pub mod module;
+pub mod driver;
+pub mod engine;
+pub mod repl;
+pub mod lsp;
+pub mod cli;
 mod test;
 
 #[cfg(test)]
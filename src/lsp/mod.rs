@@ -0,0 +1,507 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+use crate::ast::token::{self, Token, TokenKind};
+use crate::ast::{AstKind, AstNode};
+use crate::complete::{self, CompletionKind};
+use crate::diagnostic::{Diagnostic, Severity, Span};
+use crate::driver::{self, Analysis};
+use crate::lexer::Lexer;
+use crate::module::Program;
+use crate::source::SourceFile;
+use crate::printer;
+use crate::resolve::{DefKind, Resolution};
+use crate::utilities::json::{self, Json};
+
+// Order of the semantic token types announced in the legend
+const TOKEN_TYPES: [&str; 12] = ["keyword", "type", "function", "parameter", "variable", "property", "number", "string", "operator", "comment", "method", "enumMember"];
+
+// Language server for one client over stdio. Documents are fully synced and
+// analyzed again on every request.
+pub struct Server {
+  documents: BTreeMap<String, String>,
+  shut_down: bool,
+  exited: bool,
+}
+
+impl Default for Server {
+  fn default() -> Self {
+    Server::new()
+  }
+}
+
+impl Server {
+  pub fn new() -> Server {
+    Server {
+      documents: BTreeMap::new(),
+      shut_down: false,
+      exited: false,
+    }
+  }
+  pub fn is_exited(&self) -> bool {
+    self.exited
+  }
+  // Messages to send back for one message from the client
+  pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+    let method = message.get("method").and_then(|m| m.as_str()).unwrap_or("");
+    let params = message.get("params").cloned().unwrap_or(Json::Null);
+    match message.get("id") {
+      Some(id) if !method.is_empty() => {
+        let response = match self.request(method, &params) {
+          Ok(result) => Json::object(vec![("jsonrpc", Json::str("2.0")), ("id", id.clone()), ("result", result)]),
+          Err((code, text)) => Json::object(vec![
+            ("jsonrpc", Json::str("2.0")),
+            ("id", id.clone()),
+            ("error", Json::object(vec![("code", Json::Number(code as f64)), ("message", Json::str(&text))])),
+          ]),
+        };
+        vec![response]
+      },
+      // Answers to requests we never send
+      Some(_) => Vec::new(),
+      None => self.notification(method, &params),
+    }
+  }
+  fn request(&mut self, method: &str, params: &Json) -> Result<Json, (i64, String)> {
+    if method == "initialize" {
+      return Ok(capabilities());
+    }
+    if method == "shutdown" {
+      self.shut_down = true;
+      return Ok(Json::Null);
+    }
+    let uri = params.path(&["textDocument", "uri"]).and_then(|u| u.as_str()).unwrap_or("");
+    let program = match self.documents.get(uri) {
+      Some(source) => program(uri, source),
+      None if method.starts_with("textDocument/") => return Err((-32602, format!("unknown document `{}`", uri))),
+      None => return Err((-32601, format!("unknown method `{}`", method))),
+    };
+    let document = Document::new(&program);
+    let offset = params.get("position").and_then(|p| document.offset(p));
+    let result = match method {
+      "textDocument/hover" => offset.and_then(|offset| document.hover(offset)).unwrap_or(Json::Null),
+      "textDocument/definition" => offset.and_then(|offset| document.definition(uri, offset)).unwrap_or(Json::Null),
+      "textDocument/references" => {
+        let declaration = params.path(&["context", "includeDeclaration"]).and_then(|b| b.as_bool()).unwrap_or(true);
+        Json::Array(offset.map(|offset| document.references(uri, offset, declaration)).unwrap_or_default())
+      },
+      "textDocument/completion" => Json::Array(offset.map(|offset| document.completions(offset)).unwrap_or_default()),
+      "textDocument/documentSymbol" => Json::Array(document.symbols()),
+      "textDocument/semanticTokens/full" => Json::object(vec![("data", Json::Array(document.semantic_tokens()))]),
+      _ => return Err((-32601, format!("unknown method `{}`", method))),
+    };
+    Ok(result)
+  }
+  fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+    let uri = params.path(&["textDocument", "uri"]).and_then(|u| u.as_str()).unwrap_or("").to_string();
+    match method {
+      "textDocument/didOpen" => {
+        let text = params.path(&["textDocument", "text"]).and_then(|t| t.as_str()).unwrap_or("");
+        self.documents.insert(uri.clone(), text.to_string());
+      },
+      "textDocument/didChange" => {
+        let changes = params.get("contentChanges").and_then(|c| c.as_array());
+        let text = changes.and_then(|c| c.last()).and_then(|c| c.get("text")).and_then(|t| t.as_str());
+        if let Some(text) = text {
+          self.documents.insert(uri.clone(), text.to_string());
+        }
+      },
+      "textDocument/didClose" => {
+        self.documents.remove(&uri);
+        return vec![publish(&uri, Json::Array(Vec::new()))];
+      },
+      "exit" => {
+        self.exited = true;
+        return Vec::new();
+      },
+      _ => return Vec::new(),
+    }
+    let program = program(&uri, &self.documents[&uri]);
+    let document = Document::new(&program);
+    vec![publish(&uri, Json::Array(document.diagnostics()))]
+  }
+}
+
+fn capabilities() -> Json {
+  let legend = Json::object(vec![
+    ("tokenTypes", Json::Array(TOKEN_TYPES.iter().map(|t| Json::str(t)).collect())),
+    ("tokenModifiers", Json::Array(Vec::new())),
+  ]);
+  Json::object(vec![
+    ("capabilities", Json::object(vec![
+      ("textDocumentSync", Json::Number(1.0)),
+      ("hoverProvider", Json::Bool(true)),
+      ("definitionProvider", Json::Bool(true)),
+      ("referencesProvider", Json::Bool(true)),
+      ("completionProvider", Json::object(vec![])),
+      ("documentSymbolProvider", Json::Bool(true)),
+      ("semanticTokensProvider", Json::object(vec![("legend", legend), ("full", Json::Bool(true))])),
+    ])),
+    ("serverInfo", Json::object(vec![("name", Json::str("carf"))])),
+  ])
+}
+
+// The document as the root module of a program, its imports read from the
+// directory of a `file://` URI
+fn program(uri: &str, source: &str) -> Program {
+  Program::new(PathBuf::from(uri.strip_prefix("file://").unwrap_or(uri)), source.to_string())
+}
+
+fn publish(uri: &str, diagnostics: Json) -> Json {
+  Json::object(vec![
+    ("jsonrpc", Json::str("2.0")),
+    ("method", Json::str("textDocument/publishDiagnostics")),
+    ("params", Json::object(vec![("uri", Json::str(uri)), ("diagnostics", diagnostics)])),
+  ])
+}
+
+// 0-based line and UTF-16 column of a position
+fn line_char(file: &SourceFile, pos: usize) -> (usize, usize) {
+  let line = file.line_of(pos);
+  let start = file.line_start(line).unwrap_or(0) - file.get_base();
+  (line - 1, file.get_text()[start..file.clamp(pos) - file.get_base()].encode_utf16().count())
+}
+
+fn position(file: &SourceFile, pos: usize) -> Json {
+  let (line, character) = line_char(file, pos);
+  Json::object(vec![("line", Json::Number(line as f64)), ("character", Json::Number(character as f64))])
+}
+
+fn range(file: &SourceFile, span: Span) -> Json {
+  Json::object(vec![("start", position(file, span.start)), ("end", position(file, span.end))])
+}
+
+// One analyzed document and the conversions between byte offsets and LSP
+// positions, whose characters count UTF-16 units
+struct Document<'a> {
+  program: &'a Program,
+  file: &'a SourceFile,
+  source: &'a str,
+  analysis: Analysis<'a>,
+  resolution: Resolution<'a>,
+}
+
+impl<'a> Document<'a> {
+  fn new(program: &'a Program) -> Document<'a> {
+    let file = program.file_of(&program.modules[0]);
+    let analysis = driver::analyze_program(program);
+    let resolution = Resolution::resolve(&analysis.ast);
+    Document {
+      program,
+      file,
+      source: file.get_text(),
+      analysis,
+      resolution,
+    }
+  }
+  fn offset(&self, position: &Json) -> Option<usize> {
+    let line = position.get("line")?.as_f64()? as usize;
+    let character = position.get("character")?.as_f64()? as usize;
+    let start = self.file.line_start(line + 1)?;
+    let text = self.source[start..].split('\n').next().unwrap_or("");
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+      if units >= character {
+        return Some(start + i);
+      }
+      units += c.len_utf16();
+    }
+    Some(start + text.len())
+  }
+  fn range(&self, span: Span) -> Json {
+    range(self.file, span)
+  }
+  // A span of an imported module is shown in that module's file
+  fn location(&self, uri: &str, span: Span) -> Json {
+    if !self.in_document(span.start) {
+      let file = self.program.files.lookup(span.start);
+      let uri = format!("file://{}", file.get_path().display());
+      return Json::object(vec![("uri", Json::String(uri)), ("range", range(file, span))]);
+    }
+    Json::object(vec![("uri", Json::str(uri)), ("range", self.range(span))])
+  }
+  // Whether the position is in this document rather than a module it imports
+  fn in_document(&self, pos: usize) -> bool {
+    self.program.index_at(pos) == 0
+  }
+  fn tokens(&self) -> Vec<Token<'a>> {
+    let mut lexer = Lexer::new(self.source);
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next() {
+      if token.get_kind() == TokenKind::EOF {
+        break;
+      }
+      tokens.push(token);
+    }
+    tokens
+  }
+  // The identifier under the cursor, which may also sit just after it
+  fn identifier_at(&self, offset: usize) -> Option<Token<'a>> {
+    self.tokens().into_iter().find(|token| {
+      let (start, end) = token.get_span();
+      token.get_kind() == TokenKind::Identifier && start <= offset && offset <= end
+    })
+  }
+  fn diagnostics(&self) -> Vec<Json> {
+    self.analysis.diagnostics.iter().filter(|d| self.in_document(d.span.start)).map(|d: &Diagnostic| {
+      let severity = match d.severity {
+        Severity::Error => 1.0,
+        Severity::Warning => 2.0,
+      };
+      Json::object(vec![
+        ("range", self.range(d.span)),
+        ("severity", Json::Number(severity)),
+        ("source", Json::str("carf")),
+        ("message", Json::str(&d.message)),
+      ])
+    }).collect()
+  }
+  // Function or method whose name is at `name_pos`
+  fn fun_item(&self, name_pos: usize) -> Option<&AstNode<'a>> {
+    self.analysis.ast.get_root().get_children().iter()
+      .flat_map(|item| match item.get_kind() {
+        AstKind::Impl => item.get_children().iter().skip(1).collect(),
+        _ => vec![item],
+      })
+      .find(|item| item.get_kind() == AstKind::Fun && item.child_count() == 4 && item[0].get_token().get_pos() == name_pos)
+  }
+  // `Enum::Variant(types...)` for the variant whose name is at `name_pos`
+  fn variant_signature(&self, name_pos: usize) -> Option<String> {
+    self.analysis.ast.get_root().get_children().iter()
+      .filter(|item| item.get_kind() == AstKind::Enum && item.child_count() == 2)
+      .find_map(|item| {
+        let variant = item[1].get_children().iter().find(|v| v.get_token().get_pos() == name_pos)?;
+        let path = format!("{}::{}", item[0].get_token().get_value(), variant.get_token().get_value());
+        Some(match variant.child_count() {
+          0 => path,
+          _ => {
+            let fields: Vec<String> = variant.get_children().iter().map(printer::print_type).collect();
+            format!("{}({})", path, fields.join(", "))
+          },
+        })
+      })
+  }
+  // How a definition reads in hovers and symbol details
+  fn describe(&self, pos: usize) -> Option<String> {
+    let def = self.resolution.def_at(pos)?;
+    if matches!(def.kind, DefKind::Fun | DefKind::Method) {
+      let item = self.fun_item(def.span.start)?;
+      return Some(fun_signature(item));
+    }
+    let ty = self.analysis.checker.get_binding_type(def.span.start)
+      .filter(|ty| !ty.has_vars() && !ty.is_error())
+      .map(|ty| format!(": {}", ty))
+      .unwrap_or_default();
+    let prefix = match def.kind {
+      DefKind::Struct => return Some(format!("struct {}", def.name)),
+      DefKind::Enum => return Some(format!("enum {}", def.name)),
+      DefKind::Variant => return self.variant_signature(def.span.start),
+      DefKind::Param => "(parameter) ",
+      DefKind::LoopVar => "(loop variable) ",
+      DefKind::Field => "(field) ",
+      DefKind::Binding => "(binding) ",
+      _ => "let ",
+    };
+    Some(format!("{}{}{}", prefix, def.name, ty))
+  }
+  fn hover(&self, offset: usize) -> Option<Json> {
+    let token = self.identifier_at(offset)?;
+    let text = self.describe(token.get_pos())?;
+    let (start, end) = token.get_span();
+    Some(Json::object(vec![
+      ("contents", Json::object(vec![
+        ("kind", Json::str("markdown")),
+        ("value", Json::String(format!("```carf\n{}\n```", text))),
+      ])),
+      ("range", self.range(Span::new(start, end))),
+    ]))
+  }
+  fn definition(&self, uri: &str, offset: usize) -> Option<Json> {
+    let token = self.identifier_at(offset)?;
+    let def = self.resolution.def_at(token.get_pos())?;
+    Some(self.location(uri, def.span))
+  }
+  fn references(&self, uri: &str, offset: usize, declaration: bool) -> Vec<Json> {
+    let def = match self.identifier_at(offset).and_then(|token| self.resolution.def_at(token.get_pos())) {
+      Some(def) => *def,
+      None => return Vec::new(),
+    };
+    let mut positions = self.resolution.uses_of(def.span.start);
+    if declaration {
+      positions.push(def.span.start);
+    }
+    positions.sort();
+    positions.iter().map(|pos| self.location(uri, Span::new(*pos, pos + def.name.len()))).collect()
+  }
+  fn completions(&self, offset: usize) -> Vec<Json> {
+    complete::complete(self.source, offset).into_iter().map(|c| {
+      // CompletionItemKind of the protocol
+      let kind = match c.kind {
+        CompletionKind::Keyword => 14.0,
+        CompletionKind::Type => 7.0,
+        CompletionKind::Function => 3.0,
+        CompletionKind::Variable => 6.0,
+      };
+      let mut fields = vec![("label", Json::String(c.label)), ("kind", Json::Number(kind))];
+      if let Some(detail) = c.detail {
+        fields.push(("detail", Json::String(detail)));
+      }
+      Json::object(fields)
+    }).collect()
+  }
+  fn symbols(&self) -> Vec<Json> {
+    let closing = printer::match_braces(&mut Lexer::new(self.source));
+    let mut symbols = Vec::new();
+    let items = self.analysis.ast.get_root().get_children().iter().flat_map(|item| match item.get_kind() {
+      AstKind::Impl => item.get_children().iter().skip(1).map(|method| (method, true)).collect(),
+      _ => vec![(item, false)],
+    });
+    for (item, in_impl) in items.filter(|(item, _)| self.in_document(Span::of(item).start)) {
+      let kind = match item.get_kind() {
+        AstKind::Fun if item.child_count() == 4 && in_impl => 6.0,
+        AstKind::Fun if item.child_count() == 4 => 12.0,
+        AstKind::Let if item.child_count() >= 2 => 13.0,
+        AstKind::Struct if item.child_count() == 2 => 23.0,
+        AstKind::Enum if item.child_count() == 2 => 10.0,
+        _ => continue,
+      };
+      let name = &item[0];
+      let detail = self.describe(name.get_token().get_pos()).unwrap_or_default();
+      symbols.push(Json::object(vec![
+        ("name", Json::str(name.get_token().get_value())),
+        ("detail", Json::String(detail)),
+        ("kind", Json::Number(kind)),
+        ("range", self.range(Span::new(Span::of(item).start, printer::end_of(item, &closing)))),
+        ("selectionRange", self.range(Span::of_token(name))),
+      ]));
+    }
+    symbols
+  }
+  // Relative encoding of (line, start, length, type, modifiers) for every
+  // token and comment, in source order
+  fn semantic_tokens(&self) -> Vec<Json> {
+    let mut type_names = BTreeSet::new();
+    collect_type_names(self.analysis.ast.get_root(), &mut type_names);
+    let mut entries: Vec<(usize, usize, usize)> = Vec::new();
+    let mut prev = TokenKind::EOF;
+    let tokens = self.tokens();
+    for (i, token) in tokens.iter().enumerate() {
+      let pos = token.get_pos();
+      let kind = token.get_kind();
+      let after_dot = std::mem::replace(&mut prev, kind) == TokenKind::Dot;
+      let called = tokens.get(i + 1).is_some_and(|next| next.get_kind() == TokenKind::LParen);
+      let token_type = match kind {
+        TokenKind::Identifier if type_names.contains(&pos) => "type",
+        TokenKind::Identifier if after_dot && called => "method",
+        TokenKind::Identifier if after_dot => "property",
+        TokenKind::Identifier => match self.resolution.def_at(pos).map(|def| def.kind) {
+          Some(DefKind::Fun) => "function",
+          Some(DefKind::Method) => "method",
+          Some(DefKind::Param) => "parameter",
+          Some(DefKind::Struct | DefKind::Enum) => "type",
+          Some(DefKind::Variant) => "enumMember",
+          Some(DefKind::Field) => "property",
+          _ => "variable",
+        },
+        TokenKind::Int | TokenKind::Float => "number",
+        TokenKind::String | TokenKind::Char => "string",
+        _ if token::KEYWORD_LIST.contains(&token.get_value()) => "keyword",
+        _ if token::is_operator(kind) => "operator",
+        _ => continue,
+      };
+      let index = TOKEN_TYPES.iter().position(|t| *t == token_type).unwrap();
+      entries.push((pos, token.get_value().len(), index));
+    }
+    let comment = TOKEN_TYPES.iter().position(|t| *t == "comment").unwrap();
+    let lexer = Lexer::new(self.source);
+    entries.extend(lexer.get_comments().iter().map(|(pos, text)| (*pos, text.len(), comment)));
+    entries.sort();
+
+    let mut data = Vec::new();
+    let (mut prev_line, mut prev_char) = (0, 0);
+    for (pos, len, index) in entries {
+      let (line, character) = line_char(self.file, pos);
+      let length = self.source[pos..pos + len].encode_utf16().count();
+      let delta_char = if line == prev_line { character - prev_char } else { character };
+      for n in [line - prev_line, delta_char, length, index, 0] {
+        data.push(Json::Number(n as f64));
+      }
+      (prev_line, prev_char) = (line, character);
+    }
+    data
+  }
+}
+
+fn fun_signature(item: &AstNode) -> String {
+  let params: Vec<String> = item[1].get_children().iter()
+    .filter(|p| p.child_count() > 0)
+    .map(|p| match p[0].get_token().get_kind() {
+      TokenKind::Empty => p.get_token().get_value().to_string(),
+      _ => format!("{}: {}", p.get_token().get_value(), printer::print_type(&p[0])),
+    })
+    .collect();
+  format!("fun {}({}): {}", item[0].get_token().get_value(), params.join(", "), printer::print_type(&item[2]))
+}
+
+// Positions of the type names written in annotations
+fn collect_type_names(node: &AstNode, out: &mut BTreeSet<usize>) {
+  if node.get_kind() == AstKind::Type && node.get_token().get_kind() == TokenKind::Identifier {
+    out.insert(node.get_token().get_pos());
+  }
+  for child in node.get_children() {
+    collect_type_names(child, out);
+  }
+}
+
+// One message framed by a `Content-Length` header, None at the end of input
+pub fn read_message(input: &mut dyn BufRead) -> std::io::Result<Option<String>> {
+  let mut length = None;
+  loop {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+      return Ok(None);
+    }
+    let line = line.trim_end();
+    if line.is_empty() {
+      break;
+    }
+    if let Some((name, value)) = line.split_once(':')
+      && name.eq_ignore_ascii_case("Content-Length")
+    {
+      length = value.trim().parse::<usize>().ok();
+    }
+  }
+  let length = length.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "missing Content-Length"))?;
+  let mut body = vec![0; length];
+  input.read_exact(&mut body)?;
+  String::from_utf8(body).map(Some).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+pub fn write_message(out: &mut dyn Write, message: &Json) -> std::io::Result<()> {
+  let body = message.to_string();
+  write!(out, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+  out.flush()
+}
+
+// Serve until `exit`, the exit code is 0 when the client asked for shutdown first
+pub fn run(input: &mut dyn BufRead, out: &mut dyn Write) -> std::io::Result<i32> {
+  let mut server = Server::new();
+  while let Some(body) = read_message(input)? {
+    let replies = match json::parse(&body) {
+      Ok(message) => server.handle(&message),
+      Err(e) => vec![Json::object(vec![
+        ("jsonrpc", Json::str("2.0")),
+        ("id", Json::Null),
+        ("error", Json::object(vec![("code", Json::Number(-32700.0)), ("message", Json::String(e))])),
+      ])],
+    };
+    for reply in replies.iter() {
+      write_message(out, reply)?;
+    }
+    if server.is_exited() {
+      break;
+    }
+  }
+  Ok(if server.shut_down { 0 } else { 1 })
+}
@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::ast::token::Token;
+use crate::ast::{Ast, AstKind, AstNode};
+use crate::diagnostic::{Diagnostic, Span};
+use crate::parser::Parser;
+use crate::resolve::Resolution;
+use crate::source::{FileId, SourceFile, SourceMap};
+use crate::utilities::intern::intern;
+
+pub const EXTENSION: &str = "carf";
+
+// One source file of a program
+pub struct Module {
+  // Import path, `a::b` for `a/b.carf`, the root module is named after its file
+  pub name: String,
+  pub file: FileId,
+  // Modules this one imports
+  pub imports: Vec<usize>,
+}
+
+// A root module and every module it imports, directly or not. Import paths are
+// files under the directory of the root module.
+pub struct Program {
+  pub files: SourceMap,
+  // Module `i` is read from the `i`th file
+  pub modules: Vec<Module>,
+  // Each module after the ones it imports
+  pub order: Vec<usize>,
+  // Imports that name no file or form a cycle
+  pub diagnostics: Vec<Diagnostic>,
+  dir: PathBuf,
+}
+
+impl Program {
+  // Load what the root module at `path` imports, its own text is given
+  pub fn new(path: PathBuf, source: String) -> Program {
+    let name = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let mut program = Program {
+      files: SourceMap::new(),
+      modules: Vec::new(),
+      order: Vec::new(),
+      diagnostics: Vec::new(),
+      dir: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+    };
+    program.add(name, path, source);
+    program.visit(0, &mut Vec::new());
+    program
+  }
+  // Index of the module whose text holds the position
+  pub fn index_at(&self, pos: usize) -> usize {
+    let file = self.files.file_at(pos);
+    self.modules.iter().position(|module| module.file == file).unwrap_or(0)
+  }
+  pub fn file_of(&self, module: &Module) -> &SourceFile {
+    self.files.get(module.file)
+  }
+  // The trees of every module joined under one root, imports first. A private
+  // item of an imported module whose name another module also defines is
+  // renamed `module::name`, with every use of it in its own module.
+  pub fn parse(&self) -> Ast<'_> {
+    let trees: Vec<Ast> = self.order.iter().map(|i| {
+      let mut parser = Parser::for_file(self.file_of(&self.modules[*i]));
+      parser.parse();
+      parser.into_ast()
+    }).collect();
+    let mut owners: BTreeMap<&str, usize> = BTreeMap::new();
+    for tree in &trees {
+      for item in tree.get_root().get_children().iter().filter(|item| is_named_item(item)) {
+        *owners.entry(item[0].get_token().get_value()).or_default() += 1;
+      }
+    }
+    let mut ast = Ast::new();
+    for (i, mut tree) in self.order.iter().zip(trees) {
+      if *i != 0 {
+        let clashing: Vec<usize> = tree.get_root().get_children().iter()
+          .filter(|item| is_named_item(item) && !item.is_pub() && owners[item[0].get_token().get_value()] > 1)
+          .map(|item| item[0].get_token().get_pos())
+          .collect();
+        qualify(&mut tree, &clashing, &self.modules[*i].name);
+      }
+      for item in std::mem::take(tree.get_mut_root().get_mut_children()) {
+        ast.get_mut_root().add_node(item);
+      }
+    }
+    ast
+  }
+  fn add(&mut self, name: String, path: PathBuf, source: String) -> usize {
+    let file = self.files.add(path, source);
+    self.modules.push(Module { name, file, imports: Vec::new() });
+    self.modules.len() - 1
+  }
+  // Load what module `i` imports, depth first, `stack` holding the modules being visited
+  fn visit(&mut self, i: usize, stack: &mut Vec<usize>) {
+    stack.push(i);
+    let mut parser = Parser::for_file(self.file_of(&self.modules[i]));
+    parser.parse();
+    let imports: Vec<(Vec<String>, Span)> = parser.into_ast().get_root().get_children().iter()
+      .filter(|item| item.get_kind() == AstKind::Import && !item.get_children().iter().any(AstNode::is_bad))
+      .map(|item| (item.get_children().iter().map(|name| name.get_token().get_value().to_string()).collect(), Span::of(item)))
+      .collect();
+    for (segments, span) in imports {
+      let path = segments.iter().fold(self.dir.clone(), |path, segment| path.join(segment)).with_extension(EXTENSION);
+      let name = segments.join("::");
+      let found = self.files.find(&path).and_then(|file| self.modules.iter().position(|module| module.file == file));
+      let j = match found {
+        Some(j) if stack.contains(&j) => {
+          let at = stack.iter().position(|k| *k == j).unwrap();
+          let mut cycle: Vec<String> = stack[at..].iter().map(|k| format!("`{}`", self.modules[*k].name)).collect();
+          cycle.push(format!("`{}`", name));
+          self.diagnostics.push(Diagnostic::error(span, format!("import cycle: {}", cycle.join(" -> "))));
+          continue;
+        },
+        Some(j) => j,
+        None => match std::fs::read_to_string(&path) {
+          Ok(source) => {
+            let j = self.add(name, path, source);
+            self.visit(j, stack);
+            j
+          },
+          Err(_) => {
+            self.diagnostics.push(Diagnostic::error(span, format!("cannot find module `{}` at `{}`", name, path.display())));
+            continue;
+          },
+        },
+      };
+      if !self.modules[i].imports.contains(&j) {
+        self.modules[i].imports.push(j);
+      }
+    }
+    stack.pop();
+    self.order.push(i);
+  }
+}
+
+// Functions, globals, structs and enums, the root level items a name can refer to
+fn is_named_item(item: &AstNode) -> bool {
+  matches!(item.get_kind(), AstKind::Fun | AstKind::Let | AstKind::Struct | AstKind::Enum) && item.child_count() > 0
+}
+
+// Rename the items of one module defined at `defs`, and their uses, `module::name`
+fn qualify(tree: &mut Ast, defs: &[usize], module: &str) {
+  if defs.is_empty() {
+    return;
+  }
+  let resolution = Resolution::resolve(tree);
+  let mut names = BTreeMap::new();
+  for def in defs {
+    let Some(found) = resolution.def_at(*def) else { continue };
+    let name = intern(&format!("{}::{}", module, found.name));
+    for pos in resolution.uses_of(*def).into_iter().chain([*def]) {
+      names.insert(pos, (found.name, name));
+    }
+  }
+  rename(tree.get_mut_root(), &names);
+}
+
+fn rename(node: &mut AstNode, names: &BTreeMap<usize, (&str, &'static str)>) {
+  let token = *node.get_token();
+  if let Some((old, new)) = names.get(&token.get_pos()) && token.get_value() == *old {
+    node.set_token(Token::new(token.get_kind(), new, token.get_pos()));
+  }
+  for child in node.get_mut_children() {
+    rename(child, names);
+  }
+}
+
+// A module can use the root level items of another only when it imports that
+// module and the item is `pub`. Private items are named per module, the others
+// once in the whole program.
+pub fn check_visibility(program: &Program, ast: &Ast) -> Vec<Diagnostic> {
+  let mut items = BTreeMap::new();
+  for item in ast.get_root().get_children() {
+    if is_named_item(item) {
+      items.insert(item[0].get_token().get_pos(), item.is_pub());
+    }
+  }
+  let resolution = Resolution::resolve(ast);
+  let mut diagnostics = Vec::new();
+  for (use_pos, def_pos) in resolution.get_uses() {
+    let (Some(is_pub), Some(def)) = (items.get(&def_pos), resolution.def_at(def_pos)) else {
+      continue;
+    };
+    let (user, owner) = (program.index_at(use_pos), program.index_at(def_pos));
+    if user == owner {
+      continue;
+    }
+    let (user, owner_index, owner) = (&program.modules[user], owner, &program.modules[owner]);
+    let span = Span::new(use_pos, use_pos + def.name.len());
+    if !user.imports.contains(&owner_index) {
+      diagnostics.push(Diagnostic::error(span, format!(
+        "`{}` is defined in module `{}`, which `{}` does not import", def.name, owner.name, user.name
+      )));
+    } else if !is_pub {
+      diagnostics.push(Diagnostic::error(span, format!("`{}` is private to module `{}`", def.name, owner.name)));
+    }
+  }
+  diagnostics
+}
+
+// Items are used by their bare names, so `math::add` is only read as a variant
+// path. Report the ones whose head names an imported module rather than an enum.
+pub fn check_paths(program: &Program, ast: &Ast) -> Vec<Diagnostic> {
+  let enums: Vec<&str> = ast.get_root().get_children().iter()
+    .filter(|item| item.get_kind() == AstKind::Enum && item.child_count() > 0)
+    .map(|item| item[0].get_token().get_value())
+    .collect();
+  let mut diagnostics = Vec::new();
+  check_path(program, &enums, ast.get_root(), &mut diagnostics);
+  diagnostics
+}
+
+fn check_path(program: &Program, enums: &[&str], node: &AstNode, diagnostics: &mut Vec<Diagnostic>) {
+  if matches!(node.get_kind(), AstKind::Path | AstKind::VariantPat) && node.child_count() >= 2 {
+    let head = node[0].get_token().get_value();
+    let module = program.modules.iter().skip(1).find(|module| module.name.rsplit("::").next() == Some(head));
+    if let Some(module) = module.filter(|_| !enums.contains(&head)) {
+      diagnostics.push(Diagnostic::error(Span::of_token(&node[0]), format!(
+        "qualified access through a module is not supported, import `{}` and use `{}` by its name",
+        module.name, node[1].get_token().get_value()
+      )));
+    }
+  }
+  for child in node.get_children() {
+    check_path(program, enums, child, diagnostics);
+  }
+}
@@ -1,119 +1,194 @@
-use std::rc::Rc;
 use crate::ast::token::{Token, TokenKind};
 use crate::ast::{Ast, AstKind, AstNode};
 use crate::lexer::Lexer;
+use crate::source::SourceFile;
 use crate::subparser::{SubParser, SubParserList};
 use std::collections::BTreeMap;
 
 type ExpectList<'a> = BTreeMap<&'a str, Vec<TokenKind>>;
 type KindIdMap<'a> = BTreeMap<TokenKind, &'a str>;
+// Binding power of infix and postfix operators, and whether they are right associative
+type PrecList = BTreeMap<TokenKind, (u8, bool)>;
+
+pub const UNARY_PREC: u8 = 12;
+// Deepest the parser recurses, through parentheses, blocks, prefix operators
+// and right operands
+pub const MAX_DEPTH: usize = 1000;
+// Deepest tree the parser builds, so the passes that recurse over it cannot
+// run out of stack. It still fits the stack of a main thread, where a flat
+// chain of operators nests one level per operator.
+pub const MAX_TREE_DEPTH: usize = 2 * MAX_DEPTH;
+// Tokens that start a root level item
+pub const ITEM_KINDS: [TokenKind; 7] = [
+  TokenKind::Fun, TokenKind::Let, TokenKind::Struct, TokenKind::Enum, TokenKind::Impl, TokenKind::Import, TokenKind::Pub,
+];
+// Items that `pub` can mark
+pub const PUB_KINDS: [TokenKind; 4] = [TokenKind::Fun, TokenKind::Let, TokenKind::Struct, TokenKind::Enum];
 
 pub struct Parser<'a> {
   lexer: Lexer<'a>,
   node_stk: Vec<AstNode<'a>>,
   expect_list: ExpectList<'a>,
   kind_id_map: KindIdMap<'a>,
+  expr_id_map: KindIdMap<'a>,
+  infix_id_map: KindIdMap<'a>,
+  prec_list: PrecList,
   subparser: SubParserList<'a>,
   ast: Ast<'a>,
+  // Off while parsing the iterator of `for` and the value of `match`, where
+  // `xs {` starts the body
+  struct_literals: bool,
+  // Subparsers running inside one another
+  depth: usize,
+  // Operators applied to the operands of the expressions being parsed, each
+  // nesting the tree one level deeper without recursing
+  chained: usize,
 }
 
 impl<'a> Parser<'a> {
-  fn get_expect_list() -> ExpectList<'a> {
+  pub(crate) fn get_expect_list() -> ExpectList<'a> {
     let mut expect_list = BTreeMap::new();
     use TokenKind::*;
-    macro_rules! kind_list {
-      ($kind:expr) => {
-        {
-          let mut k = Vec::new();
-          k.push($kind);
-          k
-        }
-      };
-      ($kind:expr, $($kinds:expr),*) => {
-        {
-          let mut k = Vec::new();
-          k.push($kind);
-          k.append(&mut kind_list![$($kinds),*]);
-          k
-        }
-      };
-    }
     macro_rules! add_expect {
       ($name:literal, $($kinds:expr),+) => {
-        expect_list.insert($name, kind_list![$($kinds),+]);
+        expect_list.insert($name, vec![$($kinds),+]);
       };
     }
-    add_expect!("fun", Fun, Identifier, LParen, Union(0), RParen, Colon, Identifier, Union(1));
+    // A rule starting with Union(0) takes its left operand from the node stack
+    add_expect!("fun", Fun, Identifier, LParen, Union(0), RParen, Colon, Union(2), Union(1));
+    add_expect!("struct_def", Struct, Identifier, LStmt, Union(0), RStmt);
+    add_expect!("enum_def", Enum, Identifier, LStmt, Union(0), RStmt);
+    add_expect!("impl_def", Impl, Identifier, LStmt, Union(0), RStmt);
+    add_expect!("import_def", Import, Identifier, Union(0), SemiColon);
+    add_expect!("pub_def", Pub, Union(0));
     add_expect!("stmt_multi", LStmt, Union(0), RStmt);
-    add_expect!("stmt_let", Let, Identifier, Colon, Identifier, Equal, Union(0));
+    add_expect!("stmt_let", Let, Identifier, Union(1), Union(0), SemiColon);
     add_expect!("stmt_while", While, LParen, Union(0), RParen, Union(1));
     add_expect!("stmt_if", If, LParen, Union(0), RParen, Union(1));
     add_expect!("stmt_else", Else, Union(0));
-    add_expect!("stmt_continue", Continue);
-    add_expect!("stmt_break", Break);
-    add_expect!("stmt_return", Return);
+    add_expect!("stmt_continue", Continue, SemiColon);
+    add_expect!("stmt_break", Break, SemiColon);
+    add_expect!("stmt_return", Return, Union(0), SemiColon);
     add_expect!("stmt_for", For, Identifier, In, Union(0), Union(1));
+    add_expect!("stmt_empty", SemiColon);
     add_expect!("expr_char", Char);
-    add_expect!("expr_paren", LParen, Union(0), RParen); // TODO: Tell it is expr or function call
-    add_expect!("expr_args", Comma, Union(0));
-    add_expect!("expr_indexing", LIndex, Union(0), RIndex);
+    add_expect!("expr_paren", LParen, Union(0), RParen);
+    add_expect!("expr_call", Union(0), LParen, Union(1), RParen);
+    add_expect!("expr_args", Union(0), Comma, Union(1));
+    add_expect!("expr_indexing", Union(0), LIndex, Union(1), RIndex);
+    add_expect!("expr_array", LIndex, Union(0), RIndex);
+    add_expect!("expr_struct", Union(0), LStmt, Union(1), RStmt);
+    add_expect!("expr_path", Union(0), DColon, Identifier);
+    add_expect!("expr_match", Match, Union(0), LStmt, Union(1), RStmt);
+    add_expect!("expr_lambda", Fun, LParen, Union(0), RParen, Colon, Union(2), Union(1));
     add_expect!("expr_identifer", Identifier);
     add_expect!("expr_integer", Int);
     add_expect!("expr_float", Float);
     add_expect!("expr_string", String);
+    add_expect!("expr_true", True);
+    add_expect!("expr_false", False);
     add_expect!("expr_u_not", Not);
-    add_expect!("expr_u_dplus", DPlus);
+    add_expect!("expr_u_pre_dplus", DPlus);
+    add_expect!("expr_u_post_dplus", Union(0), DPlus);
     add_expect!("expr_u_minus", Minus);
-    add_expect!("expr_u_dminus", DMinus);
-    add_expect!("expr_b_equal", Equal);
-    add_expect!("expr_b_plus", Plus);
-    add_expect!("expr_b_minus", Minus);
-    add_expect!("expr_b_asterisk", Asterisk);
-    add_expect!("expr_b_slash", Slash);
-    add_expect!("expr_b_mod", Mod);
-    add_expect!("expr_b_pluseq", PlusEq);
-    add_expect!("expr_b_minuseq", MinusEq);
-    add_expect!("expr_b_asteriskeq", AsteriskEq);
-    add_expect!("expr_b_slasheq", SlashEq);
-    add_expect!("expr_b_modeq", ModEq);
-    add_expect!("expr_b_and", And);
-    add_expect!("expr_b_or", Or);
-    add_expect!("expr_b_xor", Xor);
-    add_expect!("expr_b_andeq", AndEq);
-    add_expect!("expr_b_oreq", OrEq);
-    add_expect!("expr_b_xoreq", XorEq);
-    add_expect!("expr_b_lshift", LShift);
-    add_expect!("expr_b_rshift", RShift);
-    add_expect!("expr_b_lshifteq", LShiftEq);
-    add_expect!("expr_b_rshifteq", RShiftEq);
-    add_expect!("expr_b_dand", DAnd);
-    add_expect!("expr_b_dor", DOr);
-    add_expect!("expr_b_dequal", DEqual);
-    add_expect!("expr_b_nequal", NEqual);
-    add_expect!("expr_b_greater", Greater);
-    add_expect!("expr_b_less", Less);
-    add_expect!("expr_b_greatereq", GreaterEq);
-    add_expect!("expr_b_lesseq", LessEq);
-    add_expect!("expr_b_dot", Dot);
-    add_expect!("expr_b_ddot", DDot);
+    add_expect!("expr_u_pre_dminus", DMinus);
+    add_expect!("expr_u_post_dminus", Union(0), DMinus);
+    add_expect!("expr_b_equal", Union(0), Equal, Union(1));
+    add_expect!("expr_b_plus", Union(0), Plus, Union(1));
+    add_expect!("expr_b_minus", Union(0), Minus, Union(1));
+    add_expect!("expr_b_asterisk", Union(0), Asterisk, Union(1));
+    add_expect!("expr_b_slash", Union(0), Slash, Union(1));
+    add_expect!("expr_b_mod", Union(0), Mod, Union(1));
+    add_expect!("expr_b_pluseq", Union(0), PlusEq, Union(1));
+    add_expect!("expr_b_minuseq", Union(0), MinusEq, Union(1));
+    add_expect!("expr_b_asteriskeq", Union(0), AsteriskEq, Union(1));
+    add_expect!("expr_b_slasheq", Union(0), SlashEq, Union(1));
+    add_expect!("expr_b_modeq", Union(0), ModEq, Union(1));
+    add_expect!("expr_b_and", Union(0), And, Union(1));
+    add_expect!("expr_b_or", Union(0), Or, Union(1));
+    add_expect!("expr_b_xor", Union(0), Xor, Union(1));
+    add_expect!("expr_b_andeq", Union(0), AndEq, Union(1));
+    add_expect!("expr_b_oreq", Union(0), OrEq, Union(1));
+    add_expect!("expr_b_xoreq", Union(0), XorEq, Union(1));
+    add_expect!("expr_b_lshift", Union(0), LShift, Union(1));
+    add_expect!("expr_b_rshift", Union(0), RShift, Union(1));
+    add_expect!("expr_b_lshifteq", Union(0), LShiftEq, Union(1));
+    add_expect!("expr_b_rshifteq", Union(0), RShiftEq, Union(1));
+    add_expect!("expr_b_dand", Union(0), DAnd, Union(1));
+    add_expect!("expr_b_dor", Union(0), DOr, Union(1));
+    add_expect!("expr_b_dequal", Union(0), DEqual, Union(1));
+    add_expect!("expr_b_nequal", Union(0), NEqual, Union(1));
+    add_expect!("expr_b_greater", Union(0), Greater, Union(1));
+    add_expect!("expr_b_less", Union(0), Less, Union(1));
+    add_expect!("expr_b_greatereq", Union(0), GreaterEq, Union(1));
+    add_expect!("expr_b_lesseq", Union(0), LessEq, Union(1));
+    add_expect!("expr_field", Union(0), Dot, Identifier);
+    add_expect!("expr_b_ddot", Union(0), DDot, Union(1));
 
     expect_list
   }
-  fn get_kind_id_map(expect_list: Rc<&ExpectList<'a>>) -> KindIdMap<'a> {
+  pub(crate) fn get_prec_list() -> PrecList {
+    let mut prec_list = BTreeMap::new();
+    use TokenKind::*;
+    macro_rules! add_prec {
+      ($prec:literal, $right:literal, $($kinds:expr),+) => {
+        $(prec_list.insert($kinds, ($prec, $right));)+
+      };
+    }
+    add_prec!(1, true, Equal, PlusEq, MinusEq, AsteriskEq, SlashEq, ModEq, AndEq, OrEq, XorEq, LShiftEq, RShiftEq);
+    add_prec!(2, false, DDot);
+    add_prec!(3, false, DOr);
+    add_prec!(4, false, DAnd);
+    add_prec!(5, false, DEqual, NEqual, Greater, Less, GreaterEq, LessEq);
+    add_prec!(6, false, Or);
+    add_prec!(7, false, Xor);
+    add_prec!(8, false, And);
+    add_prec!(9, false, LShift, RShift);
+    add_prec!(10, false, Plus, Minus);
+    add_prec!(11, false, Asterisk, Slash, Mod);
+    // UNARY_PREC sits between binary and postfix operators
+    add_prec!(13, false, LParen, LIndex, LStmt, Dot, DColon, DPlus, DMinus);
+
+    prec_list
+  }
+  // Items win over expressions that start with the same token, `fun` is a
+  // lambda only where an expression is expected
+  fn get_kind_id_map(expect_list: &ExpectList<'a>) -> KindIdMap<'a> {
     let mut kind_id_map = BTreeMap::new();
     for (id, kinds) in expect_list.iter() {
-      if kinds[0] != TokenKind::Union(0) || kinds.len() == 1 {
+      if (kinds[0] != TokenKind::Union(0) || kinds.len() == 1) && !(id.starts_with("expr_") && ITEM_KINDS.contains(&kinds[0])) {
         kind_id_map.insert(kinds[0], *id);
-      } else {
-        kind_id_map.insert(kinds[1], *id);
       }
     }
 
     kind_id_map
   }
+  fn get_expr_id_map(expect_list: &ExpectList<'a>) -> KindIdMap<'a> {
+    let mut expr_id_map = BTreeMap::new();
+    for (id, kinds) in expect_list.iter() {
+      if id.starts_with("expr_") && (kinds[0] != TokenKind::Union(0) || kinds.len() == 1) {
+        expr_id_map.insert(kinds[0], *id);
+      }
+    }
+
+    expr_id_map
+  }
+  fn get_infix_id_map(expect_list: &ExpectList<'a>) -> KindIdMap<'a> {
+    let mut infix_id_map = BTreeMap::new();
+    for (id, kinds) in expect_list.iter() {
+      if kinds[0] == TokenKind::Union(0) && kinds.len() > 1 {
+        infix_id_map.insert(kinds[1], *id);
+      }
+    }
+
+    infix_id_map
+  }
   pub fn new(lexer: Lexer<'a>) -> Parser<'a> {
     let expect_list = Self::get_expect_list();
-    let kind_id_map = Self::get_kind_id_map(Rc::new(&expect_list));
+    let kind_id_map = Self::get_kind_id_map(&expect_list);
+    let expr_id_map = Self::get_expr_id_map(&expect_list);
+    let infix_id_map = Self::get_infix_id_map(&expect_list);
+    let prec_list = Self::get_prec_list();
     let subparser = SubParser::get_subparser_list();
     let ast = Ast::new();
     let node_stk = Vec::new();
@@ -123,127 +198,239 @@ impl<'a> Parser<'a> {
       node_stk,
       expect_list,
       kind_id_map,
+      expr_id_map,
+      infix_id_map,
+      prec_list,
       subparser,
       ast,
+      struct_literals: true,
+      depth: 0,
+      chained: 0,
     }
   }
+  // Parser of a file in a SourceMap, its spans point into that file
+  pub fn for_file(file: &'a SourceFile) -> Parser<'a> {
+    Parser::new(Lexer::for_file(file))
+  }
 }
 
 impl<'a> Parser<'a> {
   pub fn parse(&mut self) -> &Ast<'a> {
     let mut new_ast = Ast::new();
-    let current = new_ast.get_mut_root();
-    loop {
-      let token_option = self.lexer.next();
-      match token_option {
-        Some(token) => {
-          match token.get_kind() {
-            TokenKind::Fun => {
-              let new_node = current.add_node(AstNode::new(token, AstKind::Fun));
-              self.subparse(&TokenKind::Fun, new_node);
-            },
-            TokenKind::Let => {
-              let new_node = current.add_node(AstNode::new(token, AstKind::Let));
-              self.subparse(&TokenKind::Let, new_node);
-            },
-            TokenKind::EOF => break,
-            _ => panic!("Invalid token (Parser::parse)"),
-          }
-        },
-        None => break,
-      }
+    while self.lexer_peek().get_kind() != TokenKind::EOF {
+      let item = self.parse_item();
+      new_ast.get_mut_root().add_node(item);
     }
     self.ast = new_ast;
 
     &self.ast
   }
-  pub fn parse_fn(&mut self, node: &mut AstNode<'a>) {
-    if !self.node_stk_empty() {
-      node.set_kind(AstKind::Bad("Found redundant Identifier or Expr"));
-      return;
+  // A root level item
+  pub fn parse_item(&mut self) -> AstNode<'a> {
+    let token = self.lexer_next();
+    match token.get_kind() {
+      kind if ITEM_KINDS.contains(&kind) => {
+        let mut node = AstNode::new(token, AstKind::Chisato);
+        if self.subparse(&kind, &mut node).is_none() {
+          self.recover_item();
+        }
+        node
+      },
+      _ => {
+        self.recover_item();
+        AstNode::new(token, AstKind::Bad("Expected an item like 'fun', 'let', 'struct', 'enum', 'impl' or 'import' at root level"))
+      },
+    }
+  }
+  // Hand the last parsed tree over to the passes that rewrite it
+  pub fn into_ast(self) -> Ast<'a> {
+    self.ast
+  }
+  pub fn parse_stmt(&mut self) -> AstNode<'a> {
+    let token = self.lexer_peek();
+    let kind = token.get_kind();
+    if kind == TokenKind::Else {
+      self.lexer_next();
+      self.recover_stmt();
+      return AstNode::new(token, AstKind::Bad("Found 'else' without 'if'"));
+    }
+    if self.kind_id_map.get(&kind).is_some_and(|id| id.starts_with("stmt_")) {
+      self.lexer_next();
+      let mut node = AstNode::new(token, AstKind::Chisato);
+      if self.subparse(&kind, &mut node).is_none() {
+        self.recover_stmt();
+      }
+      return node;
     }
-    let current = node;
+    // Expression statement
+    let expr = match self.parse_expr(0) {
+      Ok(expr) => expr,
+      Err(expr) => {
+        self.recover_stmt();
+        return expr;
+      },
+    };
+    let token = self.lexer_peek();
+    if token.get_kind() != TokenKind::SemiColon {
+      let mut node = AstNode::new(token, AstKind::Bad("Expected ';'"));
+      node.add_node(expr);
+      self.recover_stmt();
+      return node;
+    }
+    self.lexer_next();
+    let mut node = AstNode::new(token, AstKind::Stmt);
+    node.add_node(expr);
+
+    node
+  }
+  // Parse an expression whose infix operators bind at least as tight as `min_prec`.
+  // Operands live on node_stk while the operator subparsers combine them.
+  pub fn parse_expr(&mut self, min_prec: u8) -> Result<AstNode<'a>, AstNode<'a>> {
+    let chained = self.chained;
+    let result = self.parse_operators(min_prec);
+    self.chained = chained;
+    result
+  }
+  // Each operator nests the operand before it one level deeper
+  fn parse_operators(&mut self, min_prec: u8) -> Result<AstNode<'a>, AstNode<'a>> {
+    let base = self.node_stk.len();
     loop {
-      let token_option = self.lexer.next();
-      match token_option {
-        Some(token) => {
-          let kind = token.get_kind();
-          // Check if it is a stmt now
-          match kind {
-            TokenKind::EOF => {
-              current.set_kind(AstKind::Bad("Expected some token but reached EOF"));
-              return;
-            },
-            TokenKind::Bad(msg) => {
-              current.add_node(AstNode::new(token, AstKind::Bad(msg)));
-              return;
-            },
-            TokenKind::SemiColon => {
-              current.set_kind(AstKind::Stmt);
-              // TODO: check stmt is valid in root level
-              while !self.node_stk_empty() {
-                current.add_node(self.pop_node().unwrap());
-              }
-              return;
-            }
-            _ => {},
-          };
-          // If not a stmt or expr keep parsing
-          let new_node = current.add_node(AstNode::new(token, AstKind::Chisato));
-          match self.subparse(&kind, new_node) {
-            Some(kind) => {
-              match kind {
-                AstKind::PushToStk => {
-                  let node = match current.pop_node() {
-                    Some(node) => node,
-                    None => panic!("Parser::parse_fn(): new_node did not added but popped")
-                  };
-                  self.node_stk.push(node);
-                  return;
-                },
-                AstKind::GrapArgs => {
-                  current.pop_node();
-                  while !self.node_stk_empty() {
-                    current.add_node(self.pop_node().unwrap());
-                  }
-                  current.set_kind(AstKind::Args);
-                }
-                _ => {
-                  current.set_kind(kind);
-                }
-              };
-            },
-            None => {},
-          };
-          // If current node a ExprLike
-          match current.get_kind() {
-            AstKind::Expr | AstKind::Call | AstKind::BinOper | AstKind::Args | Astkind::ExprOrCall => return,
-            _ => {},
-          };
+      let token = self.lexer_peek();
+      let kind = token.get_kind();
+      let id = if self.node_stk.len() == base {
+        match self.expr_id_map.get(&kind) {
+          Some(id) => *id,
+          _ => {
+            self.node_stk.truncate(base);
+            let msg = match kind {
+              TokenKind::Bad(msg) => msg,
+              _ => "Expected Expr or Identifier",
+            };
+            return Err(AstNode::new(token, AstKind::Bad(msg)));
+          },
+        }
+      } else {
+        match (self.infix_id_map.get(&kind), self.prec_list.get(&kind)) {
+          _ if kind == TokenKind::LStmt && !self.struct_literals => break,
+          (Some(id), Some((prec, _))) if *prec >= min_prec => *id,
+          _ => break,
+        }
+      };
+      if self.node_stk.len() != base {
+        self.chained += 1;
+      }
+      self.lexer_next();
+      let mut node = AstNode::new(token, AstKind::Chisato);
+      match self.subparse_id(id, &mut node) {
+        Some(AstKind::PushToStk) => self.node_stk.push(node),
+        _ => {
+          self.node_stk.truncate(base);
+          return Err(node);
         },
-        None => break,
+      }
+    }
+    match self.node_stk.pop() {
+      Some(node) if self.node_stk.len() == base => Ok(node),
+      _ => panic!("Parser::parse_expr(): node_stk is unbalanced"),
+    }
+  }
+  // Skip to the start of the next root level item
+  fn recover_item(&mut self) {
+    loop {
+      match self.lexer_peek().get_kind() {
+        kind if kind == TokenKind::EOF || ITEM_KINDS.contains(&kind) => return,
+        _ => { self.lexer_next(); },
       }
     }
   }
+  // Skip past the end of the current statement, keeping braces balanced
+  fn recover_stmt(&mut self) {
+    let mut depth = 0_usize;
+    loop {
+      match self.lexer_peek().get_kind() {
+        TokenKind::EOF => return,
+        TokenKind::SemiColon if depth == 0 => {
+          self.lexer_next();
+          return;
+        },
+        TokenKind::RStmt if depth == 0 => return,
+        TokenKind::RStmt => depth -= 1,
+        TokenKind::LStmt => depth += 1,
+        _ => {},
+      }
+      self.lexer_next();
+    }
+  }
 }
 
 impl<'a> Parser<'a> {
   pub fn get_expect(&self, id: &str) -> Vec<TokenKind> {
     self.expect_list.get(id).unwrap().clone()
   }
+  // Whether an expression cannot end with this token, like a binary or prefix
+  // operator, where `++` and `--` can also end one as postfix operators
+  pub fn expects_operand_after(&self, kind: &TokenKind) -> bool {
+    let binary = self.infix_id_map.get(kind).is_some_and(|id| self.expect_list[id].len() == 3);
+    let prefix = self.expr_id_map.get(kind).is_some_and(|id| id.starts_with("expr_u_"));
+    let postfix = self.infix_id_map.get(kind).is_some_and(|id| self.expect_list[id].len() == 2);
+    binary || (prefix && !postfix)
+  }
+  // Whether a statement or an expression can start with this token
+  pub fn starts_stmt(&self, kind: &TokenKind) -> bool {
+    self.kind_id_map.get(kind).is_some_and(|id| id.starts_with("stmt_"))
+  }
+  pub fn starts_expr(&self, kind: &TokenKind) -> bool {
+    self.expr_id_map.contains_key(kind)
+  }
+  // Whether this token is a whole expression by itself, like `true` or a name
+  pub fn is_atom(&self, kind: &TokenKind) -> bool {
+    self.expr_id_map.get(kind).is_some_and(|id| self.expect_list[id].len() == 1)
+  }
   pub fn get_kind_id(&self, kind: &TokenKind) -> &'a str {
     self.kind_id_map.get(kind).unwrap()
   }
+  // Binding power of an infix or postfix operator, and whether it is right associative
+  pub fn get_prec(&self, kind: &TokenKind) -> Option<(u8, bool)> {
+    self.prec_list.get(kind).copied()
+  }
+  // Minimum binding power for the right operand of an infix operator
+  pub fn get_rhs_prec(&self, kind: &TokenKind) -> u8 {
+    match self.prec_list.get(kind) {
+      Some((prec, true)) => *prec,
+      Some((prec, false)) => prec + 1,
+      None => panic!("Parser::get_rhs_prec(): {:?} is not an infix operator", kind),
+    }
+  }
+  // Whether `{` after a name opens a struct literal, returning the previous setting
+  pub fn set_struct_literals(&mut self, on: bool) -> bool {
+    std::mem::replace(&mut self.struct_literals, on)
+  }
   pub fn subparse(&mut self, id: &TokenKind, node: &mut AstNode<'a>) -> Option<AstKind> {
     let id = *self.kind_id_map.get(id).unwrap();
+    self.subparse_id(id, node)
+  }
+  pub fn subparse_id(&mut self, id: &str, node: &mut AstNode<'a>) -> Option<AstKind> {
+    if self.depth >= MAX_DEPTH || self.depth + self.chained >= MAX_TREE_DEPTH {
+      node.set_kind(AstKind::Bad("Nesting is too deep"));
+      return None;
+    }
     let subparser = *self.subparser.get(id).unwrap();
-    subparser.parse(self, node)
+    self.depth += 1;
+    let kind = subparser.parse(self, node);
+    self.depth -= 1;
+    kind
   }
-  pub fn lexer_next(&mut self) -> Option<Token<'a>> {
-    self.lexer.next()
+  pub fn lexer_next(&mut self) -> Token<'a> {
+    match self.lexer.next() {
+      Some(token) => token,
+      None => panic!("Parser::lexer_next(): lexer returned no EOF"),
+    }
   }
-  pub fn lexer_peek(&mut self) -> Option<&Token<'a>> {
-    self.lexer.peek()
+  pub fn lexer_peek(&self) -> Token<'a> {
+    match self.lexer.peek() {
+      Some(token) => *token,
+      None => panic!("Parser::lexer_peek(): lexer returned no EOF"),
+    }
   }
   pub fn push_node(&mut self, token: AstNode<'a>) {
     self.node_stk.push(token);
@@ -260,7 +447,7 @@ impl<'a> Parser<'a> {
 impl<'a> Parser<'a> {
   pub fn expose_kind_id_map() -> KindIdMap<'a> {
     let expect_list = Self::get_expect_list();
-    Self::get_kind_id_map(Rc::new(&expect_list))
+    Self::get_kind_id_map(&expect_list)
   }
   pub fn expose_expect_list() -> ExpectList<'a> {
     Self::get_expect_list()
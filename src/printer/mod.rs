@@ -0,0 +1,475 @@
+use std::collections::BTreeMap;
+
+use crate::ast::token::TokenKind;
+use crate::ast::{Ast, AstKind, AstNode};
+use crate::diagnostic::{self, Diagnostic, Span};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+const INDENT: &str = "  ";
+
+// Canonical layout of a source file, keeping its comments and single blank
+// lines. Fails with the parse errors when the source does not parse.
+pub fn format_source(source: &str) -> Result<String, Vec<Diagnostic>> {
+  let mut lexer = Lexer::new(source);
+  let comments = lexer.get_comments().clone();
+  let closing = match_braces(&mut lexer);
+  let mut parser = Parser::new(Lexer::new(source));
+  let ast = parser.parse();
+  let errors = diagnostic::parse_errors(ast);
+  if !errors.is_empty() {
+    return Err(errors);
+  }
+  let mut printer = Printer::new(source, comments, closing);
+  printer.print_root(ast.get_root());
+  Ok(printer.out)
+}
+
+// Source text of a tree that has no source of its own, like one rewritten by a pass
+pub fn print_ast(ast: &Ast) -> String {
+  let mut printer = Printer::new("", Vec::new(), BTreeMap::new());
+  printer.print_root(ast.get_root());
+  printer.out
+}
+
+// Source text of an expression, on one line unless it holds a lambda
+pub fn print_expr(node: &AstNode) -> String {
+  let token = node.get_token();
+  let op = token.get_value();
+  match node.get_kind() {
+    AstKind::Literal | AstKind::Identifier => op.to_string(),
+    AstKind::Expr if node.child_count() > 0 => format!("({})", print_expr(&node[0])),
+    AstKind::UnOper if node.child_count() > 0 => {
+      let operand = print_expr(&node[0]);
+      // `- -x` must not turn into `--x`
+      match operand.starts_with(op) {
+        true => format!("{} {}", op, operand),
+        false => format!("{}{}", op, operand),
+      }
+    },
+    AstKind::PreStep if node.child_count() > 0 => format!("{}{}", op, print_expr(&node[0])),
+    AstKind::PostStep if node.child_count() > 0 => format!("{}{}", print_expr(&node[0]), op),
+    AstKind::BinOper if node.child_count() == 2 => match token.get_kind() {
+      TokenKind::DDot => format!("{}{}{}", print_expr(&node[0]), op, print_expr(&node[1])),
+      _ => format!("{} {} {}", print_expr(&node[0]), op, print_expr(&node[1])),
+    },
+    AstKind::Call if node.child_count() == 2 => {
+      let args: Vec<String> = node[1].get_children().iter().map(print_expr).collect();
+      format!("{}({})", print_expr(&node[0]), args.join(", "))
+    },
+    AstKind::Index if node.child_count() == 2 => format!("{}[{}]", print_expr(&node[0]), print_expr(&node[1])),
+    AstKind::Array => {
+      let items: Vec<String> = node.get_children().iter().map(print_expr).collect();
+      format!("[{}]", items.join(", "))
+    },
+    AstKind::StructLit if node.child_count() > 0 => {
+      let inits: Vec<String> = node.get_children().iter().skip(1)
+        .map(|init| format!("{}: {}", init.get_token().get_value(), init.get_children().first().map(print_expr).unwrap_or_default()))
+        .collect();
+      match inits.is_empty() {
+        true => format!("{} {{}}", print_expr(&node[0])),
+        false => format!("{} {{ {} }}", print_expr(&node[0]), inits.join(", ")),
+      }
+    },
+    AstKind::MethodCall if node.child_count() == 3 => {
+      let args: Vec<String> = node[2].get_children().iter().map(print_expr).collect();
+      format!("{}.{}({})", print_expr(&node[0]), print_expr(&node[1]), args.join(", "))
+    },
+    AstKind::Path if node.child_count() == 2 => format!("{}::{}", print_expr(&node[0]), print_expr(&node[1])),
+    AstKind::Match if node.child_count() > 0 => {
+      let arms: Vec<String> = node.get_children().iter().skip(1)
+        .filter(|arm| arm.child_count() == 2)
+        .map(|arm| format!("{} => {}", print_pattern(&arm[0]), print_expr(&arm[1])))
+        .collect();
+      match arms.is_empty() {
+        true => format!("match {} {{}}", print_expr(&node[0])),
+        false => format!("match {} {{ {} }}", print_expr(&node[0]), arms.join(", ")),
+      }
+    },
+    AstKind::Member if node.child_count() == 2 => format!("{}.{}", print_expr(&node[0]), print_expr(&node[1])),
+    AstKind::Repeat if node.child_count() == 2 => format!("[{}; {}]", print_expr(&node[0]), print_expr(&node[1])),
+    // The body is laid out from column 0, the caller indents its lines
+    AstKind::Lambda if node.child_count() == 3 => {
+      let mut printer = Printer::new("", Vec::new(), BTreeMap::new());
+      printer.print_block(&node[2]);
+      format!("fun({}): {} {}", print_params(&node[0]), print_type(&node[1]), printer.out)
+    },
+    _ => op.to_string(),
+  }
+}
+
+// Source text of a `match` pattern
+pub fn print_pattern(node: &AstNode) -> String {
+  match node.get_kind() {
+    AstKind::RangePat if node.child_count() == 2 => format!("{}..{}", print_pattern(&node[0]), print_pattern(&node[1])),
+    AstKind::VariantPat if node.child_count() >= 2 => {
+      let path = format!("{}::{}", node[0].get_token().get_value(), node[1].get_token().get_value());
+      match node.child_count() {
+        2 => path,
+        _ => {
+          let fields: Vec<String> = node.get_children()[2..].iter().map(print_pattern).collect();
+          format!("{}({})", path, fields.join(", "))
+        },
+      }
+    },
+    _ => print_expr(node),
+  }
+}
+
+// Source text of a Type node, like `int`, `[[char]; 4]` or `fun(int): bool`
+pub fn print_type(node: &AstNode) -> String {
+  if node.get_token().get_kind() == TokenKind::Fun && node.child_count() > 0 {
+    let (ret, params) = node.get_children().split_last().unwrap();
+    let params: Vec<String> = params.iter().map(print_type).collect();
+    return format!("fun({}): {}", params.join(", "), print_type(ret));
+  }
+  match node.child_count() {
+    0 => node.get_token().get_value().to_string(),
+    1 => format!("[{}]", print_type(&node[0])),
+    _ => format!("[{}; {}]", print_type(&node[0]), node[1].get_token().get_value()),
+  }
+}
+
+// `name: Type, ...` of a function, a `self` receiver stays bare
+fn print_params(node: &AstNode) -> String {
+  let params: Vec<String> = node.get_children().iter()
+    .map(|p| match p[0].get_token().get_kind() {
+      TokenKind::Empty => p.get_token().get_value().to_string(),
+      _ => format!("{}: {}", p.get_token().get_value(), print_type(&p[0])),
+    })
+    .collect();
+  params.join(", ")
+}
+
+// Position of the `}` closing each `{`
+pub fn match_braces(lexer: &mut Lexer) -> BTreeMap<usize, usize> {
+  let mut closing = BTreeMap::new();
+  let mut open = Vec::new();
+  while let Some(token) = lexer.next() {
+    match token.get_kind() {
+      TokenKind::LStmt => open.push(token.get_pos()),
+      TokenKind::RStmt => {
+        if let Some(start) = open.pop() {
+          closing.insert(start, token.get_pos());
+        }
+      },
+      TokenKind::EOF => break,
+      _ => {},
+    }
+  }
+  closing
+}
+
+// End of the source of a node, past the `}` of any block it holds. The tree
+// keeps no closing braces, so `Span::of` stops before them.
+pub fn end_of(node: &AstNode, closing: &BTreeMap<usize, usize>) -> usize {
+  let span = Span::of(node);
+  closing.range(span.start..span.end).map(|(_, close)| close + 1).fold(span.end, usize::max)
+}
+
+struct Printer<'s> {
+  comments: Vec<(usize, &'s str)>,
+  next_comment: usize,
+  closing: BTreeMap<usize, usize>,
+  line_starts: Vec<usize>,
+  out: String,
+  indent: usize,
+  // Source line where the last printed code or comment ends
+  last_line: Option<usize>,
+  // Nothing but trailing comments printed since the last `{`
+  block_start: bool,
+}
+
+impl<'s> Printer<'s> {
+  fn new(source: &str, comments: Vec<(usize, &'s str)>, closing: BTreeMap<usize, usize>) -> Printer<'s> {
+    let mut line_starts = vec![0];
+    line_starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+    Printer {
+      comments,
+      next_comment: 0,
+      closing,
+      line_starts,
+      out: String::new(),
+      indent: 0,
+      last_line: None,
+      block_start: false,
+    }
+  }
+  fn line_of(&self, pos: usize) -> usize {
+    self.line_starts.partition_point(|&start| start <= pos)
+  }
+  fn write_indent(&mut self) {
+    self.block_start = false;
+    self.out.push_str(&INDENT.repeat(self.indent));
+  }
+  // Keep one blank line where the source had at least one
+  fn separate(&mut self, pos: usize) {
+    let after_gap = self.last_line.is_some_and(|last| self.line_of(pos) > last + 1);
+    if after_gap && !self.out.is_empty() && !self.out.ends_with("\n\n") && !self.block_start {
+      self.out.push('\n');
+    }
+  }
+  // Print the comments before `pos`, a comment on the line of the last code stays on that line
+  fn flush_comments(&mut self, pos: usize, trailing_only: bool) {
+    while let Some(&(start, text)) = self.comments.get(self.next_comment) {
+      let line = self.line_of(start);
+      let trailing = self.last_line == Some(line) && self.out.ends_with('\n') && !self.out.ends_with("\n\n");
+      if start >= pos || (trailing_only && !trailing) {
+        break;
+      }
+      self.next_comment += 1;
+      if trailing {
+        self.out.pop();
+        self.out.push(' ');
+      } else {
+        self.separate(start);
+        self.write_indent();
+      }
+      self.out.push_str(text);
+      self.out.push('\n');
+      self.last_line = Some(line);
+    }
+  }
+  // An expression whose lambdas span several lines continues at the current indent
+  fn expr(&self, node: &AstNode) -> String {
+    print_expr(node).replace('\n', &format!("\n{}", INDENT.repeat(self.indent)))
+  }
+  // Start the line of a statement or item found at `pos`
+  fn begin(&mut self, pos: usize) {
+    self.flush_comments(pos, false);
+    self.separate(pos);
+    self.write_indent();
+  }
+  fn print_root(&mut self, root: &AstNode) {
+    let mut prev_apart = false;
+    for (i, item) in root.get_children().iter().enumerate() {
+      let apart = matches!(item.get_kind(), AstKind::Fun | AstKind::Struct | AstKind::Enum | AstKind::Impl);
+      self.flush_comments(item.get_token().get_pos(), true);
+      // Functions, types and impls always stand apart from their neighbours
+      if i > 0 && (apart || prev_apart) && !self.out.ends_with("\n\n") {
+        self.out.push('\n');
+      }
+      self.begin(item.get_token().get_pos());
+      if item.is_pub() {
+        self.out.push_str("pub ");
+      }
+      match item.get_kind() {
+        AstKind::Import => {
+          let path: Vec<&str> = item.get_children().iter().map(|name| name.get_token().get_value()).collect();
+          self.out.push_str(&format!("import {};\n", path.join("::")));
+        },
+        AstKind::Fun => self.print_fun(item),
+        AstKind::Struct => self.print_struct(item),
+        AstKind::Enum => self.print_enum(item),
+        AstKind::Impl => self.print_impl(item),
+        _ => self.print_stmt(item),
+      }
+      prev_apart = apart;
+    }
+    self.flush_comments(usize::MAX, false);
+  }
+  fn print_fun(&mut self, node: &AstNode) {
+    self.out.push_str(&format!(
+      "fun {}({}): {} ", node[0].get_token().get_value(), print_params(&node[1]), print_type(&node[2])
+    ));
+    self.print_block(&node[3]);
+    self.out.push('\n');
+  }
+  fn print_struct(&mut self, node: &AstNode) {
+    let fields = node[1].get_children().iter()
+      .map(|field| (field, format!("{}: {}", field.get_token().get_value(), print_type(&field[0]))))
+      .collect();
+    self.print_decl(node, fields);
+  }
+  fn print_enum(&mut self, node: &AstNode) {
+    let variants = node[1].get_children().iter().map(|variant| {
+      let name = variant.get_token().get_value();
+      let text = match variant.child_count() {
+        0 => name.to_string(),
+        _ => {
+          let fields: Vec<String> = variant.get_children().iter().map(print_type).collect();
+          format!("{}({})", name, fields.join(", "))
+        },
+      };
+      (variant, text)
+    }).collect();
+    self.print_decl(node, variants);
+  }
+  // A `struct` or `enum` with one member per line, each with its `,`
+  fn print_decl(&mut self, node: &AstNode, members: Vec<(&AstNode, String)>) {
+    let keyword = match node.get_kind() {
+      AstKind::Struct => "struct",
+      _ => "enum",
+    };
+    self.out.push_str(&format!("{} {} {{", keyword, node[0].get_token().get_value()));
+    let pos = node[1].get_token().get_pos();
+    let close = self.closing.get(&pos).copied();
+    self.last_line = Some(self.line_of(pos));
+    let has_comments = close.is_some_and(|close| self.comments.get(self.next_comment).is_some_and(|c| c.0 < close));
+    if members.is_empty() && !has_comments {
+      self.out.push_str("}\n");
+      return;
+    }
+    self.out.push('\n');
+    self.block_start = true;
+    self.indent += 1;
+    for (member, text) in members {
+      self.begin(member.get_token().get_pos());
+      self.out.push_str(&text);
+      self.out.push(',');
+      self.last_line = Some(self.line_of(Span::of(member).end));
+      self.out.push('\n');
+    }
+    if let Some(close) = close {
+      self.flush_comments(close, false);
+    }
+    self.indent -= 1;
+    self.write_indent();
+    self.out.push_str("}\n");
+    self.last_line = Some(self.line_of(close.unwrap_or(Span::of(node).end)));
+  }
+  // Methods separated by a blank line, like functions at root level
+  fn print_impl(&mut self, node: &AstNode) {
+    self.out.push_str(&format!("impl {} {{", node[0].get_token().get_value()));
+    let open = node[0].get_token().get_pos();
+    let close = self.closing.range(open..).next().map(|(_, close)| *close);
+    self.last_line = Some(self.line_of(open));
+    let methods = &node.get_children()[1..];
+    let has_comments = close.is_some_and(|close| self.comments.get(self.next_comment).is_some_and(|c| c.0 < close));
+    if methods.is_empty() && !has_comments {
+      self.out.push_str("}\n");
+      return;
+    }
+    self.out.push('\n');
+    self.block_start = true;
+    self.indent += 1;
+    for (i, method) in methods.iter().enumerate() {
+      self.flush_comments(method.get_token().get_pos(), true);
+      if i > 0 && !self.out.ends_with("\n\n") {
+        self.out.push('\n');
+      }
+      self.begin(method.get_token().get_pos());
+      self.print_fun(method);
+    }
+    if let Some(close) = close {
+      self.flush_comments(close, false);
+    }
+    self.indent -= 1;
+    self.write_indent();
+    self.out.push_str("}\n");
+    self.last_line = Some(self.line_of(close.unwrap_or(Span::of(node).end)));
+  }
+  fn print_block(&mut self, node: &AstNode) {
+    let pos = node.get_token().get_pos();
+    let close = self.closing.get(&pos).copied();
+    self.out.push('{');
+    self.last_line = Some(self.line_of(pos));
+    let has_comments = close.is_some_and(|close| self.comments.get(self.next_comment).is_some_and(|c| c.0 < close));
+    if node.child_count() == 0 && !has_comments {
+      self.out.push('}');
+      return;
+    }
+    self.out.push('\n');
+    self.block_start = true;
+    self.indent += 1;
+    for stmt in node.get_children() {
+      self.begin(stmt.get_token().get_pos());
+      self.print_stmt(stmt);
+    }
+    if let Some(close) = close {
+      self.flush_comments(close, false);
+    }
+    self.indent -= 1;
+    self.write_indent();
+    self.out.push('}');
+    self.last_line = Some(self.line_of(close.unwrap_or(Span::of(node).end)));
+  }
+  // The body of `if`, `else`, `while` and `for`, a block stays on the line of its keyword
+  fn print_body(&mut self, node: &AstNode) {
+    if node.get_kind() == AstKind::Block {
+      self.print_block(node);
+      return;
+    }
+    if self.out.ends_with(' ') {
+      self.out.pop();
+    }
+    self.out.push('\n');
+    self.indent += 1;
+    self.begin(node.get_token().get_pos());
+    self.print_stmt(node);
+    self.indent -= 1;
+  }
+  // Print a statement from the current column up to and including its newline
+  fn print_stmt(&mut self, node: &AstNode) {
+    self.last_line = Some(self.line_of(Span::of(node).start));
+    match node.get_kind() {
+      AstKind::Let => {
+        self.out.push_str("let ");
+        self.out.push_str(node[0].get_token().get_value());
+        if node[1].get_token().get_kind() != TokenKind::Empty {
+          self.out.push_str(": ");
+          self.out.push_str(&print_type(&node[1]));
+        }
+        if node.child_count() > 2 {
+          self.out.push_str(" = ");
+          self.out.push_str(&self.expr(&node[2]));
+        }
+        self.out.push(';');
+      },
+      AstKind::Stmt => {
+        if node.child_count() > 0 {
+          self.out.push_str(&self.expr(&node[0]));
+        }
+        self.out.push(';');
+      },
+      AstKind::Block => self.print_block(node),
+      AstKind::If => {
+        self.out.push_str(&format!("if ({}) ", self.expr(&node[0])));
+        self.print_body(&node[1]);
+        if node.child_count() > 2 && node[2].child_count() > 0 {
+          // Comments after the body stay before `else`, which then starts a line
+          let else_pos = node[2].get_token().get_pos();
+          if self.comments.get(self.next_comment).is_some_and(|c| c.0 < else_pos) {
+            if self.out.ends_with('}') {
+              self.out.push('\n');
+            }
+            self.flush_comments(else_pos, false);
+          }
+          match self.out.ends_with('}') {
+            true => self.out.push(' '),
+            false => self.write_indent(),
+          }
+          let body = &node[2][0];
+          self.out.push_str("else ");
+          self.last_line = Some(self.line_of(node[2].get_token().get_pos()));
+          match body.get_kind() {
+            AstKind::If => self.print_stmt(body),
+            _ => self.print_body(body),
+          }
+        }
+      },
+      AstKind::While => {
+        self.out.push_str(&format!("while ({}) ", self.expr(&node[0])));
+        self.print_body(&node[1]);
+      },
+      AstKind::For => {
+        self.out.push_str(&format!("for {} in {} ", node[0].get_token().get_value(), self.expr(&node[1])));
+        self.print_body(&node[2]);
+      },
+      AstKind::Return if node.child_count() > 0 => {
+        self.out.push_str(&format!("return {};", self.expr(&node[0])));
+      },
+      _ => {
+        self.out.push_str(node.get_token().get_value());
+        self.out.push(';');
+      },
+    }
+    if self.out.ends_with('\n') {
+      return;
+    }
+    if !self.out.ends_with('}') {
+      self.last_line = Some(self.line_of(end_of(node, &self.closing)));
+    }
+    self.out.push('\n');
+  }
+}
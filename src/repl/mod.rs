@@ -0,0 +1,270 @@
+use std::cell::RefCell;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+use crate::ast::token::TokenKind;
+use crate::ast::{Ast, AstKind, AstNode};
+use crate::checker::Checker;
+use crate::diagnostic::{self, Diagnostic, Span};
+use crate::flow;
+use crate::interpreter::{Interpreter, prelude::Console, value::Value};
+use crate::lexer::{self, Lexer};
+use crate::parser::{Parser, ITEM_KINDS};
+use crate::source::SourceFile;
+use crate::utilities::intern::intern;
+
+const HELP: &str = "\
+Enter `fun`, `let`, `struct`, `enum` and `impl` items, statements or bare expressions.
+Modules cannot be imported here, and `read_line` finds no input.
+  :type <expr>    show the type of an expression without running it
+  :ast <code>     dump the syntax tree of some code
+  :tokens <code>  dump the tokens of some code
+  :help           show this message
+  :quit           leave";
+
+// State kept from one entry to the next. Accepted entries are referenced by
+// the checker and the interpreter for the rest of the session, so their text
+// and trees live as long as the process.
+pub struct Session {
+  checker: Checker<'static>,
+  interpreter: Interpreter<'static, 'static>,
+  printed: Printed,
+}
+
+// What `print` wrote during an entry, shown before the entry's value
+#[derive(Clone, Default)]
+struct Printed(Rc<RefCell<Vec<u8>>>);
+
+impl Printed {
+  fn take(&self) -> String {
+    String::from_utf8_lossy(&std::mem::take(&mut *self.0.borrow_mut())).to_string()
+  }
+}
+
+impl Write for Printed {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    self.0.borrow_mut().write(buf)
+  }
+  fn flush(&mut self) -> std::io::Result<()> {
+    Ok(())
+  }
+}
+
+impl Default for Session {
+  fn default() -> Self {
+    Session::new()
+  }
+}
+
+impl Session {
+  pub fn new() -> Session {
+    let printed = Printed::default();
+    let mut interpreter = Interpreter::empty();
+    interpreter.set_console(Console::new(Box::new(std::io::empty()), Box::new(printed.clone())));
+    Session {
+      checker: Checker::new(),
+      interpreter,
+      printed,
+    }
+  }
+  // Output of one complete entry: printed values, diagnostics or a meta-command's answer
+  pub fn eval(&mut self, input: &str) -> String {
+    let trimmed = input.trim();
+    if let Some(meta) = trimmed.strip_prefix(':') {
+      let (command, arg) = meta.split_once(char::is_whitespace).unwrap_or((meta, ""));
+      return self.meta(command, arg.trim());
+    }
+    let source = intern(&complete_stmt(input));
+    let mut out = String::new();
+    let mut ast = parse_entry(source);
+    if !report(source, &diagnostic::parse_errors(&ast), &mut out) {
+      return out;
+    }
+    for node in std::mem::take(ast.get_mut_root().get_mut_children()) {
+      let ok = match node.get_kind() {
+        AstKind::Fun | AstKind::Let | AstKind::Struct | AstKind::Enum | AstKind::Impl => self.eval_item(source, node, &mut out),
+        // A session has no file to find modules next to
+        AstKind::Import => report(source, &[Diagnostic::error(Span::of(&node), "`import` is not supported in the REPL".to_string())], &mut out),
+        _ => self.eval_stmt(source, node, &mut out),
+      };
+      if !ok {
+        break;
+      }
+    }
+    out
+  }
+  fn eval_item(&mut self, source: &str, node: AstNode<'static>, out: &mut String) -> bool {
+    let mut ast = Ast::new();
+    ast.get_mut_root().add_node(node);
+    let mut trial = self.checker.clone();
+    if ast[0].get_kind() == AstKind::Fun {
+      trial.forget_fun(ast[0][0].get_token().get_value());
+    }
+    if ast[0].get_kind() == AstKind::Impl && ast[0].child_count() > 0 {
+      let ty = ast[0][0].get_token().get_value();
+      for method in ast[0].get_children().iter().skip(1).filter(|m| m.child_count() > 0) {
+        trial.forget_method(ty, method[0].get_token().get_value());
+      }
+    }
+    let mut diagnostics = trial.check(&ast).clone();
+    diagnostics.extend(flow::check_flow(&ast));
+    if !report(source, &diagnostics, out) {
+      return false;
+    }
+    self.checker = trial;
+    let ast: &'static Ast<'static> = Box::leak(Box::new(ast));
+    let result = self.interpreter.define(&ast[0]);
+    out.push_str(&self.printed.take());
+    match result {
+      Ok(()) => true,
+      Err(error) => report(source, &[error], out),
+    }
+  }
+  fn eval_stmt(&mut self, source: &str, node: AstNode<'static>, out: &mut String) -> bool {
+    let mut trial = self.checker.clone();
+    let mut diagnostics = trial.check_root_stmt(&node).1.clone();
+    diagnostics.extend(flow::check_lambdas(&node));
+    if !report(source, &diagnostics, out) {
+      return false;
+    }
+    self.checker = trial;
+    let node: &'static AstNode<'static> = Box::leak(Box::new(node));
+    let result = self.interpreter.exec_root_stmt(node);
+    out.push_str(&self.printed.take());
+    match result {
+      Ok(Value::Unit) => true,
+      Ok(value) => {
+        out.push_str(&format!("{}\n", value.repr()));
+        true
+      },
+      Err(error) => report(source, &[error], out),
+    }
+  }
+  fn meta(&mut self, command: &str, arg: &str) -> String {
+    match command {
+      "type" | "t" => self.type_of(arg),
+      "ast" => {
+        let ast = parse_entry(intern(&complete_stmt(arg)));
+        ast.get_root().get_children().iter().map(|node| node.dump_tree()).collect()
+      },
+      "tokens" => lexer::dump_tokens(arg),
+      "help" | "h" => format!("{}\n", HELP),
+      _ => format!("unknown command `:{}`, try `:help`\n", command),
+    }
+  }
+  fn type_of(&mut self, arg: &str) -> String {
+    if let Some(sig) = self.checker.get_fun(arg) {
+      return format!("{}\n", sig);
+    }
+    let source = intern(&complete_stmt(arg));
+    let mut out = String::new();
+    let ast = parse_entry(source);
+    if !report(source, &diagnostic::parse_errors(&ast), &mut out) {
+      return out;
+    }
+    let nodes = ast.get_root().get_children();
+    match nodes.first() {
+      Some(node) if nodes.len() == 1 && node.get_kind() == AstKind::Stmt => {
+        let mut trial = self.checker.clone();
+        let (ty, diagnostics) = trial.check_root_stmt(node);
+        if report(source, diagnostics, &mut out) {
+          out.push_str(&format!("{}\n", ty));
+        }
+      },
+      _ => out.push_str("`:type` takes one expression\n"),
+    }
+    out
+  }
+}
+
+// Items and statements of one entry under a single root
+fn parse_entry(source: &'static str) -> Ast<'static> {
+  let mut parser = Parser::new(Lexer::new(source));
+  let mut ast = Ast::new();
+  loop {
+    let node = match parser.lexer_peek().get_kind() {
+      TokenKind::EOF => break,
+      kind if ITEM_KINDS.contains(&kind) => parser.parse_item(),
+      _ => parser.parse_stmt(),
+    };
+    ast.get_mut_root().add_node(node);
+  }
+  ast
+}
+
+// A bare expression does not need its `;`
+fn complete_stmt(input: &str) -> String {
+  let mut lexer = Lexer::new(input);
+  let mut first = None;
+  let mut last = TokenKind::EOF;
+  while let Some(token) = lexer.next() {
+    match token.get_kind() {
+      TokenKind::EOF => break,
+      kind => {
+        first.get_or_insert(kind);
+        last = kind;
+      },
+    }
+  }
+  // `}` ends a block or item, unless it closes a struct literal
+  let expr = first.is_some_and(|kind| !ITEM_KINDS.contains(&kind) && Parser::new(Lexer::new("")).starts_expr(&kind));
+  match last {
+    TokenKind::RStmt if expr => format!("{};", input.trim_end()),
+    TokenKind::SemiColon | TokenKind::RStmt | TokenKind::EOF => input.to_string(),
+    _ => format!("{};", input.trim_end()),
+  }
+}
+
+// Print the diagnostics, true when none of them is an error
+fn report(source: &str, diagnostics: &[Diagnostic], out: &mut String) -> bool {
+  let file = SourceFile::anonymous(source);
+  for d in diagnostics {
+    out.push_str(&file.render(d, false));
+    out.push('\n');
+  }
+  !diagnostics.iter().any(|d| d.is_error())
+}
+
+// Whether more lines are needed: an open bracket, or an operator waiting for its right side
+pub fn is_incomplete(input: &str) -> bool {
+  let parser = Parser::new(Lexer::new(""));
+  let mut lexer = Lexer::new(input);
+  let mut depth = 0_i64;
+  let mut last = TokenKind::EOF;
+  while let Some(token) = lexer.next() {
+    match token.get_kind() {
+      TokenKind::EOF => break,
+      TokenKind::LParen | TokenKind::LIndex | TokenKind::LStmt => depth += 1,
+      TokenKind::RParen | TokenKind::RIndex | TokenKind::RStmt => depth -= 1,
+      _ => {},
+    }
+    last = token.get_kind();
+  }
+  depth > 0 || parser.expects_operand_after(&last)
+}
+
+// Read entries from `input` until it ends or `:quit`
+pub fn run(input: &mut dyn BufRead, out: &mut dyn Write) -> std::io::Result<()> {
+  let mut session = Session::new();
+  let mut buffer = String::new();
+  loop {
+    write!(out, "{}", if buffer.is_empty() { "> " } else { "... " })?;
+    out.flush()?;
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+      writeln!(out)?;
+      return Ok(());
+    }
+    if buffer.is_empty() && matches!(line.trim(), ":quit" | ":q") {
+      return Ok(());
+    }
+    buffer.push_str(&line);
+    // An empty line ends an entry that is still open, so a typo cannot trap the prompt
+    let meta = buffer.trim_start().starts_with(':');
+    if !meta && !line.trim().is_empty() && is_incomplete(&buffer) {
+      continue;
+    }
+    write!(out, "{}", session.eval(&buffer))?;
+    buffer.clear();
+  }
+}
@@ -0,0 +1,382 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::ast::token::TokenKind;
+use crate::ast::{Ast, AstKind, AstNode};
+use crate::diagnostic::Span;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DefKind {
+  Fun,
+  Param,
+  Global,
+  Local,
+  LoopVar,
+  Struct,
+  Field,
+  Method,
+  Enum,
+  Variant,
+  Binding,
+}
+
+// A name introduced by the program, found at the position of its name token
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Def<'a> {
+  pub name: &'a str,
+  pub kind: DefKind,
+  pub span: Span,
+}
+
+// Links every identifier to the definition it names, following the scoping
+// rules of the checker. Identifiers that name nothing are left out.
+pub struct Resolution<'a> {
+  defs: BTreeMap<usize, Def<'a>>,
+  // Position of each use to the position of its definition
+  uses: BTreeMap<usize, usize>,
+  // Position of each lambda to the definitions it captures, in order of first use
+  captures: BTreeMap<usize, Vec<usize>>,
+  // Definitions written after they are declared
+  assigned: BTreeSet<usize>,
+}
+
+// A variable of an enclosing function used inside a lambda. Variables that are
+// written anywhere are shared with the lambda, the others are copied into it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Capture<'a> {
+  pub name: &'a str,
+  pub def: usize,
+  pub by_ref: bool,
+}
+
+impl<'a> Resolution<'a> {
+  pub fn resolve(ast: &Ast<'a>) -> Resolution<'a> {
+    let mut resolver = Resolver::new();
+    let items = ast.get_root().get_children();
+    for item in items.iter().filter(|item| item.get_kind() == AstKind::Struct && item.child_count() == 2) {
+      let pos = resolver.define(&item[0], DefKind::Struct);
+      resolver.types.entry(item[0].get_token().get_value()).or_insert(pos);
+    }
+    for item in items.iter().filter(|item| item.get_kind() == AstKind::Enum && item.child_count() == 2) {
+      let pos = resolver.define(&item[0], DefKind::Enum);
+      resolver.types.entry(item[0].get_token().get_value()).or_insert(pos);
+    }
+    for item in items.iter().filter(|item| item.get_kind() == AstKind::Enum && item.child_count() == 2) {
+      let name = item[0].get_token().get_value();
+      for variant in item[1].get_children() {
+        let pos = resolver.define(variant, DefKind::Variant);
+        resolver.variants.entry((name, variant.get_token().get_value())).or_insert(pos);
+        for ty in variant.get_children() {
+          resolver.ty(ty);
+        }
+      }
+    }
+    for item in items.iter().filter(|item| item.get_kind() == AstKind::Struct && item.child_count() == 2) {
+      let name = item[0].get_token().get_value();
+      for field in item[1].get_children() {
+        let pos = resolver.define(field, DefKind::Field);
+        resolver.fields.entry((name, field.get_token().get_value())).or_insert(pos);
+        if field.child_count() > 0 {
+          resolver.ty(&field[0]);
+        }
+      }
+    }
+    for item in items.iter().filter(|item| item.get_kind() == AstKind::Fun && item.child_count() == 4) {
+      let pos = resolver.define(&item[0], DefKind::Fun);
+      resolver.funs.entry(item[0].get_token().get_value()).or_insert(pos);
+    }
+    for item in items.iter().filter(|item| item.get_kind() == AstKind::Let) {
+      resolver.stmt(item);
+    }
+    for item in items.iter().filter(|item| item.get_kind() == AstKind::Fun && item.child_count() == 4) {
+      resolver.fun(item);
+    }
+    // Methods are only reached through a value, so their names never enter a scope
+    for item in items.iter().filter(|item| item.get_kind() == AstKind::Impl && item.child_count() > 0) {
+      resolver.ty(&item[0]);
+      for method in item.get_children().iter().skip(1).filter(|m| m.child_count() == 4) {
+        resolver.define(&method[0], DefKind::Method);
+        resolver.fun(method);
+      }
+    }
+    resolver.resolution
+  }
+  // Resolve a lone item or a statement outside of any function, as the REPL gives
+  // them. Names defined elsewhere are left out.
+  pub fn resolve_stmt(node: &AstNode<'a>) -> Resolution<'a> {
+    let mut resolver = Resolver::new();
+    match node.get_kind() {
+      AstKind::Fun if node.child_count() == 4 => resolver.fun(node),
+      AstKind::Impl => {
+        for method in node.get_children().iter().skip(1).filter(|m| m.child_count() == 4) {
+          resolver.fun(method);
+        }
+      },
+      AstKind::Let => resolver.stmt(node),
+      AstKind::Struct | AstKind::Enum => {},
+      _ => {
+        resolver.scopes.push(BTreeMap::new());
+        resolver.stmt(node);
+      },
+    }
+    resolver.resolution
+  }
+  pub fn get_defs(&self) -> impl Iterator<Item = &Def<'a>> {
+    self.defs.values()
+  }
+  // Position of each use with the position of its definition
+  pub fn get_uses(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+    self.uses.iter().map(|(pos, def)| (*pos, *def))
+  }
+  // Definition named by the identifier at `pos`, whether it is a use or the definition itself
+  pub fn def_at(&self, pos: usize) -> Option<&Def<'a>> {
+    let pos = self.uses.get(&pos).copied().unwrap_or(pos);
+    self.defs.get(&pos)
+  }
+  // Positions of every use of the definition at `def_pos`
+  pub fn uses_of(&self, def_pos: usize) -> Vec<usize> {
+    self.uses.iter().filter(|(_, def)| **def == def_pos).map(|(pos, _)| *pos).collect()
+  }
+  // Variables captured by the lambda whose `fun` keyword is at `lambda_pos`
+  pub fn captures_of(&self, lambda_pos: usize) -> Vec<Capture<'a>> {
+    let defs = self.captures.get(&lambda_pos).map(|defs| &defs[..]).unwrap_or_default();
+    defs.iter().map(|def| Capture {
+      name: self.defs[def].name,
+      def: *def,
+      by_ref: self.assigned.contains(def),
+    }).collect()
+  }
+}
+
+struct Resolver<'a> {
+  resolution: Resolution<'a>,
+  funs: BTreeMap<&'a str, usize>,
+  // Struct and enum names
+  types: BTreeMap<&'a str, usize>,
+  // Keyed by enum and variant name
+  variants: BTreeMap<(&'a str, &'a str), usize>,
+  // Keyed by struct and field name
+  fields: BTreeMap<(&'a str, &'a str), usize>,
+  scopes: Vec<BTreeMap<&'a str, usize>>,
+  // Position of each lambda being resolved and the number of scopes outside it, innermost last
+  lambdas: Vec<(usize, usize)>,
+}
+
+impl<'a> Resolver<'a> {
+  fn new() -> Resolver<'a> {
+    Resolver {
+      resolution: Resolution {
+        defs: BTreeMap::new(),
+        uses: BTreeMap::new(),
+        captures: BTreeMap::new(),
+        assigned: BTreeSet::new(),
+      },
+      funs: BTreeMap::new(),
+      types: BTreeMap::new(),
+      variants: BTreeMap::new(),
+      fields: BTreeMap::new(),
+      scopes: vec![BTreeMap::new()],
+      lambdas: Vec::new(),
+    }
+  }
+  fn define(&mut self, name: &AstNode<'a>, kind: DefKind) -> usize {
+    let token = name.get_token();
+    let def = Def { name: token.get_value(), kind, span: Span::of_token(name) };
+    self.resolution.defs.insert(token.get_pos(), def);
+    token.get_pos()
+  }
+  fn declare(&mut self, name: &AstNode<'a>, kind: DefKind) {
+    let pos = self.define(name, kind);
+    self.scopes.last_mut().unwrap().insert(name.get_token().get_value(), pos);
+  }
+  fn lookup(&self, name: &str) -> Option<usize> {
+    self.scopes.iter().rev().find_map(|scope| scope.get(name).copied())
+  }
+  // A local of an enclosing function is captured by every lambda between it and the use
+  fn capture(&mut self, name: &str) {
+    let depth = match self.scopes.iter().rposition(|scope| scope.contains_key(name)) {
+      Some(depth) if depth > 0 => depth,
+      _ => return,
+    };
+    let def = self.scopes[depth][name];
+    for (lambda, _) in self.lambdas.iter().rev().take_while(|(_, outside)| *outside > depth) {
+      let captures = self.resolution.captures.entry(*lambda).or_default();
+      if !captures.contains(&def) {
+        captures.push(def);
+      }
+    }
+  }
+  fn link(&mut self, node: &AstNode<'a>, def: Option<usize>) {
+    if let Some(def) = def {
+      self.resolution.uses.insert(node.get_token().get_pos(), def);
+    }
+  }
+  fn fun(&mut self, item: &AstNode<'a>) {
+    self.body(&item[1], &item[2], &item[3]);
+  }
+  fn body(&mut self, params: &AstNode<'a>, ret: &AstNode<'a>, block: &AstNode<'a>) {
+    self.scopes.push(BTreeMap::new());
+    for param in params.get_children() {
+      self.declare(param, DefKind::Param);
+      if param.child_count() > 0 {
+        self.ty(&param[0]);
+      }
+    }
+    self.ty(ret);
+    self.stmt(block);
+    self.scopes.pop();
+  }
+  // Struct and enum names used in a type annotation
+  fn ty(&mut self, node: &AstNode<'a>) {
+    if node.child_count() == 0 {
+      self.link(node, self.types.get(node.get_token().get_value()).copied());
+    }
+    if node.get_token().get_kind() == TokenKind::LIndex && node.child_count() > 0 {
+      self.ty(&node[0]);
+    }
+    if node.get_token().get_kind() == TokenKind::Fun {
+      for child in node.get_children() {
+        self.ty(child);
+      }
+    }
+  }
+  fn stmt(&mut self, node: &AstNode<'a>) {
+    match node.get_kind() {
+      AstKind::Let if node.child_count() >= 2 => {
+        self.ty(&node[1]);
+        if node.child_count() > 2 {
+          self.expr(&node[2]);
+        }
+        let kind = match self.scopes.len() {
+          1 => DefKind::Global,
+          _ => DefKind::Local,
+        };
+        self.declare(&node[0], kind);
+      },
+      AstKind::Block => {
+        self.scopes.push(BTreeMap::new());
+        for stmt in node.get_children() {
+          self.stmt(stmt);
+        }
+        self.scopes.pop();
+      },
+      AstKind::For if node.child_count() == 3 => {
+        self.expr(&node[1]);
+        self.scopes.push(BTreeMap::new());
+        self.declare(&node[0], DefKind::LoopVar);
+        self.stmt(&node[2]);
+        self.scopes.pop();
+      },
+      AstKind::If | AstKind::While | AstKind::Else | AstKind::Stmt | AstKind::Return => {
+        for child in node.get_children() {
+          match child.get_kind() {
+            AstKind::Block | AstKind::Let | AstKind::If | AstKind::Else | AstKind::While | AstKind::For
+            | AstKind::Stmt | AstKind::Return => self.stmt(child),
+            _ => self.expr(child),
+          }
+        }
+      },
+      _ => {},
+    }
+  }
+  fn expr(&mut self, node: &AstNode<'a>) {
+    match node.get_kind() {
+      // Variables shadow functions, as in the checker
+      AstKind::Identifier => {
+        let name = node.get_token().get_value();
+        let def = self.lookup(name).or_else(|| self.funs.get(name).copied());
+        self.link(node, def);
+        self.capture(name);
+      },
+      AstKind::Lambda if node.child_count() == 3 => {
+        self.lambdas.push((node.get_token().get_pos(), self.scopes.len()));
+        self.body(&node[0], &node[1], &node[2]);
+        self.lambdas.pop();
+      },
+      AstKind::BinOper | AstKind::PreStep | AstKind::PostStep if node.child_count() > 0 && is_write(node) => {
+        for child in node.get_children() {
+          self.expr(child);
+        }
+        if let Some(def) = target(&node[0]).and_then(|name| self.lookup(name)) {
+          self.resolution.assigned.insert(def);
+        }
+      },
+      AstKind::StructLit => {
+        let name = node[0].get_token().get_value();
+        self.link(&node[0], self.types.get(name).copied());
+        for init in node.get_children().iter().skip(1) {
+          self.link(init, self.fields.get(&(name, init.get_token().get_value())).copied());
+          for child in init.get_children() {
+            self.expr(child);
+          }
+        }
+      },
+      // The field after `.` depends on the type before it, which is left to the checker
+      AstKind::Member => {
+        if node.child_count() > 0 {
+          self.expr(&node[0]);
+        }
+      },
+      AstKind::Path if node.child_count() == 2 => self.path(node),
+      // Each arm sees the names its pattern binds
+      AstKind::Match => {
+        if node.child_count() > 0 {
+          self.expr(&node[0]);
+        }
+        for arm in node.get_children().iter().skip(1) {
+          self.scopes.push(BTreeMap::new());
+          if arm.child_count() > 0 {
+            self.pattern(&arm[0]);
+          }
+          if arm.child_count() > 1 {
+            self.expr(&arm[1]);
+          }
+          self.scopes.pop();
+        }
+      },
+      AstKind::MethodCall if node.child_count() == 3 => {
+        self.expr(&node[0]);
+        self.expr(&node[2]);
+      },
+      _ => {
+        for child in node.get_children() {
+          self.expr(child);
+        }
+      },
+    }
+  }
+  // `Enum::Variant` in an expression or a pattern
+  fn path(&mut self, node: &AstNode<'a>) {
+    let name = node[0].get_token().get_value();
+    self.link(&node[0], self.types.get(name).copied());
+    self.link(&node[1], self.variants.get(&(name, node[1].get_token().get_value())).copied());
+  }
+  fn pattern(&mut self, node: &AstNode<'a>) {
+    match node.get_kind() {
+      AstKind::Binding => self.declare(node, DefKind::Binding),
+      AstKind::VariantPat if node.child_count() >= 2 => {
+        self.path(node);
+        for sub in node.get_children().iter().skip(2) {
+          self.pattern(sub);
+        }
+      },
+      _ => {},
+    }
+  }
+}
+
+// Assignments, `++` and `--`
+fn is_write(node: &AstNode) -> bool {
+  use TokenKind::*;
+  matches!(node.get_kind(), AstKind::PreStep | AstKind::PostStep)
+    || matches!(node.get_token().get_kind(), Equal | PlusEq | MinusEq | AsteriskEq | SlashEq | ModEq | AndEq | OrEq | XorEq | LShiftEq | RShiftEq)
+}
+
+// Variable a write goes through. Writing a char of a string rebuilds the string,
+// so an indexed write counts as writing the variable.
+fn target<'a>(node: &AstNode<'a>) -> Option<&'a str> {
+  match node.get_kind() {
+    AstKind::Identifier => Some(node.get_token().get_value()),
+    AstKind::Expr | AstKind::Index if node.child_count() > 0 => target(&node[0]),
+    _ => None,
+  }
+}
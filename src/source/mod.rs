@@ -0,0 +1,155 @@
+use std::path::{Path, PathBuf};
+
+use crate::diagnostic::{Diagnostic, Severity, Span};
+use crate::utilities::json::Json;
+
+// Index of a file in its SourceMap
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub struct FileId(usize);
+
+// One loaded text. Its positions start at `base`, after the ones of the files
+// added before it, so a span alone tells which file it points into.
+pub struct SourceFile {
+  path: PathBuf,
+  text: String,
+  base: usize,
+  // Offset of the first byte of each line, counted from the start of the file
+  line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+  pub fn new(path: PathBuf, text: String, base: usize) -> SourceFile {
+    let mut line_starts = vec![0];
+    line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+    SourceFile {
+      path,
+      text,
+      base,
+      line_starts,
+    }
+  }
+  // A lone text, read as a file of its own
+  pub fn anonymous(text: &str) -> SourceFile {
+    SourceFile::new(PathBuf::new(), text.to_string(), 0)
+  }
+  pub fn get_path(&self) -> &Path {
+    &self.path
+  }
+  pub fn get_text(&self) -> &str {
+    &self.text
+  }
+  pub fn get_base(&self) -> usize {
+    self.base
+  }
+  // The span counted from the start of the file
+  pub fn local(&self, span: Span) -> Span {
+    let end = self.text.len();
+    Span::new(span.start.saturating_sub(self.base).min(end), span.end.saturating_sub(self.base).min(end))
+  }
+  // The position moved inside the file
+  pub fn clamp(&self, pos: usize) -> usize {
+    pos.clamp(self.base, self.base + self.text.len())
+  }
+  // 1-based line of a position
+  pub fn line_of(&self, pos: usize) -> usize {
+    let offset = self.clamp(pos) - self.base;
+    self.line_starts.partition_point(|&start| start <= offset)
+  }
+  // Position of the first byte of a 1-based line
+  pub fn line_start(&self, line: usize) -> Option<usize> {
+    self.line_starts.get(line.wrapping_sub(1)).map(|start| start + self.base)
+  }
+  // 1-based line and column (in chars) of a position
+  pub fn line_col(&self, pos: usize) -> (usize, usize) {
+    let line = self.line_of(pos);
+    let start = self.line_starts[line - 1];
+    (line, self.text[start..self.clamp(pos) - self.base].chars().count() + 1)
+  }
+  // Text of a 1-based line, without its line break
+  pub fn line_text(&self, line: usize) -> &str {
+    let start = self.line_starts.get(line.wrapping_sub(1)).copied().unwrap_or(self.text.len());
+    let end = self.line_starts.get(line).map(|next| next - 1).unwrap_or(self.text.len());
+    self.text[start..end].trim_end_matches('\r')
+  }
+  // `line:col: error: message` followed by the line and a caret marker
+  pub fn render(&self, diagnostic: &Diagnostic, color: bool) -> String {
+    let (line, col) = self.line_col(diagnostic.span.start);
+    let (severity, code) = match diagnostic.severity {
+      Severity::Error => ("error", "31"),
+      Severity::Warning => ("warning", "33"),
+    };
+    let paint = |text: &str, code: &str| match color {
+      true => format!("\x1b[{}m{}\x1b[0m", code, text),
+      false => text.to_string(),
+    };
+    let span = self.local(diagnostic.span);
+    let width = self.text[span.start..span.end]
+      .lines()
+      .next()
+      .map(|s| s.chars().count())
+      .unwrap_or(0)
+      .max(1);
+    format!(
+      "{}:{}: {}: {}\n  {}\n  {}{}",
+      line, col, paint(severity, &format!("1;{}", code)), paint(&diagnostic.message, "1"),
+      self.line_text(line), " ".repeat(col - 1), paint(&"^".repeat(width), code)
+    )
+  }
+  pub fn to_json(&self, diagnostic: &Diagnostic) -> Json {
+    let (line, column) = self.line_col(diagnostic.span.start);
+    let span = self.local(diagnostic.span);
+    let severity = match diagnostic.severity {
+      Severity::Error => "error",
+      Severity::Warning => "warning",
+    };
+    Json::object(vec![
+      ("file", Json::String(self.path.display().to_string())),
+      ("severity", Json::str(severity)),
+      ("message", Json::str(&diagnostic.message)),
+      ("line", Json::Number(line as f64)),
+      ("column", Json::Number(column as f64)),
+      ("start", Json::Number(span.start as f64)),
+      ("end", Json::Number(span.end as f64)),
+    ])
+  }
+}
+
+// Every text a compilation reads, in the order they were loaded
+#[derive(Default)]
+pub struct SourceMap {
+  files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+  pub fn new() -> SourceMap {
+    SourceMap::default()
+  }
+  // The file's positions follow the ones of the last file, one apart so that
+  // each end of file has its own position
+  pub fn add(&mut self, path: PathBuf, text: String) -> FileId {
+    let base = self.files.last().map(|last| last.base + last.text.len() + 1).unwrap_or(0);
+    self.files.push(SourceFile::new(path, text, base));
+    FileId(self.files.len() - 1)
+  }
+  pub fn get(&self, id: FileId) -> &SourceFile {
+    &self.files[id.0]
+  }
+  pub fn find(&self, path: &Path) -> Option<FileId> {
+    self.files.iter().position(|file| file.path == path).map(FileId)
+  }
+  // File whose text holds the position
+  pub fn file_at(&self, pos: usize) -> FileId {
+    FileId(self.files.partition_point(|file| file.base <= pos).saturating_sub(1))
+  }
+  pub fn lookup(&self, pos: usize) -> &SourceFile {
+    self.get(self.file_at(pos))
+  }
+  // `path:line:col: error: message` and a snippet of the file it points into
+  pub fn render(&self, diagnostic: &Diagnostic, color: bool) -> String {
+    let file = self.lookup(diagnostic.span.start);
+    format!("{}:{}", file.path.display(), file.render(diagnostic, color))
+  }
+  pub fn to_json(&self, diagnostic: &Diagnostic) -> Json {
+    self.lookup(diagnostic.span.start).to_json(diagnostic)
+  }
+}
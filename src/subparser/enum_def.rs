@@ -0,0 +1,67 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn enum_def<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("enum_def");
+  node.set_kind(AstKind::Enum);
+  let mut l_stmt = *node.get_token();
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::Enum => continue,
+      TokenKind::Identifier => {
+        if !super::add_token(parser, node, TokenKind::Identifier, AstKind::Identifier, "Expected enum name") {
+          return None;
+        }
+      },
+      TokenKind::LStmt => {
+        l_stmt = super::expect_token(parser, node, TokenKind::LStmt, "Expected '{'")?;
+      },
+      TokenKind::Union(0) => {
+        let variants = node.add_node(AstNode::new(l_stmt, AstKind::Variants));
+        if !parse_variants(parser, variants) {
+          return None;
+        }
+      },
+      TokenKind::RStmt => {
+        super::expect_token(parser, node, TokenKind::RStmt, "Expected '}' or ','")?;
+      },
+      _ => panic!("Grammer for enum_def Error"),
+    }
+  }
+
+  Some(AstKind::Enum)
+}
+
+// Variant list `Name` or `Name(Type, ...)`, up to but not including '}', a trailing ',' is allowed
+fn parse_variants<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> bool {
+  while parser.lexer_peek().get_kind() != TokenKind::RStmt {
+    let name = match super::expect_token(parser, node, TokenKind::Identifier, "Expected variant name") {
+      Some(token) => token,
+      None => return false,
+    };
+    let variant = node.add_node(AstNode::new(name, AstKind::Variant));
+    if parser.lexer_peek().get_kind() == TokenKind::LParen {
+      parser.lexer_next();
+      while parser.lexer_peek().get_kind() != TokenKind::RParen {
+        if !super::add_a_type(parser, variant, "Expected variant field Type") {
+          return false;
+        }
+        if parser.lexer_peek().get_kind() != TokenKind::Comma {
+          break;
+        }
+        parser.lexer_next();
+      }
+      if super::expect_token(parser, variant, TokenKind::RParen, "Expected ')' or ','").is_none() {
+        return false;
+      }
+    }
+    if parser.lexer_peek().get_kind() != TokenKind::Comma {
+      return true;
+    }
+    parser.lexer_next();
+  }
+  true
+}
@@ -4,20 +4,16 @@ pub fn expr_args<'a>() -> super::SubParser<'a> {
   super::SubParser::new(parse)
 }
 
+// Fill an Args node with comma separated Exprs, stopping before the closing token
 fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
   loop {
-    parser.parse_fn(&mut AstNode::new_empty());
-    match parser.lexer_peek() {
-      Some(token) => {
-        if token.get_kind() != TokenKind::Comma {
-          break;
-        }
-      },
-      None => {
-        super::error(node, "Expected Expr or Identifier");
-        return None
-      }
+    if !super::add_a_exprlike(parser, node) {
+      return None;
     }
+    if parser.lexer_peek().get_kind() != TokenKind::Comma {
+      break;
+    }
+    parser.lexer_next();
   }
-  Some(AstKind::GrapArgs)
-}
\ No newline at end of file
+  Some(AstKind::Args)
+}
@@ -0,0 +1,54 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn expr_array<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+// A `[` with no operand before it: `[a, b, c]` or `[elem; count]`
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let token = node.get_token();
+  let expect = parser.get_expect("expr_array");
+  assert_eq!(token.get_kind(), expect[0]);
+  node.set_kind(AstKind::Array);
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::LIndex => continue,
+      TokenKind::Union(0) => {
+        if !parse_elements(parser, node) {
+          return None;
+        }
+      },
+      TokenKind::RIndex => {
+        super::expect_token(parser, node, TokenKind::RIndex, "Expected ']' or ','")?;
+      },
+      _ => panic!("Grammer for expr_array Error"),
+    }
+  }
+
+  Some(AstKind::PushToStk)
+}
+
+// Elements up to but not including ']', a trailing ',' is allowed
+fn parse_elements<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> bool {
+  if parser.lexer_peek().get_kind() == TokenKind::RIndex {
+    return true;
+  }
+  if !super::add_a_exprlike(parser, node) {
+    return false;
+  }
+  if parser.lexer_peek().get_kind() == TokenKind::SemiColon {
+    parser.lexer_next();
+    node.set_kind(AstKind::Repeat);
+    return super::add_a_exprlike(parser, node);
+  }
+  while parser.lexer_peek().get_kind() == TokenKind::Comma {
+    parser.lexer_next();
+    if parser.lexer_peek().get_kind() == TokenKind::RIndex {
+      break;
+    }
+    if !super::add_a_exprlike(parser, node) {
+      return false;
+    }
+  }
+  true
+}
@@ -9,7 +9,7 @@ macro_rules! add_subparser {
       fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
         node.set_kind(AstKind::$kind);
         if !super::super::add_b_exprlike(parser, node) { return None }
-        if !super::super::add_a_exprlike(parser, node) { return None }
+        if !super::super::add_a_operand(parser, node) { return None }
         Some(AstKind::PushToStk)
       }
     }
@@ -22,7 +22,6 @@ add_subparser!(expr_b_less, BinOper);
 add_subparser!(expr_b_lesseq, BinOper);
 add_subparser!(expr_b_greater, BinOper);
 add_subparser!(expr_b_greatereq, BinOper);
-add_subparser!(expr_b_dot, BinOper);
 add_subparser!(expr_b_ddot, BinOper);
 add_subparser!(expr_b_and, BinOper);
 add_subparser!(expr_b_or, BinOper);
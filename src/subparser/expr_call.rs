@@ -0,0 +1,43 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn expr_call<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let token = *node.get_token();
+  let expect = parser.get_expect("expr_call");
+  assert_eq!(token.get_kind(), expect[1]);
+  node.set_kind(AstKind::Call);
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::Union(0) => {
+        if !super::add_b_exprlike(parser, node) {
+          return None;
+        }
+        // `a.m(...)` calls the method `m` of `a` rather than a field
+        if node[0].get_kind() == AstKind::Member && node[0].child_count() == 2 {
+          let mut member = node.pop_node().unwrap();
+          let name = member.pop_node().unwrap();
+          node.add_node(member.pop_node().unwrap());
+          node.add_node(name);
+          node.set_kind(AstKind::MethodCall);
+        }
+      },
+      TokenKind::LParen => continue,
+      TokenKind::Union(1) => {
+        let args = node.add_node(AstNode::new(token, AstKind::Args));
+        if parser.lexer_peek().get_kind() != TokenKind::RParen
+          && parser.subparse_id("expr_args", args).is_none() {
+          return None;
+        }
+      },
+      TokenKind::RParen => {
+        super::expect_token(parser, node, TokenKind::RParen, "Expected ')' or ','")?;
+      },
+      _ => panic!("Grammer for expr_call Error"),
+    }
+  }
+
+  Some(AstKind::PushToStk)
+}
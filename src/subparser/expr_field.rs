@@ -0,0 +1,28 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn expr_field<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("expr_field");
+  node.set_kind(AstKind::Member);
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::Union(0) => {
+        if !super::add_b_exprlike(parser, node) {
+          return None;
+        }
+      },
+      TokenKind::Dot => continue,
+      TokenKind::Identifier => {
+        if !super::add_token(parser, node, TokenKind::Identifier, AstKind::Identifier, "Expected field name after '.'") {
+          return None;
+        }
+      },
+      _ => panic!("Grammer for expr_field Error"),
+    }
+  }
+
+  Some(AstKind::PushToStk)
+}
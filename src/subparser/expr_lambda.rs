@@ -0,0 +1,48 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn expr_lambda<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("expr_lambda");
+  node.set_kind(AstKind::Lambda);
+  let mut l_paren = *node.get_token();
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::Fun => continue,
+      TokenKind::LParen => {
+        l_paren = super::expect_token(parser, node, TokenKind::LParen, "Expected '('")?;
+      },
+      TokenKind::Union(0) => {
+        let params = node.add_node(AstNode::new(l_paren, AstKind::Params));
+        if !super::fun::parse_params(parser, params) {
+          return None;
+        }
+      },
+      TokenKind::RParen => {
+        super::expect_token(parser, node, TokenKind::RParen, "Expected ')' or ','")?;
+      },
+      TokenKind::Colon => {
+        super::expect_token(parser, node, TokenKind::Colon, "Expected ':' and return Type")?;
+      },
+      TokenKind::Union(2) => {
+        if !super::add_a_type(parser, node, "Expected return Type") {
+          return None;
+        }
+      },
+      // The body is a block even inside the value of `for` or `match`
+      TokenKind::Union(1) => {
+        if parser.lexer_peek().get_kind() != TokenKind::LStmt {
+          super::expect_token(parser, node, TokenKind::LStmt, "Expected '{'")?;
+        }
+        let outer = parser.set_struct_literals(true);
+        super::add_a_stmt(parser, node);
+        parser.set_struct_literals(outer);
+      },
+      _ => panic!("Grammer for expr_lambda Error"),
+    }
+  }
+
+  Some(AstKind::PushToStk)
+}
@@ -0,0 +1,143 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn expr_match<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("expr_match");
+  node.set_kind(AstKind::Match);
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::Match => continue,
+      // `match shape { ... }` must not read `shape { ... }` as a struct literal
+      TokenKind::Union(0) => {
+        let outer = parser.set_struct_literals(false);
+        let ok = super::add_a_exprlike(parser, node);
+        parser.set_struct_literals(outer);
+        if !ok {
+          return None;
+        }
+      },
+      TokenKind::LStmt => {
+        super::expect_token(parser, node, TokenKind::LStmt, "Expected '{'")?;
+      },
+      TokenKind::Union(1) => {
+        let outer = parser.set_struct_literals(true);
+        let ok = parse_arms(parser, node);
+        parser.set_struct_literals(outer);
+        if !ok {
+          return None;
+        }
+      },
+      TokenKind::RStmt => {
+        super::expect_token(parser, node, TokenKind::RStmt, "Expected '}' or ','")?;
+      },
+      _ => panic!("Grammer for expr_match Error"),
+    }
+  }
+
+  Some(AstKind::PushToStk)
+}
+
+// Arms `Pattern => Expr, ...` up to but not including '}', a trailing ',' is allowed
+fn parse_arms<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> bool {
+  while parser.lexer_peek().get_kind() != TokenKind::RStmt {
+    let mut arm = AstNode::new(parser.lexer_peek(), AstKind::Arm);
+    let ok = add_a_pattern(parser, &mut arm);
+    let arm = node.add_node(arm);
+    if !ok {
+      return false;
+    }
+    match super::expect_token(parser, arm, TokenKind::FatArrow, "Expected '=>'") {
+      Some(arrow) => arm.set_token(arrow),
+      None => return false,
+    }
+    if !super::add_a_exprlike(parser, arm) {
+      return false;
+    }
+    if parser.lexer_peek().get_kind() != TokenKind::Comma {
+      return true;
+    }
+    parser.lexer_next();
+  }
+  true
+}
+
+// `_`, a name to bind, a literal, `lo..hi` or `Enum::Variant(Pattern, ...)`
+fn add_a_pattern<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> bool {
+  let token = parser.lexer_peek();
+  if token.get_kind() != TokenKind::Identifier {
+    let lo = match literal_pattern(parser) {
+      Ok(lo) => lo,
+      Err(bad) => {
+        node.add_node(bad);
+        return false;
+      },
+    };
+    if parser.lexer_peek().get_kind() != TokenKind::DDot {
+      node.add_node(lo);
+      return true;
+    }
+    let range = node.add_node(AstNode::new(parser.lexer_next(), AstKind::RangePat));
+    range.add_node(lo);
+    return match literal_pattern(parser) {
+      Ok(hi) => {
+        range.add_node(hi);
+        true
+      },
+      Err(bad) => {
+        range.add_node(bad);
+        false
+      },
+    };
+  }
+  parser.lexer_next();
+  if token.get_value() == "_" {
+    node.add_node(AstNode::new(token, AstKind::Wildcard));
+    return true;
+  }
+  if parser.lexer_peek().get_kind() != TokenKind::DColon {
+    node.add_node(AstNode::new(token, AstKind::Binding));
+    return true;
+  }
+  let variant = node.add_node(AstNode::new(parser.lexer_next(), AstKind::VariantPat));
+  variant.add_node(AstNode::new(token, AstKind::Identifier));
+  if !super::add_token(parser, variant, TokenKind::Identifier, AstKind::Identifier, "Expected variant name after '::'") {
+    return false;
+  }
+  if parser.lexer_peek().get_kind() != TokenKind::LParen {
+    return true;
+  }
+  parser.lexer_next();
+  while parser.lexer_peek().get_kind() != TokenKind::RParen {
+    if !add_a_pattern(parser, variant) {
+      return false;
+    }
+    if parser.lexer_peek().get_kind() != TokenKind::Comma {
+      break;
+    }
+    parser.lexer_next();
+  }
+  super::expect_token(parser, variant, TokenKind::RParen, "Expected ')' or ','").is_some()
+}
+
+// A literal, with a leading '-' for numbers
+fn literal_pattern<'a>(parser: &mut Parser<'a>) -> Result<AstNode<'a>, AstNode<'a>> {
+  let token = parser.lexer_peek();
+  match token.get_kind() {
+    TokenKind::Int | TokenKind::Float | TokenKind::Char | TokenKind::String | TokenKind::True | TokenKind::False => {
+      Ok(AstNode::new(parser.lexer_next(), AstKind::Literal))
+    },
+    TokenKind::Minus => {
+      let mut minus = AstNode::new(parser.lexer_next(), AstKind::UnOper);
+      let number = parser.lexer_peek();
+      if !matches!(number.get_kind(), TokenKind::Int | TokenKind::Float) {
+        return Err(AstNode::new(number, AstKind::Bad("Expected a number after '-'")));
+      }
+      minus.add_node(AstNode::new(parser.lexer_next(), AstKind::Literal));
+      Ok(minus)
+    },
+    _ => Err(AstNode::new(token, AstKind::Bad("Expected a pattern"))),
+  }
+}
@@ -0,0 +1,33 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn expr_path<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+// `Enum::Variant`, only a name can stand before the `::`
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("expr_path");
+  node.set_kind(AstKind::Path);
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::Union(0) => {
+        if !super::add_b_exprlike(parser, node) {
+          return None;
+        }
+        if node[0].get_kind() != AstKind::Identifier {
+          super::error(node, "Expected enum name before '::'");
+          return None;
+        }
+      },
+      TokenKind::DColon => continue,
+      TokenKind::Identifier => {
+        if !super::add_token(parser, node, TokenKind::Identifier, AstKind::Identifier, "Expected variant name after '::'") {
+          return None;
+        }
+      },
+      _ => panic!("Grammer for expr_path Error"),
+    }
+  }
+
+  Some(AstKind::PushToStk)
+}
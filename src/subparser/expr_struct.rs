@@ -0,0 +1,58 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn expr_struct<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+// `Name { field: Expr, ... }`, the `{` follows the struct name
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("expr_struct");
+  node.set_kind(AstKind::StructLit);
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::Union(0) => {
+        if !super::add_b_exprlike(parser, node) {
+          return None;
+        }
+        if node[0].get_kind() != AstKind::Identifier {
+          super::error(node, "Expected struct name before '{'");
+          return None;
+        }
+      },
+      TokenKind::LStmt => continue,
+      TokenKind::Union(1) => {
+        if !parse_inits(parser, node) {
+          return None;
+        }
+      },
+      TokenKind::RStmt => {
+        super::expect_token(parser, node, TokenKind::RStmt, "Expected '}' or ','")?;
+      },
+      _ => panic!("Grammer for expr_struct Error"),
+    }
+  }
+
+  Some(AstKind::PushToStk)
+}
+
+// Initializers `name: Expr, ...` up to but not including '}', a trailing ',' is allowed
+fn parse_inits<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> bool {
+  while parser.lexer_peek().get_kind() != TokenKind::RStmt {
+    let name = match super::expect_token(parser, node, TokenKind::Identifier, "Expected field name") {
+      Some(token) => token,
+      None => return false,
+    };
+    let init = node.add_node(AstNode::new(name, AstKind::FieldInit));
+    if super::expect_token(parser, init, TokenKind::Colon, "Expected ':' and field value").is_none() {
+      return false;
+    }
+    if !super::add_a_exprlike(parser, init) {
+      return false;
+    }
+    if parser.lexer_peek().get_kind() != TokenKind::Comma {
+      return true;
+    }
+    parser.lexer_next();
+  }
+  true
+}
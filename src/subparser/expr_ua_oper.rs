@@ -15,5 +15,5 @@ macro_rules! add_subparser {
   }
 }
 
-add_subparser!(expr_u_dminus, BinOper);
-add_subparser!(expr_u_dplus, BinOper);
+add_subparser!(expr_u_post_dminus, PostStep);
+add_subparser!(expr_u_post_dplus, PostStep);
@@ -8,12 +8,14 @@ macro_rules! add_subparser {
 
       fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
         node.set_kind(AstKind::$kind);
-        if !super::super::add_a_exprlike(parser, node) { return None }
+        if !super::super::add_a_operand(parser, node) { return None }
         Some(AstKind::PushToStk)
       }
     }
   }
 }
 
-add_subparser!(expr_u_minus, BinOper);
-add_subparser!(expr_u_not, BinOper);
+add_subparser!(expr_u_minus, UnOper);
+add_subparser!(expr_u_not, UnOper);
+add_subparser!(expr_u_pre_dminus, PreStep);
+add_subparser!(expr_u_pre_dplus, PreStep);
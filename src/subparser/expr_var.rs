@@ -15,7 +15,9 @@ macro_rules! add_subparser {
 }
 
 add_subparser!(expr_identifer, Identifier);
-add_subparser!(expr_int, Literal);
+add_subparser!(expr_integer, Literal);
 add_subparser!(expr_float, Literal);
 add_subparser!(expr_char, Literal);
-add_subparser!(expr_string, Literal);
\ No newline at end of file
+add_subparser!(expr_string, Literal);
+add_subparser!(expr_true, Literal);
+add_subparser!(expr_false, Literal);
\ No newline at end of file
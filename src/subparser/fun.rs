@@ -0,0 +1,81 @@
+use crate::{ast::{token::{Token, TokenKind}, AstKind, AstNode}, parser::Parser};
+
+pub fn fun<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let token = *node.get_token();
+  let expect = parser.get_expect("fun");
+  assert_eq!(token.get_kind(), expect[0]);
+  node.set_kind(AstKind::Fun);
+  let mut l_paren = token;
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::Fun => {},
+      TokenKind::Union(2) => {
+        if !super::add_a_type(parser, node, "Expected return Type") {
+          return None;
+        }
+      },
+      TokenKind::Identifier => {
+        if !super::add_token(parser, node, TokenKind::Identifier, AstKind::Identifier, "Expected function name") {
+          return None;
+        }
+      },
+      TokenKind::LParen => {
+        l_paren = super::expect_token(parser, node, TokenKind::LParen, "Expected '('")?;
+      },
+      TokenKind::Union(0) => {
+        let params = node.add_node(AstNode::new(l_paren, AstKind::Params));
+        if !parse_params(parser, params) {
+          return None;
+        }
+      },
+      TokenKind::RParen => {
+        super::expect_token(parser, node, TokenKind::RParen, "Expected ')' or ','")?;
+      },
+      TokenKind::Colon => {
+        super::expect_token(parser, node, TokenKind::Colon, "Expected ':' and return Type")?;
+      },
+      TokenKind::Union(1) => {
+        if parser.lexer_peek().get_kind() != TokenKind::LStmt {
+          super::expect_token(parser, node, TokenKind::LStmt, "Expected '{'")?;
+        }
+        super::add_a_stmt(parser, node);
+      },
+      _ => panic!("Grammer for fun Error"),
+    }
+  }
+
+  Some(AstKind::Fun)
+}
+
+// Param list `name: Type, ...` up to but not including ')', shared with lambdas
+pub fn parse_params<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> bool {
+  if parser.lexer_peek().get_kind() == TokenKind::RParen {
+    return true;
+  }
+  loop {
+    let name = match super::expect_token(parser, node, TokenKind::Identifier, "Expected parameter name") {
+      Some(token) => token,
+      None => return false,
+    };
+    let param = node.add_node(AstNode::new(name, AstKind::Param));
+    // The receiver of a method takes the implemented Type
+    if name.get_value() == "self" && parser.lexer_peek().get_kind() != TokenKind::Colon {
+      param.add_node(AstNode::new(Token::new_empty(), AstKind::Type));
+    } else {
+      if super::expect_token(parser, param, TokenKind::Colon, "Expected ':' and parameter Type").is_none() {
+        return false;
+      }
+      if !super::add_a_type(parser, param, "Expected parameter Type") {
+        return false;
+      }
+    }
+    if parser.lexer_peek().get_kind() != TokenKind::Comma {
+      return true;
+    }
+    parser.lexer_next();
+  }
+}
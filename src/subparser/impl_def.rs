@@ -0,0 +1,40 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn impl_def<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("impl_def");
+  node.set_kind(AstKind::Impl);
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::Impl => continue,
+      TokenKind::Identifier => {
+        if !super::add_token(parser, node, TokenKind::Identifier, AstKind::Type, "Expected the Type to implement") {
+          return None;
+        }
+      },
+      TokenKind::LStmt => {
+        super::expect_token(parser, node, TokenKind::LStmt, "Expected '{'")?;
+      },
+      // Methods up to but not including '}'
+      TokenKind::Union(0) => {
+        while parser.lexer_peek().get_kind() == TokenKind::Fun {
+          let mut method = AstNode::new(parser.lexer_next(), AstKind::Chisato);
+          let ok = parser.subparse(&TokenKind::Fun, &mut method).is_some();
+          node.add_node(method);
+          if !ok {
+            return None;
+          }
+        }
+      },
+      TokenKind::RStmt => {
+        super::expect_token(parser, node, TokenKind::RStmt, "Expected 'fun' or '}'")?;
+      },
+      _ => panic!("Grammer for impl_def Error"),
+    }
+  }
+
+  Some(AstKind::Impl)
+}
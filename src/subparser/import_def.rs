@@ -0,0 +1,35 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn import_def<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("import_def");
+  node.set_kind(AstKind::Import);
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::Import => continue,
+      TokenKind::Identifier => {
+        if !super::add_token(parser, node, TokenKind::Identifier, AstKind::Identifier, "Expected module name") {
+          return None;
+        }
+      },
+      // `::name` segments of the module path
+      TokenKind::Union(0) => {
+        while parser.lexer_peek().get_kind() == TokenKind::DColon {
+          parser.lexer_next();
+          if !super::add_token(parser, node, TokenKind::Identifier, AstKind::Identifier, "Expected module name after '::'") {
+            return None;
+          }
+        }
+      },
+      TokenKind::SemiColon => {
+        super::expect_token(parser, node, TokenKind::SemiColon, "Expected ';' or '::'")?;
+      },
+      _ => panic!("Grammer for import_def Error"),
+    }
+  }
+
+  Some(AstKind::Import)
+}
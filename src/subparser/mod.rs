@@ -1,4 +1,5 @@
 mod expr_paren;
+mod expr_call;
 mod expr_b_oper;
 use expr_b_oper::*;
 mod expr_ua_oper;
@@ -9,10 +10,32 @@ mod expr_var;
 use expr_var::*;
 mod expr_args;
 mod expr_indexing;
+mod expr_array;
+mod expr_struct;
+mod expr_field;
+mod expr_path;
+mod expr_match;
+mod expr_lambda;
+mod fun;
+mod struct_def;
+mod enum_def;
+mod impl_def;
+mod import_def;
+mod pub_def;
+mod stmt_multi;
+mod stmt_let;
+mod stmt_if;
+mod stmt_else;
+mod stmt_while;
+mod stmt_for;
+mod stmt_return;
+mod stmt_jump;
+use stmt_jump::*;
 
 use std::collections::BTreeMap;
-use crate::parser::Parser;
+use crate::parser::{Parser, UNARY_PREC};
 use crate::ast::{AstNode, AstKind};
+use crate::ast::token::{Token, TokenKind};
 
 pub type SubParserList<'a> = BTreeMap<&'static str, SubParser<'a>>;
 
@@ -24,21 +47,34 @@ pub fn error(node: &mut AstNode, msg: &'static str) {
 
 // Add an Expr to node or report error
 pub fn add_a_exprlike<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> bool {
-  let new_node = node.add_node(AstNode::new_empty());
-  parser.parse_fn(new_node);
-  if new_node.child_count() > 0 {
-    error(node, "Expected Expr or Identifier");
-    return false;
+  match parser.parse_expr(0) {
+    Ok(expr) => {
+      node.add_node(expr);
+      true
+    },
+    Err(bad) => {
+      node.add_node(bad);
+      false
+    },
   }
-  node.add_node(match parser.pop_node() {
-    Some(node) => node,
-    None => {
-      error(node, "Expected Expr or Identifier");
-      return false;
-    }
-  });
+}
 
-  true
+// Add the operand after an operator, binding as tight as the operator requires
+pub fn add_a_operand<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> bool {
+  let min_prec = match node.get_kind() {
+    AstKind::UnOper | AstKind::PreStep => UNARY_PREC,
+    _ => parser.get_rhs_prec(&node.get_token().get_kind()),
+  };
+  match parser.parse_expr(min_prec) {
+    Ok(expr) => {
+      node.add_node(expr);
+      true
+    },
+    Err(bad) => {
+      node.add_node(bad);
+      false
+    },
+  }
 }
 
 pub fn add_b_exprlike<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> bool {
@@ -55,6 +91,78 @@ pub fn add_b_exprlike<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> bo
   true
 }
 
+// Add a Stmt to node, the statement recovers from its own errors
+pub fn add_a_stmt<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) {
+  let stmt = parser.parse_stmt();
+  node.add_node(stmt);
+}
+
+// Consume the next token if it is of `kind`, otherwise record a Bad node at it
+pub fn expect_token<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>, kind: TokenKind, msg: &'static str) -> Option<Token<'a>> {
+  let token = parser.lexer_peek();
+  if token.get_kind() != kind {
+    node.add_node(AstNode::new(token, AstKind::Bad(msg)));
+    return None;
+  }
+  Some(parser.lexer_next())
+}
+
+// Consume the next token if it is of `kind` and add it to node as `ast_kind`
+pub fn add_token<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>, kind: TokenKind, ast_kind: AstKind, msg: &'static str) -> bool {
+  match expect_token(parser, node, kind, msg) {
+    Some(token) => {
+      node.add_node(AstNode::new(token, ast_kind));
+      true
+    },
+    None => false,
+  }
+}
+
+// Add a Type to node: a name, `[Type]` and `[Type; N]` with the element
+// Type and the length Literal as children of the `[`, or `fun(Type, ...): Type`
+// with the parameter and return Types as children of the `fun`
+pub fn add_a_type<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>, msg: &'static str) -> bool {
+  match parser.lexer_peek().get_kind() {
+    TokenKind::LIndex => {},
+    TokenKind::Fun => return add_a_fun_type(parser, node),
+    _ => return add_token(parser, node, TokenKind::Identifier, AstKind::Type, msg),
+  }
+  let array = node.add_node(AstNode::new(parser.lexer_next(), AstKind::Type));
+  if !add_a_type(parser, array, "Expected element Type") {
+    return false;
+  }
+  if parser.lexer_peek().get_kind() == TokenKind::SemiColon {
+    parser.lexer_next();
+    if !add_token(parser, array, TokenKind::Int, AstKind::Literal, "Expected array length") {
+      return false;
+    }
+  }
+  expect_token(parser, array, TokenKind::RIndex, "Expected ']'").is_some()
+}
+
+fn add_a_fun_type<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> bool {
+  let fun = node.add_node(AstNode::new(parser.lexer_next(), AstKind::Type));
+  if expect_token(parser, fun, TokenKind::LParen, "Expected '('").is_none() {
+    return false;
+  }
+  while parser.lexer_peek().get_kind() != TokenKind::RParen {
+    if !add_a_type(parser, fun, "Expected parameter Type") {
+      return false;
+    }
+    if parser.lexer_peek().get_kind() != TokenKind::Comma {
+      break;
+    }
+    parser.lexer_next();
+  }
+  if expect_token(parser, fun, TokenKind::RParen, "Expected ')' or ','").is_none() {
+    return false;
+  }
+  if expect_token(parser, fun, TokenKind::Colon, "Expected ':' and return Type").is_none() {
+    return false;
+  }
+  add_a_type(parser, fun, "Expected return Type")
+}
+
 #[derive(Copy, Clone)]
 pub struct SubParser<'a> {
   parse_token: ParseToken<'a>,
@@ -68,8 +176,8 @@ impl<'a> SubParser<'a> {
 
 impl<'a> SubParser<'a> {
   pub fn new(
-    parse_token: ParseToken,
-  ) -> SubParser {
+    parse_token: ParseToken<'a>,
+  ) -> SubParser<'a> {
 
     SubParser {
       parse_token,
@@ -83,19 +191,38 @@ impl<'a> SubParser<'a> {
         subparser_list.insert(stringify!($parser), $parser::$parser());
       };
     }
+    add_subparser!(fun);
+    add_subparser!(struct_def);
+    add_subparser!(enum_def);
+    add_subparser!(impl_def);
+    add_subparser!(import_def);
+    add_subparser!(pub_def);
+    add_subparser!(stmt_multi);
+    add_subparser!(stmt_let);
+    add_subparser!(stmt_if);
+    add_subparser!(stmt_else);
+    add_subparser!(stmt_while);
+    add_subparser!(stmt_for);
+    add_subparser!(stmt_return);
+    add_subparser!(stmt_break);
+    add_subparser!(stmt_continue);
+    add_subparser!(stmt_empty);
     add_subparser!(expr_paren);
+    add_subparser!(expr_call);
+    add_subparser!(expr_args);
     add_subparser!(expr_identifer);
-    add_subparser!(expr_int);
+    add_subparser!(expr_integer);
     add_subparser!(expr_float);
     add_subparser!(expr_char);
     add_subparser!(expr_string);
+    add_subparser!(expr_true);
+    add_subparser!(expr_false);
     add_subparser!(expr_b_equal);
     add_subparser!(expr_b_nequal);
     add_subparser!(expr_b_less);
     add_subparser!(expr_b_lesseq);
     add_subparser!(expr_b_greater);
     add_subparser!(expr_b_greatereq);
-    add_subparser!(expr_b_dot);
     add_subparser!(expr_b_ddot);
     add_subparser!(expr_b_and);
     add_subparser!(expr_b_or);
@@ -120,11 +247,19 @@ impl<'a> SubParser<'a> {
     add_subparser!(expr_b_dand);
     add_subparser!(expr_b_dor);
     add_subparser!(expr_b_dequal);
-    add_subparser!(expr_u_dminus);
-    add_subparser!(expr_u_dplus);
+    add_subparser!(expr_u_pre_dminus);
+    add_subparser!(expr_u_pre_dplus);
+    add_subparser!(expr_u_post_dminus);
+    add_subparser!(expr_u_post_dplus);
     add_subparser!(expr_u_minus);
     add_subparser!(expr_u_not);
     add_subparser!(expr_indexing);
+    add_subparser!(expr_array);
+    add_subparser!(expr_struct);
+    add_subparser!(expr_field);
+    add_subparser!(expr_path);
+    add_subparser!(expr_match);
+    add_subparser!(expr_lambda);
 
     subparser_list
   }
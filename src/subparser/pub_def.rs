@@ -0,0 +1,30 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::{Parser, PUB_KINDS}};
+
+pub fn pub_def<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+// The marked item is parsed into this node, then gets `pub` back as its token
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("pub_def");
+  let pub_token = *node.get_token();
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::Pub => continue,
+      TokenKind::Union(0) => {
+        let kind = parser.lexer_peek().get_kind();
+        if !PUB_KINDS.contains(&kind) {
+          node.add_node(AstNode::new(parser.lexer_peek(), AstKind::Bad("Expected 'fun', 'let', 'struct' or 'enum' after 'pub'")));
+          return None;
+        }
+        node.set_token(parser.lexer_next());
+        let item = parser.subparse(&kind, node);
+        node.set_token(pub_token);
+        return item;
+      },
+      _ => panic!("Grammer for pub_def Error"),
+    }
+  }
+
+  None
+}
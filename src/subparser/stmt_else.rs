@@ -0,0 +1,19 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn stmt_else<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("stmt_else");
+  node.set_kind(AstKind::Else);
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::Else => continue,
+      TokenKind::Union(0) => super::add_a_stmt(parser, node),
+      _ => panic!("Grammer for stmt_else Error"),
+    }
+  }
+
+  Some(AstKind::Else)
+}
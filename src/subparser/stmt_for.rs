@@ -0,0 +1,36 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn stmt_for<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("stmt_for");
+  node.set_kind(AstKind::For);
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::For => continue,
+      TokenKind::Identifier => {
+        if !super::add_token(parser, node, TokenKind::Identifier, AstKind::Identifier, "Expected loop variable") {
+          return None;
+        }
+      },
+      TokenKind::In => {
+        super::expect_token(parser, node, TokenKind::In, "Expected 'in'")?;
+      },
+      // `for p in points { ... }` must not read `points { ... }` as a struct literal
+      TokenKind::Union(0) => {
+        let outer = parser.set_struct_literals(false);
+        let ok = super::add_a_exprlike(parser, node);
+        parser.set_struct_literals(outer);
+        if !ok {
+          return None;
+        }
+      },
+      TokenKind::Union(1) => super::add_a_stmt(parser, node),
+      _ => panic!("Grammer for stmt_for Error"),
+    }
+  }
+
+  Some(AstKind::For)
+}
@@ -0,0 +1,36 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn stmt_if<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("stmt_if");
+  node.set_kind(AstKind::If);
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::If => continue,
+      TokenKind::LParen => {
+        super::expect_token(parser, node, TokenKind::LParen, "Expected '(' after 'if'")?;
+      },
+      TokenKind::Union(0) => {
+        if !super::add_a_exprlike(parser, node) {
+          return None;
+        }
+      },
+      TokenKind::RParen => {
+        super::expect_token(parser, node, TokenKind::RParen, "Expected ')'")?;
+      },
+      TokenKind::Union(1) => super::add_a_stmt(parser, node),
+      _ => panic!("Grammer for stmt_if Error"),
+    }
+  }
+  // An `else` right after the body belongs to this `if`
+  if parser.lexer_peek().get_kind() == TokenKind::Else {
+    let token = parser.lexer_next();
+    let else_node = node.add_node(AstNode::new(token, AstKind::Chisato));
+    parser.subparse(&TokenKind::Else, else_node)?;
+  }
+
+  Some(AstKind::If)
+}
@@ -0,0 +1,22 @@
+macro_rules! add_subparser {
+  ($name:ident, $kind:ident) => {
+    pub mod $name {
+      use crate::{ast::{AstKind, AstNode}, parser::Parser, subparser::SubParser};
+      pub fn $name<'a>() -> SubParser<'a> {
+        SubParser::new(parse)
+      }
+
+      fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+        node.set_kind(AstKind::$kind);
+        for e_kind in parser.get_expect(stringify!($name)).into_iter().skip(1) {
+          super::super::expect_token(parser, node, e_kind, "Expected ';'")?;
+        }
+        Some(AstKind::$kind)
+      }
+    }
+  };
+}
+
+add_subparser!(stmt_break, Break);
+add_subparser!(stmt_continue, Continue);
+add_subparser!(stmt_empty, Stmt);
@@ -0,0 +1,47 @@
+use crate::{ast::{token::{Token, TokenKind}, AstKind, AstNode}, parser::Parser};
+
+pub fn stmt_let<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+// Union(1) is the optional `: Type`, Union(0) the optional `= Expr`
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("stmt_let");
+  node.set_kind(AstKind::Let);
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::Let => continue,
+      TokenKind::Identifier => {
+        if !super::add_token(parser, node, TokenKind::Identifier, AstKind::Identifier, "Expected variable name") {
+          return None;
+        }
+      },
+      TokenKind::Union(1) => {
+        if parser.lexer_peek().get_kind() != TokenKind::Colon {
+          // Left for the checker to infer
+          node.add_node(AstNode::new(Token::new_empty(), AstKind::Type));
+          continue;
+        }
+        parser.lexer_next();
+        if !super::add_a_type(parser, node, "Expected Type") {
+          return None;
+        }
+      },
+      TokenKind::Union(0) => {
+        if parser.lexer_peek().get_kind() != TokenKind::Equal {
+          continue;
+        }
+        parser.lexer_next();
+        if !super::add_a_exprlike(parser, node) {
+          return None;
+        }
+      },
+      TokenKind::SemiColon => {
+        super::expect_token(parser, node, TokenKind::SemiColon, "Expected ';'")?;
+      },
+      _ => panic!("Grammer for stmt_let Error"),
+    }
+  }
+
+  Some(AstKind::Let)
+}
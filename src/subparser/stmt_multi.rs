@@ -0,0 +1,29 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn stmt_multi<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("stmt_multi");
+  node.set_kind(AstKind::Block);
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::LStmt => continue,
+      TokenKind::Union(0) => {
+        loop {
+          match parser.lexer_peek().get_kind() {
+            TokenKind::RStmt | TokenKind::EOF => break,
+            _ => super::add_a_stmt(parser, node),
+          }
+        }
+      },
+      TokenKind::RStmt => {
+        super::expect_token(parser, node, TokenKind::RStmt, "Expected '}'")?;
+      },
+      _ => panic!("Grammer for stmt_multi Error"),
+    }
+  }
+
+  Some(AstKind::Block)
+}
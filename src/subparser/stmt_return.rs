@@ -0,0 +1,27 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn stmt_return<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("stmt_return");
+  node.set_kind(AstKind::Return);
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::Return => continue,
+      TokenKind::Union(0) => {
+        // The value is optional in `return;`
+        if parser.lexer_peek().get_kind() != TokenKind::SemiColon && !super::add_a_exprlike(parser, node) {
+          return None;
+        }
+      },
+      TokenKind::SemiColon => {
+        super::expect_token(parser, node, TokenKind::SemiColon, "Expected ';'")?;
+      },
+      _ => panic!("Grammer for stmt_return Error"),
+    }
+  }
+
+  Some(AstKind::Return)
+}
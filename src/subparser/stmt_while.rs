@@ -0,0 +1,30 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn stmt_while<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("stmt_while");
+  node.set_kind(AstKind::While);
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::While => continue,
+      TokenKind::LParen => {
+        super::expect_token(parser, node, TokenKind::LParen, "Expected '(' after 'while'")?;
+      },
+      TokenKind::Union(0) => {
+        if !super::add_a_exprlike(parser, node) {
+          return None;
+        }
+      },
+      TokenKind::RParen => {
+        super::expect_token(parser, node, TokenKind::RParen, "Expected ')'")?;
+      },
+      TokenKind::Union(1) => super::add_a_stmt(parser, node),
+      _ => panic!("Grammer for stmt_while Error"),
+    }
+  }
+
+  Some(AstKind::While)
+}
@@ -0,0 +1,58 @@
+use crate::{ast::{token::TokenKind, AstKind, AstNode}, parser::Parser};
+
+pub fn struct_def<'a>() -> super::SubParser<'a> {
+  super::SubParser::new(parse)
+}
+
+fn parse<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> Option<AstKind> {
+  let expect = parser.get_expect("struct_def");
+  node.set_kind(AstKind::Struct);
+  let mut l_stmt = *node.get_token();
+  for e_kind in expect {
+    match e_kind {
+      TokenKind::Struct => continue,
+      TokenKind::Identifier => {
+        if !super::add_token(parser, node, TokenKind::Identifier, AstKind::Identifier, "Expected struct name") {
+          return None;
+        }
+      },
+      TokenKind::LStmt => {
+        l_stmt = super::expect_token(parser, node, TokenKind::LStmt, "Expected '{'")?;
+      },
+      TokenKind::Union(0) => {
+        let fields = node.add_node(AstNode::new(l_stmt, AstKind::Fields));
+        if !parse_fields(parser, fields) {
+          return None;
+        }
+      },
+      TokenKind::RStmt => {
+        super::expect_token(parser, node, TokenKind::RStmt, "Expected '}' or ','")?;
+      },
+      _ => panic!("Grammer for struct_def Error"),
+    }
+  }
+
+  Some(AstKind::Struct)
+}
+
+// Field list `name: Type, ...` up to but not including '}', a trailing ',' is allowed
+fn parse_fields<'a>(parser: &mut Parser<'a>, node: &mut AstNode<'a>) -> bool {
+  while parser.lexer_peek().get_kind() != TokenKind::RStmt {
+    let name = match super::expect_token(parser, node, TokenKind::Identifier, "Expected field name") {
+      Some(token) => token,
+      None => return false,
+    };
+    let field = node.add_node(AstNode::new(name, AstKind::Field));
+    if super::expect_token(parser, field, TokenKind::Colon, "Expected ':' and field Type").is_none() {
+      return false;
+    }
+    if !super::add_a_type(parser, field, "Expected field Type") {
+      return false;
+    }
+    if parser.lexer_peek().get_kind() != TokenKind::Comma {
+      return true;
+    }
+    parser.lexer_next();
+  }
+  true
+}
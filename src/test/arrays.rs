@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+  use crate::driver;
+  use crate::interpreter::{self, value::Value};
+  use crate::lexer::Lexer;
+  use crate::parser::Parser;
+  use crate::test::{headlines, messages};
+
+  #[test]
+  fn literal_or_index_by_context() {
+    let mut parser = Parser::new(Lexer::new("let g: [[int; 2]] = [[1, 2,], [0; n]][i][0];"));
+    let ast = parser.parse();
+    assert_eq!(
+      ast.get_root()[0].dump(),
+      "(Let let (Identifier g) (Type [ (Type [ (Type int) (Literal 2))) \
+       (Index [ (Index [ (Array [ (Array [ (Literal 1) (Literal 2)) (Repeat [ (Literal 0) (Identifier n))) (Identifier i)) (Literal 0)))"
+    );
+  }
+
+  #[test]
+  fn lengths_are_part_of_the_type() {
+    assert_eq!(messages("
+fun sum(a: [int]): int { return a[0]; }
+fun main(): int {
+  let a: [int; 2] = [1, 2, 3];
+  let b: [int; 3] = [0; 3];
+  let c = [1, 'x'];
+  let d = [];
+  return sum(b) + sum([0; b[0]]);
+}"), vec![
+      "4:21: error: mismatched types in `let` initializer: expected `[int; 2]`, found `[int; 3]`",
+      "6:15: error: mismatched types in array element: expected `int`, found `char`",
+      "7:7: error: type annotations needed: cannot infer the type of `d`",
+      "8:14: error: mismatched types in argument 1 of `sum`: expected `[int]`, found `[int; 3]`",
+    ]);
+    // A fixed array never becomes a resizable one that could change its length
+    assert_eq!(messages("
+fun main(): int {
+  let a: [int; 3] = [1, 2, 3];
+  let b: [int] = a;
+  push(b, 4);
+  let c: [int; 3] = b;
+  return len(c);
+}"), vec![
+      "4:18: error: mismatched types in `let` initializer: expected `[int]`, found `[int; 3]`",
+      "6:21: error: mismatched types in `let` initializer: expected `[int; 3]`, found `[int]`",
+    ]);
+  }
+
+  #[test]
+  fn literals_take_the_length_they_are_checked_against() {
+    let source = "
+fun corner(m: [[int; 2]; 2]): int { return m[1][1]; }
+fun main(): int {
+  let xs = [1, 2, 3];
+  xs.push(4);
+  let m: [[int; 2]; 2] = [[1, 2], [0; 2]];
+  m[1][1] = 5;
+  return len(xs) * 10 + corner([[0, 0], [0, 1]]) + corner(m);
+}";
+    let analysis = driver::analyze(source);
+    assert!(analysis.diagnostics.is_empty(), "{:?}", analysis.diagnostics);
+    assert_eq!(interpreter::run(&analysis.ast), Ok(Value::Int(46)));
+  }
+
+  #[test]
+  fn runs_nested_arrays_with_bounds_checks() {
+    let source = "
+fun main(): int {
+  let grid = [[0; 3]; 2];
+  grid[1][2] = 7;
+  let rows = [grid[0], grid[1]];
+  return rows[1][2] + grid[0][2] + [5, 6,][1] + grid[2][0];
+}";
+    let analysis = driver::analyze(source);
+    assert!(analysis.diagnostics.is_empty(), "{:?}", analysis.diagnostics);
+    let error = interpreter::run(&analysis.ast).unwrap_err();
+    assert_eq!(headlines(source, &[error]), ["6:49: error: index 2 out of bounds for length 2"]);
+
+    let source = source.replace(" + grid[2][0]", "");
+    let analysis = driver::analyze(&source);
+    assert_eq!(interpreter::run(&analysis.ast).ok(), Some(Value::Int(13)));
+  }
+}
@@ -0,0 +1,135 @@
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+  use std::process::Command;
+
+  use crate::test::carf;
+
+  const PROGRAM: &str = "
+struct Point { x: int, y: float, name: string }
+impl Point {
+  fun norm(self): float { return sqrt(self.y * self.y); }
+}
+let count = 3;
+fun fib(n: int): int { if (n < 2) { return n; } return fib(n - 1) + fib(n - 2); }
+fun bump(xs: [int]): unit { return xs[fib(2)] += 5; }
+fun main(): int {
+  let p = Point { x: 1, y: -2.5, name: \"h\u{e9}llo\\n\" };
+  println(p);
+  println(p.norm());
+  let xs: [int] = [3, 1, 2];
+  push(xs, 10);
+  xs.push(-4);
+  sort(xs);
+  bump(xs);
+  println(xs);
+  let k = 1;
+  println([k++, ++k, xs[0]--, --xs[0], k]);
+  let grid = [[0; 2]; 2];
+  grid[0][1] = 5;
+  grid[fib(1)][count - 3] += 7;
+  println(grid);
+  let s = \"abc\";
+  s[1] = '\u{e9}';
+  println(s + \"!\" + to_string(len(s)));
+  for c in s { print(c); print(' '); }
+  println(\"\");
+  let total = 0;
+  for i in 0..10 { if (i == 3) { continue; } if (i == 8) { break; } total += i; }
+  let __i_next = \"user\";
+  for i in 0..2 { println(__i_next + to_string(i)); }
+  println(split(\"a,b,,c\", \",\"));
+  println([1.0, 0.1, 10000000000000000.0 * 3.0, 0.00000015, -0.0, 0.0 / 0.0, -1.0 / 0.0]);
+  println(to_int(\" -42 \") + to_int(3.9) + floor(-2.5) + pow(2, 10) + pow(-1, 5000000001) + min(3, 4) + abs(-7));
+  println(['a', '\\n']);
+  println([p == Point { x: 1, y: -2.5, name: \"\" }, p == Point { x: 1, y: -2.5, name: p.name }]);
+  return total + fib(count * 5);
+}";
+
+  // Exit code, stdout and stderr of the program compiled with `cc`, `None`
+  // where there is no C compiler
+  fn compile_and_run(name: &str, source: &str) -> Option<(i32, String, String)> {
+    let (code, c, err) = carf(&["c", "--color=never"], source);
+    assert_eq!(code, 0, "{}", err);
+    run_c(name, c)
+  }
+
+  fn run_c(name: &str, c: String) -> Option<(i32, String, String)> {
+    let dir: PathBuf = std::env::temp_dir().join(format!("carf-c-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("main.c"), c).unwrap();
+    let status = Command::new("cc")
+      .args(["-std=c99", "-Wall", "-Wextra", "-pedantic", "-Werror", "-o", "main", "main.c", "-lm"])
+      .current_dir(&dir)
+      .status();
+    match status {
+      Ok(status) => assert!(status.success(), "cc failed on {}", dir.display()),
+      Err(_) => return None,
+    }
+    let output = Command::new(dir.join("main")).output().unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+    let text = |bytes: Vec<u8>| String::from_utf8(bytes).unwrap();
+    Some((output.status.code().unwrap(), text(output.stdout), text(output.stderr)))
+  }
+
+  #[test]
+  fn compiled_output_matches_run() {
+    let Some((code, out, err)) = compile_and_run("match", PROGRAM) else { return };
+    assert_eq!(err, "");
+    assert_eq!(code, 0);
+    assert_eq!(carf(&["run"], PROGRAM), (0, out, String::new()));
+  }
+
+  #[test]
+  fn private_names_of_modules_stay_apart_from_the_others() {
+    let dir: PathBuf = std::env::temp_dir().join(format!("carf-c-modules-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("util")).unwrap();
+    std::fs::write(dir.join("util/math.carf"), "fun helper(): int { return 13; }\npub fun one(): int { return helper(); }\n").unwrap();
+    let path = dir.join("main.carf");
+    std::fs::write(&path, "import util::math;
+fun helper(): int { return 0; }
+fun util__math__helper(): int { return 100; }
+fun main(): int { return util__math__helper() + one() + helper(); }
+").unwrap();
+    let path = path.to_str().unwrap();
+    assert_eq!(carf(&["run", path], ""), (0, "113\n".to_string(), String::new()));
+    let (code, c, err) = carf(&["c", path], "");
+    let _ = std::fs::remove_dir_all(&dir);
+    assert_eq!(code, 0, "{}", err);
+    let Some(run) = run_c("modules", c) else { return };
+    assert_eq!(run, (0, "113\n".to_string(), String::new()));
+  }
+
+  #[test]
+  fn runtime_errors_exit_with_a_location() {
+    let Some((code, out, err)) = compile_and_run("bounds", "fun main(): int {
+  println(1);
+  let xs = [1, 2];
+  return xs[5];
+}") else { return };
+    assert_eq!((code, out.as_str()), (1, "1\n"));
+    assert_eq!(err, "<stdin>:4:10: error: index 5 out of bounds for length 2\n");
+
+    let source = "fun twice(n: int): int { return n * 2; }
+fun main(): int { let n = 1; while (true) { n = twice(n); } return n; }";
+    let Some((code, _, err)) = compile_and_run("overflow", source) else { return };
+    assert_eq!(code, 1);
+    assert!(err.starts_with("<stdin>:1:33: error: arithmetic overflow"), "{}", err);
+  }
+
+  #[test]
+  fn unsupported_features_are_reported() {
+    let (code, out, err) = carf(&["c", "--color=never"], "
+enum Shape { Dot }
+fun main(): int {
+  let f = fun(x: int): int { return x; };
+  return f(1);
+}");
+    assert_eq!((code, out.as_str()), (1, ""));
+    let lines: Vec<&str> = err.lines().filter(|l| l.starts_with("<stdin>")).collect();
+    assert_eq!(lines, [
+      "<stdin>:2:1: error: enums cannot be compiled to C",
+      "<stdin>:4:7: error: function values cannot be compiled to C",
+    ]);
+  }
+}
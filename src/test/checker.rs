@@ -0,0 +1,162 @@
+#[cfg(test)]
+mod tests {
+  use crate::lexer::Lexer;
+  use crate::parser::Parser;
+  use crate::checker::Checker;
+  use crate::diagnostic;
+  use crate::test::render;
+
+  fn check(source: &str) -> Vec<String> {
+    let mut parser = Parser::new(Lexer::new(source));
+    let ast = parser.parse();
+    let parse_errors = diagnostic::parse_errors(ast);
+    assert!(parse_errors.is_empty(), "{:?}", parse_errors);
+    let mut checker = Checker::new();
+    render(source, checker.check(ast))
+  }
+
+  fn messages(source: &str) -> Vec<String> {
+    check(source).iter().map(|d| d.lines().next().unwrap().to_string()).collect()
+  }
+
+  #[test]
+  fn well_typed_program() {
+    let source = "
+let limit: int = 10;
+fun fib(n: int): int {
+  if (n < 2) { return n; }
+  return fib(n - 1) + fib(n - 2);
+}
+fun main(): unit {
+  let total: int = 0;
+  let ratio: float = 1.5 * 2.0;
+  let name: string = \"carf\" + \"!\";
+  let first: char = name[0];
+  let ok: bool = !(total == 1) && first != 'x';
+  for i in 0..limit {
+    total += fib(i) % 7;
+    total++;
+  }
+  while (ok) { ok = false; }
+  return;
+}";
+    assert_eq!(check(source), Vec::<String>::new());
+  }
+
+  #[test]
+  fn initializer_and_operator_mismatch() {
+    let errors = messages("
+fun main(): unit {
+  let a: int = 1.5;
+  let b: float = 1 + 2.0;
+  let c: bool = -true;
+  let d: int = 1 << 2.0;
+  let e: whatever = 1;
+}");
+    assert_eq!(errors, vec![
+      "3:16: error: mismatched types in `let` initializer: expected `int`, found `float`",
+      "4:18: error: cannot apply binary operator `+` to types `int` and `float`",
+      "5:17: error: cannot apply unary operator `-` to type `bool`",
+      "6:16: error: cannot apply binary operator `<<` to types `int` and `float`",
+      "7:10: error: cannot find type `whatever`",
+    ]);
+  }
+
+  #[test]
+  fn calls_returns_and_conditions() {
+    let errors = messages("
+fun add(a: int, b: int): int { return a + b; }
+fun main(): int {
+  add(1);
+  add(1, 'c');
+  if (1) { return true; }
+  while (add(1, 2)) { return; }
+  missing(2);
+  3 = 4;
+  return add(1, 2);
+}");
+    assert_eq!(errors, vec![
+      "4:3: error: function `add` takes 2 arguments but 1 was supplied",
+      "5:10: error: mismatched types in argument 2 of `add`: expected `int`, found `char`",
+      "6:7: error: mismatched types in `if` condition: expected `bool`, found `int`",
+      "6:19: error: mismatched types in `return` value: expected `int`, found `bool`",
+      "7:10: error: mismatched types in `while` condition: expected `bool`, found `int`",
+      "7:23: error: `return;` in a function returning `int`",
+      "8:3: error: cannot find function `missing`",
+      "9:3: error: cannot assign to this expression",
+    ]);
+  }
+}
+
+#[cfg(test)]
+mod inference {
+  use crate::lexer::Lexer;
+  use crate::parser::Parser;
+  use crate::checker::Checker;
+  use crate::checker::types::Type;
+  use crate::diagnostic;
+  use crate::test::headlines;
+
+  fn messages(source: &str) -> Vec<String> {
+    let mut parser = Parser::new(Lexer::new(source));
+    let ast = parser.parse();
+    assert!(diagnostic::parse_errors(ast).is_empty());
+    let mut checker = Checker::new();
+    headlines(source, checker.check(ast))
+  }
+
+  #[test]
+  fn infers_from_initializers_and_later_uses() {
+    let source = "
+let scale = 2.5;
+fun main(): unit {
+  let n = 3;
+  let name = \"x\" + \"y\";
+  let later;
+  let total;
+  total = n * 2;
+  later = scale;
+  let both = later > 1.0 && n < total;
+}";
+    let mut parser = Parser::new(Lexer::new(source));
+    let ast = parser.parse();
+    let mut checker = Checker::new();
+    assert!(checker.check(ast).is_empty(), "{:?}", checker.get_diagnostics());
+    let type_of = |name: &str| {
+      let pos = source.find(&format!("let {}", name)).unwrap() + 4;
+      checker.get_binding_type(pos).unwrap().clone()
+    };
+    assert_eq!(type_of("scale"), Type::Float);
+    assert_eq!(type_of("n"), Type::Int);
+    assert_eq!(type_of("name"), Type::String);
+    assert_eq!(type_of("later"), Type::Float);
+    assert_eq!(type_of("total"), Type::Int);
+    assert_eq!(type_of("both"), Type::Bool);
+  }
+
+  #[test]
+  fn reports_ambiguity_and_conflicts() {
+    let errors = messages("
+let unset;
+fun main(): unit {
+  let a;
+  let b;
+  let c = a + b;
+  let d;
+  d = 1;
+  d = 'c';
+  let e;
+  let f = e + e;
+  e = true;
+}");
+    assert_eq!(errors, vec![
+      "2:5: error: global `unset` must be initialized",
+      "2:5: error: type annotations needed: cannot infer the type of `unset`",
+      "4:7: error: type annotations needed: cannot infer the type of `a`",
+      "5:7: error: type annotations needed: cannot infer the type of `b`",
+      "6:7: error: type annotations needed: cannot infer the type of `c`",
+      "9:7: error: mismatched types in assignment: expected `int`, found `char`",
+      "11:11: error: cannot apply binary operator `+` to types `bool` and `bool`",
+    ]);
+  }
+}
@@ -0,0 +1,31 @@
+#[cfg(test)]
+mod tests {
+  use crate::test::carf;
+
+  // Exit code, stdout and stderr of `carf` given `input` on stdin
+  #[test]
+  fn runs_and_checks_programs() {
+    let program = "fun main(): int { return 6 * 7; }";
+    assert_eq!(carf(&["run", "--color=never"], program), (0, "42\n".to_string(), String::new()));
+    assert_eq!(carf(&["check", "-"], program).0, 0);
+
+    let (code, _, err) = carf(&["check", "--color=never"], "fun main(): unit { let x: int = true; }");
+    assert_eq!(code, 1);
+    assert!(err.starts_with("<stdin>:1:33: error: mismatched types"), "{}", err);
+
+    let (code, out, _) = carf(&["check", "--message-format=json"], "let x: int = 1 / 0;");
+    assert_eq!(code, 1);
+    assert_eq!(out, "{\"file\":\"<stdin>\",\"severity\":\"error\",\"message\":\"division by zero\",\
+\"line\":1,\"column\":14,\"start\":13,\"end\":18}\n");
+  }
+
+  #[test]
+  fn formats_and_dumps() {
+    assert_eq!(carf(&["fmt"], "let x=1;"), (0, "let x = 1;\n".to_string(), String::new()));
+    assert_eq!(carf(&["fmt", "--check"], "let x = 1;\n").0, 0);
+    assert_eq!(carf(&["fmt", "--check"], "let x=1;").0, 1);
+    assert_eq!(carf(&["tokens"], "x;").1, "1:1 Identifier x\n1:2 SemiColon ;\n1:3 EOF \n");
+    assert_eq!(carf(&["ast"], "let x = 1;").1, "Root\n  Let let\n    Identifier x\n    Type\n    Literal 1\n");
+    assert_eq!(carf(&["frobnicate"], "").0, 2);
+  }
+}
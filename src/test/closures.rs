@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod tests {
+  use crate::driver;
+  use crate::interpreter::{self, value::Value};
+  use crate::printer::format_source;
+  use crate::resolve::Resolution;
+  use crate::test::messages;
+
+  #[test]
+  fn lambdas_print_and_capture() {
+    let source = "fun f(g: fun(int, bool): int): fun(): int {
+  let a = 1;
+  let b = 2;
+  b += 1;
+  return fun(): int { let c = a; return c + b; };
+}
+";
+    assert_eq!(format_source(source).unwrap(), "fun f(g: fun(int, bool): int): fun(): int {
+  let a = 1;
+  let b = 2;
+  b += 1;
+  return fun(): int {
+    let c = a;
+    return c + b;
+  };
+}
+");
+    let analysis = driver::analyze(source);
+    let resolution = Resolution::resolve(&analysis.ast);
+    let lambda = source.rfind("fun()").unwrap();
+    let captures: Vec<(&str, bool)> = resolution.captures_of(lambda).iter().map(|c| (c.name, c.by_ref)).collect();
+    assert_eq!(captures, vec![("a", false), ("b", true)]);
+  }
+
+  #[test]
+  fn function_types_are_checked() {
+    assert_eq!(messages("
+fun twice(f: fun(int): int, x: int): int { return f(f(x)); }
+fun main(): int {
+  let inc = fun(x: int): int { return x + 1; };
+  let s: string = inc(1);
+  let n = 3;
+  twice(main, 1);
+  inc == inc;
+  n(1);
+  let bad = fun(x: int): bool { if (x > 0) { return true; } };
+  return twice(inc, 1, 2);
+}"), vec![
+      "5:19: error: mismatched types in `let` initializer: expected `string`, found `int`",
+      "7:9: error: mismatched types in argument 1 of `twice`: expected `fun(int): int`, found `fun(): int`",
+      "8:3: error: cannot apply binary operator `==` to types `fun(int): int` and `fun(int): int`",
+      "9:3: error: `n` is a `int`, not a function",
+      "10:13: error: lambda returns `bool` but can reach the end of its body without `return`",
+      "11:10: error: function `twice` takes 2 arguments but 3 were supplied",
+    ]);
+  }
+
+  #[test]
+  fn closures_share_what_they_write() {
+    let source = "
+fun apply(f: fun(int): int, x: int): int { return f(x); }
+fun adder(n: int): fun(int): int { return fun(x: int): int { return x + n; }; }
+fun counter(): fun(): int {
+  let count = 0;
+  return fun(): int { count += 1; return count; };
+}
+fun double(x: int): int { return x * 2; }
+fun main(): int {
+  let next = counter();
+  next();
+  let total = 0;
+  for i in 0..4 {
+    let add = fun(v: int): int { total += v; return total; };
+    add(i);
+  }
+  let k = 10;
+  let get = fun(): int { return k; };
+  k = 20;
+  return apply(adder(3), 1) * 10000 + next() * 1000 + total * 100 + get() + apply(double, 1);
+}";
+    let analysis = driver::analyze(source);
+    assert!(analysis.diagnostics.is_empty(), "{:?}", analysis.diagnostics);
+    assert_eq!(interpreter::run(&analysis.ast).ok(), Some(Value::Int(42622)));
+  }
+}
@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+  use crate::complete::{complete, CompletionKind};
+
+  // Labels offered at the `|` marker, which is removed from the source
+  fn labels(marked: &str) -> Vec<String> {
+    let offset = marked.find('|').unwrap();
+    let source = marked.replacen('|', "", 1);
+    complete(&source, offset).into_iter().map(|c| c.label).collect()
+  }
+
+  #[test]
+  fn keywords_follow_the_grammar() {
+    assert_eq!(labels("|"), vec!["enum", "fun", "impl", "import", "let", "pub", "struct"]);
+    assert_eq!(labels("pub |"), vec!["enum", "fun", "let", "struct"]);
+    assert_eq!(labels("import |"), Vec::<String>::new());
+    assert_eq!(labels("fun main(): unit { if (true) { } e|"), vec!["else"]);
+    assert_eq!(labels("fun main(): unit { while (true) { } e|"), Vec::<String>::new());
+    assert_eq!(labels("fun main(): unit { for i |"), vec!["in"]);
+    assert_eq!(labels("fun main(): unit { for i in 0..3 { br|"), vec!["break"]);
+    assert_eq!(labels("fun main(): unit { br|"), Vec::<String>::new());
+    assert_eq!(labels("fun f(a: i|"), vec!["int"]);
+    assert_eq!(labels("fun main(): unit { let x = t|"), vec!["to_int", "to_string", "true"]);
+    // Nothing inside a name being declared, a string or a comment
+    assert_eq!(labels("fun ma|"), Vec::<String>::new());
+    assert_eq!(labels("fun main(): unit { let s = \"t|"), Vec::<String>::new());
+    assert_eq!(labels("// t|"), Vec::<String>::new());
+  }
+
+  #[test]
+  fn struct_names_and_fields() {
+    let decl = "struct Point { x: int, y: int }\n";
+    assert_eq!(labels(&format!("{}fun f(p: P|", decl)), vec!["Point"]);
+    assert_eq!(labels(&format!("{}struct Line {{ from: Po|", decl)), vec!["Point"]);
+    assert_eq!(labels(&format!("{}s|", decl)), vec!["struct"]);
+    // Field names are not guessed, their values are expressions
+    assert_eq!(labels(&format!("{}fun main(): unit {{ let p = Point {{ |", decl)), Vec::<String>::new());
+    assert_eq!(labels(&format!("{}fun main(): unit {{ let p = Point {{ x: 1, |", decl)), Vec::<String>::new());
+    assert_eq!(labels(&format!("{}fun main(): unit {{ let p = Point {{ x: tr|", decl)), vec!["true"]);
+    assert_eq!(labels(&format!("{}fun main(): unit {{ let p = Point {{ x: 1, y: 2 }}; p.|", decl)), Vec::<String>::new());
+  }
+
+  #[test]
+  fn names_in_scope_on_broken_source() {
+    let source = "let total: int = 0;
+fun tally(count: int, tag: string): int {
+  let tmp = count +
+  for t in 0..count {
+    let inner = t;
+  }
+  return t|
+}
+fun tail(): int { return 1; }";
+    let offset = source.find('|').unwrap();
+    let source = source.replacen('|', "", 1);
+    let found: Vec<(String, CompletionKind, Option<String>)> = complete(&source, offset).into_iter()
+      .map(|c| (c.label, c.kind, c.detail))
+      .collect();
+    assert_eq!(found, vec![
+      ("tag".to_string(), CompletionKind::Variable, Some("(parameter) tag: string".to_string())),
+      ("tail".to_string(), CompletionKind::Function, Some("fun tail(): int".to_string())),
+      ("tally".to_string(), CompletionKind::Function, Some("fun tally(count: int, tag: string): int".to_string())),
+      ("to_int".to_string(), CompletionKind::Function, Some("fun to_int(value: string | float | char): int".to_string())),
+      ("to_string".to_string(), CompletionKind::Function, Some("fun to_string(value: T): string".to_string())),
+      ("total".to_string(), CompletionKind::Variable, Some("let total: int".to_string())),
+      ("true".to_string(), CompletionKind::Keyword, None),
+    ]);
+  }
+}
@@ -0,0 +1,119 @@
+#[cfg(test)]
+mod tests {
+  use crate::desugar::desugar;
+  use crate::diagnostic::Span;
+  use crate::lexer::Lexer;
+  use crate::parser::Parser;
+  use crate::test::carf;
+
+  #[test]
+  fn rewrites_updates_into_plain_assignments() {
+    let (code, out, err) = carf(&["desugar"], "
+fun next(): int { return 1; }
+fun main(): unit {
+  let x = 3;
+  let xs = [1, 2, 3];
+  x *= x - 1;
+  x >>= 1;
+  x--;
+  xs[0] += x;
+  ++xs[1];
+  xs[next()] %= 2;
+  let y = x++;
+}");
+    assert_eq!((code, err.as_str()), (0, ""));
+    assert_eq!(out, "\
+fun next(): int {
+  return 1;
+}
+
+fun main(): unit {
+  let x = 3;
+  let xs = [1, 2, 3];
+  x = x * (x - 1);
+  x = x >> 1;
+  x = x - 1;
+  xs[0] = xs[0] + x;
+  xs[1] = xs[1] + 1;
+  {
+    let __index0 = next();
+    xs[__index0] = xs[__index0] % 2;
+  }
+  let y = x++;
+}
+");
+  }
+
+  #[test]
+  fn range_loops_become_counted_whiles() {
+    let source = "
+fun main(): unit {
+  let n = 4;
+  for i in 0..n {
+    if (i == 1) { continue; }
+    let i = i * 10;
+    n = 0;
+    print(i);
+  }
+  for c in \"ok\" print(c);
+}";
+    let (code, out, err) = carf(&["desugar"], source);
+    assert_eq!((code, err.as_str()), (0, ""));
+    assert_eq!(out, "\
+fun main(): unit {
+  let n = 4;
+  {
+    let __i_next = 0;
+    let __i_end = n;
+    while (__i_next < __i_end) {
+      let i = __i_next;
+      __i_next = __i_next + 1;
+      if (i == 1) {
+        continue;
+      }
+      let i = i * 10;
+      n = 0;
+      print(i);
+    }
+  }
+  for c in \"ok\"
+    print(c);
+}
+");
+    // The bound is read once and `continue` still steps the counter
+    assert_eq!(carf(&["run"], source), (0, "02030ok".to_string(), String::new()));
+    assert_eq!(carf(&["run"], &out), (0, "02030ok".to_string(), String::new()));
+
+    // The counter is named past the names the program uses
+    let source = "fun main(): unit { let __i_next = \"user\"; for i in 0..2 { println(__i_next); } }";
+    assert_eq!(carf(&["run"], source), (0, "user\nuser\n".to_string(), String::new()));
+    let (_, out, _) = carf(&["desugar"], source);
+    assert!(out.contains("let __i_next1 = 0;") && out.contains("println(__i_next);"), "{}", out);
+  }
+
+  #[test]
+  fn rewritten_nodes_keep_their_spans() {
+    let source = "fun main(): unit { let xs = [0]; xs[f()] <<= 2; for i in 1..3 { xs[0]++; } }";
+    let mut parser = Parser::new(Lexer::new(source));
+    parser.parse();
+    let mut ast = parser.into_ast();
+    let spans = |ast: &crate::ast::Ast| -> Vec<(usize, usize)> {
+      ast.get_root()[0][3].get_children().iter().map(|stmt| {
+        let span = Span::of(stmt);
+        (span.start, span.end)
+      }).collect()
+    };
+    let before = spans(&ast);
+    desugar(&mut ast);
+    assert_eq!(spans(&ast), before);
+    assert_eq!(ast.get_root()[0][3][1].dump(), "\
+(Block { (Let let (Identifier __index0) (Type) (Call ( (Identifier f) (Args ())) \
+(Stmt ; (BinOper = (Index [ (Identifier xs) (Identifier __index0)) \
+(BinOper << (Index [ (Identifier xs) (Identifier __index0)) (Literal 2)))))");
+
+    // A runtime error in the rewritten update still points at the source
+    let (code, _, err) = carf(&["run", "--color=never"], "fun main(): unit { let x = 9223372036854775807; x += 1; }");
+    assert_eq!(code, 1);
+    assert!(err.starts_with("<stdin>:1:49: error: arithmetic overflow") && err.contains(" ^^^^^^\n"), "{}", err);
+  }
+}
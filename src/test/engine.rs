@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+  use std::cell::RefCell;
+  use std::rc::Rc;
+
+  use crate::engine::{Engine, Error};
+  use crate::interpreter::prelude::Console;
+
+  #[test]
+  fn natives_and_constants() {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let seen = log.clone();
+    let mut engine = Engine::new();
+    engine
+      .register_fn("hypot", |a: f64, b: f64| (a * a + b * b).sqrt())
+      .register_fn("shout", |s: String, n: i64| s.to_uppercase().repeat(n as usize))
+      .register_fn("record", move |xs: Vec<i64>| seen.borrow_mut().push(xs.iter().sum::<i64>()))
+      .register_fn("halve", |n: i64| if n % 2 == 0 { Ok(n / 2) } else { Err(format!("{} is odd", n)) })
+      .register_const("LIMIT", 40_i64)
+      .register_const("NAMES", vec!["ada".to_string(), "bo".to_string()]);
+    let source = "
+fun main(): string {
+  record([1, 2, 3]);
+  let apply = halve;
+  record([apply(LIMIT), len(NAMES)]);
+  return shout(NAMES[1], 2) + to_string(hypot(3.0, 4.0));
+}";
+    assert_eq!(engine.run::<String>(source), Ok("BOBO5.0".to_string()));
+    assert_eq!(*log.borrow(), vec![6, 22]);
+    let error = engine.run::<i64>("fun main(): int { return halve(LIMIT + 1); }").unwrap_err();
+    assert_eq!(error.to_string(), "41 is odd");
+    assert!(matches!(error, Error::Runtime(d) if d.span.start == 25));
+
+    // Constants and their elements are read only, though a local may take the name
+    let messages = |source: &str| -> Vec<String> {
+      match engine.compile(source).err() {
+        Some(Error::Compile(diagnostics)) => diagnostics.iter().map(|d| d.message.clone()).collect(),
+        other => panic!("{:?}", other.map(|e| e.to_string())),
+      }
+    };
+    assert_eq!(messages("fun main(): unit { LIMIT = 9; LIMIT += 1; LIMIT++; NAMES[0] = \"x\"; }"), vec![
+      "cannot assign to `LIMIT`, a constant of the host",
+      "cannot assign to `LIMIT`, a constant of the host",
+      "cannot assign to `LIMIT`, a constant of the host",
+      "cannot assign to `NAMES`, a constant of the host",
+    ]);
+    assert_eq!(messages("let LIMIT = 7;\nfun main(): int { return LIMIT; }"), vec!["global `LIMIT` is already defined by the host"]);
+    assert_eq!(engine.run::<i64>("fun main(): int { let LIMIT = 7; LIMIT += 1; return LIMIT; }"), Ok(8));
+  }
+
+  #[test]
+  fn calls_into_a_script_keep_its_globals() {
+    let engine = Engine::new();
+    let script = engine.compile("
+let count = 0;
+fun bump(by: int): int { count += by; return count; }
+fun greet(name: string, times: int): [string] {
+  println(name);
+  let out: [string] = [];
+  for i in 0..times { push(out, \"hi \" + name); }
+  return out;
+}").unwrap();
+    let mut printed = Vec::new();
+    {
+      let mut instance = script.instantiate_with(Console::new(Box::new(&b""[..]), Box::new(&mut printed))).unwrap();
+      assert_eq!(instance.call::<i64>("bump", (2,)), Ok(2));
+      assert_eq!(instance.call::<i64>("bump", (3,)), Ok(5));
+      assert_eq!(instance.get_global::<i64>("count"), Some(5));
+      assert_eq!(instance.call::<Vec<String>>("greet", ("jo", 2)), Ok(vec!["hi jo".to_string(), "hi jo".to_string()]));
+      assert_eq!(instance.call::<i64>("nope", ()), Err(Error::Call("the script has no function `nope`".to_string())));
+      assert_eq!(instance.call::<i64>("bump", ("x",)), Err(Error::Call("`bump` is `fun(int): int`, it cannot take (string)".to_string())));
+      assert_eq!(instance.call::<bool>("bump", (1,)), Err(Error::Call("`bump` returns `int`, not `bool`".to_string())));
+      // The rejected call did not run
+      assert_eq!(instance.get_global::<i64>("count"), Some(5));
+    }
+    assert_eq!(String::from_utf8(printed).unwrap(), "jo\n");
+    // Each instance starts over
+    assert_eq!(script.instantiate().unwrap().call::<i64>("bump", (1,)), Ok(1));
+  }
+
+  #[test]
+  fn compile_errors_are_reported_together() {
+    let mut engine = Engine::new();
+    engine.register_fn("twice", |n: i64| n * 2);
+    let source = "fun main(): int {\n  let s: string = twice(1);\n  return twice(true);\n}";
+    let error = engine.compile(source).err().unwrap();
+    let Error::Compile(diagnostics) = &error else { panic!("{:?}", error) };
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(error.render(source).lines().next(), Some("2:19: error: mismatched types in `let` initializer: expected `string`, found `int`"));
+    assert_eq!(
+      engine.compile("fun twice(n: int): int { return n; }").err().map(|e| e.to_string()),
+      Some("function `twice` is defined more than once".to_string())
+    );
+    assert!(matches!(engine.run::<i64>("fun main(): int { return 1 / 0; }"), Err(Error::Compile(_))));
+  }
+}
@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod tests {
+  use crate::driver;
+  use crate::interpreter::{self, value::Value};
+  use crate::lexer::Lexer;
+  use crate::parser::Parser;
+  use crate::test::messages;
+
+  #[test]
+  fn enums_paths_and_match_arms() {
+    let mut parser = Parser::new(Lexer::new("
+enum Shape { Rect(int, int), Empty, }
+fun f(s: Shape): int { return match s { Shape::Rect(w, _) => w, -1..3 => 0, n => Shape::Empty, }; }"));
+    let ast = parser.parse();
+    assert_eq!(
+      ast.get_root()[0].dump(),
+      "(Enum enum (Identifier Shape) (Variants { (Variant Rect (Type int) (Type int)) (Variant Empty)))"
+    );
+    assert_eq!(
+      ast.get_root()[1][3].dump(),
+      "(Block { (Return return (Match match (Identifier s) \
+       (Arm => (VariantPat :: (Identifier Shape) (Identifier Rect) (Binding w) (Wildcard _)) (Identifier w)) \
+       (Arm => (RangePat .. (UnOper - (Literal 1)) (Literal 3)) (Literal 0)) \
+       (Arm => (Binding n) (Path :: (Identifier Shape) (Identifier Empty))))))"
+    );
+  }
+
+  #[test]
+  fn matches_are_checked_for_exhaustiveness() {
+    assert_eq!(messages("
+enum E { A(int), B, B }
+enum E { C }
+fun main(): int {
+  let e = E::A(1, 2);
+  let b = E::A;
+  let x = match e { E::A(n) => n };
+  let y = match true { true => 1 };
+  let z = match 'c' { 'a'..'z' => 1, 'q' => 2, _ => 3 };
+  let w = match e { E::A(n, n) => 1, E::Nope => 2, E::B(1) => 3, _ => \"s\" };
+  let v = match 3 { 5..1 => 1, _ => 0 };
+  return 0;
+}"), vec![
+      "2:21: error: variant `B` is declared more than once",
+      "3:6: error: enum `E` is defined more than once",
+      "5:11: error: variant `E::A` takes 1 argument but 2 were supplied",
+      "6:11: error: variant `E::A` has fields and must be called like `E::A(...)`",
+      "7:11: error: non-exhaustive `match`: pattern `E::B` not covered",
+      "8:11: error: non-exhaustive `match`: pattern `false` not covered",
+      "9:38: warning: unreachable pattern",
+      "10:21: error: variant `E::A` has 1 field, but the pattern has 2",
+      "10:29: error: `n` is bound more than once in the same pattern",
+      "10:41: error: no variant `Nope` in enum `E`",
+      "10:52: error: variant `E::B` has 0 fields, but the pattern has 1",
+      "10:71: error: mismatched types in `match` arm: expected `int`, found `string`",
+      "11:21: error: range pattern `5..1` matches nothing",
+    ]);
+  }
+
+  #[test]
+  fn runs_matches_over_variants_and_ranges() {
+    let source = "
+enum Shape { Circle(int), Rect(int, int), Empty }
+fun area(s: Shape): int {
+  return match s { Shape::Circle(r) => 3 * r * r, Shape::Rect(w, h) => w * h, Shape::Empty => 0 };
+}
+fun sign(n: int): int {
+  return match n { -100..0 => -1, 0 => 0, _ => 1 };
+}
+fun main(): int {
+  let shapes = [Shape::Circle(2), Shape::Rect(2, 5), Shape::Empty];
+  let total = 0;
+  for i in 0..3 { total += area(shapes[i]); }
+  return total * sign(-7) + sign(0) + sign(9) * 100;
+}";
+    let analysis = driver::analyze(source);
+    assert!(analysis.diagnostics.is_empty(), "{:?}", analysis.diagnostics);
+    assert_eq!(interpreter::run(&analysis.ast).ok(), Some(Value::Int(78)));
+  }
+}
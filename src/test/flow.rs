@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+  use crate::lexer::Lexer;
+  use crate::parser::Parser;
+  use crate::flow;
+  use crate::test::headlines;
+
+  fn messages(source: &str) -> Vec<String> {
+    let mut parser = Parser::new(Lexer::new(source));
+    let ast = parser.parse();
+    headlines(source, &flow::check_flow(ast))
+  }
+
+  #[test]
+  fn complete_functions_pass() {
+    let errors = messages("
+fun sign(n: int): int {
+  if (n < 0) { return -1; } else if (n == 0) { return 0; } else { return 1; }
+}
+fun spin(): int {
+  while (true) { if (ready()) { return 1; } }
+}
+fun nothing(): unit {
+  for i in 0..3 { if (i == 1) { continue; } break; }
+}");
+    assert_eq!(errors, Vec::<String>::new());
+  }
+
+  #[test]
+  fn jumps_outside_loops() {
+    let errors = messages("
+fun f(): unit {
+  break;
+  while (x) { if (y) { continue; } }
+  continue;
+}");
+    assert_eq!(errors, vec![
+      "3:3: error: `break` outside of a loop",
+      "5:3: error: `continue` outside of a loop",
+    ]);
+  }
+
+  #[test]
+  fn missing_return_and_unreachable_code() {
+    let errors = messages("
+fun f(n: int): int {
+  if (n > 0) { return 1; }
+}
+fun g(n: int): int {
+  while (n > 0) { n--; }
+  return n;
+  n = 1;
+  n = 2;
+}
+fun h(): unit {
+  while (true) { break; n++; }
+  for i in 0..2 { continue; { i++; } }
+}");
+    assert_eq!(errors, vec![
+      "2:5: error: function `f` returns `int` but can reach the end of its body without `return`",
+      "8:3: warning: unreachable statement",
+      "12:25: warning: unreachable statement",
+      "13:29: warning: unreachable statement",
+    ]);
+  }
+}
@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests {
+  use crate::lexer::Lexer;
+  use crate::parser::Parser;
+  use crate::engine::Engine;
+  use crate::fold;
+  use crate::test::headlines;
+
+  // Folded initializer of each root level let, and the rendered diagnostics
+  fn fold(source: &str) -> (Vec<String>, Vec<String>) {
+    let mut parser = Parser::new(Lexer::new(source));
+    parser.parse();
+    let mut ast = parser.into_ast();
+    let diagnostics = fold::fold(&mut ast);
+    let inits = ast.get_root().get_children().iter().map(|n| n[2].dump()).collect();
+    let messages = headlines(source, &diagnostics);
+    (inits, messages)
+  }
+
+  #[test]
+  fn folds_every_operator_family() {
+    let (inits, messages) = fold("
+let a: int = (1 + 2) * 3 - 10 / 4 % 3;
+let b: int = 1 << 4 | 3 & 6 ^ 1 >> 1;
+let c: bool = 2 < 3 && 1.5 >= 2.5 || 'a' != 'b';
+let d: string = \"ab\" + \"c\\n\";
+let e: float = -(1.5 * 2.0);
+let f: bool = !(1 == 1);
+let g: int = x + 1 * 2;
+let h: float = -1.0 / 0.0;");
+    assert_eq!(messages, Vec::<String>::new());
+    assert_eq!(inits, vec![
+      "(Literal 7)",
+      "(Literal 18)",
+      "(Literal true)",
+      "(Literal \"abc\\n\")",
+      "(Literal -3.0)",
+      "(Literal false)",
+      "(BinOper + (Identifier x) (Literal 2))",
+      "(Literal -inf)",
+    ]);
+    // Folded floats read back, the infinite ones included
+    assert_eq!(crate::utilities::literal::parse_float("-inf"), Some(f64::NEG_INFINITY));
+    assert!(crate::utilities::literal::parse_float("NaN").is_some_and(f64::is_nan));
+  }
+
+  #[test]
+  fn simplifies_identities() {
+    let (inits, _) = fold("
+let a: int = x * 1;
+let b: int = 0 + (y - 0);
+let c: bool = !!ok;
+let d: bool = true && ready;
+let e: bool = false && call();
+let f: int = z << 0;
+let g: int = - -n;");
+    assert_eq!(inits, vec![
+      "(Identifier x)",
+      "(Expr ( (Identifier y))",
+      "(Identifier ok)",
+      "(Identifier ready)",
+      "(Literal false)",
+      "(Identifier z)",
+      "(UnOper - (UnOper - (Identifier n)))",
+    ]);
+    let source = "fun main(): int { let n = -9223372036854775807 - 1; return - -n; }";
+    assert_eq!(Engine::new().run::<i64>(source).unwrap_err().to_string(), "arithmetic overflow");
+  }
+
+  #[test]
+  fn reports_constant_errors() {
+    let (inits, messages) = fold("
+let a: int = x / (2 - 2);
+let b: int = 5 % 0;
+let c: int = 1 << 64;
+let d: int = 9223372036854775807 + 1;
+let e: range = 5..1;");
+    assert_eq!(messages, vec![
+      "2:14: error: division by zero",
+      "3:14: error: remainder by zero",
+      "4:14: error: shift by 64 bits overflows a 64-bit `int`",
+      "5:14: error: arithmetic overflow in constant expression",
+      "6:16: warning: range `5..1` is empty",
+    ]);
+    assert_eq!(inits[0], "(BinOper / (Identifier x) (Literal 0))");
+  }
+}
@@ -0,0 +1,35 @@
+#[cfg(test)]
+mod tests {
+  use crate::grammar::{self, ebnf, railroad, textmate, tree_sitter};
+
+  // Fail on a stale file. With `CARF_BLESS=1` set, write the fresh copy instead.
+  fn ensure_file(path: &str, contents: &str) {
+    let full = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), path);
+    if std::fs::read_to_string(&full).is_ok_and(|old| old == contents) {
+      return;
+    }
+    if std::env::var_os("CARF_BLESS").is_some_and(|v| v == "1") {
+      std::fs::create_dir_all(std::path::Path::new(&full).parent().unwrap()).unwrap();
+      std::fs::write(&full, contents).unwrap();
+      return;
+    }
+    panic!("{} is out of date with the token tables, rerun with CARF_BLESS=1 to regenerate it", path);
+  }
+
+  #[test]
+  fn rules_and_subparsers_agree() {
+    assert_eq!(grammar::check_rules(), Vec::<String>::new());
+  }
+
+  #[test]
+  fn editor_grammars_are_up_to_date() {
+    ensure_file(textmate::PATH, &textmate::generate());
+    ensure_file(tree_sitter::PATH, &tree_sitter::generate());
+  }
+
+  #[test]
+  fn grammar_docs_are_up_to_date() {
+    ensure_file(ebnf::PATH, &ebnf::generate());
+    ensure_file(railroad::PATH, &railroad::generate());
+  }
+}
@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+  use crate::driver;
+  use crate::interpreter::{self, value::Value};
+  use crate::test::headlines;
+
+  // Value returned by `main`, or the first line of the runtime error
+  fn run(source: &str) -> Result<Value, String> {
+    let analysis = driver::analyze(source);
+    assert!(analysis.diagnostics.is_empty(), "{:?}", analysis.diagnostics);
+    interpreter::run(&analysis.ast).map_err(|d| headlines(source, &[d]).remove(0))
+  }
+
+  #[test]
+  fn runs_loops_calls_and_assignments() {
+    let result = run("
+let base: int = 100;
+fun fib(n: int): int {
+  if (n < 2) { return n; }
+  return fib(n - 1) + fib(n - 2);
+}
+fun main(): string {
+  let total = base;
+  for i in 0..10 { total += fib(i); }
+  let n = 0;
+  while (true) {
+    n++;
+    if (n == 3) { continue; }
+    if (n > 5) { break; }
+    total -= 1;
+  }
+  let word: string = \"carf\";
+  word[0] = 'C';
+  let last: char;
+  for c in word { last = c; }
+  if (total == 184 && last == 'f' && 7 / 2 == 3 && 1.5 * 2.0 == 3.0) {
+    return word + \"!\";
+  }
+  return \"wrong\";
+}");
+    assert_eq!(result, Ok(Value::Str("Carf!".into())));
+  }
+
+  #[test]
+  fn prefix_steps_give_the_new_value() {
+    let result = run("
+fun main(): [int] {
+  let x = 5;
+  let xs = [10];
+  let a = x++;
+  let b = ++x;
+  let c = x--;
+  let d = --x;
+  return [a, b, c, d, x, ++xs[0] * 2, xs[0]--, xs[0]];
+}");
+    assert_eq!(result.map(|v| v.to_string()), Ok("[5, 7, 7, 5, 5, 22, 11, 10]".to_string()));
+    assert_eq!(run("
+fun main(): int { let x = -9223372036854775807; return --x - --x; }"), Err("2:62: error: arithmetic overflow".to_string()));
+
+    let analysis = driver::analyze("fun f(): int { return 1; } fun main(): unit { ++1; f()--; }");
+    let messages: Vec<(usize, &str)> = analysis.diagnostics.iter().map(|d| (d.span.start, d.message.as_str())).collect();
+    assert_eq!(messages, [(48, "cannot assign to this expression"), (51, "cannot assign to this expression")]);
+  }
+
+  #[test]
+  fn reports_runtime_errors() {
+    assert_eq!(run("
+fun div(a: int, b: int): int { return a / b; }
+fun main(): int { return div(1, 0); }"), Err("2:39: error: division by zero".to_string()));
+    assert_eq!(run("
+fun main(): char { let s: string = \"ab\"; return s[2]; }"), Err("2:49: error: index 2 out of bounds for length 2".to_string()));
+    assert_eq!(run("
+fun main(): int { let x = 9223372036854775807; x++; return x; }"), Err("2:48: error: arithmetic overflow".to_string()));
+  }
+}
@@ -0,0 +1,154 @@
+#[cfg(test)]
+mod tests {
+  use crate::ir::{parse::parse, passes::PassManager, verify::verify};
+  use crate::test::carf;
+
+  #[test]
+  fn lowering_merges_loop_variables_with_phis() {
+    let (code, out, err) = carf(&["ir"], "
+fun steps(n: int): int {
+  let count = 0;
+  while (n > 1) {
+    if (n % 2 == 0) { n = n / 2; } else { n = 3 * n + 1; }
+    count++;
+  }
+  return count;
+}");
+    assert_eq!((code, err.as_str()), (0, ""));
+    assert_eq!(out, "\
+fun @steps(%0: int): int {
+b0:
+  %1 = const int 0
+  jump b1
+b1:
+  %2 = phi int [b0: %0], [b6: %16]
+  %3 = phi int [b0: %1], [b6: %18]
+  %4 = const int 1
+  %5 = gt bool %2, %4
+  br %5, b2, b3
+b2:
+  %6 = const int 2
+  %7 = rem int %2, %6
+  %8 = const int 0
+  %9 = eq bool %7, %8
+  br %9, b4, b5
+b3:
+  ret %3
+b4:
+  %10 = const int 2
+  %11 = div int %2, %10
+  jump b6
+b5:
+  %12 = const int 3
+  %13 = mul int %12, %2
+  %14 = const int 1
+  %15 = add int %13, %14
+  jump b6
+b6:
+  %16 = phi int [b4: %11], [b5: %15]
+  %17 = const int 1
+  %18 = add int %3, %17
+  jump b1
+}
+");
+
+    // The text reads back to the same module, which verifies
+    let module = parse(&out).unwrap();
+    assert_eq!(module.to_string(), out);
+    assert_eq!(verify(&module), Ok(()));
+
+    let (code, _, err) = carf(&["ir", "--color=never"], "fun main(): unit { let xs = [1]; }");
+    assert_eq!(code, 1);
+    assert!(err.starts_with("<stdin>:1:24: error: values of type `[int]` cannot be lowered to IR"), "{}", err);
+  }
+
+  #[test]
+  fn pipeline_removes_common_and_dead_code() {
+    let source = "
+fun f(a: int, b: int, ok: bool): int {
+  let x = a * b + 1;
+  let same = a == b;
+  for i in 0..b {
+    if (ok && i > 2) { x += a * b; }
+  }
+  return x + (a * b);
+}";
+    let (code, out, err) = carf(&["ir", "--passes=cse,copy-prop,dce"], source);
+    assert_eq!((code, err.as_str()), (0, ""));
+    assert_eq!(out, "\
+fun @f(%0: int, %1: int, %2: bool): int {
+b0:
+  %3 = mul int %0, %1
+  %4 = const int 1
+  %5 = add int %3, %4
+  %7 = const int 0
+  jump b1
+b1:
+  %11 = phi int [b0: %5], [b7: %23]
+  %13 = phi int [b0: %7], [b7: %17]
+  %15 = lt bool %13, %1
+  br %15, b2, b3
+b2:
+  %17 = add int %13, %4
+  br %2, b4, b5
+b3:
+  %25 = add int %11, %3
+  ret %25
+b4:
+  %18 = const int 2
+  %19 = gt bool %13, %18
+  jump b5
+b5:
+  %20 = phi bool [b2: %2], [b4: %19]
+  br %20, b6, b7
+b6:
+  %22 = add int %11, %3
+  jump b7
+b7:
+  %23 = phi int [b5: %11], [b6: %22]
+  jump b1
+}
+");
+
+    // Without `copy-prop` the copies `cse` leaves are still used
+    let (_, raw, _) = carf(&["ir"], source);
+    let mut module = parse(&raw).unwrap();
+    let passes = PassManager::parse("cse,dce").unwrap();
+    assert_eq!(passes.get_names(), ["cse", "dce"]);
+    assert_eq!(passes.run(&mut module), Ok(vec!["cse", "dce"]));
+    assert!(module.to_string().contains("  %21 = copy int %3\n"), "{}", module);
+    assert_eq!(PassManager::parse("dce,inline").err().unwrap(), "unknown pass `inline`, expected one of dce, copy-prop, cse");
+  }
+
+  #[test]
+  fn verifier_reports_broken_ssa() {
+    let check = |text: &str| parse(text).and_then(|module| verify(&module));
+    assert_eq!(check("fun @f(%0: int): int {\nb0:\n  br %0, b1, b1\nb1:\n  ret %0\n}"), Err("@f b0: %0 has type `int`, expected `bool`".to_string()));
+    assert_eq!(check("
+fun @f(%0: bool): int {
+b0:
+  br %0, b1, b2
+b1:
+  %1 = const int 1
+  jump b2
+b2:
+  ret %1
+}"), Err("@f b2: %1 is used where its definition does not dominate".to_string()));
+    assert_eq!(check("
+fun @f(%0: bool): int {
+b0:
+  br %0, b1, b2
+b1:
+  %1 = const int 1
+  jump b2
+b2:
+  %2 = phi int [b1: %1]
+  ret %2
+}"), Err("@f b2: phi %2 does not have one value for each predecessor".to_string()));
+    assert_eq!(check("global @g: int\nfun @f(): unit {\nb0:\n  %0 = load float @g\n  ret\n}"), Err("@f b0: `@g` has type `int`, not `float`".to_string()));
+    assert_eq!(check("fun @f(): unit {\nb0:\n  ret\nb1:\n  ret\n}"), Err("@f b1: is unreachable".to_string()));
+
+    assert_eq!(check("fun @f(): unit {\nb0:\n  %0 = mov int %1\n  ret\n}"), Err("line 3: unknown instruction `mov`".to_string()));
+    assert_eq!(check("fun @f(): unit {\nb1:\n  ret\n}"), Err("line 2: expected block b0".to_string()));
+  }
+}
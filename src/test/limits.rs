@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use crate::driver;
+  use crate::engine::{Engine, Error};
+  use crate::interpreter::limits::{CancelHandle, Limits};
+  use crate::test::carf;
+
+  const SPIN: &str = "
+fun spin(): int { let i = 0; while (true) { i += 1; } return i; }
+fun count(n: int): int { let i = 0; while (i < n) { i += 1; } return i; }";
+
+  fn message(error: Error) -> String {
+    match error {
+      Error::Limit(d) => d.message,
+      other => panic!("expected a limit error, found {:?}", other),
+    }
+  }
+
+  #[test]
+  fn steps_time_and_cancellation() {
+    let mut engine = Engine::new();
+    engine.set_limits(Limits { max_steps: Some(1000), ..Limits::default() });
+    let script = engine.compile(SPIN).unwrap();
+    let mut instance = script.instantiate().unwrap();
+    assert_eq!(message(instance.call::<i64>("spin", ()).unwrap_err()), "step limit of 1000 exceeded");
+    // Each call gets the whole budget again
+    assert_eq!(instance.call::<i64>("count", (100,)), Ok(100));
+    assert!(instance.call::<i64>("count", (1000,)).is_err());
+
+    let mut engine = Engine::new();
+    engine.set_limits(Limits { timeout: Some(Duration::from_millis(20)), ..Limits::default() });
+    let error = engine.compile(SPIN).unwrap().instantiate().unwrap().call::<i64>("spin", ()).unwrap_err();
+    assert_eq!(message(error), "time limit of 20ms exceeded");
+
+    let cancel = CancelHandle::new();
+    let mut engine = Engine::new();
+    engine.set_limits(Limits { cancel: Some(cancel.clone()), ..Limits::default() });
+    let canceller = std::thread::spawn(move || {
+      std::thread::sleep(Duration::from_millis(20));
+      cancel.cancel();
+    });
+    let error = engine.compile(SPIN).unwrap().instantiate().unwrap().call::<i64>("spin", ()).unwrap_err();
+    canceller.join().unwrap();
+    assert_eq!(message(error), "execution cancelled");
+  }
+
+  #[test]
+  fn call_depth_and_heap() {
+    let source = "
+fun down(n: int): int { if (n == 0) { return 0; } return down(n - 1) + 1; }
+fun grow(n: int): int { let s = \"\"; for i in 0..n { s = s + \"abcd\"; } return len(s); }
+fun huge(n: int): int { let xs = [[0; 4]; n]; return len(xs); }";
+    let mut engine = Engine::new();
+    engine.set_limits(Limits { max_depth: 20, max_heap: Some(4096), ..Limits::default() });
+    let script = engine.compile(source).unwrap();
+    let mut instance = script.instantiate().unwrap();
+    assert_eq!(instance.call::<i64>("down", (19,)), Ok(19));
+    let error = instance.call::<i64>("down", (20,)).unwrap_err();
+    assert_eq!(error.to_string(), "call depth limit of 20 exceeded");
+    assert!(matches!(error, Error::Limit(d) if d.span.start == 58));
+    assert_eq!(instance.call::<i64>("grow", (10,)), Ok(40));
+    assert_eq!(message(instance.call::<i64>("grow", (100,)).unwrap_err()), "heap limit of 4096 bytes exceeded");
+    // Counted before the array is built
+    assert_eq!(message(instance.call::<i64>("huge", (1 << 60,)).unwrap_err()), "heap limit of 4096 bytes exceeded");
+    // A runtime error of the script is not a limit
+    assert!(matches!(instance.call::<i64>("huge", (-1,)), Err(Error::Runtime(_))));
+  }
+
+  #[test]
+  fn deep_calls_stop_before_the_stack_overflows() {
+    let source = "fun f(n: int): int { return f(n + 1) + 1; }\nfun main(): int { return f(0); }";
+    // A spawned thread of the default size holds fewer calls than the default depth in a debug build
+    let thread = std::thread::Builder::new().stack_size(2 << 20).spawn(move || message(Engine::new().run::<i64>(source).unwrap_err()));
+    let error = thread.unwrap().join().unwrap();
+    assert!(error.starts_with("call depth limit exceeded, "), "{}", error);
+    let (code, out, err) = carf(&["run", "--max-depth=1000000000", "--color=never"], source);
+    assert_eq!((code, out.as_str()), (1, ""));
+    assert!(err.starts_with("<stdin>:1:29: error: call depth limit exceeded, "), "{}", err);
+  }
+
+  #[test]
+  fn deep_nesting_is_a_parse_error() {
+    // The limit leaves room on the stack of a main thread, more than a test gets
+    let thread = std::thread::Builder::new().stack_size(8 << 20).spawn(nest_too_deep);
+    thread.unwrap().join().unwrap();
+  }
+
+  fn nest_too_deep() {
+    let parens = format!("fun main(): int {{ return {}1{}; }}", "(".repeat(100_000), ")".repeat(100_000));
+    let blocks = format!("fun main(): unit {{ {}{} }}", "{".repeat(100_000), "}".repeat(100_000));
+    let chain = format!("fun main(): int {{ return 1{}; }}", " + 1".repeat(100_000));
+    for source in [parens, blocks, chain] {
+      let analysis = driver::analyze(&source);
+      assert!(analysis.diagnostics.iter().any(|d| d.message.starts_with("Nesting is too deep")), "{:?}", analysis.diagnostics);
+    }
+    // Shallower trees still run, such as a long generated sum, whose operators
+    // do not count as nesting the parser
+    let source = format!("fun main(): int {{ return {}1{}; }}", "(".repeat(150), ")".repeat(150));
+    assert_eq!(Engine::new().run::<i64>(&source), Ok(1));
+    let source = format!("fun main(): int {{ return 1{}; }}", " + 1".repeat(1500));
+    assert_eq!(Engine::new().run::<i64>(&source), Ok(1501));
+  }
+}
@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod tests {
+  use crate::lsp::{self, Server};
+  use crate::utilities::json::{self, Json};
+
+  const URI: &str = "file:///main.carf";
+  const SOURCE: &str = "let limit: int = 10;
+fun twice(n: int): int {
+  return n * 2;
+}
+fun main(): int {
+  let total = 0;
+  for i in 0..limit { total += twice(i); }
+  return total;
+}";
+
+  fn request(id: u32, method: &str, params: &str) -> Json {
+    json::parse(&format!("{{\"jsonrpc\":\"2.0\",\"id\":{},\"method\":\"{}\",\"params\":{}}}", id, method, params)).unwrap()
+  }
+
+  // The `result` of a request made at `line`:`character` of the open document
+  fn at(server: &mut Server, method: &str, line: u32, character: u32) -> Json {
+    let params = format!(
+      "{{\"textDocument\":{{\"uri\":\"{}\"}},\"position\":{{\"line\":{},\"character\":{}}},\"context\":{{\"includeDeclaration\":true}}}}",
+      URI, line, character
+    );
+    server.handle(&request(1, method, &params))[0].get("result").unwrap().clone()
+  }
+
+  fn open(server: &mut Server, text: &str) -> Vec<Json> {
+    let params = Json::object(vec![
+      ("textDocument", Json::object(vec![("uri", Json::str(URI)), ("text", Json::str(text))])),
+    ]);
+    server.handle(&Json::object(vec![("method", Json::str("textDocument/didOpen")), ("params", params)]))
+  }
+
+  // `line:character` of every location in a result
+  fn starts(locations: &Json) -> Vec<String> {
+    locations.as_array().unwrap().iter().map(|l| {
+      let start = l.path(&["range", "start"]).unwrap();
+      format!("{}:{}", start.get("line").unwrap(), start.get("character").unwrap())
+    }).collect()
+  }
+
+  #[test]
+  fn answers_navigation_requests() {
+    let mut server = Server::new();
+    let published = open(&mut server, SOURCE);
+    assert_eq!(published[0].path(&["params", "diagnostics"]), Some(&Json::Array(Vec::new())));
+
+    let hover = at(&mut server, "textDocument/hover", 6, 36);
+    assert_eq!(hover.path(&["contents", "value"]).unwrap().as_str(), Some("```carf\nfun twice(n: int): int\n```"));
+    let hover = at(&mut server, "textDocument/hover", 7, 10);
+    assert_eq!(hover.path(&["contents", "value"]).unwrap().as_str(), Some("```carf\nlet total: int\n```"));
+
+    let definition = at(&mut server, "textDocument/definition", 6, 15);
+    assert_eq!(starts(&Json::Array(vec![definition])), vec!["0:4"]);
+    let references = at(&mut server, "textDocument/references", 5, 6);
+    assert_eq!(starts(&references), vec!["5:6", "6:22", "7:9"]);
+
+    let symbols = server.handle(&request(2, "textDocument/documentSymbol", &format!("{{\"textDocument\":{{\"uri\":\"{}\"}}}}", URI)));
+    let names: Vec<String> = symbols[0].get("result").unwrap().as_array().unwrap().iter()
+      .map(|s| format!("{} {}", s.get("name").unwrap().as_str().unwrap(), s.get("detail").unwrap().as_str().unwrap()))
+      .collect();
+    assert_eq!(names, vec!["limit let limit: int", "twice fun twice(n: int): int", "main fun main(): int"]);
+    // A function ends at its closing brace
+    let ends: Vec<String> = symbols[0].get("result").unwrap().as_array().unwrap().iter().map(|s| {
+      let end = s.path(&["range", "end"]).unwrap();
+      format!("{}:{}", end.get("line").unwrap(), end.get("character").unwrap())
+    }).collect();
+    assert_eq!(ends, vec!["0:19", "3:1", "8:1"]);
+
+    let tokens = server.handle(&request(3, "textDocument/semanticTokens/full", &format!("{{\"textDocument\":{{\"uri\":\"{}\"}}}}", URI)));
+    let data = tokens[0].path(&["result", "data"]).unwrap().as_array().unwrap();
+    // `let` keyword, then `limit` variable 4 characters later, then its `int` type
+    assert_eq!(data[..15].iter().map(|n| n.as_f64().unwrap() as u32).collect::<Vec<_>>(), vec![
+      0, 0, 3, 0, 0,
+      0, 4, 5, 4, 0,
+      0, 7, 3, 1, 0,
+    ]);
+  }
+
+  #[test]
+  fn serves_framed_messages_over_stdio() {
+    let mut input = String::new();
+    for body in [
+      "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"initialize\",\"params\":{}}".to_string(),
+      format!("{{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/didOpen\",\"params\":{{\"textDocument\":{{\"uri\":\"{}\",\"text\":\"let x: int = true;\"}}}}}}", URI),
+      "{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"shutdown\"}".to_string(),
+      "{\"jsonrpc\":\"2.0\",\"method\":\"exit\"}".to_string(),
+    ] {
+      input.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+    }
+    let mut out = Vec::new();
+    let code = lsp::run(&mut input.as_bytes(), &mut out).unwrap();
+    assert_eq!(code, 0);
+
+    let mut replies = Vec::new();
+    let mut output = out.as_slice();
+    while let Some(body) = lsp::read_message(&mut output).unwrap() {
+      replies.push(json::parse(&body).unwrap());
+    }
+    assert_eq!(replies.len(), 3);
+    assert_eq!(replies[0].path(&["result", "capabilities", "hoverProvider"]), Some(&Json::Bool(true)));
+    let diagnostic = &replies[1].path(&["params", "diagnostics"]).unwrap().as_array().unwrap()[0];
+    assert_eq!(diagnostic.get("message").unwrap().as_str(), Some("mismatched types in `let` initializer: expected `int`, found `bool`"));
+    assert_eq!(replies[2].get("result"), Some(&Json::Null));
+  }
+}
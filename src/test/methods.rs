@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+  use crate::driver;
+  use crate::interpreter::{self, value::Value};
+  use crate::lexer::Lexer;
+  use crate::parser::Parser;
+  use crate::test::{headlines, messages};
+
+  #[test]
+  fn method_calls_and_field_access() {
+    let mut parser = Parser::new(Lexer::new("
+impl Point { fun norm(self, k: int): int { return self.x.twice() * k; } }"));
+    let ast = parser.parse();
+    assert_eq!(
+      ast.get_root()[0].dump(),
+      "(Impl impl (Type Point) (Fun fun (Identifier norm) (Params ( (Param self (Type)) (Param k (Type int))) (Type int) \
+       (Block { (Return return (BinOper * (MethodCall ( (Member . (Identifier self) (Identifier x)) (Identifier twice) (Args ()) (Identifier k))))))"
+    );
+  }
+
+  #[test]
+  fn methods_are_checked_against_their_receiver() {
+    assert_eq!(messages("
+struct P { x: int }
+impl P {
+  fun get(self): int { return self.x; }
+  fun get(self): int { return 1; }
+  fun make(x: int): P { return P { x: x }; }
+}
+impl string { fun len(self): int { return 0; } }
+fun f(self): int { return 1; }
+fun main(): int {
+  let p = P { x: 1 };
+  let a: [int; 1] = [1];
+  a.push(2);
+  return p.get(3) + p.nope() + 1.len();
+}"), vec![
+      "5:7: error: method `get` of `P` is defined more than once",
+      "6:7: error: method `make` must take `self` as its first parameter",
+      "8:19: error: method `len` of `string` is built in and cannot be redefined",
+      "9:7: error: `self` is only allowed as the first parameter of a method",
+      "13:5: error: cannot `push` on `[int; 1]`, its length is fixed",
+      "14:10: error: method `get` takes 0 arguments but 1 was supplied",
+      "14:23: error: no method `nope` on type `P`",
+      "14:34: error: no method `len` on type `int`",
+    ]);
+  }
+
+  #[test]
+  fn runs_user_and_builtin_methods() {
+    let source = "
+struct Stack { items: [int] }
+impl Stack {
+  fun push(self, item: int): unit { self.items.push(item); }
+  fun take(self): int { return self.items.pop(); }
+}
+impl int { fun twice(self): int { return self * 2; } }
+fun main(): int {
+  let s = Stack { items: [] };
+  s.push(20);
+  s.push(1);
+  return s.take().twice() + s.items.len() * \"ab\".len() + s.take() + s.take();
+}";
+    let analysis = driver::analyze(source);
+    assert!(analysis.diagnostics.is_empty(), "{:?}", analysis.diagnostics);
+    let error = interpreter::run(&analysis.ast).unwrap_err();
+    assert_eq!(headlines(source, &[error]), ["5:32: error: cannot pop from an empty array"]);
+
+    let source = source.replacen(" + s.take()", "", 1);
+    let analysis = driver::analyze(&source);
+    assert_eq!(interpreter::run(&analysis.ast).ok(), Some(Value::Int(24)));
+  }
+}
@@ -1,3 +1,67 @@
+mod parser;
+mod checker;
+mod flow;
+mod fold;
+mod desugar;
+mod interpreter;
+mod arrays;
+mod structs;
+mod methods;
+mod enums;
+mod closures;
+mod prelude;
+mod modules;
+mod engine;
+mod limits;
+mod c_backend;
+mod wat_backend;
+mod ir;
+mod source;
+mod printer;
+mod cli;
+mod repl;
+mod lsp;
+mod complete;
+mod grammar;
+
+#[cfg(test)]
+use crate::diagnostic::Diagnostic;
+#[cfg(test)]
+use crate::source::SourceFile;
+
+// Each diagnostic rendered against one copy of the source
+#[cfg(test)]
+fn render(source: &str, diagnostics: &[Diagnostic]) -> Vec<String> {
+  let file = SourceFile::anonymous(source);
+  diagnostics.iter().map(|d| file.render(d, false)).collect()
+}
+
+// Just the `line:col: error: message` line of each
+#[cfg(test)]
+fn headlines(source: &str, diagnostics: &[Diagnostic]) -> Vec<String> {
+  render(source, diagnostics).iter().map(|r| r.lines().next().unwrap().to_string()).collect()
+}
+
+// Headlines of every diagnostic of the program
+#[cfg(test)]
+fn messages(source: &str) -> Vec<String> {
+  headlines(source, &crate::driver::analyze(source).diagnostics)
+}
+
+// Exit code, stdout and stderr of the `carf` command line, run on a thread
+// with the stack the binary gives it
+#[cfg(test)]
+fn carf(args: &[&str], input: &str) -> (i32, String, String) {
+  let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+  let input = input.to_string();
+  let thread = std::thread::Builder::new().stack_size(crate::cli::STACK_SIZE).spawn(move || {
+    let (mut out, mut err) = (Vec::new(), Vec::new());
+    let code = crate::cli::run(&args, &mut input.as_bytes(), &mut out, &mut err);
+    (code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+  });
+  thread.unwrap().join().unwrap()
+}
+
 #[cfg(test)]
 mod tests {
   #[test]
@@ -36,13 +100,10 @@ mod tests {
 
     let mut cnt = 0;
     for k in keys {
-      match k {
-        TokenKind::Union(i) => {
-          cnt += 1;
-          assert!(i == 0 && cnt <= 1, "{:?}: {}", k, kind_id_map.get(&k).unwrap());
-        },
-        _ => {},
+      if let TokenKind::Union(i) = k {
+        cnt += 1;
+        assert!(i == 0 && cnt <= 1, "{:?}: {}", k, kind_id_map.get(&k).unwrap());
       }
     }
   }
-}
\ No newline at end of file
+}
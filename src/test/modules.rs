@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod tests {
+  use std::path::{Path, PathBuf};
+
+  use crate::driver;
+  use crate::interpreter::{self, value::Value};
+  use crate::module::Program;
+  use crate::printer::format_source;
+  use crate::test::carf;
+
+  // A fresh directory holding the files, returns the path of the first one
+  fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("carf-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    for (path, source) in files {
+      let path = dir.join(path);
+      std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+      std::fs::write(&path, source).unwrap();
+    }
+    dir.join(files[0].0)
+  }
+
+  fn messages(program: &Program) -> Vec<String> {
+    driver::analyze_program(program).diagnostics.iter().map(|d| {
+      let file = program.files.lookup(d.span.start);
+      let name = file.get_path().file_name().unwrap().to_string_lossy().to_string();
+      format!("{}:{}", name, file.render(d, false).lines().next().unwrap())
+    }).collect()
+  }
+
+  fn load(path: &Path) -> Program {
+    Program::new(path.to_path_buf(), std::fs::read_to_string(path).unwrap())
+  }
+
+  #[test]
+  fn pub_and_import_print() {
+    let source = "import geo :: point;\npub  fun f(): int { return 1; }\npub struct P { x: int }\nfun g(): int { return f(); }\n";
+    assert_eq!(format_source(source).unwrap(), "import geo::point;\n
+pub fun f(): int {
+  return 1;
+}
+
+pub struct P {
+  x: int,
+}
+
+fun g(): int {
+  return f();
+}
+");
+    assert!(format_source("pub impl P {}").is_err());
+    assert!(format_source("import a::;").is_err());
+  }
+
+  #[test]
+  fn imported_items_run() {
+    let path = write_files("run", &[
+      ("main.carf", "import util;\nimport geo::point;\nfun main(): int { let p = origin(); return double(p.x + 70) + 9; }\n"),
+      ("util.carf", "pub fun double(x: int): int { return x * 2; }\n"),
+      ("geo/point.carf", "import util;\npub struct Point { x: int, y: int }\npub fun origin(): Point { return Point { x: double(0), y: 0 }; }\n"),
+    ]);
+    let program = load(&path);
+    let names: Vec<&str> = program.order.iter().map(|i| program.modules[*i].name.as_str()).collect();
+    assert_eq!(names, vec!["util", "geo::point", "main"]);
+    let analysis = driver::analyze_program(&program);
+    assert!(analysis.diagnostics.is_empty(), "{:?}", messages(&program));
+    assert_eq!(interpreter::run(&analysis.ast).ok(), Some(Value::Int(149)));
+  }
+
+  #[test]
+  fn visibility_and_import_errors() {
+    let path = write_files("errors", &[
+      ("main.carf", "import a;\nimport gone;\nfun main(): int { return secret() + hidden(); }\n"),
+      ("a.carf", "import b;\nfun secret(): int { return 1; }\n"),
+      ("b.carf", "import a;\npub fun hidden(): int { return 2; }\n"),
+    ]);
+    let program = load(&path);
+    assert_eq!(messages(&program), vec![
+      "main.carf:2:1: error: cannot find module `gone` at `{}`".replace("{}", &path.with_file_name("gone.carf").display().to_string()),
+      "b.carf:1:1: error: import cycle: `a` -> `b` -> `a`".to_string(),
+    ]);
+    let path = write_files("private", &[
+      ("main.carf", "import a;\nfun main(): int { return secret() + hidden(); }\n"),
+      ("a.carf", "import b;\nfun secret(): int { return hidden(); }\n"),
+      ("b.carf", "pub fun hidden(): int { return 2; }\n"),
+    ]);
+    assert_eq!(messages(&load(&path)), vec![
+      "main.carf:2:26: error: `secret` is private to module `a`",
+      "main.carf:2:37: error: `hidden` is defined in module `b`, which `main` does not import",
+    ]);
+  }
+
+  #[test]
+  fn private_names_are_per_module() {
+    let path = write_files("private-names", &[
+      ("main.carf", "import util::math;\nimport util::str;\nfun helper(): int { return 4000; }\nfun main(): int { return one() + twenty() + helper(); }\n"),
+      ("util/math.carf", "let base = 0;\nfun helper(): int { return base + 1; }\npub fun one(): int { return helper(); }\n"),
+      ("util/str.carf", "let base = 300;\nfun helper(): int { return 20; }\npub fun twenty(): int { return helper() + base; }\n"),
+    ]);
+    let program = load(&path);
+    let analysis = driver::analyze_program(&program);
+    assert!(analysis.diagnostics.is_empty(), "{:?}", messages(&program));
+    assert_eq!(interpreter::run(&analysis.ast).ok(), Some(Value::Int(4321)));
+    let (code, c, err) = carf(&["c", path.to_str().unwrap()], "");
+    assert_eq!(code, 0, "{}", err);
+    assert!(c.contains("f_4util4math6helper(") && c.contains("g_4util3str4base"), "{}", c);
+
+    let path = write_files("qualified", &[
+      ("main.carf", "import util::math;\nfun main(): int { return math::one(); }\n"),
+      ("util/math.carf", "pub fun one(): int { return 1; }\n"),
+    ]);
+    assert_eq!(messages(&load(&path)), vec![
+      "main.carf:2:26: error: qualified access through a module is not supported, import `util::math` and use `one` by its name",
+    ]);
+  }
+}
@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+  use crate::lexer::Lexer;
+  use crate::parser::Parser;
+  use crate::diagnostic;
+  use crate::test::render;
+
+  fn dump(source: &str) -> Vec<String> {
+    let mut parser = Parser::new(Lexer::new(source));
+    let ast = parser.parse();
+    ast.get_root().get_children().iter().map(|n| n.dump()).collect()
+  }
+
+  #[test]
+  fn lex_longest_symbol_and_ranges() {
+    use crate::ast::token::TokenKind::*;
+    let mut lexer = Lexer::new("a <<= 0..10 1.5f 'c' \"s\" // comment\n iffy if");
+    let mut kinds = Vec::new();
+    while let Some(token) = lexer.next() {
+      kinds.push(token.get_kind());
+      if token.get_kind() == EOF {
+        break;
+      }
+    }
+    assert_eq!(kinds, vec![Identifier, LShiftEq, Int, DDot, Int, Float, Char, String, Identifier, If, EOF]);
+  }
+
+  #[test]
+  fn binary_operator_precedence() {
+    let items = dump("let x: int = 1 + 2 * 3 - -4;");
+    assert_eq!(
+      items[0],
+      "(Let let (Identifier x) (Type int) (BinOper - (BinOper + (Literal 1) (BinOper * (Literal 2) (Literal 3))) (UnOper - (Literal 4))))"
+    );
+    let items = dump("fun f(): unit { a = b = c && d || e; }");
+    assert!(items[0].contains("(BinOper = (Identifier a) (BinOper = (Identifier b) (BinOper || (BinOper && (Identifier c) (Identifier d)) (Identifier e))))"));
+  }
+
+  #[test]
+  fn increments_are_prefix_or_postfix() {
+    let items = dump("let x: int = ++a * b-- - - --c[0];");
+    assert_eq!(items, vec![
+      "(Let let (Identifier x) (Type int) (BinOper - (BinOper * (PreStep ++ (Identifier a)) (PostStep -- (Identifier b))) \
+(UnOper - (PreStep -- (Index [ (Identifier c) (Literal 0))))))",
+    ]);
+    let items = dump("fun f(): unit { ++n; n++; }");
+    assert!(items[0].ends_with("(Block { (Stmt ; (PreStep ++ (Identifier n))) (Stmt ; (PostStep ++ (Identifier n)))))"), "{}", items[0]);
+  }
+
+  #[test]
+  fn statements_and_calls() {
+    let items = dump("fun f(a: int): int { if (a < 1) return g(a, b[0]); else { a++; } while (a) ; for i in 0..a break; }");
+    assert_eq!(
+      items[0],
+      "(Fun fun (Identifier f) (Params ( (Param a (Type int))) (Type int) (Block { \
+(If if (BinOper < (Identifier a) (Literal 1)) (Return return (Call ( (Identifier g) (Args ( (Identifier a) (Index [ (Identifier b) (Literal 0))))) \
+(Else else (Block { (Stmt ; (PostStep ++ (Identifier a)))))) \
+(While while (Identifier a) (Stmt ;)) \
+(For for (Identifier i) (BinOper .. (Literal 0) (Identifier a)) (Break break))))"
+    );
+  }
+
+  #[test]
+  fn parse_errors_recover() {
+    let source = "fun f(): int { let x: int = ; x = 1 }\nlet y: int = 2;\n@";
+    let mut parser = Parser::new(Lexer::new(source));
+    let ast = parser.parse();
+    let errors: Vec<String> = render(source, &diagnostic::parse_errors(ast));
+    assert_eq!(errors.len(), 3, "{:?}", errors);
+    assert!(errors[0].starts_with("1:29: error: Expected Expr or Identifier, found `;`"));
+    assert!(errors[1].starts_with("1:37: error: Expected ';', found `}`"));
+    assert!(errors[2].starts_with("3:1: error: Unknown Character `@`"));
+    assert_eq!(ast.get_root().child_count(), 3);
+  }
+}
@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod tests {
+  use crate::driver;
+  use crate::interpreter::{self, prelude::Console, value::Value};
+  use crate::test::messages;
+
+  // Printed text and value of `main`, reading `input`
+  fn run(source: &str, input: &str) -> (String, Result<Value, String>) {
+    let analysis = driver::analyze(source);
+    assert!(analysis.diagnostics.is_empty(), "{:?}", analysis.diagnostics);
+    let mut printed = Vec::new();
+    let result = interpreter::run_with(&analysis.ast, Console::new(Box::new(input.as_bytes()), Box::new(&mut printed)));
+    (String::from_utf8(printed).unwrap(), result.map_err(|d| d.message))
+  }
+
+  #[test]
+  fn io_and_strings() {
+    let (printed, result) = run("
+fun main(): int {
+  print(\"name? \");
+  let name = read_line();
+  println(\"hi \" + name + \"!\");
+  let words = split(read_line(), \",\");
+  let total = 0;
+  for i in 0..len(words) {
+    total += to_int(words[i]);
+  }
+  println(words);
+  println(substr(\"carfish\", 1, 3) + to_string(len(\"héllo\")) + to_string(2.5) + to_string([1, 2]));
+  println(to_int('A') + to_int(-2.7));
+  println(read_line() == \"\");
+  return total;
+}", "Ada\r\n1, 2,39\n");
+    assert_eq!(printed, "name? hi Ada!\n[\"1\", \" 2\", \"39\"]\narf52.5[1, 2]\n63\ntrue\n");
+    assert_eq!(result, Ok(Value::Int(42)));
+    assert_eq!(run("fun main(): int { return to_int(\"4x\"); }", "").1, Err("cannot parse \"4x\" as an int".to_string()));
+    assert_eq!(run("fun main(): string { return substr(\"abc\", 2, 2); }", "").1, Err("substring of 2 chars at 2 out of bounds for length 3".to_string()));
+  }
+
+  #[test]
+  fn math_and_arrays() {
+    let (printed, result) = run("
+fun main(): int {
+  println(abs(-3) + min(4, 9) + max(4, 9) + pow(2, 10));
+  println(abs(-1.5) + min(0.5, 0.25) + pow(2.0, 0.5) * pow(2.0, 0.5) + sqrt(16.0));
+  println(floor(2.9) + floor(-2.1));
+  let xs: [int] = [5, 3, 8];
+  let ys = [\"pear\", \"fig\", \"apple\"];
+  let none = 0;
+  let zs = [0.5; none];
+  push(xs, 1);
+  sort(xs);
+  sort(ys);
+  push(zs, 2.0);
+  println(xs);
+  println(ys);
+  return pop(xs) * 10 + len(xs) + len(zs);
+}", "");
+    assert_eq!(printed, "1040\n7.75\n-1\n[1, 3, 5, 8]\n[\"apple\", \"fig\", \"pear\"]\n");
+    assert_eq!(result, Ok(Value::Int(84)));
+    assert_eq!(run("fun main(): int { return pow(2, -1); }", "").1, Err("negative exponent -1 in `pow`".to_string()));
+    assert_eq!(run("fun main(): int { return pow(1, 5000000000) + pow(-1, 5000000001) + pow(0, 5000000000); }", "").1, Ok(Value::Int(0)));
+    assert_eq!(run("fun main(): int { return pow(2, 5000000000); }", "").1, Err("arithmetic overflow".to_string()));
+    assert_eq!(run("fun main(): int { return pow(3, 40); }", "").1, Err("arithmetic overflow".to_string()));
+    assert_eq!(run("fun main(): int { let n = 0; let a = [1; n]; return pop(a); }", "").1, Err("cannot pop from an empty array".to_string()));
+  }
+
+  #[test]
+  fn prelude_is_type_checked() {
+    assert_eq!(messages("
+fun len(x: int): int { return x; }
+fun main(): int {
+  let p: fun(int): unit = print;
+  min(1, 2.0);
+  sqrt(4);
+  let fixed: [int; 2] = [1, 2];
+  push(fixed, 3);
+  sort([true]);
+  let none = 0;
+  push([1; none], \"a\");
+  substr(\"a\", 1);
+  return len(3) + pop(5);
+}"), vec![
+      "4:27: error: built-in function `print` can only be called",
+      "5:10: error: mismatched types in argument 2 of `min`: expected `int`, found `float`",
+      "6:8: error: mismatched types in argument 1 of `sqrt`: expected `float`, found `int`",
+      "8:8: error: cannot `push` on `[int; 2]`, its length is fixed",
+      "9:8: error: cannot sort `[bool]`, its elements cannot be compared with `<`",
+      "11:19: error: mismatched types in argument 2 of `push`: expected `int`, found `string`",
+      "12:3: error: function `substr` takes 3 arguments but 2 were supplied",
+      "13:23: error: mismatched types in argument 1 of `pop`: expected `[_]`, found `int`",
+    ]);
+  }
+}
@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod tests {
+  use crate::printer::format_source;
+
+  #[test]
+  fn formats_layout_and_keeps_comments() {
+    let source = "// header
+let limit:int=10;
+let  other = - -limit;
+fun main():int{ // entry
+
+
+  if(limit>1)return limit;else if (limit<0) { return 0; }
+  for i in 0..limit {}
+  while(true){break;}   // spin
+  // trailing
+}";
+    let expected = "// header
+let limit: int = 10;
+let other = - -limit;
+
+fun main(): int { // entry
+  if (limit > 1)
+    return limit;
+  else if (limit < 0) {
+    return 0;
+  }
+  for i in 0..limit {}
+  while (true) {
+    break;
+  } // spin
+  // trailing
+}
+";
+    let formatted = format_source(source).unwrap();
+    assert_eq!(formatted, expected);
+    assert_eq!(format_source(&formatted).unwrap(), formatted);
+  }
+
+  #[test]
+  fn blocks_inside_statements_end_at_their_brace() {
+    let source = "fun main(): unit {
+  let f = fun(x: int): int {
+    return x;
+  };
+  let y = f(1);
+  if (y > 0) {
+    y = 2;
+  } // after if
+  else {
+    y = 3;
+  }
+}
+";
+    assert_eq!(format_source(source).unwrap(), source);
+  }
+
+  #[test]
+  fn refuses_source_that_does_not_parse() {
+    let errors = format_source("fun main(): unit { let = 1; }").unwrap_err();
+    assert_eq!(errors.len(), 1);
+  }
+}
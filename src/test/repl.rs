@@ -0,0 +1,37 @@
+#[cfg(test)]
+mod tests {
+  use crate::repl::{self, Session};
+
+  #[test]
+  fn keeps_state_between_entries() {
+    let mut session = Session::new();
+    assert_eq!(session.eval("let total = 40;"), "");
+    assert_eq!(session.eval("fun add(a: int, b: int): int { return a + b; }"), "");
+    assert_eq!(session.eval("total = add(total, 2)"), "");
+    assert_eq!(session.eval("total"), "42\n");
+    assert_eq!(session.eval(":type add"), "fun(int, int): int\n");
+    assert_eq!(session.eval(":type total > 1"), "bool\n");
+    // A rejected entry leaves the session as it was
+    assert!(session.eval("let total: string = 1;").contains("mismatched types"));
+    assert_eq!(session.eval("total + 1"), "43\n");
+    // Functions can be redefined
+    session.eval("fun add(a: int, b: int): int { return a * b; }");
+    assert_eq!(session.eval("add(total, 2)"), "84\n");
+    assert_eq!(session.eval("\"car\" + \"f\""), "\"carf\"\n");
+  }
+
+  #[test]
+  fn continues_incomplete_input() {
+    assert!(repl::is_incomplete("fun f(): int {"));
+    assert!(repl::is_incomplete("add(1,"));
+    assert!(repl::is_incomplete("1 +"));
+    assert!(repl::is_incomplete("!"));
+    assert!(!repl::is_incomplete("x++"));
+    assert!(!repl::is_incomplete("fun f(): int { return 1; }"));
+
+    let mut input = "let x =\n  1 +\n  2;\nx * 2\n:quit\nx\n".as_bytes();
+    let mut out = Vec::new();
+    repl::run(&mut input, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "> ... ... > 6\n> ");
+  }
+}
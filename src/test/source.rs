@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+
+  use crate::ast::token::TokenKind;
+  use crate::diagnostic::{self, Diagnostic, Span};
+  use crate::lexer::Lexer;
+  use crate::parser::Parser;
+  use crate::source::SourceMap;
+  use crate::test::render;
+
+  fn two_files() -> SourceMap {
+    let mut files = SourceMap::new();
+    files.add(PathBuf::from("a.carf"), "let a = 1;\nlet é = 2;\n".to_string());
+    files.add(PathBuf::from("b.carf"), "fun f(): int {\n  return 1;\n}".to_string());
+    files
+  }
+
+  #[test]
+  fn positions_follow_each_other() {
+    let files = two_files();
+    let (a, b) = (files.lookup(0), files.lookup(24));
+    assert_eq!(a.get_path(), PathBuf::from("a.carf"));
+    assert_eq!(b.get_base(), 24);
+    assert_eq!(files.file_at(23), files.file_at(0));
+    assert_ne!(files.file_at(24), files.file_at(23));
+    assert_eq!(a.line_col(15), (2, 5));
+    assert_eq!(a.line_col(23), (3, 1));
+    assert_eq!(b.line_col(24 + 17), (2, 3));
+    assert_eq!(b.line_start(3), Some(24 + 27));
+    assert_eq!(b.line_text(2), "  return 1;");
+  }
+
+  #[test]
+  fn files_are_lexed_and_parsed_in_place() {
+    let files = two_files();
+    let b = files.lookup(24);
+    let mut lexer = Lexer::for_file(b);
+    let token = lexer.next().unwrap();
+    assert_eq!((token.get_kind(), token.get_pos()), (TokenKind::Fun, 24));
+    let mut parser = Parser::for_file(files.lookup(0));
+    let ast = parser.parse();
+    assert!(diagnostic::parse_errors(ast).is_empty());
+    assert_eq!(Span::of(ast.get_root()), Span::new(0, 21));
+  }
+
+  #[test]
+  fn diagnostics_render_in_their_file() {
+    let files = two_files();
+    let d = Diagnostic::error(Span::new(24 + 17, 24 + 23), "no".to_string());
+    assert_eq!(files.render(&d, false), "b.carf:2:3: error: no\n    return 1;\n    ^^^^^^");
+    let json = files.to_json(&d).to_string();
+    assert!(json.contains("\"file\":\"b.carf\"") && json.contains("\"start\":17"), "{}", json);
+    assert_eq!(render("x", &[d]), ["1:2: error: no\n  x\n   ^"]);
+  }
+}
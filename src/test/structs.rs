@@ -0,0 +1,65 @@
+#[cfg(test)]
+mod tests {
+  use crate::driver;
+  use crate::interpreter::{self, value::Value};
+  use crate::lexer::Lexer;
+  use crate::parser::Parser;
+  use crate::test::messages;
+
+  #[test]
+  fn declarations_literals_and_fields() {
+    let mut parser = Parser::new(Lexer::new("
+struct Point { x: int, y: [int; 2], }
+fun f(): unit { for p in ps { q = Point { x: a.b, y: [] }.x; } }"));
+    let ast = parser.parse();
+    let items: Vec<String> = ast.get_root().get_children().iter().map(|n| n.dump()).collect();
+    assert_eq!(items[0], "(Struct struct (Identifier Point) (Fields { (Field x (Type int)) (Field y (Type [ (Type int) (Literal 2)))))");
+    // `ps {` opens the loop body, not a literal
+    assert!(items[1].contains(
+      "(For for (Identifier p) (Identifier ps) (Block { (Stmt ; (BinOper = (Identifier q) \
+       (Member . (StructLit { (Identifier Point) (FieldInit x (Member . (Identifier a) (Identifier b))) (FieldInit y (Array [))) (Identifier x))))))"
+    ), "{}", items[1]);
+  }
+
+  #[test]
+  fn fields_are_checked_by_name_and_type() {
+    assert_eq!(messages("
+struct Point { x: int, y: int }
+struct Point { z: int }
+fun main(): int {
+  let p = Point { x: 1, z: 2 };
+  let q = Point { x: 1, y: 'c', x: 3 };
+  p.x = true;
+  return p.w + q.x;
+}"), vec![
+      "3:8: error: struct `Point` is defined more than once",
+      "5:11: error: missing field `y` in `Point` literal",
+      "5:25: error: struct `Point` has no field `z`",
+      "6:28: error: mismatched types in field `y`: expected `int`, found `char`",
+      "6:33: error: field `x` is given more than once",
+      "7:9: error: mismatched types in assignment: expected `int`, found `bool`",
+      "8:12: error: no field `w` on type `Point`",
+    ]);
+  }
+
+  #[test]
+  fn runs_field_reads_and_writes() {
+    let source = "
+struct Point { x: int, y: int }
+struct Path { points: [Point], name: string }
+fun main(): string {
+  let path = Path { name: \"p\", points: [Point { x: 1, y: 2 }; 2] };
+  path.points[1].y += 10;
+  path.name[0] = 'P';
+  let first = path.points[0];
+  first.x = 5;
+  if (path.points[0] == Point { x: 5, y: 2 } && path.points[1].y == 12) {
+    return path.name;
+  }
+  return \"wrong\";
+}";
+    let analysis = driver::analyze(source);
+    assert!(analysis.diagnostics.is_empty(), "{:?}", analysis.diagnostics);
+    assert_eq!(interpreter::run(&analysis.ast).ok(), Some(Value::Str("P".into())));
+  }
+}
@@ -0,0 +1,159 @@
+#[cfg(test)]
+mod tests {
+  use wasmi::{Caller, Engine, ExternType, Linker, Memory, Module, Store};
+
+  use crate::test::carf;
+  use crate::utilities::literal;
+
+  const PROGRAM: &str = "
+struct Item { name: string, tags: [string], score: float }
+impl Item {
+  fun bump(self, by: float): unit { self.score += by; }
+  fun label(self): string { return self.name + \"#\" + to_string(len(self.tags)); }
+}
+let total = 0;
+fun side(x: int): bool { total += x; return x > 1; }
+fun fib(n: int): int { if (n < 2) { return n; } return fib(n - 1) + fib(n - 2); }
+fun bump(xs: [int]): unit { return xs[fib(2)] += 5; }
+fun main(): int {
+  let it = Item { name: \"h\u{e9}\", tags: [\"t1\"], score: 0.5 };
+  it.bump(1.25);
+  it.tags.push(\"t2\");
+  it.tags[0] = \"T\";
+  println(it.label());
+  println(it);
+  let rows = [it; 2];
+  rows[0].name = \"y\";
+  println([rows[1] == it, rows[0] == rows[1]]);
+  println([side(1) && side(2), side(2) || side(5)]);
+  let words = split(\"b,a,,c\", \",\");
+  sort(words);
+  println(words);
+  let grid: [[int]] = [[1, 2], [3, 4]];
+  grid[1][0] *= 10;
+  println([pop(grid[1]), len(grid[1])]);
+  println(grid);
+  let s = \"\";
+  for c in \"h\u{e9}llo w\u{f6}rld\" { if (c == 'o') { continue; } if (c == 'w') { break; } s += to_string(c); }
+  s[1] = 'E';
+  println(s + substr(\"w\u{f6}rld\", 1, 3));
+  let i = 0;
+  while (i < 10) { i += 3; if (i == 6) { continue; } print(i); print(' '); }
+  for j in 0..2 { if (j == 1) { continue; } grid[j][0] += i; }
+  bump(grid[0]);
+  println([i++, ++i, grid[0][1]--, --grid[0][1], i]);
+  println([1.0, 0.1, 10000000000000000.0 * 3.0, -0.0, 0.0 / 0.0, min(1.5, -2.0), sqrt(2.0)]);
+  println([to_int(\" -42 \"), to_int(-2.7), floor(-2.5), pow(3, 4), pow(-1, 5000000001), -7 % 3, -16 >> 2, 5 ^ 3]);
+  return total + fib(15);
+}";
+
+  // Output written and runtime error reported by the module
+  #[derive(Default)]
+  struct Host {
+    out: Vec<u8>,
+    error: Option<String>,
+  }
+
+  fn memory(caller: &Caller<'_, Host>) -> Memory {
+    caller.get_export("memory").and_then(|export| export.into_memory()).unwrap()
+  }
+
+  fn read(caller: &Caller<'_, Host>, ptr: i32, len: i32) -> String {
+    let mut bytes = vec![0; len as usize];
+    memory(caller).read(caller, ptr as usize, &mut bytes).unwrap();
+    String::from_utf8(bytes).unwrap()
+  }
+
+  // The module text `carf wat` prints, or its exit code and stderr
+  fn compile(source: &str) -> Result<String, (i32, String)> {
+    match carf(&["wat", "--color=never"], source) {
+      (0, out, _) => Ok(out),
+      (code, _, err) => Err((code, err)),
+    }
+  }
+
+  // Exit code, stdout and stderr of running `main` in the interpreter crate,
+  // with the host functions a browser would provide
+  fn execute(module: &Module) -> (i32, String, String) {
+    let mut store = Store::new(module.engine(), Host::default());
+    let mut linker = Linker::<Host>::new(module.engine());
+    linker.func_wrap("carf", "write", |mut caller: Caller<'_, Host>, ptr: i32, len: i32| {
+      let text = read(&caller, ptr, len);
+      caller.data_mut().out.extend(text.bytes());
+    }).unwrap();
+    linker.func_wrap("carf", "fail", |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+      caller.data_mut().error = Some(read(&caller, ptr, len));
+      Err(wasmi::Error::new("runtime error"))
+    }).unwrap();
+    linker.func_wrap("carf", "format_float", |mut caller: Caller<'_, Host>, x: f64, ptr: i32| -> i32 {
+      let text = literal::format_float(x);
+      memory(&caller).write(&mut caller, ptr as usize, text.as_bytes()).unwrap();
+      text.len() as i32
+    }).unwrap();
+    let instance = linker.instantiate(&mut store, module).unwrap().start(&mut store).unwrap();
+    let main = instance.get_typed_func::<(), ()>(&store, "main").unwrap();
+    let code = match main.call(&mut store, ()) {
+      Ok(()) => 0,
+      Err(_) => 1,
+    };
+    let host = store.into_data();
+    let err = host.error.map(|e| e + "\n").unwrap_or_default();
+    (code, String::from_utf8(host.out).unwrap(), err)
+  }
+
+  fn load(text: &str) -> Module {
+    let wasm = wat::parse_str(text).unwrap_or_else(|e| panic!("{}", e));
+    Module::new(&Engine::default(), &wasm[..]).unwrap()
+  }
+
+  #[test]
+  fn module_imports_the_host_and_exports_main() {
+    let module = load(&compile(PROGRAM).unwrap());
+    let imports: Vec<String> = module.imports().map(|i| format!("{}.{}", i.module(), i.name())).collect();
+    assert_eq!(imports, ["carf.write", "carf.fail", "carf.format_float"]);
+    let mut exports: Vec<(&str, bool)> = module.exports().map(|e| (e.name(), matches!(e.ty(), ExternType::Func(_)))).collect();
+    exports.sort();
+    assert_eq!(exports, [("main", true), ("memory", false)]);
+
+    // Strings are interned once in the data segment
+    let text = compile("fun main(): unit { println(\"twice\"); println(\"twice\"); }").unwrap();
+    assert_eq!(text.matches("\\05\\00\\00\\00twice").count(), 1);
+    assert!(text.contains("(func $f_main\n"), "{}", text);
+  }
+
+  #[test]
+  fn compiled_output_matches_run() {
+    let (code, out, err) = execute(&load(&compile(PROGRAM).unwrap()));
+    assert_eq!(err, "");
+    assert_eq!(code, 0);
+    let (code, expected, _) = carf(&["run"], PROGRAM);
+    assert_eq!(code, 0);
+    assert_eq!(out, expected);
+  }
+
+  #[test]
+  fn runtime_errors_and_unsupported_features() {
+    let (code, out, err) = execute(&load(&compile("fun main(): int {
+  println(1);
+  let xs = [1, 2];
+  return xs[5];
+}").unwrap()));
+    assert_eq!((code, out.as_str()), (1, "1\n"));
+    assert_eq!(err, "<stdin>:4:10: error: index 5 out of bounds for length 2\n");
+
+    let source = "fun main(): int { let n = 1; while (n > 0) { n = n * 3; } return n; }";
+    let (code, _, err) = execute(&load(&compile(source).unwrap()));
+    assert_eq!((code, err.as_str()), (1, "<stdin>:1:50: error: arithmetic overflow\n"));
+
+    let (code, err) = compile("fun main(): unit {
+  let f = fun(x: int): int { return x; };
+}
+fun echo(): unit { println(read_line()); }").unwrap_err();
+    assert_eq!(code, 1);
+    let lines: Vec<&str> = err.lines().filter(|l| l.starts_with("<stdin>")).collect();
+    assert_eq!(lines, [
+      "<stdin>:2:7: error: function values cannot be compiled to WebAssembly",
+      "<stdin>:4:28: error: `read_line` cannot be compiled to WebAssembly",
+    ]);
+  }
+}
@@ -0,0 +1,15 @@
+use std::collections::BTreeSet;
+use std::sync::{Mutex, OnceLock};
+
+// Token text created by compiler passes rather than read from a source, kept
+// for the lifetime of the process. Each distinct string is stored once.
+pub fn intern(s: &str) -> &'static str {
+  static STRINGS: OnceLock<Mutex<BTreeSet<&'static str>>> = OnceLock::new();
+  let mut strings = STRINGS.get_or_init(|| Mutex::new(BTreeSet::new())).lock().unwrap();
+  if let Some(interned) = strings.get(s) {
+    return interned;
+  }
+  let interned: &'static str = Box::leak(s.to_string().into_boxed_str());
+  strings.insert(interned);
+  interned
+}
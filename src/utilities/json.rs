@@ -0,0 +1,271 @@
+use std::fmt;
+
+// Just enough JSON for machine-readable output and the language server
+#[derive(Clone, PartialEq, Debug)]
+pub enum Json {
+  Null,
+  Bool(bool),
+  Number(f64),
+  String(String),
+  Array(Vec<Json>),
+  // Keys keep their insertion order
+  Object(Vec<(String, Json)>),
+}
+
+impl Json {
+  pub fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+  }
+  pub fn str(s: &str) -> Json {
+    Json::String(s.to_string())
+  }
+  pub fn get(&self, key: &str) -> Option<&Json> {
+    match self {
+      Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+      _ => None,
+    }
+  }
+  // Follow a chain of object keys
+  pub fn path(&self, keys: &[&str]) -> Option<&Json> {
+    keys.iter().try_fold(self, |json, key| json.get(key))
+  }
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      Json::String(s) => Some(s),
+      _ => None,
+    }
+  }
+  pub fn as_f64(&self) -> Option<f64> {
+    match self {
+      Json::Number(n) => Some(*n),
+      _ => None,
+    }
+  }
+  pub fn as_bool(&self) -> Option<bool> {
+    match self {
+      Json::Bool(b) => Some(*b),
+      _ => None,
+    }
+  }
+  pub fn as_array(&self) -> Option<&Vec<Json>> {
+    match self {
+      Json::Array(items) => Some(items),
+      _ => None,
+    }
+  }
+  // Indented by two spaces, for files people read
+  pub fn pretty(&self) -> String {
+    let mut out = String::new();
+    self.write_pretty(&mut out, 0);
+    out
+  }
+  fn write_pretty(&self, out: &mut String, indent: usize) {
+    let pad = "  ".repeat(indent + 1);
+    match self {
+      Json::Array(items) if !items.is_empty() => {
+        out.push_str("[\n");
+        for (i, item) in items.iter().enumerate() {
+          out.push_str(&pad);
+          item.write_pretty(out, indent + 1);
+          out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+        }
+        out.push_str(&format!("{}]", "  ".repeat(indent)));
+      },
+      Json::Object(fields) if !fields.is_empty() => {
+        out.push_str("{\n");
+        for (i, (key, value)) in fields.iter().enumerate() {
+          out.push_str(&format!("{}{}: ", pad, escape(key)));
+          value.write_pretty(out, indent + 1);
+          out.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+        }
+        out.push_str(&format!("{}}}", "  ".repeat(indent)));
+      },
+      _ => out.push_str(&self.to_string()),
+    }
+  }
+}
+
+pub fn parse(text: &str) -> Result<Json, String> {
+  let mut reader = Reader { chars: text.chars().collect(), pos: 0 };
+  let value = reader.value()?;
+  reader.skip_space();
+  match reader.pos < reader.chars.len() {
+    true => Err(format!("unexpected `{}` after the value", reader.chars[reader.pos])),
+    false => Ok(value),
+  }
+}
+
+struct Reader {
+  chars: Vec<char>,
+  pos: usize,
+}
+
+impl Reader {
+  fn skip_space(&mut self) {
+    while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+      self.pos += 1;
+    }
+  }
+  fn next(&mut self) -> Result<char, String> {
+    let c = self.chars.get(self.pos).copied().ok_or("unexpected end of input")?;
+    self.pos += 1;
+    Ok(c)
+  }
+  fn expect(&mut self, word: &str) -> Result<(), String> {
+    for c in word.chars() {
+      if self.next()? != c {
+        return Err(format!("expected `{}`", word));
+      }
+    }
+    Ok(())
+  }
+  fn value(&mut self) -> Result<Json, String> {
+    self.skip_space();
+    match self.chars.get(self.pos).copied().ok_or("unexpected end of input")? {
+      'n' => self.expect("null").map(|_| Json::Null),
+      't' => self.expect("true").map(|_| Json::Bool(true)),
+      'f' => self.expect("false").map(|_| Json::Bool(false)),
+      '"' => self.string().map(Json::String),
+      '[' => {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.skip_space();
+        if self.chars.get(self.pos) == Some(&']') {
+          self.pos += 1;
+          return Ok(Json::Array(items));
+        }
+        loop {
+          items.push(self.value()?);
+          self.skip_space();
+          match self.next()? {
+            ',' => continue,
+            ']' => return Ok(Json::Array(items)),
+            c => return Err(format!("unexpected `{}` in array", c)),
+          }
+        }
+      },
+      '{' => {
+        self.pos += 1;
+        let mut fields = Vec::new();
+        self.skip_space();
+        if self.chars.get(self.pos) == Some(&'}') {
+          self.pos += 1;
+          return Ok(Json::Object(fields));
+        }
+        loop {
+          self.skip_space();
+          if self.chars.get(self.pos) != Some(&'"') {
+            return Err("expected a string key".to_string());
+          }
+          let key = self.string()?;
+          self.skip_space();
+          if self.next()? != ':' {
+            return Err("expected `:` after a key".to_string());
+          }
+          fields.push((key, self.value()?));
+          self.skip_space();
+          match self.next()? {
+            ',' => continue,
+            '}' => return Ok(Json::Object(fields)),
+            c => return Err(format!("unexpected `{}` in object", c)),
+          }
+        }
+      },
+      c if c == '-' || c.is_ascii_digit() => {
+        let start = self.pos;
+        while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(*c)) {
+          self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse().map(Json::Number).map_err(|_| format!("invalid number `{}`", text))
+      },
+      c => Err(format!("unexpected `{}`", c)),
+    }
+  }
+  fn string(&mut self) -> Result<String, String> {
+    self.pos += 1;
+    let mut out = String::new();
+    loop {
+      match self.next()? {
+        '"' => return Ok(out),
+        '\\' => match self.next()? {
+          'n' => out.push('\n'),
+          't' => out.push('\t'),
+          'r' => out.push('\r'),
+          'b' => out.push('\u{8}'),
+          'f' => out.push('\u{c}'),
+          'u' => {
+            let mut code = self.hex4()?;
+            // Surrogate pair
+            if (0xd800..0xdc00).contains(&code) {
+              self.expect("\\u")?;
+              let low = self.hex4()?;
+              code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+            }
+            out.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+          },
+          c => out.push(c),
+        },
+        c => out.push(c),
+      }
+    }
+  }
+  fn hex4(&mut self) -> Result<u32, String> {
+    let mut code = 0;
+    for _ in 0..4 {
+      let digit = self.next()?.to_digit(16).ok_or("invalid `\\u` escape")?;
+      code = code * 16 + digit;
+    }
+    Ok(code)
+  }
+}
+
+pub fn escape(s: &str) -> String {
+  let mut out = String::from("\"");
+  for c in s.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\n' => out.push_str("\\n"),
+      '\r' => out.push_str("\\r"),
+      '\t' => out.push_str("\\t"),
+      c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+  out
+}
+
+impl fmt::Display for Json {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Json::Null => write!(f, "null"),
+      Json::Bool(b) => write!(f, "{}", b),
+      Json::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+      Json::Number(n) if n.is_finite() => write!(f, "{}", n),
+      Json::Number(_) => write!(f, "null"),
+      Json::String(s) => write!(f, "{}", escape(s)),
+      Json::Array(items) => {
+        write!(f, "[")?;
+        for (i, item) in items.iter().enumerate() {
+          if i > 0 {
+            write!(f, ",")?;
+          }
+          write!(f, "{}", item)?;
+        }
+        write!(f, "]")
+      },
+      Json::Object(fields) => {
+        write!(f, "{{")?;
+        for (i, (key, value)) in fields.iter().enumerate() {
+          if i > 0 {
+            write!(f, ",")?;
+          }
+          write!(f, "{}:{}", escape(key), value)?;
+        }
+        write!(f, "}}")
+      },
+    }
+  }
+}
@@ -0,0 +1,93 @@
+// Decoding and encoding of the text of char and string literals
+
+fn unescape_char(c: char) -> Option<char> {
+  match c {
+    'n' => Some('\n'),
+    't' => Some('\t'),
+    'r' => Some('\r'),
+    '0' => Some('\0'),
+    '\\' => Some('\\'),
+    '\'' => Some('\''),
+    '"' => Some('"'),
+    _ => None,
+  }
+}
+
+fn escape_char(c: char, quote: char, out: &mut String) {
+  match c {
+    '\n' => out.push_str("\\n"),
+    '\t' => out.push_str("\\t"),
+    '\r' => out.push_str("\\r"),
+    '\0' => out.push_str("\\0"),
+    '\\' => out.push_str("\\\\"),
+    c if c == quote => {
+      out.push('\\');
+      out.push(c);
+    },
+    c => out.push(c),
+  }
+}
+
+// `'a'` or `'\n'` to the char it denotes
+pub fn parse_char(text: &str) -> Option<char> {
+  let inner = text.strip_prefix('\'')?.strip_suffix('\'')?;
+  let mut chars = inner.chars();
+  let c = match chars.next()? {
+    '\\' => unescape_char(chars.next()?)?,
+    c => c,
+  };
+  match chars.next() {
+    Some(_) => None,
+    None => Some(c),
+  }
+}
+
+// `"a\tb"` to the string it denotes
+pub fn parse_string(text: &str) -> Option<String> {
+  let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+  let mut out = String::new();
+  let mut chars = inner.chars();
+  while let Some(c) = chars.next() {
+    match c {
+      '\\' => out.push(unescape_char(chars.next()?)?),
+      c => out.push(c),
+    }
+  }
+  Some(out)
+}
+
+// Int literal text, which the lexer only produces as plain digits
+pub fn parse_int(text: &str) -> Option<i64> {
+  text.parse().ok()
+}
+
+// Float literal text with an optional `f` suffix, or the `inf` and `NaN` folding writes
+pub fn parse_float(text: &str) -> Option<f64> {
+  text.parse().ok().or_else(|| text.strip_suffix('f')?.parse().ok())
+}
+
+pub fn quote_char(c: char) -> String {
+  let mut out = String::from("'");
+  escape_char(c, '\'', &mut out);
+  out.push('\'');
+  out
+}
+
+pub fn quote_string(s: &str) -> String {
+  let mut out = String::from("\"");
+  for c in s.chars() {
+    escape_char(c, '"', &mut out);
+  }
+  out.push('"');
+  out
+}
+
+// Float text that always reads back as a float literal
+pub fn format_float(f: f64) -> String {
+  let text = format!("{:?}", f);
+  if text.contains('.') || text.contains('e') || !f.is_finite() {
+    text
+  } else {
+    format!("{}.0", text)
+  }
+}
@@ -1 +1,4 @@
-pub mod trie;
\ No newline at end of file
+pub mod trie;
+pub mod intern;
+pub mod literal;
+pub mod json;
@@ -31,6 +31,12 @@ pub struct Trie {
   root: TrieNode,
 }
 
+impl Default for Trie {
+  fn default() -> Self {
+    Trie::new()
+  }
+}
+
 impl Trie {
   pub fn new() -> Trie {
     Trie {
@@ -54,4 +60,38 @@ impl Trie {
     }
     current.is_end
   }
-}
\ No newline at end of file
+  // Every word that starts with `prefix`, in order
+  pub fn words_with_prefix(&self, prefix: &str) -> Vec<String> {
+    let mut current = &self.root;
+    for c in prefix.chars() {
+      match current.children.get(&c) {
+        Some(node) => current = node,
+        None => return Vec::new(),
+      }
+    }
+    let mut words = Vec::new();
+    let mut word = prefix.to_string();
+    Self::collect(current, &mut word, &mut words);
+    words
+  }
+  fn collect(node: &TrieNode, word: &mut String, words: &mut Vec<String>) {
+    if node.is_end {
+      words.push(word.clone());
+    }
+    for child in node.children.values() {
+      word.push(child.value);
+      Self::collect(child, word, words);
+      word.pop();
+    }
+  }
+  pub fn starts_with(&self, prefix: &str) -> bool {
+    let mut current = &self.root;
+    for c in prefix.chars() {
+      match current.children.get(&c) {
+        Some(node) => current = node,
+        None => return false,
+      }
+    }
+    true
+  }
+}